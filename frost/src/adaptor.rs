@@ -0,0 +1,282 @@
+//! Adaptor signatures over the FROST group key.
+//!
+//! A pre-signature `(R', s')` is encrypted under an adaptor point `T = t * G`:
+//! it satisfies `s' * G + T == R' + c * P` with `c = H(R', P, m)`, so on its own
+//! it is not a valid signature. Whoever learns `t` can complete it into the
+//! valid signature `(R', s' + t)`, and whoever sees both the pre-signature and
+//! the completed signature can extract `t`. This is the building block for
+//! atomic swaps and PTLCs.
+
+use crate::error::AppError;
+use crate::{commit_round1, default_signers, frost, KeyPackages};
+use frost_core::{Field, Group};
+use rand::{CryptoRng, RngCore};
+use std::collections::BTreeMap;
+
+type Ciphersuite = frost::Secp256K1Sha256;
+pub type Element = frost_core::Element<Ciphersuite>;
+pub type Scalar = frost_core::Scalar<Ciphersuite>;
+
+/// Length of a serialized compressed point.
+const ELEMENT_LEN: usize = 33;
+/// Length of a serialized scalar.
+const SCALAR_LEN: usize = 32;
+
+/// A FROST signature that still has to be completed with the adaptor secret.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PreSignature {
+    /// The adapted nonce R' = R + T, which the completed signature will carry.
+    r: Element,
+    s: Scalar,
+}
+
+impl PreSignature {
+    /// Serializes as `R' || s'`, the same layout as a [`frost::Signature`].
+    pub fn serialize(&self) -> Result<Vec<u8>, AppError> {
+        Ok(frost::Signature::new(self.r, self.s).serialize()?)
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, AppError> {
+        let (r, s) = split_signature(bytes)?;
+        Ok(PreSignature { r, s })
+    }
+}
+
+/// Parses a hex-encoded compressed point.
+pub fn parse_element(hex_str: &str) -> Result<Element, AppError> {
+    let bytes: [u8; ELEMENT_LEN] = hex::decode(hex_str.trim())?
+        .try_into()
+        .map_err(|_| "adaptor point must be a 33-byte compressed point")?;
+    Ok(frost::Secp256K1Group::deserialize(&bytes)?)
+}
+
+/// Parses a hex-encoded scalar.
+pub fn parse_scalar(hex_str: &str) -> Result<Scalar, AppError> {
+    let bytes: [u8; SCALAR_LEN] = hex::decode(hex_str.trim())?
+        .try_into()
+        .map_err(|_| "adaptor secret must be a 32-byte scalar")?;
+    Ok(frost::Secp256K1ScalarField::deserialize(&bytes)?)
+}
+
+/// Splits a serialized `R || z` signature into its point and scalar.
+fn split_signature(bytes: &[u8]) -> Result<(Element, Scalar), AppError> {
+    if bytes.len() != ELEMENT_LEN + SCALAR_LEN {
+        return Err("signature must be 65 bytes (R || s)".into());
+    }
+    let (r_bytes, s_bytes) = bytes.split_at(ELEMENT_LEN);
+    let r = frost::Secp256K1Group::deserialize(&r_bytes.try_into()?)?;
+    let s = frost::Secp256K1ScalarField::deserialize(&s_bytes.try_into()?)?;
+    Ok((r, s))
+}
+
+/// Runs a FROST ceremony whose challenge commits to `R + adaptor_point`
+/// instead of `R`, producing a pre-signature over `message`.
+pub fn adaptor_sign<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    message: &[u8],
+    adaptor_point: &Element,
+    rng: &mut R,
+) -> Result<PreSignature, AppError> {
+    let (nonces_map, commitments_map) =
+        commit_round1(key_packages, &default_signers(key_packages), rng)?;
+    let signing_package = frost::SigningPackage::new(commitments_map, message);
+
+    let verifying_key = pubkey_package.verifying_key();
+    let binding_factor_list =
+        frost_core::compute_binding_factor_list(&signing_package, verifying_key, &[])?;
+    let group_commitment =
+        frost_core::compute_group_commitment(&signing_package, &binding_factor_list)?;
+    let adapted_nonce = group_commitment.to_element() + *adaptor_point;
+    let challenge = frost_core::challenge(&adapted_nonce, verifying_key, message)?;
+
+    let s = sum_verified_shares(
+        key_packages,
+        pubkey_package,
+        &signing_package,
+        &nonces_map,
+        &binding_factor_list,
+        challenge,
+    )?;
+
+    Ok(PreSignature {
+        r: adapted_nonce,
+        s,
+    })
+}
+
+/// Runs round 2 against an externally computed `challenge`, checks every
+/// signature share against its participant's verifying share and returns
+/// their sum.
+///
+/// This stands in for `frost::round2::sign` plus `frost::aggregate` when the
+/// challenge doesn't commit to the plain group nonce and message.
+pub fn sum_verified_shares(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signing_package: &frost::SigningPackage,
+    nonces_map: &BTreeMap<frost::Identifier, frost::round1::SigningNonces>,
+    binding_factor_list: &frost_core::BindingFactorList<Ciphersuite>,
+    challenge: frost_core::Challenge<Ciphersuite>,
+) -> Result<Scalar, AppError> {
+    let mut s = frost::Secp256K1ScalarField::zero();
+    for (identifier, nonces) in nonces_map {
+        let key_package = &key_packages[identifier];
+        let lambda_i = frost_core::derive_interpolating_value(identifier, signing_package)?;
+        let binding_factor = binding_factor_list
+            .get(identifier)
+            .ok_or(frost::Error::UnknownIdentifier)?
+            .clone();
+        let commitment_share = signing_package
+            .signing_commitment(identifier)
+            .ok_or(frost::Error::MissingCommitment)?
+            .to_group_commitment_share(&binding_factor);
+
+        let signature_share = frost_core::round2::compute_signature_share(
+            nonces,
+            binding_factor,
+            lambda_i,
+            key_package,
+            challenge,
+        );
+        signature_share.verify(
+            *identifier,
+            &commitment_share,
+            &pubkey_package.verifying_shares()[identifier],
+            lambda_i,
+            &challenge,
+        )?;
+
+        let share_bytes = signature_share
+            .serialize()
+            .try_into()
+            .expect("scalar length");
+        s += frost::Secp256K1ScalarField::deserialize(&share_bytes)?;
+    }
+    Ok(s)
+}
+
+/// Checks `s' * G + T == R' + c * P`, i.e. that completing the pre-signature
+/// with the discrete log of `adaptor_point` yields a valid group signature.
+pub fn adaptor_verify(
+    verifying_key: &frost::VerifyingKey,
+    message: &[u8],
+    adaptor_point: &Element,
+    pre_signature: &PreSignature,
+) -> Result<(), AppError> {
+    let challenge = frost_core::challenge(&pre_signature.r, verifying_key, message)?;
+    let lhs = frost::Secp256K1Group::generator() * pre_signature.s + *adaptor_point;
+    let rhs = pre_signature.r + verifying_key.to_element() * challenge.to_scalar();
+    if lhs != rhs {
+        return Err("pre-signature is not valid for this adaptor point".into());
+    }
+    Ok(())
+}
+
+/// Completes a pre-signature into a regular group signature.
+pub fn adaptor_complete(pre_signature: &PreSignature, adaptor_secret: &Scalar) -> frost::Signature {
+    frost::Signature::new(pre_signature.r, pre_signature.s + *adaptor_secret)
+}
+
+/// Recovers the adaptor secret from a pre-signature and its completion.
+pub fn extract_secret(
+    pre_signature: &PreSignature,
+    signature: &frost::Signature,
+) -> Result<Scalar, AppError> {
+    let (r, s) = split_signature(&signature.serialize()?)?;
+    if r != pre_signature.r {
+        return Err("signature was not completed from this pre-signature".into());
+    }
+    Ok(s - pre_signature.s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, MAX_SIGNERS, MIN_SIGNERS};
+    use rand::thread_rng;
+
+    const MESSAGE: &[u8] = b"swap 0.1 BTC for 1000 units";
+
+    #[test]
+    fn adaptor_lifecycle() {
+        let (key_packages, pubkey_package) =
+            dealer_keygen(MIN_SIGNERS, MAX_SIGNERS, &mut thread_rng()).unwrap();
+        let verifying_key = pubkey_package.verifying_key();
+        let mut rng = thread_rng();
+
+        let adaptor_secret = frost::Secp256K1ScalarField::random(&mut rng);
+        let adaptor_point = frost::Secp256K1Group::generator() * adaptor_secret;
+
+        let pre_signature = adaptor_sign(
+            &key_packages,
+            &pubkey_package,
+            MESSAGE,
+            &adaptor_point,
+            &mut rng,
+        )
+        .unwrap();
+        adaptor_verify(verifying_key, MESSAGE, &adaptor_point, &pre_signature).unwrap();
+
+        // The pre-signature must not pass as a signature on its own.
+        let bytes = pre_signature.serialize().unwrap();
+        let as_signature = frost::Signature::deserialize(&bytes).unwrap();
+        assert!(verifying_key.verify(MESSAGE, &as_signature).is_err());
+        assert_eq!(PreSignature::deserialize(&bytes).unwrap(), pre_signature);
+
+        let signature = adaptor_complete(&pre_signature, &adaptor_secret);
+        verifying_key.verify(MESSAGE, &signature).unwrap();
+
+        let extracted = extract_secret(&pre_signature, &signature).unwrap();
+        assert_eq!(extracted, adaptor_secret);
+    }
+
+    #[test]
+    fn adaptor_verify_rejects_wrong_point_and_message() {
+        let (key_packages, pubkey_package) =
+            dealer_keygen(MIN_SIGNERS, MAX_SIGNERS, &mut thread_rng()).unwrap();
+        let verifying_key = pubkey_package.verifying_key();
+        let mut rng = thread_rng();
+
+        let adaptor_point =
+            frost::Secp256K1Group::generator() * frost::Secp256K1ScalarField::random(&mut rng);
+        let other_point =
+            frost::Secp256K1Group::generator() * frost::Secp256K1ScalarField::random(&mut rng);
+
+        let pre_signature = adaptor_sign(
+            &key_packages,
+            &pubkey_package,
+            MESSAGE,
+            &adaptor_point,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(adaptor_verify(verifying_key, MESSAGE, &other_point, &pre_signature).is_err());
+        assert!(adaptor_verify(verifying_key, b"other", &adaptor_point, &pre_signature).is_err());
+    }
+
+    #[test]
+    fn completing_with_wrong_secret_gives_invalid_signature() {
+        let (key_packages, pubkey_package) =
+            dealer_keygen(MIN_SIGNERS, MAX_SIGNERS, &mut thread_rng()).unwrap();
+        let mut rng = thread_rng();
+
+        let adaptor_point =
+            frost::Secp256K1Group::generator() * frost::Secp256K1ScalarField::random(&mut rng);
+        let pre_signature = adaptor_sign(
+            &key_packages,
+            &pubkey_package,
+            MESSAGE,
+            &adaptor_point,
+            &mut rng,
+        )
+        .unwrap();
+
+        let wrong_secret = frost::Secp256K1ScalarField::random(&mut rng);
+        let signature = adaptor_complete(&pre_signature, &wrong_secret);
+        assert!(pubkey_package
+            .verifying_key()
+            .verify(MESSAGE, &signature)
+            .is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Evidence that a signature was produced by a threshold of the group.
+//!
+//! A FROST signature looks the same whether a quorum made it or someone
+//! holding the reconstructed group key did. An attestation keeps what only
+//! the ceremony produces: the signing package every signer committed to and
+//! each signer's share. A verifier checks every share against that
+//! participant's verifying share and that the shares aggregate to the
+//! signature, so a single key holder would have to forge shares for the
+//! verifying shares of at least a threshold of participants. The group also
+//! signs the whole attestation, so the claimed threshold can't be edited.
+
+use crate::error::AppError;
+use crate::{frost, quorum, Ceremony, KeyPackages};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const VERSION: u8 = 1;
+
+/// What the group signs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AttestationBody {
+    pub version: u8,
+    pub message_hex: String,
+    pub signature_hex: String,
+    pub threshold: u16,
+    pub signing_package_hex: String,
+    /// Signature shares by hex identifier.
+    pub shares: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Attestation {
+    #[serde(flatten)]
+    pub body: AttestationBody,
+    /// Group signature over the JSON of the body.
+    pub group_signature_hex: String,
+}
+
+/// The result of checking an attestation.
+#[derive(Debug)]
+pub struct AttestationCheck {
+    /// The signing package is over the attested message.
+    pub message_matches: bool,
+    /// The group signature over the body verifies.
+    pub body_signed: bool,
+    pub audit: quorum::QuorumAudit,
+}
+
+impl AttestationCheck {
+    pub fn passed(&self) -> bool {
+        self.message_matches && self.body_signed && self.audit.met()
+    }
+}
+
+impl AttestationBody {
+    fn from_ceremony(
+        ceremony: &Ceremony,
+        message: &[u8],
+        threshold: u16,
+    ) -> Result<Self, AppError> {
+        Ok(AttestationBody {
+            version: VERSION,
+            message_hex: hex::encode(message),
+            signature_hex: hex::encode(ceremony.signature.serialize()?),
+            threshold,
+            signing_package_hex: hex::encode(ceremony.signing_package.serialize()?),
+            shares: ceremony
+                .signature_shares
+                .iter()
+                .map(|(identifier, share)| {
+                    (
+                        hex::encode(identifier.serialize()),
+                        hex::encode(share.serialize()),
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// The bytes the group signs.
+    pub fn signed_bytes(&self) -> Result<Vec<u8>, AppError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+/// Signs `message` with `signers` and has the same signers attest to the
+/// ceremony.
+pub fn attest<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    message: &[u8],
+    rng: &mut R,
+) -> Result<Attestation, AppError> {
+    let (threshold, _) = crate::map_threshold(key_packages)?;
+    let ceremony = crate::run_ceremony(key_packages, pubkey_package, signers, message, rng)?;
+    let body = AttestationBody::from_ceremony(&ceremony, message, threshold)?;
+    let group_signature = crate::sign_message(
+        key_packages,
+        pubkey_package,
+        signers,
+        &body.signed_bytes()?,
+        rng,
+    )?;
+    Ok(Attestation {
+        body,
+        group_signature_hex: hex::encode(group_signature.serialize()?),
+    })
+}
+
+/// Checks `attestation` against the group's public key package.
+pub fn verify(
+    attestation: &Attestation,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+) -> Result<AttestationCheck, AppError> {
+    let body = &attestation.body;
+    if body.version != VERSION {
+        return Err(format!("unsupported attestation version {}", body.version).into());
+    }
+    let message = hex::decode(&body.message_hex)?;
+    let signature = frost::Signature::deserialize(&hex::decode(&body.signature_hex)?)?;
+    let signing_package =
+        frost::SigningPackage::deserialize(&hex::decode(&body.signing_package_hex)?)?;
+    let shares = body
+        .shares
+        .iter()
+        .map(|(identifier, share)| {
+            Ok((
+                frost::Identifier::deserialize(&hex::decode(identifier)?)?,
+                frost::round2::SignatureShare::deserialize(&hex::decode(share)?)?,
+            ))
+        })
+        .collect::<Result<BTreeMap<_, _>, AppError>>()?;
+
+    let group_signature =
+        frost::Signature::deserialize(&hex::decode(&attestation.group_signature_hex)?)?;
+    let body_signed = pubkey_package
+        .verifying_key()
+        .verify(&body.signed_bytes()?, &group_signature)
+        .is_ok();
+
+    Ok(AttestationCheck {
+        message_matches: signing_package.message() == &message,
+        body_signed,
+        audit: quorum::audit(
+            pubkey_package,
+            &signing_package,
+            &shares,
+            &signature,
+            body.threshold,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, default_signers, DEMO_MESSAGE};
+    use rand::thread_rng;
+
+    fn attestation() -> (Attestation, frost::keys::PublicKeyPackage) {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let attestation = attest(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            DEMO_MESSAGE,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        (attestation, pubkey_package)
+    }
+
+    #[test]
+    fn an_attestation_round_trips_and_verifies() {
+        let (attestation, pubkey_package) = attestation();
+        let json = serde_json::to_string(&attestation).unwrap();
+        let parsed: Attestation = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, attestation);
+        assert!(verify(&parsed, &pubkey_package).unwrap().passed());
+    }
+
+    #[test]
+    fn edited_attestations_fail() {
+        let (attestation, pubkey_package) = attestation();
+
+        let mut missing_share = attestation.clone();
+        let first = missing_share.body.shares.keys().next().unwrap().clone();
+        missing_share.body.shares.remove(&first);
+        assert!(!verify(&missing_share, &pubkey_package).unwrap().passed());
+
+        let mut lowered = attestation.clone();
+        lowered.body.threshold = 1;
+        let check = verify(&lowered, &pubkey_package).unwrap();
+        assert!(!check.body_signed);
+        assert!(!check.passed());
+
+        let (_, other_group) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        assert!(!verify(&attestation, &other_group).unwrap().passed());
+    }
+}
@@ -0,0 +1,460 @@
+//! Append-only JSONL log of signing ceremonies: who signed, what and with
+//! which result. It records only public values, never shares or nonces.
+//!
+//! A ceremony also records each signer's round 1 commitments, so
+//! [`nonce_reuse`] can find a commitment that appears in two sessions: a
+//! signer that used the same nonces twice has given its share away.
+//!
+//! Changes to the group's shares, threshold or participants go in the same
+//! log as [`QuorumChange`] entries. Each carries the old and new parameters
+//! and a group signature over them, made with the new shares, which
+//! [`verify_log`] checks against the group key.
+
+use crate::error::AppError;
+use crate::{frost, KeyPackages};
+use bitcoin::hashes::{sha256, Hash};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+/// One line of the audit log.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Unix timestamp, in seconds, of the end of the ceremony.
+    pub timestamp: u64,
+    /// Kind of signing operation, as passed to the policy hook.
+    pub kind: String,
+    /// Hex identifiers of the participants that signed.
+    pub participants: Vec<String>,
+    /// Hex SHA-256 of the signed message.
+    pub message_sha256: String,
+    pub signature_hex: String,
+    /// Hex round 1 commitments by hex identifier; absent from entries
+    /// written before they were recorded.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub commitments: BTreeMap<String, String>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        kind: &str,
+        signers: &[frost::Identifier],
+        message: &[u8],
+        signature: &frost::Signature,
+    ) -> Result<Self, AppError> {
+        Ok(AuditEntry {
+            timestamp: crate::report::unix_now(),
+            kind: kind.to_string(),
+            participants: signers
+                .iter()
+                .map(|identifier| hex::encode(identifier.serialize()))
+                .collect(),
+            message_sha256: sha256::Hash::hash(message).to_string(),
+            signature_hex: hex::encode(signature.serialize()?),
+            commitments: BTreeMap::new(),
+        })
+    }
+
+    /// Records the round 1 commitments of `signing_package`.
+    pub fn with_commitments(
+        mut self,
+        signing_package: &frost::SigningPackage,
+    ) -> Result<Self, AppError> {
+        self.commitments = commitments_hex(signing_package.signing_commitments())?;
+        Ok(self)
+    }
+}
+
+/// `commitments` as the log records them: hex commitments by hex identifier.
+pub fn commitments_hex(
+    commitments: &BTreeMap<frost::Identifier, frost::round1::SigningCommitments>,
+) -> Result<BTreeMap<String, String>, AppError> {
+    commitments
+        .iter()
+        .map(|(identifier, commitments)| {
+            Ok((
+                hex::encode(identifier.serialize()),
+                hex::encode(commitments.serialize()?),
+            ))
+        })
+        .collect()
+}
+
+/// The threshold and participants of the group, and a digest of its public
+/// key package, which changes with every share.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct QuorumParameters {
+    pub min_signers: u16,
+    /// Hex identifiers of the participants holding shares.
+    pub participants: Vec<String>,
+    /// Hex SHA-256 of the JSON public key package.
+    pub pubkey_package_sha256: String,
+}
+
+impl QuorumParameters {
+    /// The parameters of the group `key_packages` form.
+    pub fn of(key_packages: &KeyPackages) -> Result<Self, AppError> {
+        let (min_signers, _) = crate::map_threshold(key_packages)?;
+        let pubkey_package = crate::map_pubkey_package(key_packages)?;
+        Ok(QuorumParameters {
+            min_signers,
+            participants: key_packages
+                .keys()
+                .map(|identifier| hex::encode(identifier.serialize()))
+                .collect(),
+            pubkey_package_sha256: sha256::Hash::hash(&serde_json::to_vec(&pubkey_package)?)
+                .to_string(),
+        })
+    }
+}
+
+/// A line of the audit log recording a change to the group's shares,
+/// threshold or participants, attested by the group.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct QuorumChange {
+    /// Unix timestamp, in seconds, of the change.
+    pub timestamp: u64,
+    /// The operation that made the change, e.g. `"refresh"`.
+    pub operation: String,
+    /// Hex group verifying key, which the change keeps.
+    pub group_key: String,
+    pub old: QuorumParameters,
+    pub new: QuorumParameters,
+    /// Hex FROST signature of the group over [`QuorumChange::message`].
+    pub signature_hex: String,
+}
+
+impl QuorumChange {
+    /// Records `operation` changing the group from `old` to the shares of
+    /// `key_packages`, which sign the record as the group.
+    pub fn attest<R: RngCore + CryptoRng>(
+        operation: &str,
+        old: QuorumParameters,
+        key_packages: &KeyPackages,
+        pubkey_package: &frost::keys::PublicKeyPackage,
+        rng: &mut R,
+    ) -> Result<Self, AppError> {
+        let mut change = QuorumChange {
+            timestamp: crate::report::unix_now(),
+            operation: operation.to_string(),
+            group_key: hex::encode(pubkey_package.verifying_key().serialize()?),
+            old,
+            new: QuorumParameters::of(key_packages)?,
+            signature_hex: String::new(),
+        };
+        let signature = crate::sign_message(
+            key_packages,
+            pubkey_package,
+            &crate::default_signers(key_packages),
+            &change.message()?,
+            rng,
+        )?;
+        change.signature_hex = hex::encode(signature.serialize()?);
+        Ok(change)
+    }
+
+    /// What the group signs: every field but the signature, as JSON.
+    pub fn message(&self) -> Result<Vec<u8>, AppError> {
+        Ok(serde_json::to_vec(&serde_json::json!({
+            "timestamp": self.timestamp,
+            "operation": self.operation,
+            "group_key": self.group_key,
+            "old": self.old,
+            "new": self.new,
+        }))?)
+    }
+
+    /// Checks that `group_key` signed this record.
+    pub fn verify(&self, group_key: &frost::VerifyingKey) -> Result<(), AppError> {
+        if self.group_key != hex::encode(group_key.serialize()?) {
+            return Err(format!("it names group key {}", self.group_key).into());
+        }
+        let signature = frost::Signature::deserialize(&hex::decode(&self.signature_hex)?)?;
+        group_key
+            .verify(&self.message()?, &signature)
+            .map_err(|_| "the group signature doesn't verify".into())
+    }
+}
+
+/// A line of the audit log: a quorum change or a signing ceremony.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum LogEntry {
+    QuorumChange(QuorumChange),
+    Ceremony(AuditEntry),
+}
+
+/// What [`verify_log`] found.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LogCheck {
+    /// Quorum changes, each verified.
+    pub quorum_changes: usize,
+    /// Signing ceremonies, which record only a digest of their message.
+    pub ceremonies: usize,
+}
+
+/// Reads the audit log at `path`.
+pub fn read(path: &Path) -> Result<Vec<LogEntry>, AppError> {
+    std::fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|err| format!("{} line {}: {err}", path.display(), index + 1).into())
+        })
+        .collect()
+}
+
+/// Checks every quorum change in `entries` against `group_key`, and that
+/// each starts from the parameters the one before it left.
+pub fn verify_log(
+    entries: &[LogEntry],
+    group_key: &frost::VerifyingKey,
+) -> Result<LogCheck, AppError> {
+    let mut check = LogCheck {
+        quorum_changes: 0,
+        ceremonies: 0,
+    };
+    let mut current: Option<&QuorumParameters> = None;
+    for (index, entry) in entries.iter().enumerate() {
+        let change = match entry {
+            LogEntry::Ceremony(_) => {
+                check.ceremonies += 1;
+                continue;
+            }
+            LogEntry::QuorumChange(change) => change,
+        };
+        change
+            .verify(group_key)
+            .map_err(|err| format!("entry {}, {}: {err}", index + 1, change.operation))?;
+        if current.is_some_and(|current| *current != change.old) {
+            return Err(format!(
+                "entry {}, {}: its old parameters aren't those the previous change left",
+                index + 1,
+                change.operation
+            )
+            .into());
+        }
+        current = Some(&change.new);
+        check.quorum_changes += 1;
+    }
+    Ok(check)
+}
+
+/// A participant's round 1 commitments found in more than one session.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct NonceReuse {
+    /// Hex identifier of the participant.
+    pub participant: String,
+    /// Hex of the reused commitments.
+    pub commitments: String,
+    /// Every session they appear in.
+    pub sessions: Vec<String>,
+}
+
+/// The commitments each signing ceremony of `entries` recorded, labelled by
+/// their place in the log.
+pub fn logged_sessions(entries: &[LogEntry]) -> Vec<(String, BTreeMap<String, String>)> {
+    entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| match entry {
+            LogEntry::Ceremony(ceremony) if !ceremony.commitments.is_empty() => Some((
+                format!("audit log entry {} at {}", index + 1, ceremony.timestamp),
+                ceremony.commitments.clone(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Every commitment that appears in more than one of `sessions`, each a
+/// label and the commitments by participant it used.
+pub fn nonce_reuse(sessions: &[(String, BTreeMap<String, String>)]) -> Vec<NonceReuse> {
+    let mut seen: BTreeMap<(&str, &str), Vec<String>> = BTreeMap::new();
+    for (label, commitments) in sessions {
+        for (participant, commitment) in commitments {
+            seen.entry((participant, commitment))
+                .or_default()
+                .push(label.clone());
+        }
+    }
+    seen.into_iter()
+        .filter(|(_, sessions)| sessions.len() > 1)
+        .map(|((participant, commitments), sessions)| NonceReuse {
+            participant: participant.to_string(),
+            commitments: commitments.to_string(),
+            sessions,
+        })
+        .collect()
+}
+
+/// Appends `entry` to the log at `path` as one JSON line, creating the file
+/// if needed, and flushes it to disk.
+pub fn append<T: Serialize>(path: &Path, entry: &T) -> Result<(), AppError> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all((serde_json::to_string(entry)? + "\n").as_bytes())?;
+    file.flush()?;
+    file.sync_data()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, default_signers, sign_message};
+    use rand::thread_rng;
+
+    #[test]
+    fn entries_are_appended_without_secret_material() {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let signers = default_signers(&key_packages);
+        let path = std::env::temp_dir().join(format!("frost-audit-{}.jsonl", std::process::id()));
+        for message in [b"first".as_slice(), b"second"] {
+            let signature = sign_message(
+                &key_packages,
+                &pubkey_package,
+                &signers,
+                message,
+                &mut thread_rng(),
+            )
+            .unwrap();
+            append(
+                &path,
+                &AuditEntry::new("message", &signers, message, &signature).unwrap(),
+            )
+            .unwrap();
+        }
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let entries: Vec<AuditEntry> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[1].message_sha256,
+            sha256::Hash::hash(b"second").to_string()
+        );
+        assert_eq!(entries[0].participants.len(), signers.len());
+        for key_package in key_packages.values() {
+            assert!(!log.contains(&hex::encode(key_package.signing_share().serialize())));
+        }
+    }
+
+    #[test]
+    fn commitments_seen_in_two_sessions_are_reported() {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let signers = default_signers(&key_packages);
+        let ceremony = |message: &[u8]| {
+            let ceremony = crate::run_ceremony(
+                &key_packages,
+                &pubkey_package,
+                &signers,
+                message,
+                &mut thread_rng(),
+            )
+            .unwrap();
+            AuditEntry::new("message", &signers, message, &ceremony.signature)
+                .unwrap()
+                .with_commitments(&ceremony.signing_package)
+                .unwrap()
+        };
+        let first = ceremony(b"first");
+        let second = ceremony(b"second");
+        assert_eq!(first.commitments.len(), signers.len());
+
+        let entries = [LogEntry::Ceremony(first), LogEntry::Ceremony(second)];
+        let sessions = logged_sessions(&entries);
+        assert_eq!(sessions.len(), 2);
+        assert!(nonce_reuse(&sessions).is_empty());
+
+        // The second session reuses participant 1's nonces.
+        let mut reused = sessions.clone();
+        let (participant, commitment) = sessions[0].1.iter().next().unwrap();
+        reused[1].1.insert(participant.clone(), commitment.clone());
+        let found = nonce_reuse(&reused);
+        assert_eq!(found.len(), 1);
+        assert_eq!(&found[0].participant, participant);
+        assert_eq!(
+            found[0].sessions,
+            [sessions[0].0.clone(), sessions[1].0.clone()]
+        );
+    }
+
+    #[test]
+    fn quorum_changes_are_attested_by_the_group() {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let group_key = *pubkey_package.verifying_key();
+        let (refreshed, refreshed_pubkey) =
+            crate::refresh::refresh(&key_packages, &mut thread_rng()).unwrap();
+        let old = QuorumParameters::of(&key_packages).unwrap();
+        let change = QuorumChange::attest(
+            "refresh",
+            old.clone(),
+            &refreshed,
+            &refreshed_pubkey,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        assert_eq!(change.new.min_signers, 2);
+        assert_eq!(change.new.participants, old.participants);
+        assert_ne!(change.new.pubkey_package_sha256, old.pubkey_package_sha256);
+
+        let path =
+            std::env::temp_dir().join(format!("frost-quorum-audit-{}.jsonl", std::process::id()));
+        let signers = default_signers(&refreshed);
+        let signature = sign_message(
+            &refreshed,
+            &refreshed_pubkey,
+            &signers,
+            b"after",
+            &mut thread_rng(),
+        )
+        .unwrap();
+        append(&path, &change).unwrap();
+        append(
+            &path,
+            &AuditEntry::new("message", &signers, b"after", &signature).unwrap(),
+        )
+        .unwrap();
+        let entries = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            verify_log(&entries, &group_key).unwrap(),
+            LogCheck {
+                quorum_changes: 1,
+                ceremonies: 1,
+            }
+        );
+
+        let (_, other_group) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        assert!(verify_log(&entries, other_group.verifying_key()).is_err());
+        let mut tampered = entries;
+        if let LogEntry::QuorumChange(change) = &mut tampered[0] {
+            change.new.min_signers = 1;
+        }
+        assert!(verify_log(&tampered, &group_key).is_err());
+
+        // A second refresh must start where the first left off.
+        let (again, again_pubkey) = crate::refresh::refresh(&refreshed, &mut thread_rng()).unwrap();
+        let next = |old| {
+            LogEntry::QuorumChange(
+                QuorumChange::attest("refresh", old, &again, &again_pubkey, &mut thread_rng())
+                    .unwrap(),
+            )
+        };
+        let first = LogEntry::QuorumChange(change);
+        let chained = [first, next(QuorumParameters::of(&refreshed).unwrap())];
+        assert_eq!(verify_log(&chained, &group_key).unwrap().quorum_changes, 2);
+        let [first, _] = chained;
+        assert!(verify_log(&[first, next(old)], &group_key).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Micro-benchmarks for choosing how to store and run the group's key material.
+
+use crate::error::AppError;
+use crate::{default_signers, frost, sign_message, sign_messages, KeyPackages};
+use rand::thread_rng;
+use std::time::{Duration, Instant};
+
+/// Encodings a key-package map can be stored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Cbor,
+    Bincode,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Json, Format::Cbor, Format::Bincode];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Cbor => "cbor",
+            Format::Bincode => "bincode",
+        }
+    }
+
+    pub fn encode(self, key_packages: &KeyPackages) -> Result<Vec<u8>, AppError> {
+        Ok(match self {
+            Format::Json => serde_json::to_vec(key_packages)?,
+            Format::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(key_packages, &mut bytes)?;
+                bytes
+            }
+            Format::Bincode => bincode::serialize(key_packages)?,
+        })
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> Result<KeyPackages, AppError> {
+        Ok(match self {
+            Format::Json => serde_json::from_slice(bytes)?,
+            Format::Cbor => ciborium::from_reader(bytes)?,
+            Format::Bincode => bincode::deserialize(bytes)?,
+        })
+    }
+}
+
+/// Average timings and encoded size of one format for one group size.
+#[derive(Debug)]
+pub struct FormatSample {
+    pub signers: u16,
+    pub format: Format,
+    pub size: usize,
+    pub serialize: Duration,
+    pub deserialize: Duration,
+}
+
+/// Serializes and deserializes `key_packages` `iterations` times in every
+/// format, checking that each round trip is lossless.
+pub fn serialization(
+    key_packages: &KeyPackages,
+    iterations: u32,
+) -> Result<Vec<FormatSample>, AppError> {
+    let signers = key_packages.len().try_into()?;
+    let mut samples = Vec::new();
+    for format in Format::ALL {
+        let start = Instant::now();
+        let mut bytes = Vec::new();
+        for _ in 0..iterations {
+            bytes = format.encode(key_packages)?;
+        }
+        let serialize = start.elapsed() / iterations;
+
+        let start = Instant::now();
+        let mut decoded = KeyPackages::new();
+        for _ in 0..iterations {
+            decoded = format.decode(&bytes)?;
+        }
+        let deserialize = start.elapsed() / iterations;
+        if decoded != *key_packages {
+            return Err(format!("{} round trip changed the key packages", format.name()).into());
+        }
+
+        samples.push(FormatSample {
+            signers,
+            format,
+            size: bytes.len(),
+            serialize,
+            deserialize,
+        });
+    }
+    Ok(samples)
+}
+
+/// Times signing `messages` by reloading the keys with `load` for every
+/// message, against loading them once and signing the whole batch.
+pub fn batch_signing<L>(messages: &[&[u8]], load: L) -> Result<(Duration, Duration), AppError>
+where
+    L: Fn() -> Result<(KeyPackages, frost::keys::PublicKeyPackage), AppError>,
+{
+    let mut rng = thread_rng();
+
+    let start = Instant::now();
+    for message in messages {
+        let (key_packages, pubkey_package) = load()?;
+        sign_message(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            message,
+            &mut rng,
+        )?;
+    }
+    let naive = start.elapsed();
+
+    let start = Instant::now();
+    let (key_packages, pubkey_package) = load()?;
+    let signers = default_signers(&key_packages);
+    sign_messages(&key_packages, &pubkey_package, &signers, messages, &mut rng)?;
+    let cached = start.elapsed();
+
+    Ok((naive, cached))
+}
+
+/// Times `operations` consecutive operations that each obtain the keys with
+/// `load`, against loading them once and sharing them between the
+/// operations.
+pub fn key_reuse<L>(operations: u32, load: L) -> Result<(Duration, Duration), AppError>
+where
+    L: Fn() -> Result<(KeyPackages, frost::keys::PublicKeyPackage), AppError>,
+{
+    let start = Instant::now();
+    for _ in 0..operations {
+        std::hint::black_box(load()?);
+    }
+    let per_operation = start.elapsed();
+
+    let start = Instant::now();
+    let keys = load()?;
+    for _ in 0..operations {
+        std::hint::black_box(keys.clone());
+    }
+    let shared = start.elapsed();
+
+    Ok((per_operation, shared))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dealer_keygen;
+
+    #[test]
+    fn shared_keys_are_loaded_once() {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let loads = std::cell::Cell::new(0);
+        key_reuse(2, || {
+            loads.set(loads.get() + 1);
+            Ok((key_packages.clone(), pubkey_package.clone()))
+        })
+        .unwrap();
+        assert_eq!(loads.get(), 2 + 1);
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let (key_packages, _) = dealer_keygen(2, 4, &mut thread_rng()).unwrap();
+        for format in Format::ALL {
+            let bytes = format.encode(&key_packages).unwrap();
+            assert_eq!(
+                format.decode(&bytes).unwrap(),
+                key_packages,
+                "{}",
+                format.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! BIP322 "simple" message signing: the virtual `to_spend`/`to_sign`
+//! transactions and the taproot sighash a signer has to sign.
+
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::opcodes::all::OP_RETURN;
+use bitcoin::script::{Builder, ScriptBuf};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighash, TapSighashType};
+use bitcoin::transaction::Version;
+use bitcoin::{Amount, OutPoint, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+
+const MESSAGE_TAG: &[u8] = b"BIP0322-signed-message";
+
+/// `tagged_hash("BIP0322-signed-message", message)`.
+pub fn message_hash(message: &[u8]) -> [u8; 32] {
+    let tag = sha256::Hash::hash(MESSAGE_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(message);
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+/// The virtual transaction whose single output is the message challenge.
+pub fn to_spend(message_challenge: &ScriptBuf, message: &[u8]) -> Transaction {
+    let script_sig = Builder::new()
+        .push_int(0)
+        .push_slice(message_hash(message))
+        .into_script();
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0xFFFF_FFFF,
+            },
+            script_sig,
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: message_challenge.clone(),
+        }],
+    }
+}
+
+/// The virtual transaction spending `to_spend` whose witness carries the signature.
+pub fn to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: to_spend.compute_txid(),
+                vout: 0,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+/// The BIP341 key-path sighash (`SIGHASH_DEFAULT`) of the `to_sign` input
+/// for a taproot message challenge.
+pub fn taproot_sighash(
+    message_challenge: &ScriptBuf,
+    message: &[u8],
+) -> Result<TapSighash, bitcoin::sighash::TaprootError> {
+    let to_spend = to_spend(message_challenge, message);
+    let to_sign = to_sign(&to_spend);
+    SighashCache::new(&to_sign).taproot_key_spend_signature_hash(
+        0,
+        &Prevouts::All(&to_spend.output),
+        TapSighashType::Default,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Address;
+
+    // Test vectors from BIP322.
+    const ADDRESS: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+
+    fn challenge() -> ScriptBuf {
+        ADDRESS
+            .parse::<Address<_>>()
+            .unwrap()
+            .assume_checked()
+            .script_pubkey()
+    }
+
+    #[test]
+    fn message_hashes_match_bip322() {
+        assert_eq!(
+            hex::encode(message_hash(b"")),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            hex::encode(message_hash(b"Hello World")),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+    }
+
+    #[test]
+    fn virtual_transactions_match_bip322() {
+        let empty = to_spend(&challenge(), b"");
+        assert_eq!(
+            empty.compute_txid().to_string(),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        assert_eq!(
+            to_sign(&empty).compute_txid().to_string(),
+            "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"
+        );
+
+        let hello = to_spend(&challenge(), b"Hello World");
+        assert_eq!(
+            hello.compute_txid().to_string(),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+        assert_eq!(
+            to_sign(&hello).compute_txid().to_string(),
+            "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! What the commands ask a Bitcoin Core node about the chain: the group's
+//! unspent outputs, fee estimates and block times, over [`crate::rpc`].
+
+use crate::error::AppError;
+use crate::rpc::{RpcClient, RpcError};
+use bitcoin::{Address, Amount, OutPoint, Script, ScriptBuf, Transaction, TxOut};
+use serde_json::{json, Value};
+
+/// Confirmation targets, in blocks, to ask the node's fee estimator about.
+pub const CONFIRMATION_TARGETS: &[u16] = &[1, 2, 3, 6, 12, 24, 72, 144, 504, 1008];
+
+/// The node's fee estimate, in sat/vB, for confirming within `target`
+/// blocks; `None` while it lacks enough fee data.
+pub fn fee_estimate(client: &RpcClient, target: u16) -> Result<Option<f64>, RpcError> {
+    let estimate = client.call("estimatesmartfee", json!([target]))?;
+    // BTC/kvB to sat/vB.
+    Ok(estimate["feerate"]
+        .as_f64()
+        .map(|btc_per_kvb| btc_per_kvb * 100_000.0))
+}
+
+/// The soonest target whose estimated fee rate (sat/vB) is covered by `fee_rate`.
+pub fn confirmation_target(estimates: &[(u16, Option<f64>)], fee_rate: f64) -> Option<u16> {
+    estimates
+        .iter()
+        .filter_map(|(target, estimate)| Some((*target, (*estimate)?)))
+        .find(|(_, estimate)| fee_rate >= *estimate)
+        .map(|(target, _)| target)
+}
+
+/// The outpoints and amounts of every unspent output paying `address`, from
+/// a `scantxoutset` of the node's UTXO set.
+pub fn scan_utxos(
+    client: &RpcClient,
+    address: &Address,
+) -> Result<Vec<(OutPoint, Amount)>, AppError> {
+    let scan = client.call(
+        "scantxoutset",
+        json!(["start", [format!("addr({address})")]]),
+    )?;
+    scanned_utxos(&scan, &address.script_pubkey())
+}
+
+/// The outpoints and amounts of a `scantxoutset` result, all of which must
+/// pay to `script_pubkey`.
+pub fn scanned_utxos(
+    scan: &Value,
+    script_pubkey: &Script,
+) -> Result<Vec<(OutPoint, Amount)>, AppError> {
+    unspents(scan)?
+        .into_iter()
+        .map(|(script, outpoint, amount)| {
+            if script.as_script() != script_pubkey {
+                return Err(format!("scantxoutset returned an output paying {script}").into());
+            }
+            Ok((outpoint, amount))
+        })
+        .collect()
+}
+
+/// The number of unspent outputs and the balance of each of the first
+/// `scripts.len()` scripts of the ranged `descriptor`, from a `scantxoutset`
+/// of the node's UTXO set.
+pub fn scan_descriptor(
+    client: &RpcClient,
+    descriptor: &str,
+    scripts: &[ScriptBuf],
+) -> Result<Vec<(usize, Amount)>, AppError> {
+    let range = scripts.len().saturating_sub(1);
+    let scan = client.call(
+        "scantxoutset",
+        json!(["start", [{ "desc": descriptor.trim(), "range": [0, range] }]]),
+    )?;
+    script_balances(&scan, scripts)
+}
+
+/// The number of unspent outputs and the balance of each of `scripts` in a
+/// `scantxoutset` result, every output of which must pay one of them.
+pub fn script_balances(
+    scan: &Value,
+    scripts: &[ScriptBuf],
+) -> Result<Vec<(usize, Amount)>, AppError> {
+    let mut balances = vec![(0, Amount::ZERO); scripts.len()];
+    for (script, outpoint, amount) in unspents(scan)? {
+        let index = scripts
+            .iter()
+            .position(|derived| *derived == script)
+            .ok_or_else(|| format!("scantxoutset returned {outpoint} paying {script}"))?;
+        balances[index].0 += 1;
+        balances[index].1 += amount;
+    }
+    Ok(balances)
+}
+
+/// The script, outpoint and amount of every unspent output of a
+/// `scantxoutset` result.
+fn unspents(scan: &Value) -> Result<Vec<(ScriptBuf, OutPoint, Amount)>, AppError> {
+    let unspents = scan["unspents"]
+        .as_array()
+        .ok_or("scantxoutset returned no unspents")?;
+    unspents
+        .iter()
+        .map(|unspent| {
+            let script = ScriptBuf::from_hex(unspent["scriptPubKey"].as_str().unwrap_or_default())?;
+            let txid = unspent["txid"].as_str().unwrap_or_default();
+            let vout = unspent["vout"]
+                .as_u64()
+                .ok_or("an unspent output has no vout")?;
+            let outpoint = format!("{txid}:{vout}")
+                .parse()
+                .map_err(|err| format!("unspent output {txid}:{vout}: {err}"))?;
+            let amount = unspent["amount"]
+                .as_f64()
+                .map(Amount::from_btc)
+                .ok_or("an unspent output has no amount")?
+                .map_err(|err| format!("unspent output {outpoint}: {err}"))?;
+            Ok((script, outpoint, amount))
+        })
+        .collect()
+}
+
+/// The outputs `tx` spends, from the node's confirmed UTXO set, which still
+/// holds them while `tx` waits in the mempool.
+pub fn prevouts(client: &RpcClient, tx: &Transaction) -> Result<Vec<TxOut>, AppError> {
+    tx.input
+        .iter()
+        .map(|input| {
+            let outpoint = input.previous_output;
+            let txout = client.call(
+                "gettxout",
+                json!([outpoint.txid.to_string(), outpoint.vout, false]),
+            )?;
+            unspent_output(&txout)
+                .map_err(|err| format!("the node has no unspent output {outpoint}: {err}").into())
+        })
+        .collect()
+}
+
+/// The value and script of a `gettxout` result.
+pub fn unspent_output(txout: &Value) -> Result<TxOut, AppError> {
+    if txout.is_null() {
+        return Err("it is spent or unknown".into());
+    }
+    let script_pubkey =
+        ScriptBuf::from_hex(txout["scriptPubKey"]["hex"].as_str().unwrap_or_default())?;
+    let value = txout["value"]
+        .as_f64()
+        .map(Amount::from_btc)
+        .ok_or("the output has no value")?
+        .map_err(|err| err.to_string())?;
+    Ok(TxOut {
+        value,
+        script_pubkey,
+    })
+}
+
+/// Median time past of the block at `height`.
+pub fn median_time_at(client: &RpcClient, height: u32) -> Result<u32, AppError> {
+    let hash = client.call("getblockhash", json!([height]))?;
+    let header = client.call("getblockheader", json!([hash]))?;
+    Ok(header["mediantime"]
+        .as_u64()
+        .ok_or("block header without mediantime")?
+        .try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirmation_target_picks_the_soonest_covered_target() {
+        let estimates = [
+            (1, Some(30.0)),
+            (2, None),
+            (6, Some(12.5)),
+            (144, Some(2.0)),
+        ];
+        assert_eq!(confirmation_target(&estimates, 50.0), Some(1));
+        assert_eq!(confirmation_target(&estimates, 12.5), Some(6));
+        assert_eq!(confirmation_target(&estimates, 5.0), Some(144));
+        assert_eq!(confirmation_target(&estimates, 1.0), None);
+    }
+
+    #[test]
+    fn scanned_utxos_must_pay_the_group() {
+        let script_pubkey = ScriptBuf::from_hex(&format!("5120{}", "11".repeat(32))).unwrap();
+        let unspent = |script: &str| {
+            json!({
+                "txid": "22".repeat(32),
+                "vout": 1,
+                "scriptPubKey": script,
+                "amount": 0.0015,
+            })
+        };
+        let scan = json!({ "unspents": [unspent(&script_pubkey.to_hex_string())] });
+        let utxos = scanned_utxos(&scan, &script_pubkey).unwrap();
+        assert_eq!(utxos[0].0.vout, 1);
+        assert_eq!(utxos[0].1, Amount::from_sat(150_000));
+
+        let foreign = json!({ "unspents": [unspent("0014")] });
+        assert!(scanned_utxos(&foreign, &script_pubkey).is_err());
+    }
+
+    #[test]
+    fn descriptor_scans_add_up_per_script() {
+        let scripts: Vec<_> = ["11", "22", "33"]
+            .map(|byte| ScriptBuf::from_hex(&format!("5120{}", byte.repeat(32))).unwrap())
+            .to_vec();
+        let unspent = |script: &ScriptBuf, vout: u32, amount: f64| {
+            json!({
+                "txid": "44".repeat(32),
+                "vout": vout,
+                "scriptPubKey": script.to_hex_string(),
+                "amount": amount,
+            })
+        };
+        let scan = json!({ "unspents": [
+            unspent(&scripts[2], 0, 0.001),
+            unspent(&scripts[0], 1, 0.0005),
+            unspent(&scripts[2], 2, 0.002),
+        ] });
+        assert_eq!(
+            script_balances(&scan, &scripts).unwrap(),
+            [
+                (1, Amount::from_sat(50_000)),
+                (0, Amount::ZERO),
+                (2, Amount::from_sat(300_000)),
+            ]
+        );
+        assert!(script_balances(&scan, &scripts[..2]).is_err());
+    }
+
+    #[test]
+    fn unspent_outputs_are_read_from_gettxout() {
+        let script = format!("5120{}", "11".repeat(32));
+        let txout = json!({
+            "confirmations": 3,
+            "value": 0.0015,
+            "scriptPubKey": { "hex": script },
+        });
+        let output = unspent_output(&txout).unwrap();
+        assert_eq!(output.value, Amount::from_sat(150_000));
+        assert_eq!(output.script_pubkey.to_hex_string(), script);
+        assert!(unspent_output(&Value::Null).is_err());
+    }
+}
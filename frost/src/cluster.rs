@@ -0,0 +1,236 @@
+//! A shared-directory work queue for spreading signing jobs over workers.
+//!
+//! ```text
+//! <queue>/pending/<job>.hex   message to sign, hex-encoded
+//! <queue>/claimed/<job>.hex   moved here by the worker that claimed it
+//! <queue>/done/<job>.json     the worker's result
+//! ```
+//!
+//! Claiming is a `rename` from `pending` to `claimed`, which only one worker
+//! can win, so any number of processes or machines (sharing the directory
+//! over e.g. NFS) can drain the same queue. [`work`] is one worker's loop,
+//! and [`tally`] checks and counts what the workers signed.
+
+use crate::error::AppError;
+use crate::{frost, KeyPackages};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const PENDING: &str = "pending";
+const CLAIMED: &str = "claimed";
+const DONE: &str = "done";
+
+/// A claimed signing job.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Job {
+    pub name: String,
+    pub message: Vec<u8>,
+}
+
+/// What a worker reports for a finished job.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct JobResult {
+    pub job: String,
+    pub worker: String,
+    pub message_hex: String,
+    pub signature_hex: String,
+    /// Time spent signing, in microseconds.
+    pub micros: u64,
+}
+
+fn dir(queue: &Path, state: &str) -> PathBuf {
+    queue.join(state)
+}
+
+/// Creates the queue layout and adds one pending job per message.
+pub fn submit(queue: &Path, messages: &[&[u8]]) -> io::Result<()> {
+    for state in [PENDING, CLAIMED, DONE] {
+        fs::create_dir_all(dir(queue, state))?;
+    }
+    for (index, message) in messages.iter().enumerate() {
+        fs::write(
+            dir(queue, PENDING).join(format!("{index:06}.hex")),
+            hex::encode(message),
+        )?;
+    }
+    Ok(())
+}
+
+/// Claims the next pending job, or returns `None` once the queue is drained.
+pub fn claim_next(queue: &Path) -> Result<Option<Job>, AppError> {
+    let mut pending: Vec<_> = fs::read_dir(dir(queue, PENDING))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.file_name()))
+        .collect();
+    pending.sort();
+
+    for file_name in pending {
+        let claimed = dir(queue, CLAIMED).join(&file_name);
+        // Losing the race to another worker shows up as the source vanishing.
+        if fs::rename(dir(queue, PENDING).join(&file_name), &claimed).is_err() {
+            continue;
+        }
+        let name = Path::new(&file_name)
+            .file_stem()
+            .ok_or("job file without a name")?
+            .to_string_lossy()
+            .into_owned();
+        let message = hex::decode(fs::read_to_string(&claimed)?.trim())?;
+        return Ok(Some(Job { name, message }));
+    }
+    Ok(None)
+}
+
+/// Records the result of a claimed job.
+pub fn complete(queue: &Path, result: &JobResult) -> Result<(), AppError> {
+    let path = dir(queue, DONE).join(format!("{}.json", result.job));
+    fs::write(path, serde_json::to_vec_pretty(result)?)?;
+    Ok(())
+}
+
+/// All finished results, ordered by job.
+pub fn collect(queue: &Path) -> Result<Vec<JobResult>, AppError> {
+    let mut results = Vec::new();
+    for entry in fs::read_dir(dir(queue, DONE))? {
+        results.push(serde_json::from_slice(&fs::read(entry?.path())?)?);
+    }
+    results.sort_by(|a: &JobResult, b: &JobResult| a.job.cmp(&b.job));
+    Ok(results)
+}
+
+/// Claims and signs jobs with `signers` until the queue is drained,
+/// recording each result as `worker`, and returns how many it signed.
+pub fn work<R: RngCore + CryptoRng>(
+    queue: &Path,
+    worker: &str,
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    rng: &mut R,
+) -> Result<usize, AppError> {
+    let mut signed = 0;
+    while let Some(job) = claim_next(queue)? {
+        let start = std::time::Instant::now();
+        let signature =
+            crate::sign_message(key_packages, pubkey_package, signers, &job.message, rng)?;
+        complete(
+            queue,
+            &JobResult {
+                job: job.name,
+                worker: worker.to_string(),
+                message_hex: hex::encode(&job.message),
+                signature_hex: hex::encode(signature.serialize()?),
+                micros: start.elapsed().as_micros().try_into()?,
+            },
+        )?;
+        signed += 1;
+    }
+    Ok(signed)
+}
+
+/// Jobs and signing time per worker, and the jobs whose signature doesn't
+/// verify against the group key.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Tally {
+    /// Jobs and total microseconds spent signing them, by worker.
+    pub per_worker: BTreeMap<String, (usize, u64)>,
+    pub invalid: Vec<String>,
+}
+
+/// Verifies every result against `pubkey_package` and counts them by worker.
+pub fn tally(
+    results: &[JobResult],
+    pubkey_package: &frost::keys::PublicKeyPackage,
+) -> Result<Tally, AppError> {
+    let mut tally = Tally::default();
+    for result in results {
+        let signature = frost::Signature::deserialize(&hex::decode(&result.signature_hex)?)?;
+        let message = hex::decode(&result.message_hex)?;
+        if pubkey_package
+            .verifying_key()
+            .verify(&message, &signature)
+            .is_err()
+        {
+            tally.invalid.push(result.job.clone());
+        }
+        let entry = tally.per_worker.entry(result.worker.clone()).or_default();
+        entry.0 += 1;
+        entry.1 += result.micros;
+    }
+    Ok(tally)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue_dir(name: &str) -> PathBuf {
+        let queue =
+            std::env::temp_dir().join(format!("frost-cluster-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&queue);
+        queue
+    }
+
+    #[test]
+    fn jobs_are_claimed_once_and_collected() {
+        let queue = queue_dir("claim");
+        submit(&queue, &[b"first", b"second"]).unwrap();
+
+        let first = claim_next(&queue).unwrap().unwrap();
+        let second = claim_next(&queue).unwrap().unwrap();
+        assert_eq!(first.message, b"first");
+        assert_eq!(second.message, b"second");
+        assert_eq!(claim_next(&queue).unwrap(), None);
+
+        for job in [&second, &first] {
+            complete(
+                &queue,
+                &JobResult {
+                    job: job.name.clone(),
+                    worker: "w".to_string(),
+                    message_hex: hex::encode(&job.message),
+                    signature_hex: String::new(),
+                    micros: 1,
+                },
+            )
+            .unwrap();
+        }
+        let jobs: Vec<_> = collect(&queue)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.job)
+            .collect();
+        assert_eq!(jobs, vec![first.name, second.name]);
+        fs::remove_dir_all(&queue).unwrap();
+    }
+
+    #[test]
+    fn workers_drain_the_queue_and_are_tallied() {
+        let queue = queue_dir("work");
+        submit(&queue, &[b"first", b"second", b"third"]).unwrap();
+        let (key_packages, pubkey_package) =
+            crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let signers = crate::default_signers(&key_packages);
+
+        let signed = work(
+            &queue,
+            "w",
+            &key_packages,
+            &pubkey_package,
+            &signers,
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
+        assert_eq!(signed, 3);
+
+        let mut results = collect(&queue).unwrap();
+        results[1].message_hex = hex::encode(b"forged");
+        let tally = tally(&results, &pubkey_package).unwrap();
+        assert_eq!(tally.per_worker["w"].0, 3);
+        assert_eq!(tally.invalid, [results[1].job.clone()]);
+        fs::remove_dir_all(&queue).unwrap();
+    }
+}
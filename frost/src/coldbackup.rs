@@ -0,0 +1,92 @@
+//! Printable, encrypted break-glass backup of the reconstructed group secret.
+//!
+//! The group signing key is sealed with the encrypted environment file format
+//! (see [`crate::envfile`]) and followed by the first 4 bytes of its SHA-256,
+//! so a mistyped transcription is caught before decryption is attempted. The
+//! result is printed as hex in groups of four characters:
+//!
+//! ```text
+//! FROST COLD BACKUP v1
+//! 4652 4f53 5445 4e56 0117 ...
+//! ```
+//!
+//! Whoever holds the backup and its key controls the group's funds on their
+//! own, which defeats the point of splitting the key.
+
+use crate::error::AppError;
+use crate::{envfile, frost};
+use bitcoin::hashes::{sha256, Hash};
+
+const HEADER: &str = "FROST COLD BACKUP v1";
+const CHECKSUM_LEN: usize = 4;
+const GROUPS_PER_LINE: usize = 8;
+
+/// Encrypts `signing_key` under `key` into the printable backup format.
+pub fn export(key: &[u8; 32], signing_key: &frost::SigningKey) -> Result<String, AppError> {
+    let mut payload = envfile::encrypt(key, &signing_key.serialize())?;
+    let checksum = sha256::Hash::hash(&payload);
+    payload.extend_from_slice(&checksum[..CHECKSUM_LEN]);
+
+    let groups: Vec<_> = payload.chunks(2).map(hex::encode).collect();
+    let mut text = String::from(HEADER);
+    for line in groups.chunks(GROUPS_PER_LINE) {
+        text.push('\n');
+        text.push_str(&line.join(" "));
+    }
+    text.push('\n');
+    Ok(text)
+}
+
+/// Checks the checksum of a printed backup and decrypts it under `key`.
+pub fn restore(key: &[u8; 32], text: &str) -> Result<frost::SigningKey, AppError> {
+    let body = text
+        .trim_start()
+        .strip_prefix(HEADER)
+        .ok_or("not a FROST cold backup")?;
+    let digits: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+    let payload = hex::decode(digits)?;
+    if payload.len() <= CHECKSUM_LEN {
+        return Err("cold backup is truncated".into());
+    }
+
+    let (sealed, checksum) = payload.split_at(payload.len() - CHECKSUM_LEN);
+    if sha256::Hash::hash(sealed)[..CHECKSUM_LEN] != *checksum {
+        return Err("cold backup checksum mismatch; check the transcription".into());
+    }
+    let secret = envfile::decrypt(key, sealed)?;
+    Ok(frost::SigningKey::deserialize(&secret)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    const KEY: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn backup_restores_the_same_group_key() {
+        let signing_key = frost::SigningKey::new(&mut thread_rng());
+        let text = export(&KEY, &signing_key).unwrap();
+        assert!(text.starts_with(HEADER));
+
+        let restored = restore(&KEY, &text).unwrap();
+        assert_eq!(
+            frost::VerifyingKey::from(&restored),
+            frost::VerifyingKey::from(&signing_key)
+        );
+        assert!(restore(&[4u8; 32], &text).is_err());
+    }
+
+    #[test]
+    fn transcription_errors_fail_the_checksum() {
+        let signing_key = frost::SigningKey::new(&mut thread_rng());
+        let text = export(&KEY, &signing_key).unwrap();
+        let last_line = text.trim_end().rsplit('\n').next().unwrap();
+        let flipped = if last_line.starts_with('0') { "1" } else { "0" };
+        let typo = text.replacen(last_line, &format!("{flipped}{}", &last_line[1..]), 1);
+
+        let error = restore(&KEY, &typo).unwrap_err();
+        assert!(error.to_string().contains("checksum"));
+    }
+}
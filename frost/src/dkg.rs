@@ -0,0 +1,117 @@
+//! Distributed key generation, so no party ever holds the group secret.
+//!
+//! `generate` deals every share from one random secret, and `get_keys`
+//! splits `PRIVATE_KEY`, so one party knows the group key either way. In
+//! frost's DKG each participant instead picks its own random polynomial. In
+//! round 1 it broadcasts a commitment to it. In round 2 it sends every other
+//! participant that participant's evaluation. In round 3 it checks what it
+//! received against the commitments and sums it into its share. The group
+//! secret is the sum of every participant's constant term and is never
+//! computed anywhere.
+//!
+//! [`run`] plays every participant in one process. Each one's secret
+//! packages stay with it, and only the packages the protocol sends change
+//! hands. The key packages it returns have the dealer's format, so the
+//! resulting `my_map.json` signs unchanged.
+
+use crate::error::AppError;
+use crate::{frost, KeyPackages};
+use frost::keys::dkg::{self, round1, round2};
+use rand::{CryptoRng, RngCore};
+use std::collections::BTreeMap;
+
+/// What `identifier` receives in round 1: every other participant's broadcast.
+fn others(
+    broadcasts: &BTreeMap<frost::Identifier, round1::Package>,
+    identifier: &frost::Identifier,
+) -> BTreeMap<frost::Identifier, round1::Package> {
+    broadcasts
+        .iter()
+        .filter(|(sender, _)| *sender != identifier)
+        .map(|(sender, package)| (*sender, package.clone()))
+        .collect()
+}
+
+/// Runs the three DKG rounds for `identifiers` with threshold `min_signers`.
+pub fn run<R: RngCore + CryptoRng>(
+    min_signers: u16,
+    identifiers: &[frost::Identifier],
+    rng: &mut R,
+) -> Result<(KeyPackages, frost::keys::PublicKeyPackage), AppError> {
+    let max_signers = u16::try_from(identifiers.len())?;
+
+    let mut round1_secrets = BTreeMap::new();
+    let mut broadcasts = BTreeMap::new();
+    for identifier in identifiers {
+        let (secret, package) = dkg::part1(*identifier, max_signers, min_signers, &mut *rng)?;
+        round1_secrets.insert(*identifier, secret);
+        broadcasts.insert(*identifier, package);
+    }
+
+    let mut round2_secrets = BTreeMap::new();
+    let mut inboxes: BTreeMap<frost::Identifier, BTreeMap<frost::Identifier, round2::Package>> =
+        BTreeMap::new();
+    for (identifier, secret) in round1_secrets {
+        let (secret, packages) = dkg::part2(secret, &others(&broadcasts, &identifier))?;
+        for (recipient, package) in packages {
+            inboxes
+                .entry(recipient)
+                .or_default()
+                .insert(identifier, package);
+        }
+        round2_secrets.insert(identifier, secret);
+    }
+
+    let mut key_packages = KeyPackages::new();
+    let mut group: Option<frost::keys::PublicKeyPackage> = None;
+    for (identifier, secret) in &round2_secrets {
+        let inbox = inboxes.remove(identifier).unwrap_or_default();
+        let (key_package, pubkey_package) =
+            dkg::part3(secret, &others(&broadcasts, identifier), &inbox)?;
+        match &group {
+            Some(group) if *group != pubkey_package => {
+                return Err("the participants derived different public key packages".into())
+            }
+            Some(_) => {}
+            None => group = Some(pubkey_package),
+        }
+        key_packages.insert(*identifier, key_package);
+    }
+    let pubkey_package = group.ok_or("no participants")?;
+    Ok((key_packages, pubkey_package))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_signers, sign_message, validate_map, DEMO_MESSAGE};
+    use rand::thread_rng;
+
+    #[test]
+    fn dkg_key_packages_load_and_sign_like_dealt_ones() {
+        let identifiers: Vec<_> = (1..=3u16)
+            .map(|index| frost::Identifier::try_from(index).unwrap())
+            .collect();
+        let (key_packages, pubkey_package) = run(2, &identifiers, &mut thread_rng()).unwrap();
+
+        let reloaded: KeyPackages =
+            serde_json::from_str(&serde_json::to_string(&key_packages).unwrap()).unwrap();
+        validate_map(&reloaded, None).unwrap();
+        assert!(reloaded
+            .values()
+            .all(|key_package| key_package.verifying_key() == pubkey_package.verifying_key()));
+
+        let signature = sign_message(
+            &reloaded,
+            &pubkey_package,
+            &default_signers(&reloaded),
+            DEMO_MESSAGE,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        assert!(pubkey_package
+            .verifying_key()
+            .verify(DEMO_MESSAGE, &signature)
+            .is_ok());
+    }
+}
@@ -0,0 +1,161 @@
+//! Loading of the environment file, optionally encrypted at rest.
+//!
+//! An encrypted environment file (`.env.enc`) has the layout
+//!
+//! ```text
+//! magic "FROSTENV" (8 bytes) || version 0x01 (1 byte) || nonce (12 bytes) || ciphertext
+//! ```
+//!
+//! where the ciphertext is the plaintext `.env` contents sealed with
+//! ChaCha20-Poly1305 under a 32-byte key, with the magic and version bytes as
+//! associated data. When no `.env.enc` is present the plaintext `.env` is
+//! loaded as before.
+
+use crate::error::AppError;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+pub const ENCRYPTED_ENV_FILE: &str = ".env.enc";
+
+const MAGIC: &[u8; 8] = b"FROSTENV";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+const NONCE_LEN: usize = 12;
+
+/// Loads `.env.enc` if it exists, falling back to the plaintext `.env`.
+///
+/// Like `dotenv`, variables that are already set in the process environment
+/// take precedence over the file.
+pub fn load(env_key: Option<&str>) -> Result<(), AppError> {
+    if !Path::new(ENCRYPTED_ENV_FILE).exists() {
+        dotenv::dotenv().ok();
+        return Ok(());
+    }
+
+    let env_key = env_key.ok_or("found .env.enc but no --env-key was given to decrypt it")?;
+    let plaintext = decrypt(&parse_key(env_key)?, &fs::read(ENCRYPTED_ENV_FILE)?)?;
+    for (name, value) in parse_env(std::str::from_utf8(&plaintext)?) {
+        if env::var_os(&name).is_none() {
+            env::set_var(name, value);
+        }
+    }
+    Ok(())
+}
+
+/// Parses a 32-byte hex-encoded key.
+pub fn parse_key(env_key: &str) -> Result<[u8; 32], AppError> {
+    hex::decode(env_key.trim())?
+        .try_into()
+        .map_err(|_| "--env-key must be 32 bytes of hex".into())
+}
+
+pub fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, AppError> {
+    let mut header = MAGIC.to_vec();
+    header.push(VERSION);
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &header,
+            },
+        )
+        .map_err(|_| "failed to encrypt the environment file")?;
+
+    let mut sealed = header;
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+pub fn decrypt(key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>, AppError> {
+    if sealed.len() < HEADER_LEN + NONCE_LEN || &sealed[..MAGIC.len()] != MAGIC {
+        return Err("not an encrypted environment file".into());
+    }
+    if sealed[MAGIC.len()] != VERSION {
+        return Err(format!(
+            "unsupported encrypted environment file version {}",
+            sealed[MAGIC.len()]
+        )
+        .into());
+    }
+    let (header, rest) = sealed.split_at(HEADER_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| "failed to decrypt the environment file (wrong key or corrupted file)")?;
+    Ok(plaintext)
+}
+
+/// Parses `KEY=VALUE` lines, skipping blanks and `#` comments. An optional
+/// `export ` prefix and matching surrounding quotes are stripped.
+fn parse_env(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (name, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            Some((name.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let plaintext = b"PRIVATE_KEY=00112233\n";
+        let sealed = encrypt(&KEY, plaintext).unwrap();
+        assert_eq!(&sealed[..MAGIC.len()], MAGIC);
+        assert_eq!(decrypt(&KEY, &sealed).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn decrypt_rejects_wrong_key_and_tampering() {
+        let mut sealed = encrypt(&KEY, b"PRIVATE_KEY=00112233\n").unwrap();
+        assert!(decrypt(&[8u8; 32], &sealed).is_err());
+
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(decrypt(&KEY, &sealed).is_err());
+    }
+
+    #[test]
+    fn parse_env_handles_comments_quotes_and_export() {
+        let parsed =
+            parse_env("# secret\nexport PRIVATE_KEY=\"abcd\"\n\nRUST_LOG='info'\nBAD LINE\n");
+        assert_eq!(
+            parsed,
+            vec![
+                ("PRIVATE_KEY".to_string(), "abcd".to_string()),
+                ("RUST_LOG".to_string(), "info".to_string()),
+            ]
+        );
+    }
+}
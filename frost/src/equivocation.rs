@@ -0,0 +1,120 @@
+//! Simulation of a coordinator that equivocates.
+//!
+//! The coordinator may hand different signers different `SigningPackage`s,
+//! e.g. different messages, hoping to collect shares it can misuse. Before
+//! round 2 each signer echoes a digest of the package it received to every
+//! other signer, and nobody signs unless all digests agree.
+
+use crate::error::AppError;
+use crate::{commit_round1, frost, KeyPackages};
+use bitcoin::hashes::{sha256, Hash};
+use rand::{CryptoRng, RngCore};
+use std::collections::BTreeMap;
+
+/// What came out of a simulated ceremony.
+#[derive(Debug)]
+pub enum Outcome {
+    /// All signers saw the same package and the group signed it.
+    Signed(frost::Signature),
+    /// The echo step found diverging views; signers holding each package digest.
+    Detected(BTreeMap<sha256::Hash, Vec<frost::Identifier>>),
+}
+
+/// Runs a ceremony over `message` in which, if `equivocate` is set, the
+/// coordinator sends `other_message` to the last signer instead.
+pub fn simulate<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    message: &[u8],
+    other_message: &[u8],
+    equivocate: bool,
+    rng: &mut R,
+) -> Result<Outcome, AppError> {
+    let (nonces_map, commitments_map) = commit_round1(key_packages, signers, rng)?;
+    let honest_package = frost::SigningPackage::new(commitments_map.clone(), message);
+    let forged_package = frost::SigningPackage::new(commitments_map, other_message);
+    let victim = signers.last().ok_or("no signers")?;
+
+    let mut received = BTreeMap::new();
+    for identifier in signers {
+        let package = if equivocate && identifier == victim {
+            &forged_package
+        } else {
+            &honest_package
+        };
+        received.insert(*identifier, package);
+    }
+
+    // Echo step: every signer broadcasts the digest of what it received.
+    let mut views: BTreeMap<sha256::Hash, Vec<frost::Identifier>> = BTreeMap::new();
+    for (identifier, package) in &received {
+        let digest = sha256::Hash::hash(&package.serialize()?);
+        views.entry(digest).or_default().push(*identifier);
+    }
+    if views.len() > 1 {
+        return Ok(Outcome::Detected(views));
+    }
+
+    let mut signature_shares = BTreeMap::new();
+    for (identifier, package) in &received {
+        let share =
+            frost::round2::sign(package, &nonces_map[identifier], &key_packages[identifier])?;
+        signature_shares.insert(*identifier, share);
+    }
+    let signature = frost::aggregate(&honest_package, &signature_shares, pubkey_package)?;
+    Ok(Outcome::Signed(signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, default_signers, DEMO_MESSAGE, MAX_SIGNERS, MIN_SIGNERS};
+    use rand::thread_rng;
+
+    #[test]
+    fn honest_coordinator_gets_a_signature() {
+        let (key_packages, pubkey_package) =
+            dealer_keygen(MIN_SIGNERS, MAX_SIGNERS, &mut thread_rng()).unwrap();
+        let outcome = simulate(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            DEMO_MESSAGE,
+            b"pay the coordinator",
+            false,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        let Outcome::Signed(signature) = outcome else {
+            panic!("honest ceremony was aborted");
+        };
+        pubkey_package
+            .verifying_key()
+            .verify(DEMO_MESSAGE, &signature)
+            .unwrap();
+    }
+
+    #[test]
+    fn equivocation_is_detected_before_signing() {
+        let (key_packages, pubkey_package) =
+            dealer_keygen(MIN_SIGNERS, MAX_SIGNERS, &mut thread_rng()).unwrap();
+        let signers = default_signers(&key_packages);
+        let outcome = simulate(
+            &key_packages,
+            &pubkey_package,
+            &signers,
+            DEMO_MESSAGE,
+            b"pay the coordinator",
+            true,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        let Outcome::Detected(views) = outcome else {
+            panic!("equivocation went unnoticed");
+        };
+        let mut groups: Vec<_> = views.into_values().collect();
+        groups.sort_by_key(Vec::len);
+        assert_eq!(groups, vec![signers[2..].to_vec(), signers[..2].to_vec()]);
+    }
+}
@@ -0,0 +1,125 @@
+//! The error type every command returns.
+
+use crate::frost;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AppError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Frost(#[from] frost::Error),
+    /// From the BIP340 ciphersuite that signs taproot key-path spends.
+    #[error(transparent)]
+    Taproot(#[from] frost_secp256k1_tr::Error),
+    /// Any error from the `bitcoin` or `miniscript` crates.
+    #[error(transparent)]
+    Bitcoin(Box<dyn std::error::Error + Send + Sync>),
+    #[error(transparent)]
+    Hex(#[from] hex::FromHexError),
+    /// Malformed input in any of the other encodings: CBOR, bincode, bech32,
+    /// UTF-8 or fixed-size byte strings.
+    #[error(transparent)]
+    Encoding(Box<dyn std::error::Error + Send + Sync>),
+    #[cfg(feature = "rpc")]
+    #[error(transparent)]
+    Rpc(#[from] crate::rpc::RpcError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("environment variable {0} is not set")]
+    MissingEnv(&'static str),
+    #[error("invalid threshold: {0}")]
+    InvalidThreshold(String),
+    /// A key-package map that breaks an invariant `generate` guarantees.
+    #[error("inconsistent key file: {0}")]
+    InvalidKeyFile(String),
+    /// Signature shares that don't verify against their participant's
+    /// verifying share, naming every participant that sent one.
+    #[error(
+        "invalid signature shares from participants {}",
+        .0.iter().map(crate::identifier_label).collect::<Vec<_>>().join(",")
+    )]
+    InvalidShares(Vec<frost::Identifier>),
+    /// Failures with nothing to match on beyond their message.
+    #[error("{0}")]
+    Other(String),
+}
+
+impl AppError {
+    /// The process exit status for this error: 2 for missing or invalid
+    /// configuration, 3 for I/O failures, 4 for RPC failures and 1 for
+    /// everything else, including signatures that don't verify.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::MissingEnv(_)
+            | AppError::InvalidThreshold(_)
+            | AppError::InvalidKeyFile(_) => 2,
+            AppError::Io(_) => 3,
+            #[cfg(feature = "rpc")]
+            AppError::Rpc(_) => 4,
+            _ => 1,
+        }
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Other(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Other(message.to_string())
+    }
+}
+
+impl From<frost::FieldError> for AppError {
+    fn from(err: frost::FieldError) -> Self {
+        AppError::Frost(err.into())
+    }
+}
+
+impl From<frost::GroupError> for AppError {
+    fn from(err: frost::GroupError) -> Self {
+        AppError::Frost(err.into())
+    }
+}
+
+macro_rules! boxed_from {
+    ($variant:ident: $($error:ty),* $(,)?) => {
+        $(
+            impl From<$error> for AppError {
+                fn from(err: $error) -> Self {
+                    AppError::$variant(Box::new(err))
+                }
+            }
+        )*
+    };
+}
+
+boxed_from!(
+    Bitcoin: bitcoin::address::ParseError,
+    bitcoin::consensus::encode::FromHexError,
+    bitcoin::hex::HexToBytesError,
+    bitcoin::psbt::Error,
+    bitcoin::psbt::ExtractTxError,
+    bitcoin::psbt::PsbtParseError,
+    bitcoin::psbt::SignError,
+    bitcoin::secp256k1::Error,
+    bitcoin::sighash::TaprootError,
+    bitcoin::taproot::TaprootBuilderError,
+    miniscript::Error,
+    miniscript::descriptor::ConversionError,
+);
+
+boxed_from!(
+    Encoding: bincode::Error,
+    ciborium::de::Error<std::io::Error>,
+    ciborium::ser::Error<std::io::Error>,
+    bech32::DecodeError,
+    bech32::EncodeError,
+    std::array::TryFromSliceError,
+    std::num::TryFromIntError,
+    std::str::Utf8Error,
+);
@@ -0,0 +1,87 @@
+//! Passphrase encryption of the key-package map at rest.
+//!
+//! An encrypted key file has the layout
+//!
+//! ```text
+//! magic "FROSTKEY" (8 bytes) || version 0x01 (1 byte) || salt (16 bytes) || sealed
+//! ```
+//!
+//! where `sealed` is the JSON map in the encrypted environment file format
+//! (see [`crate::envfile`]), under a key derived from the passphrase and the
+//! salt with Argon2id. A file without the magic is read as plaintext JSON, so
+//! key files written before encryption keep loading.
+
+use crate::envfile;
+use crate::error::AppError;
+use argon2::Argon2;
+use rand::{thread_rng, RngCore};
+use zeroize::Zeroizing;
+
+const MAGIC: &[u8; 8] = b"FROSTKEY";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN;
+
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, AppError> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|err| format!("cannot derive the key file key: {err}"))?;
+    Ok(key)
+}
+
+pub fn encrypt(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>, AppError> {
+    if passphrase.is_empty() {
+        return Err("the key file passphrase is empty".into());
+    }
+    let mut salt = [0u8; SALT_LEN];
+    thread_rng().fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+
+    let mut sealed = MAGIC.to_vec();
+    sealed.push(VERSION);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&envfile::encrypt(&key, plaintext)?);
+    Ok(sealed)
+}
+
+pub fn decrypt(passphrase: &str, sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>, AppError> {
+    if sealed.len() < HEADER_LEN || !is_encrypted(sealed) {
+        return Err("not an encrypted key file".into());
+    }
+    if sealed[MAGIC.len()] != VERSION {
+        return Err(format!(
+            "unsupported encrypted key file version {}",
+            sealed[MAGIC.len()]
+        )
+        .into());
+    }
+    let salt = &sealed[MAGIC.len() + 1..HEADER_LEN];
+    let key = derive_key(passphrase, salt)?;
+    envfile::decrypt(&key, &sealed[HEADER_LEN..])
+        .map(Zeroizing::new)
+        .map_err(|_| "cannot decrypt the key file: wrong passphrase or corrupted file".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_map_round_trips_only_under_its_passphrase() {
+        let sealed = encrypt("correct horse", b"{}").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!is_encrypted(b"{}"));
+        assert_eq!(decrypt("correct horse", &sealed).unwrap().as_slice(), b"{}");
+        assert!(decrypt("wrong", &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        tampered[MAGIC.len() + 1] ^= 1;
+        assert!(decrypt("correct horse", &tampered).is_err());
+        assert!(encrypt("", b"{}").is_err());
+    }
+}
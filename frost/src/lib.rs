@@ -0,0 +1,1502 @@
+//! FROST threshold signing of Bitcoin taproot keys, as a library.
+//!
+//! Everything here takes its keys, paths and passphrases as parameters. The
+//! `frost` binary reads those from the command line and the environment and
+//! calls into this crate.
+
+pub mod adaptor;
+pub mod attestation;
+pub mod audit;
+pub mod bench;
+pub mod bip322;
+pub mod blind;
+#[cfg(feature = "rpc")]
+pub mod chain;
+pub mod cluster;
+pub mod coldbackup;
+pub mod dkg;
+pub mod envfile;
+pub mod equivocation;
+pub mod error;
+pub mod fixtures;
+pub mod keystore;
+pub mod nostr;
+pub mod output;
+pub mod policy;
+pub mod psbt;
+pub mod quorum;
+pub mod refresh;
+pub mod repl;
+pub mod report;
+pub mod retry;
+pub mod rounds;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod secret;
+pub mod selection;
+pub mod spend;
+pub mod taproot;
+#[cfg(feature = "rpc")]
+pub mod timelock;
+pub mod wire;
+
+pub use frost_secp256k1 as frost;
+pub use secret::KeyPackages;
+
+use bitcoin::address::Address;
+use bitcoin::hashes::Hash;
+use bitcoin::key::UntweakedPublicKey;
+use bitcoin::network::Network;
+use error::AppError;
+use log::error;
+use rand::seq::SliceRandom;
+use rand::{CryptoRng, RngCore};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::prelude::*;
+use zeroize::Zeroize;
+
+pub const MAX_SIGNERS: u16 = 5;
+
+pub const MIN_SIGNERS: u16 = 3;
+
+/// Message signed by the `Verify` demo ceremony.
+pub const DEMO_MESSAGE: &[u8] =
+    b"0x68c158664c20d9d7df31a747782bcc9d36d1f595c36184ee0fc62627e2a72fc0";
+
+pub fn metadata_file_for(key_file: &std::path::Path) -> std::path::PathBuf {
+    key_file.with_extension("meta.json")
+}
+
+/// `path` with `_<index>` appended to its file stem: my_map.json becomes
+/// my_map_0.json.
+pub fn numbered_path(path: &std::path::Path, index: u32) -> std::path::PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}_{index}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_{index}"),
+    };
+    path.with_file_name(name)
+}
+
+/// Each signer's secret nonces and the commitments sent to the coordinator.
+pub type Round1 = (
+    BTreeMap<frost::Identifier, frost::round1::SigningNonces>,
+    BTreeMap<frost::Identifier, frost::round1::SigningCommitments>,
+);
+
+/// Creates one of the files `generate` writes, explaining a missing directory.
+pub fn create_key_artifact(path: &std::path::Path) -> Result<File, AppError> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        if !parent.is_dir() {
+            let err = format!(
+                "cannot write {}: directory {} does not exist",
+                path.display(),
+                parent.display()
+            );
+            error!("{}", err);
+            return Err(err.into());
+        }
+    }
+    File::create(path).map_err(|err| format!("cannot write {}: {}", path.display(), err).into())
+}
+
+/// Creates a file for a secret that isn't encrypted, readable only by its
+/// owner on unix. The mode only applies to a file that `open` creates, so an
+/// existing file's permissions are narrowed before anything is written.
+pub fn create_secret_file(path: &std::path::Path) -> std::io::Result<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options.open(path)?;
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    Ok(file)
+}
+
+/// Opens one of the files `generate` wrote, naming it in the error.
+pub fn open_key_artifact(path: &std::path::Path) -> Result<File, AppError> {
+    File::open(path).map_err(|err| format!("cannot read {}: {}", path.display(), err).into())
+}
+
+/// [`dealer_keygen_with`] with the default identifiers 1..=n.
+pub fn dealer_keygen<R: RngCore + CryptoRng>(
+    min_signers: u16,
+    max_signers: u16,
+    rng: &mut R,
+) -> Result<(KeyPackages, frost::keys::PublicKeyPackage), AppError> {
+    dealer_keygen_with(
+        min_signers,
+        max_signers,
+        frost::keys::IdentifierList::Default,
+        rng,
+    )
+}
+
+/// Runs the trusted dealer keygen. The shares depend only on `rng`, so a seeded
+/// RNG makes the ceremony reproducible.
+pub fn dealer_keygen_with<R: RngCore + CryptoRng>(
+    min_signers: u16,
+    max_signers: u16,
+    identifiers: frost::keys::IdentifierList,
+    rng: &mut R,
+) -> Result<(KeyPackages, frost::keys::PublicKeyPackage), AppError> {
+    let (shares, pubkey_package) =
+        frost::keys::generate_with_dealer(max_signers, min_signers, identifiers, rng)?;
+
+    // Verifies the secret shares from the dealer and store them in a BTreeMap.
+    // In practice, the KeyPackages must be sent to its respective participants
+    // through a confidential and authenticated channel.
+    let mut key_packages = KeyPackages::new();
+
+    for (identifier, secret_share) in shares {
+        let key_package = frost::keys::KeyPackage::try_from(secret_share)?;
+        key_packages.insert(identifier, key_package);
+    }
+    Ok((key_packages, pubkey_package))
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct KeyMetadata {
+    /// SHA-256 of the RNG seed, for keys generated with `--rng-seed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed_commitment: Option<String>,
+    /// The participants' identifiers, for keys generated with `--dealer-identifiers`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifiers: Option<Vec<frost::Identifier>>,
+    /// The group's key-path address when the keys were generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taproot_address: Option<String>,
+    /// The signing threshold, for a coordinator that holds no key packages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_signers: Option<u16>,
+}
+
+pub fn read_metadata(key_file: &std::path::Path) -> Result<KeyMetadata, AppError> {
+    let mut contents = String::new();
+    open_key_artifact(&metadata_file_for(key_file))?.read_to_string(&mut contents)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// The identifiers `generate` recorded for `key_file`, unless they are the
+/// default 1..=n.
+pub fn recorded_identifiers(
+    key_file: &std::path::Path,
+) -> Result<Option<Vec<frost::Identifier>>, AppError> {
+    if !metadata_file_for(key_file).exists() {
+        return Ok(None);
+    }
+    Ok(read_metadata(key_file)?.identifiers)
+}
+
+/// The address `generate` recorded for `key_file`, if any.
+pub fn recorded_address(key_file: &std::path::Path) -> Result<Option<String>, AppError> {
+    if !metadata_file_for(key_file).exists() {
+        return Ok(None);
+    }
+    Ok(read_metadata(key_file)?.taproot_address)
+}
+
+/// The threshold `generate` recorded for `key_file`, if any.
+pub fn recorded_threshold(key_file: &std::path::Path) -> Result<Option<u16>, AppError> {
+    if !metadata_file_for(key_file).exists() {
+        return Ok(None);
+    }
+    Ok(read_metadata(key_file)?.min_signers)
+}
+
+pub fn identifier_list(
+    identifiers: &Option<Vec<frost::Identifier>>,
+) -> frost::keys::IdentifierList<'_> {
+    match identifiers {
+        Some(identifiers) => frost::keys::IdentifierList::Custom(identifiers),
+        None => frost::keys::IdentifierList::Default,
+    }
+}
+
+/// Parses a participant given on the command line: a number is that
+/// identifier, anything else is a participant name hashed into one.
+pub fn parse_identifier(value: &str) -> Result<frost::Identifier, AppError> {
+    let value = value.trim();
+    Ok(match value.parse::<u16>() {
+        Ok(index) => frost::Identifier::try_from(index)?,
+        Err(_) => frost::Identifier::derive(value.as_bytes())?,
+    })
+}
+
+/// Parses `--dealer-identifiers` or `dkg --identifiers`, see
+/// [`parse_identifier`].
+pub fn parse_dealer_identifiers(
+    values: &[String],
+    max_signers: u16,
+) -> Result<Vec<frost::Identifier>, AppError> {
+    if values.len() != usize::from(max_signers) {
+        return Err(format!(
+            "{} identifiers for {} participants",
+            values.len(),
+            max_signers
+        )
+        .into());
+    }
+    let mut identifiers = Vec::new();
+    for value in values {
+        let identifier = parse_identifier(value)?;
+        if identifiers.contains(&identifier) {
+            return Err(format!("identifier {value:?} is given twice").into());
+        }
+        identifiers.push(identifier);
+    }
+    Ok(identifiers)
+}
+
+pub fn parse_rng_seed(seed_hex: &str) -> Result<[u8; 32], AppError> {
+    hex::decode(seed_hex)?
+        .try_into()
+        .map_err(|_| "the RNG seed must be 32 bytes of hex".into())
+}
+
+pub fn parse_merkle_root(merkle_root_hex: &str) -> Result<bitcoin::TapNodeHash, AppError> {
+    let bytes: [u8; 32] = hex::decode(merkle_root_hex)?
+        .try_into()
+        .map_err(|_| "the merkle root must be 32 bytes of hex")?;
+    Ok(bitcoin::TapNodeHash::from_byte_array(bytes))
+}
+
+/// SHA-256 of the little-endian bytes of a `--rng-seed`.
+pub fn seed_commitment(seed: u64) -> [u8; 32] {
+    bitcoin::hashes::sha256::Hash::hash(&seed.to_le_bytes()).to_byte_array()
+}
+
+/// Checks a t-of-n configuration before any shares are dealt.
+pub fn validate_threshold(min_signers: u16, max_signers: u16) -> Result<(), AppError> {
+    // frost-core itself rejects a threshold of 1, which would make every
+    // share a copy of the group key.
+    if min_signers < 2 {
+        return Err(AppError::InvalidThreshold(format!(
+            "--min-signers must be at least 2, got {min_signers}"
+        )));
+    }
+    if min_signers > max_signers {
+        return Err(AppError::InvalidThreshold(format!(
+            "--min-signers ({min_signers}) must not exceed --max-signers ({max_signers})"
+        )));
+    }
+    Ok(())
+}
+
+/// The t-of-n configuration of a key-package map. Every `KeyPackage` records
+/// t, and the map holds one package per participant.
+pub fn map_threshold(key_packages: &KeyPackages) -> Result<(u16, u16), AppError> {
+    let mut thresholds = key_packages
+        .values()
+        .map(|key_package| *key_package.min_signers());
+    let min_signers = thresholds.next().ok_or("no key packages")?;
+    if thresholds.any(|threshold| threshold != min_signers) {
+        return Err("the key packages disagree on the threshold".into());
+    }
+    Ok((min_signers, key_packages.len().try_into()?))
+}
+
+/// The public key package of a key-package map that holds every participant.
+pub fn map_pubkey_package(
+    key_packages: &KeyPackages,
+) -> Result<frost::keys::PublicKeyPackage, AppError> {
+    let first = key_packages.values().next().ok_or("no key packages")?;
+    let verifying_shares = key_packages
+        .iter()
+        .map(|(identifier, key_package)| (*identifier, *key_package.verifying_share()))
+        .collect();
+    Ok(frost::keys::PublicKeyPackage::new(
+        verifying_shares,
+        *first.verifying_key(),
+    ))
+}
+
+/// Loads and validates the key-package map at `path`, decrypting it with
+/// `passphrase` if it is encrypted.
+pub fn load_map(path: &std::path::Path, passphrase: Option<&str>) -> Result<KeyPackages, AppError> {
+    let my_map = read_map(path, passphrase)?;
+    validate_map(&my_map, recorded_identifiers(path)?.as_deref())?;
+    Ok(my_map)
+}
+
+/// Reads the key-package map at `path` like [`load_map`], but leaves
+/// validating it to the caller.
+pub fn read_map(path: &std::path::Path, passphrase: Option<&str>) -> Result<KeyPackages, AppError> {
+    // 从文件中读取 JSON 字符串
+    let mut file = open_key_artifact(path)?;
+    let mut contents = zeroize::Zeroizing::new(Vec::new());
+    file.read_to_end(&mut contents)?;
+    if keystore::is_encrypted(&contents) {
+        let passphrase = passphrase.ok_or_else(|| {
+            format!(
+                "{} is encrypted; pass --passphrase or KEY_PASSPHRASE",
+                path.display()
+            )
+        })?;
+        contents = keystore::decrypt(passphrase, &contents)?;
+    }
+
+    // 反序列化 JSON 字符串为 BTreeMap
+    Ok(serde_json::from_slice(&contents)?)
+}
+
+/// The identifiers of a key-package map, unless they are the default 1..=n
+/// that `generate` leaves unrecorded.
+pub fn inferred_identifiers(key_packages: &KeyPackages) -> Option<Vec<frost::Identifier>> {
+    let default = (1..=key_packages.len())
+        .map(|index| {
+            u16::try_from(index)
+                .ok()
+                .and_then(|index| index.try_into().ok())
+        })
+        .collect::<Option<Vec<frost::Identifier>>>();
+    let identifiers: Vec<_> = key_packages.keys().copied().collect();
+    (default.as_ref() != Some(&identifiers)).then_some(identifiers)
+}
+
+/// Checks what `generate` guarantees of a key-package map, so a truncated,
+/// tampered or mixed-up file fails on load rather than deep inside signing.
+/// The participants must be `identifiers` when given, or else 1..=n.
+pub fn validate_map(
+    key_packages: &KeyPackages,
+    identifiers: Option<&[frost::Identifier]>,
+) -> Result<(), AppError> {
+    let invalid = |message: String| Err(AppError::InvalidKeyFile(message));
+    let (min_signers, max_signers) = map_threshold(key_packages)?;
+    if max_signers < min_signers {
+        return invalid(format!(
+            "{max_signers} key packages for a threshold of {min_signers}"
+        ));
+    }
+    match identifiers {
+        Some(identifiers) => {
+            let expected: std::collections::BTreeSet<_> = identifiers.iter().collect();
+            if !key_packages.keys().eq(expected.iter().copied()) {
+                return invalid(format!(
+                    "the participants are not the {} identifiers recorded at generation",
+                    expected.len()
+                ));
+            }
+        }
+        None => {
+            for (index, identifier) in (1..=max_signers).zip(key_packages.keys()) {
+                if *identifier != frost::Identifier::try_from(index)? {
+                    return invalid(format!(
+                        "the identifiers are not 1..={max_signers}: found {} in place of {index}",
+                        identifier_label(identifier)
+                    ));
+                }
+            }
+        }
+    }
+    let group_key = key_packages
+        .values()
+        .next()
+        .map(|key_package| *key_package.verifying_key());
+    for (identifier, key_package) in key_packages {
+        let label = identifier_label(identifier);
+        if key_package.identifier() != identifier {
+            return invalid(format!(
+                "the key package stored for participant {label} is participant {}'s",
+                identifier_label(key_package.identifier())
+            ));
+        }
+        if Some(*key_package.verifying_key()) != group_key {
+            return invalid(format!("participant {label} has another group key"));
+        }
+        if frost::keys::VerifyingShare::from(*key_package.signing_share())
+            != *key_package.verifying_share()
+        {
+            return invalid(format!(
+                "participant {label}'s signing share doesn't match its verifying share"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Writes a key-package map, encrypted when a passphrase was given.
+pub fn write_map(
+    path: &std::path::Path,
+    key_packages: &KeyPackages,
+    passphrase: Option<&str>,
+) -> Result<(), AppError> {
+    // 序列化 BTreeMap 为 JSON
+    let my_map_json = zeroize::Zeroizing::new(serde_json::to_vec(key_packages)?);
+    let contents = match passphrase {
+        Some(passphrase) => keystore::encrypt(passphrase, &my_map_json)?,
+        None => my_map_json.to_vec(),
+    };
+
+    // 将 JSON 保存到文件
+    let mut file = create_key_artifact(path)?;
+    file.write_all(&contents)?;
+    Ok(())
+}
+
+pub fn private_key_to_signing_key(private_key_str: &str) -> Result<frost::SigningKey, AppError> {
+    // 这里需要根据 frost-secp256k1 的 API 来转换私钥字符串为 SigningKey
+    // 以下代码是一个示例，具体实现可能需要根据库的文档进行调整
+
+    // 假设私钥是十六进制字符串，需要将其转换为字节序列
+    let private_key_bytes = zeroize::Zeroizing::new(hex::decode(private_key_str)?);
+
+    // 然后使用 frost-secp256k1 的函数来从字节序列创建 SigningKey
+    // 这里需要查阅 frost-secp256k1 的文档来找到正确的方法
+    let signing_key = frost::SigningKey::deserialize(&private_key_bytes)?;
+
+    Ok(signing_key)
+}
+
+/// Splits `signing_key` into fresh shares, one key package per participant.
+pub fn split_signing_key<R: RngCore + CryptoRng>(
+    signing_key: &frost::SigningKey,
+    min_signers: u16,
+    max_signers: u16,
+    identifiers: frost::keys::IdentifierList,
+    rng: &mut R,
+) -> Result<(KeyPackages, frost::keys::PublicKeyPackage), AppError> {
+    let (shares, pubkey_package) =
+        frost::keys::split(signing_key, max_signers, min_signers, identifiers, rng)?;
+
+    let mut key_packages = KeyPackages::new();
+
+    for (identifier, secret_share) in shares {
+        let key_package = frost::keys::KeyPackage::try_from(secret_share)?;
+        key_packages.insert(identifier, key_package);
+    }
+
+    Ok((key_packages, pubkey_package))
+}
+
+/// The group key as a taproot internal key, and the address on `network`
+/// whose output key is that key tweaked with `merkle_root` (BIP341). Key-path
+/// spends of it are signed with [`taproot::sign_key_path`].
+pub fn group_taproot_address(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    merkle_root: Option<bitcoin::TapNodeHash>,
+    network: Network,
+) -> Result<(UntweakedPublicKey, Address), AppError> {
+    let internal_key = taproot::internal_key(pubkey_package)?;
+    let taproot_address = Address::p2tr(
+        &bitcoin::secp256k1::Secp256k1::new(),
+        internal_key,
+        merkle_root,
+        network,
+    );
+    Ok((internal_key, taproot_address))
+}
+
+/// The group's BIP86 address: the group key tweaked with no script tree, as a
+/// single-key wallet's `tr(KEY)` derives it, whatever merkle root the group's
+/// own outputs commit to.
+pub fn group_bip86_address(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    network: Network,
+) -> Result<Address, AppError> {
+    Ok(group_taproot_address(pubkey_package, None, network)?.1)
+}
+
+/// Whether `persisted` pays to the same output as `address`, on any network.
+pub fn address_matches(persisted: &str, address: &Address) -> Result<bool, AppError> {
+    let persisted: Address<bitcoin::address::NetworkUnchecked> = persisted.trim().parse()?;
+    Ok(persisted.assume_checked_ref().script_pubkey() == address.script_pubkey())
+}
+
+/// Short, human-checkable identifier of the group key: the first 4 bytes of
+/// HASH160 of its compressed encoding, as used for BIP32 key fingerprints.
+pub fn group_fingerprint(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+) -> Result<String, AppError> {
+    let pubkey_buffer = pubkey_package.verifying_key().serialize()?;
+    let digest = bitcoin::hashes::hash160::Hash::hash(&pubkey_buffer);
+    Ok(hex::encode(&digest[..4]))
+}
+
+/// A public key package received from outside, such as from a DKG
+/// coordinator, once `signature_hex` checks out over it: a BIP340 signature by
+/// the setup authority's x-only `authority_key_hex` over the SHA-256 of the
+/// package file's exact bytes. Without that, a substituted group key would be
+/// trusted for verification.
+pub fn import_pubkey_package(
+    json: &[u8],
+    signature_hex: &str,
+    authority_key_hex: &str,
+) -> Result<frost::keys::PublicKeyPackage, AppError> {
+    use bitcoin::secp256k1::{schnorr, Message, Secp256k1, XOnlyPublicKey};
+    let authority_key = XOnlyPublicKey::from_slice(&hex::decode(authority_key_hex)?)
+        .map_err(|err| format!("invalid --authority-key: {err}"))?;
+    let signature = schnorr::Signature::from_slice(&hex::decode(signature_hex)?)
+        .map_err(|err| format!("invalid --pubkey-package-sig: {err}"))?;
+    let digest = bitcoin::hashes::sha256::Hash::hash(json).to_byte_array();
+    Secp256k1::verification_only()
+        .verify_schnorr(&signature, &Message::from_digest(digest), &authority_key)
+        .map_err(|_| "the public key package is not signed by the authority key")?;
+    let pubkey_package: frost::keys::PublicKeyPackage =
+        serde_json::from_slice(json).map_err(|err| format!("invalid public key package: {err}"))?;
+    if pubkey_package.verifying_shares().is_empty() {
+        return Err("the public key package has no verifying shares".into());
+    }
+    Ok(pubkey_package)
+}
+
+/// Checks that each participant's nonces commit to exactly the commitments
+/// the coordinator holds for them.
+///
+/// The commitments are recomputed from the nonce scalars rather than taken
+/// from the copy cached inside `SigningNonces`, which a nonces file written
+/// between rounds could carry stale. A mismatch would only surface later as
+/// an invalid signature share.
+pub fn check_nonces_match_commitments(
+    nonces_map: &BTreeMap<frost::Identifier, frost::round1::SigningNonces>,
+    commitments_map: &BTreeMap<frost::Identifier, frost::round1::SigningCommitments>,
+) -> Result<(), AppError> {
+    for (identifier, nonces) in nonces_map {
+        let commitments = commitments_map.get(identifier).ok_or_else(|| {
+            format!(
+                "no commitments loaded for participant {}",
+                hex::encode(identifier.serialize())
+            )
+        })?;
+        let derived =
+            frost::round1::SigningNonces::from_nonces(*nonces.hiding(), *nonces.binding());
+        if derived.commitments() != commitments {
+            return Err(format!(
+                "nonces of participant {} don't match their commitments",
+                hex::encode(identifier.serialize())
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// The artifacts of one signing ceremony, as exchanged between participants
+/// and the coordinator.
+pub struct Ceremony {
+    pub signing_package: frost::SigningPackage,
+    pub signature_shares: BTreeMap<frost::Identifier, frost::round2::SignatureShare>,
+    pub signature: frost::Signature,
+}
+
+/// Runs both FROST rounds with `signers` and aggregates their shares into a
+/// group signature over `message`.
+pub fn sign_message<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    message: &[u8],
+    rng: &mut R,
+) -> Result<frost::Signature, AppError> {
+    Ok(run_ceremony(key_packages, pubkey_package, signers, message, rng)?.signature)
+}
+
+/// Like [`sign_message`], but keeps the signing package and shares.
+pub fn run_ceremony<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    message: &[u8],
+    rng: &mut R,
+) -> Result<Ceremony, AppError> {
+    let (mut nonces_map, commitments_map) = commit_round1(key_packages, signers, rng)?;
+    check_nonces_match_commitments(&nonces_map, &commitments_map)?;
+
+    // This is what the signature aggregator / coordinator needs to do:
+    // - decide what message to sign
+    // - take one (unused) commitment per signing participant
+    let mut signature_shares = BTreeMap::new();
+    let signing_package = frost::SigningPackage::new(commitments_map, message);
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Round 2: each participant generates their signature share
+    ////////////////////////////////////////////////////////////////////////////
+
+    // In practice, each iteration of this loop will be executed by its respective participant.
+    for participant_identifier in nonces_map.keys() {
+        let key_package = &key_packages[participant_identifier];
+
+        let nonces = &nonces_map[participant_identifier];
+
+        // Each participant generates their signature share.
+        let signature_share = frost::round2::sign(&signing_package, nonces, key_package)?;
+
+        // In practice, the signature share must be sent to the Coordinator
+        // using an authenticated channel.
+        signature_shares.insert(*participant_identifier, signature_share);
+    }
+    for nonces in nonces_map.values_mut() {
+        nonces.zeroize();
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Aggregation: collects the signing shares from all participants,
+    // generates the final signature.
+    ////////////////////////////////////////////////////////////////////////////
+
+    // Aggregate (also verifies the signature shares)
+    let group_signature =
+        aggregate_shares(&signing_package, &signature_shares, pubkey_package, false)?;
+
+    Ok(Ceremony {
+        signing_package,
+        signature_shares,
+        signature: group_signature,
+    })
+}
+
+/// Aggregates `signature_shares` into the group signature. When aggregation
+/// fails, or before it is tried with `verify_shares`, every share is checked
+/// against its participant's verifying share, and any that don't verify are
+/// logged and returned as [`AppError::InvalidShares`].
+pub fn aggregate_shares(
+    signing_package: &frost::SigningPackage,
+    signature_shares: &BTreeMap<frost::Identifier, frost::round2::SignatureShare>,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    verify_shares: bool,
+) -> Result<frost::Signature, AppError> {
+    let attribute = || {
+        let culprits = quorum::invalid_shares(pubkey_package, signing_package, signature_shares);
+        for identifier in &culprits {
+            error!(
+                "Invalid signature share from participant {}",
+                identifier_label(identifier)
+            );
+        }
+        culprits
+    };
+    if verify_shares {
+        let culprits = attribute();
+        if !culprits.is_empty() {
+            return Err(AppError::InvalidShares(culprits));
+        }
+    }
+    frost::aggregate(signing_package, signature_shares, pubkey_package).map_err(|err| {
+        let culprits = if verify_shares {
+            Vec::new()
+        } else {
+            attribute()
+        };
+        if culprits.is_empty() {
+            err.into()
+        } else {
+            AppError::InvalidShares(culprits)
+        }
+    })
+}
+
+/// Signs each of `messages` with `signers`, reusing the loaded key packages.
+///
+/// A FROST nonce must never be used for two messages: the two signature shares
+/// would reveal the participant's signing share. Round 1 is therefore rerun for
+/// every message, and each message's nonces are dropped once its shares exist.
+pub fn sign_messages<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    messages: &[&[u8]],
+    rng: &mut R,
+) -> Result<Vec<frost::Signature>, AppError> {
+    messages
+        .iter()
+        .map(|message| sign_message(key_packages, pubkey_package, signers, message, rng))
+        .collect()
+}
+
+/// Parses a `--messages-file`: one hex message per line, blank lines skipped.
+pub fn parse_hex_messages(contents: &str) -> Result<Vec<Vec<u8>>, AppError> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            hex::decode(line.trim()).map_err(|err| format!("line {}: {}", index + 1, err).into())
+        })
+        .collect()
+}
+
+/// The participants the demo ceremonies sign with: the first `min_signers`
+/// identifiers of `key_packages`.
+pub fn default_signers(key_packages: &KeyPackages) -> Vec<frost::Identifier> {
+    let min_signers = key_packages
+        .values()
+        .next()
+        .map_or(0, |key_package| *key_package.min_signers());
+    key_packages
+        .keys()
+        .copied()
+        .take(min_signers.into())
+        .collect()
+}
+
+/// Round 1: generating nonces and signing commitments for each of `signers`.
+pub fn commit_round1<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    signers: &[frost::Identifier],
+    rng: &mut R,
+) -> Result<Round1, AppError> {
+    let mut nonces_map = BTreeMap::new();
+    let mut commitments_map = BTreeMap::new();
+
+    // In practice, each iteration of this loop will be executed by its respective participant.
+    for participant_identifier in signers {
+        let key_package = key_packages
+            .get(participant_identifier)
+            .ok_or(frost::Error::UnknownIdentifier)?;
+        // Generate one (1) nonce and one SigningCommitments instance for each
+        // participant, up to _threshold_.
+        let (nonces, commitments) = frost::round1::commit(key_package.signing_share(), rng);
+        // In practice, the nonces must be kept by the participant to use in the
+        // next round, while the commitment must be sent to the coordinator
+        // (or to every other participant if there is no coordinator) using
+        // an authenticated channel.
+        nonces_map.insert(*participant_identifier, nonces);
+        commitments_map.insert(*participant_identifier, commitments);
+    }
+
+    Ok((nonces_map, commitments_map))
+}
+
+/// The binding factor rho_i of each signer in `signing_package`.
+///
+/// rho_i hashes the message and every signer's commitments, and binds signer
+/// i's binding nonce into the group commitment as `D_i + rho_i * E_i`. An
+/// attacker who opens many sessions at once therefore cannot pick its
+/// commitments after seeing the others', which is what Wagner's attack on
+/// plain multi-party Schnorr needs. The factors are public: anyone holding
+/// the signing package can derive them.
+pub fn binding_factors(
+    signing_package: &frost::SigningPackage,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+) -> Result<
+    Vec<(
+        frost::Identifier,
+        frost_core::BindingFactor<frost::Secp256K1Sha256>,
+    )>,
+    AppError,
+> {
+    let binding_factor_list = frost_core::compute_binding_factor_list(
+        signing_package,
+        pubkey_package.verifying_key(),
+        &[],
+    )?;
+    signing_package
+        .signing_commitments()
+        .keys()
+        .map(|identifier| {
+            let binding_factor = binding_factor_list
+                .get(identifier)
+                .ok_or(frost::Error::UnknownIdentifier)?;
+            Ok((*identifier, binding_factor.clone()))
+        })
+        .collect()
+}
+
+/// Computes the group nonce commitment R = sum(D_i + rho_i * E_i) that the
+/// aggregated signature over the signing package's message will carry.
+pub fn group_commitment(
+    signing_package: &frost::SigningPackage,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+) -> Result<frost_core::Element<frost::Secp256K1Sha256>, AppError> {
+    // `round2::sign` derives the binding factors without an additional prefix.
+    let binding_factor_list = frost_core::compute_binding_factor_list(
+        signing_package,
+        pubkey_package.verifying_key(),
+        &[],
+    )?;
+    let group_commitment =
+        frost_core::compute_group_commitment(signing_package, &binding_factor_list)?;
+    Ok(group_commitment.to_element())
+}
+
+/// Recovers the group signing key from `participants`' key packages, or from
+/// the first threshold of them when `participants` is empty.
+pub fn reconstruct_group_key(
+    key_packages: &KeyPackages,
+    participants: &[frost::Identifier],
+) -> Result<frost::SigningKey, AppError> {
+    let (threshold, _) = map_threshold(key_packages)?;
+    let quorum: Vec<_> = if participants.is_empty() {
+        key_packages
+            .values()
+            .take(threshold.into())
+            .cloned()
+            .collect()
+    } else {
+        participants
+            .iter()
+            .map(|identifier| {
+                key_packages
+                    .get(identifier)
+                    .cloned()
+                    .ok_or(frost::Error::UnknownIdentifier)
+            })
+            .collect::<Result<_, _>>()?
+    };
+    if quorum.len() < usize::from(threshold) {
+        return Err(format!(
+            "{} key packages available, but {} are needed to reconstruct the group key",
+            quorum.len(),
+            threshold
+        )
+        .into());
+    }
+
+    let signing_key = frost::keys::reconstruct(&quorum)?;
+    let group_key = *quorum[0].verifying_key();
+    if frost::VerifyingKey::from(&signing_key) != group_key {
+        return Err("reconstructed secret doesn't match the group key".into());
+    }
+    Ok(signing_key)
+}
+
+/// The group verifying key as a Lightning node id: the 33-byte compressed
+/// SEC1 encoding, hex-encoded.
+pub fn ln_node_id(pubkey_package: &frost::keys::PublicKeyPackage) -> Result<String, AppError> {
+    let compressed = pubkey_package.verifying_key().serialize()?;
+    // Node ids are compressed points, so anything else is a bug in the export.
+    let pubkey = bitcoin::secp256k1::PublicKey::from_slice(&compressed)?;
+    if compressed.len() != 33 || pubkey.serialize() != compressed[..] {
+        return Err("group key does not serialize as a compressed node id".into());
+    }
+    Ok(hex::encode(compressed))
+}
+
+/// Qualitative assessment of a t-of-n configuration.
+pub fn security_assessment(min_signers: u16, max_signers: u16) -> &'static str {
+    if min_signers == 1 {
+        "weak: any single participant can sign alone"
+    } else if min_signers == max_signers {
+        "fragile: losing any single share makes the key unusable"
+    } else if u32::from(min_signers) * 2 <= u32::from(max_signers) {
+        "minority quorum: two disjoint groups of signers can each sign"
+    } else {
+        "balanced: signing requires a majority and tolerates some offline signers"
+    }
+}
+
+/// Short form of an identifier for reports. Default identifiers are small
+/// integers, so the scalar's leading zeros are dropped.
+pub fn identifier_label(identifier: &frost::Identifier) -> String {
+    hex::encode(identifier.serialize())
+        .trim_start_matches('0')
+        .to_string()
+}
+
+/// All `size`-element subsets of `identifiers`, in lexicographic order.
+pub fn threshold_subsets(
+    identifiers: &[frost::Identifier],
+    size: usize,
+) -> Vec<Vec<frost::Identifier>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    let mut subsets = Vec::new();
+    for (index, first) in identifiers.iter().enumerate() {
+        for mut rest in threshold_subsets(&identifiers[index + 1..], size - 1) {
+            rest.insert(0, *first);
+            subsets.push(rest);
+        }
+    }
+    subsets
+}
+
+/// Number of `size`-element subsets of `n` elements.
+pub fn subset_count(n: usize, size: usize) -> u128 {
+    (0..size as u128).fold(1, |count, i| count * (n as u128 - i) / (i + 1))
+}
+
+/// `samples` distinct `size`-element subsets of `identifiers` drawn at
+/// random, in lexicographic order, or all of them when there are no more
+/// than `samples` (or `samples` is `None`).
+pub fn sampled_subsets<R: RngCore + CryptoRng>(
+    identifiers: &[frost::Identifier],
+    size: usize,
+    samples: Option<usize>,
+    rng: &mut R,
+) -> Vec<Vec<frost::Identifier>> {
+    match samples {
+        Some(samples) if (samples as u128) < subset_count(identifiers.len(), size) => {
+            let mut sampled = std::collections::BTreeSet::new();
+            while sampled.len() < samples {
+                let mut subset: Vec<_> = identifiers.choose_multiple(rng, size).copied().collect();
+                subset.sort();
+                sampled.insert(subset);
+            }
+            sampled.into_iter().collect()
+        }
+        _ => threshold_subsets(identifiers, size),
+    }
+}
+
+/// Runs a full sign and verify of [`DEMO_MESSAGE`] with each of `subsets`,
+/// returning each one's outcome in the same order.
+pub fn check_subsets<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    subsets: &[Vec<frost::Identifier>],
+    rng: &mut R,
+) -> Vec<Result<(), AppError>> {
+    subsets
+        .iter()
+        .map(|subset| {
+            let signature = sign_message(key_packages, pubkey_package, subset, DEMO_MESSAGE, rng)?;
+            Ok(pubkey_package
+                .verifying_key()
+                .verify(DEMO_MESSAGE, &signature)?)
+        })
+        .collect()
+}
+
+/// The SHA-256 digest `--prehash` signs in place of the message itself,
+/// read in chunks so the message never has to fit in memory.
+pub fn prehash(mut message: impl Read) -> std::io::Result<[u8; 32]> {
+    let mut engine = bitcoin::hashes::sha256::Hash::engine();
+    std::io::copy(&mut message, &mut engine)?;
+    Ok(bitcoin::hashes::sha256::Hash::from_engine(engine).to_byte_array())
+}
+
+/// Whether `signature_hex` is the group's signature over `message`.
+pub fn check_signature(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    message: &[u8],
+    signature_hex: &str,
+) -> Result<bool, AppError> {
+    let signature = frost::Signature::deserialize(&hex::decode(signature_hex.trim())?)?;
+    Ok(pubkey_package
+        .verifying_key()
+        .verify(message, &signature)
+        .is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, SeedableRng};
+
+    // Golden artifacts produced by frost-secp256k1 2.0.0. They must keep
+    // deserializing and verifying after dependency bumps, otherwise existing
+    // `my_map.json` files would silently stop loading.
+    const GOLDEN_KEY_PACKAGES: &str =
+        include_str!("../tests/fixtures/frost-secp256k1-2.0.0/key_packages.json");
+    const GOLDEN_PUBKEY_PACKAGE: &str =
+        include_str!("../tests/fixtures/frost-secp256k1-2.0.0/pubkey_package.json");
+    const GOLDEN_SIGNATURE: &str =
+        include_str!("../tests/fixtures/frost-secp256k1-2.0.0/signature.hex");
+
+    fn golden_keys() -> (KeyPackages, frost::keys::PublicKeyPackage) {
+        let key_packages: KeyPackages =
+            serde_json::from_str(GOLDEN_KEY_PACKAGES).expect("golden key packages should load");
+        let pubkey_package: frost::keys::PublicKeyPackage =
+            serde_json::from_str(GOLDEN_PUBKEY_PACKAGE).expect("golden pubkey package should load");
+        (key_packages, pubkey_package)
+    }
+
+    #[test]
+    fn golden_fixtures_deserialize_and_reserialize_identically() {
+        let (key_packages, pubkey_package) = golden_keys();
+
+        assert_eq!(key_packages.len(), MAX_SIGNERS as usize);
+        for key_package in key_packages.values() {
+            assert_eq!(key_package.verifying_key(), pubkey_package.verifying_key());
+            assert_eq!(
+                key_package.verifying_share(),
+                &pubkey_package.verifying_shares()[key_package.identifier()]
+            );
+        }
+
+        let key_packages_json = serde_json::to_string_pretty(&key_packages).unwrap() + "\n";
+        assert_eq!(key_packages_json, GOLDEN_KEY_PACKAGES);
+        let pubkey_package_json = serde_json::to_string_pretty(&pubkey_package).unwrap() + "\n";
+        assert_eq!(pubkey_package_json, GOLDEN_PUBKEY_PACKAGE);
+    }
+
+    #[test]
+    fn golden_signature_still_verifies() {
+        let (_, pubkey_package) = golden_keys();
+
+        let signature_bytes = hex::decode(GOLDEN_SIGNATURE.trim()).unwrap();
+        let signature = frost::Signature::deserialize(&signature_bytes).unwrap();
+        assert!(pubkey_package
+            .verifying_key()
+            .verify(DEMO_MESSAGE, &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn golden_keys_still_sign() {
+        let (key_packages, pubkey_package) = golden_keys();
+
+        let signature = sign_message(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            DEMO_MESSAGE,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        assert!(pubkey_package
+            .verifying_key()
+            .verify(DEMO_MESSAGE, &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn group_commitment_matches_aggregated_signature_nonce() {
+        let (key_packages, pubkey_package) = golden_keys();
+        let mut rng = thread_rng();
+
+        let (nonces_map, commitments_map) =
+            commit_round1(&key_packages, &default_signers(&key_packages), &mut rng).unwrap();
+        let signing_package = frost::SigningPackage::new(commitments_map, DEMO_MESSAGE);
+        let group_nonce = group_commitment(&signing_package, &pubkey_package).unwrap();
+
+        let signature_shares: BTreeMap<_, _> = nonces_map
+            .iter()
+            .map(|(identifier, nonces)| {
+                let share =
+                    frost::round2::sign(&signing_package, nonces, &key_packages[identifier])
+                        .unwrap();
+                (*identifier, share)
+            })
+            .collect();
+        let signature =
+            frost::aggregate(&signing_package, &signature_shares, &pubkey_package).unwrap();
+
+        // The serialized signature is R || z.
+        let group_nonce =
+            <frost::Secp256K1Group as frost_core::Group>::serialize(&group_nonce).unwrap();
+        assert_eq!(signature.serialize().unwrap()[..33], group_nonce[..]);
+    }
+
+    #[test]
+    fn threshold_subsets_enumerates_every_combination() {
+        let identifiers: Vec<frost::Identifier> =
+            (1..=5u16).map(|i| i.try_into().unwrap()).collect();
+        let subsets = threshold_subsets(&identifiers, 3);
+        assert_eq!(subsets.len() as u128, subset_count(5, 3));
+        assert_eq!(subsets.len(), 10);
+        assert_eq!(subsets[0], identifiers[..3]);
+        assert_eq!(subsets[9], identifiers[2..]);
+    }
+
+    #[test]
+    fn sampled_subsets_are_distinct_and_checked() {
+        let (key_packages, pubkey_package) = golden_keys();
+        let identifiers: Vec<_> = key_packages.keys().copied().collect();
+        let sampled = sampled_subsets(&identifiers, 3, Some(4), &mut thread_rng());
+        assert_eq!(sampled.len(), 4);
+        assert!(sampled.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            sampled_subsets(&identifiers, 3, Some(50), &mut thread_rng()),
+            threshold_subsets(&identifiers, 3)
+        );
+
+        let outcomes = check_subsets(
+            &key_packages,
+            &pubkey_package,
+            &[sampled[0].clone(), identifiers[..2].to_vec()],
+            &mut thread_rng(),
+        );
+        assert!(outcomes[0].is_ok());
+        assert!(outcomes[1].is_err());
+    }
+
+    #[test]
+    fn messages_files_are_parsed_in_order() {
+        let messages = parse_hex_messages("00ff\n\n  68656c6c6f \n").unwrap();
+        assert_eq!(messages, [vec![0x00, 0xff], b"hello".to_vec()]);
+        assert!(parse_hex_messages("00\nnot hex\n")
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 2"));
+    }
+
+    #[test]
+    fn numbered_paths_keep_the_directory_and_extension() {
+        use std::path::Path;
+
+        assert_eq!(
+            numbered_path(Path::new("my_map.json"), 0),
+            Path::new("my_map_0.json")
+        );
+        assert_eq!(
+            numbered_path(Path::new("keys/vault.json"), 12),
+            Path::new("keys/vault_12.json")
+        );
+        assert_eq!(numbered_path(Path::new("keys"), 1), Path::new("keys_1"));
+    }
+
+    #[test]
+    fn persisted_addresses_match_only_their_own_key() {
+        let (_, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let (_, other_group) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let (_, address) = group_taproot_address(&pubkey_package, None, Network::Bitcoin).unwrap();
+        let (_, testnet) = group_taproot_address(&pubkey_package, None, Network::Testnet).unwrap();
+        let (_, other) = group_taproot_address(&other_group, None, Network::Bitcoin).unwrap();
+
+        assert!(address_matches(&address.to_string(), &address).unwrap());
+        assert!(address_matches(&testnet.to_string(), &address).unwrap());
+        assert!(!address_matches(&other.to_string(), &address).unwrap());
+        assert!(address_matches("not an address", &address).is_err());
+    }
+
+    #[test]
+    fn sign_messages_uses_fresh_nonces_per_message() {
+        let (key_packages, pubkey_package) = golden_keys();
+        let messages: [&[u8]; 3] = [b"one", b"two", b"one"];
+        let signatures = sign_messages(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            &messages,
+            &mut thread_rng(),
+        )
+        .unwrap();
+
+        let mut nonces = std::collections::BTreeSet::new();
+        for (message, signature) in messages.iter().zip(&signatures) {
+            pubkey_package
+                .verifying_key()
+                .verify(message, signature)
+                .unwrap();
+            nonces.insert(signature.serialize().unwrap()[..33].to_vec());
+        }
+        assert_eq!(nonces.len(), messages.len());
+    }
+
+    #[test]
+    fn failed_aggregation_names_every_invalid_share() {
+        let (key_packages, pubkey_package) = golden_keys();
+        let signers = default_signers(&key_packages);
+        let ceremony = run_ceremony(
+            &key_packages,
+            &pubkey_package,
+            &signers,
+            DEMO_MESSAGE,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        let mut shares = ceremony.signature_shares.clone();
+        shares.insert(signers[0], ceremony.signature_shares[&signers[2]]);
+        shares.insert(signers[1], ceremony.signature_shares[&signers[2]]);
+
+        for verify_shares in [false, true] {
+            match aggregate_shares(
+                &ceremony.signing_package,
+                &shares,
+                &pubkey_package,
+                verify_shares,
+            ) {
+                Err(AppError::InvalidShares(culprits)) => assert_eq!(culprits, signers[..2]),
+                other => panic!("expected invalid shares, got {other:?}"),
+            }
+        }
+        assert_eq!(
+            aggregate_shares(
+                &ceremony.signing_package,
+                &ceremony.signature_shares,
+                &pubkey_package,
+                true
+            )
+            .unwrap(),
+            ceremony.signature
+        );
+    }
+
+    #[test]
+    fn prehashed_multi_megabyte_messages_sign_and_verify() {
+        let (key_packages, pubkey_package) = golden_keys();
+        let message = vec![0xa5u8; 8 << 20];
+        let digest = prehash(message.as_slice()).unwrap();
+        assert_eq!(
+            digest,
+            bitcoin::hashes::sha256::Hash::hash(&message).to_byte_array()
+        );
+        // A reader larger than any buffer is hashed in chunks.
+        let streamed = prehash(std::io::Read::take(std::io::repeat(0xa5), 8 << 20)).unwrap();
+        assert_eq!(streamed, digest);
+
+        let signature = sign_message(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            &digest,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        let verifying_key = pubkey_package.verifying_key();
+        assert!(verifying_key.verify(&digest, &signature).is_ok());
+        assert!(verifying_key.verify(&message, &signature).is_err());
+    }
+
+    #[test]
+    fn mismatched_nonce_and_commitment_files_are_rejected() {
+        let (key_packages, _) = golden_keys();
+        let mut rng = thread_rng();
+        let (nonces_map, commitments_map) =
+            commit_round1(&key_packages, &default_signers(&key_packages), &mut rng).unwrap();
+        let (_, other_commitments_map) =
+            commit_round1(&key_packages, &default_signers(&key_packages), &mut rng).unwrap();
+
+        // Round-trip through files the way an async round 1/round 2 split would.
+        let nonces_file = serde_json::to_string(&nonces_map).unwrap();
+        let loaded_nonces: BTreeMap<_, _> = serde_json::from_str(&nonces_file).unwrap();
+        let commitments_file = serde_json::to_string(&commitments_map).unwrap();
+        let loaded_commitments: BTreeMap<_, _> = serde_json::from_str(&commitments_file).unwrap();
+
+        check_nonces_match_commitments(&loaded_nonces, &loaded_commitments).unwrap();
+        let error =
+            check_nonces_match_commitments(&loaded_nonces, &other_commitments_map).unwrap_err();
+        assert!(error.to_string().contains("don't match"));
+    }
+
+    #[test]
+    fn seeded_keygen_is_reproducible() {
+        let keygen = |seed| {
+            let (key_packages, _) = dealer_keygen(
+                MIN_SIGNERS,
+                MAX_SIGNERS,
+                &mut rand_chacha::ChaCha20Rng::from_seed(seed),
+            )
+            .unwrap();
+            serde_json::to_value(key_packages).unwrap()
+        };
+        assert_eq!(keygen([1u8; 32]), keygen([1u8; 32]));
+        assert_ne!(keygen([1u8; 32]), keygen([2u8; 32]));
+        assert!(parse_rng_seed("00").is_err());
+    }
+
+    #[test]
+    fn threshold_is_validated_and_read_back_from_the_key_packages() {
+        assert!(matches!(
+            validate_threshold(1, 5),
+            Err(AppError::InvalidThreshold(_))
+        ));
+        assert!(matches!(
+            validate_threshold(4, 3),
+            Err(AppError::InvalidThreshold(_))
+        ));
+        assert!(validate_threshold(2, 2).is_ok());
+
+        let (key_packages, _) = dealer_keygen(2, 4, &mut thread_rng()).unwrap();
+        assert_eq!(map_threshold(&key_packages).unwrap(), (2, 4));
+        assert_eq!(default_signers(&key_packages).len(), 2);
+    }
+
+    #[test]
+    fn any_threshold_of_key_packages_reconstructs_the_group_key() {
+        let (key_packages, pubkey_package) = dealer_keygen(3, 5, &mut thread_rng()).unwrap();
+        let identifiers: Vec<_> = key_packages.keys().copied().collect();
+
+        let signing_key = reconstruct_group_key(
+            &key_packages,
+            &[identifiers[4], identifiers[1], identifiers[2]],
+        )
+        .unwrap();
+        assert_eq!(
+            frost::VerifyingKey::from(&signing_key),
+            *pubkey_package.verifying_key()
+        );
+        assert!(reconstruct_group_key(&key_packages, &identifiers[..2]).is_err());
+    }
+
+    #[test]
+    fn ln_node_id_is_a_compressed_public_key() {
+        let (_, pubkey_package) = golden_keys();
+        let node_id = ln_node_id(&pubkey_package).unwrap();
+        assert_eq!(node_id.len(), 66);
+        assert!(node_id.starts_with("02") || node_id.starts_with("03"));
+        assert_eq!(
+            hex::decode(&node_id).unwrap(),
+            pubkey_package.verifying_key().serialize().unwrap()
+        );
+    }
+
+    #[test]
+    fn binding_factors_are_per_signer_and_per_message() {
+        let (key_packages, pubkey_package) = golden_keys();
+        let signers = default_signers(&key_packages);
+        let (_, commitments) = commit_round1(&key_packages, &signers, &mut thread_rng()).unwrap();
+
+        let factors = |message: &[u8]| {
+            let signing_package = frost::SigningPackage::new(commitments.clone(), message);
+            binding_factors(&signing_package, &pubkey_package)
+                .unwrap()
+                .into_iter()
+                .map(|(identifier, factor)| (identifier, factor.serialize()))
+                .collect::<Vec<_>>()
+        };
+        let demo = factors(DEMO_MESSAGE);
+        assert_eq!(
+            demo.iter()
+                .map(|(identifier, _)| *identifier)
+                .collect::<Vec<_>>(),
+            signers
+        );
+        assert_ne!(demo[0].1, demo[1].1);
+        assert_ne!(demo, factors(b"another message"));
+    }
+
+    #[test]
+    fn supplied_signatures_check_against_the_public_key_package_alone() {
+        let (key_packages, pubkey_package) = golden_keys();
+        let signature = sign_message(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            DEMO_MESSAGE,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        let signature_hex = hex::encode(signature.serialize().unwrap());
+
+        assert!(check_signature(&pubkey_package, DEMO_MESSAGE, &signature_hex).unwrap());
+        assert!(!check_signature(&pubkey_package, b"other", &signature_hex).unwrap());
+        assert!(check_signature(&pubkey_package, DEMO_MESSAGE, "00").is_err());
+    }
+
+    #[test]
+    fn corrupted_key_files_fail_validation_on_load() {
+        let path = std::env::temp_dir().join(format!("frost-map-{}.json", std::process::id()));
+        let golden: serde_json::Value = serde_json::from_str(GOLDEN_KEY_PACKAGES).unwrap();
+        let load = |map: &serde_json::Value| {
+            std::fs::write(&path, serde_json::to_vec(map).unwrap()).unwrap();
+            load_map(&path, None)
+        };
+        assert!(load(&golden).is_ok());
+
+        let id = |index: u8| format!("{index:064x}");
+        let mut missing = golden.clone();
+        missing.as_object_mut().unwrap().remove(&id(2));
+        assert!(
+            matches!(load(&missing), Err(AppError::InvalidKeyFile(message)) if message.contains("not 1..=4"))
+        );
+
+        let mut swapped = golden.clone();
+        let share = swapped[id(1)]["signing_share"].take();
+        swapped[id(1)]["signing_share"] = swapped[id(2)]["signing_share"].take();
+        swapped[id(2)]["signing_share"] = share;
+        assert!(
+            matches!(load(&swapped), Err(AppError::InvalidKeyFile(message)) if message.contains("signing share"))
+        );
+
+        std::fs::write(&path, &GOLDEN_KEY_PACKAGES[..GOLDEN_KEY_PACKAGES.len() / 2]).unwrap();
+        assert!(matches!(load_map(&path, None), Err(AppError::Json(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn dealer_identifiers_are_custom_and_recorded_for_validation() {
+        let values = |list: &str| list.split(',').map(str::to_string).collect::<Vec<_>>();
+        assert!(parse_dealer_identifiers(&values("1,2"), 3).is_err());
+        assert!(parse_dealer_identifiers(&values("7,07,alice"), 3).is_err());
+        assert!(parse_dealer_identifiers(&values("0,2,3"), 3).is_err());
+
+        let identifiers = parse_dealer_identifiers(&values("7,9,alice"), 3).unwrap();
+        let recorded = Some(identifiers.clone());
+        let (key_packages, _) =
+            dealer_keygen_with(2, 3, identifier_list(&recorded), &mut thread_rng()).unwrap();
+        assert!(key_packages.contains_key(&frost::Identifier::try_from(9).unwrap()));
+        assert!(key_packages.contains_key(&frost::Identifier::derive(b"alice").unwrap()));
+        assert!(validate_map(&key_packages, Some(&identifiers)).is_ok());
+        assert!(matches!(
+            validate_map(&key_packages, None),
+            Err(AppError::InvalidKeyFile(_))
+        ));
+        assert!(validate_map(&key_packages, Some(&identifiers[..2])).is_err());
+    }
+
+    #[test]
+    fn identifiers_are_inferred_from_a_bare_key_file() {
+        let (key_packages, _) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        assert_eq!(inferred_identifiers(&key_packages), None);
+
+        let named = Some(vec![
+            frost::Identifier::derive(b"carol").unwrap(),
+            frost::Identifier::try_from(2).unwrap(),
+            frost::Identifier::try_from(3).unwrap(),
+        ]);
+        let (key_packages, _) =
+            dealer_keygen_with(2, 3, identifier_list(&named), &mut thread_rng()).unwrap();
+        let inferred = inferred_identifiers(&key_packages).unwrap();
+        assert_eq!(inferred.len(), 3);
+        assert!(validate_map(&key_packages, Some(&inferred)).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secret_files_are_readable_only_by_their_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("frost-secret-{}", std::process::id()));
+        create_secret_file(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // An existing, world-readable file is narrowed too.
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        create_secret_file(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn imported_pubkey_packages_must_be_signed_by_the_authority() {
+        use bitcoin::secp256k1::{Keypair, Message, Secp256k1};
+
+        let secp = Secp256k1::new();
+        let authority = Keypair::from_seckey_slice(&secp, &[7; 32]).unwrap();
+        let authority_key = hex::encode(authority.x_only_public_key().0.serialize());
+        let sign = |json: &[u8]| {
+            let digest = bitcoin::hashes::sha256::Hash::hash(json).to_byte_array();
+            let signature =
+                secp.sign_schnorr_no_aux_rand(&Message::from_digest(digest), &authority);
+            hex::encode(signature.serialize())
+        };
+
+        let signature = sign(GOLDEN_PUBKEY_PACKAGE.as_bytes());
+        let (_, pubkey_package) = golden_keys();
+        let imported =
+            import_pubkey_package(GOLDEN_PUBKEY_PACKAGE.as_bytes(), &signature, &authority_key);
+        assert_eq!(imported.unwrap(), pubkey_package);
+
+        // A substituted package, or one signed by anyone else, is refused.
+        let (_, substitute) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let substitute = serde_json::to_vec(&substitute).unwrap();
+        assert!(import_pubkey_package(&substitute, &signature, &authority_key).is_err());
+        let other_key = hex::encode(
+            Keypair::from_seckey_slice(&secp, &[8; 32])
+                .unwrap()
+                .x_only_public_key()
+                .0
+                .serialize(),
+        );
+        assert!(
+            import_pubkey_package(GOLDEN_PUBKEY_PACKAGE.as_bytes(), &signature, &other_key)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn bip86_address_matches_the_bip86_test_vector() {
+        // BIP86, m/86'/0'/0'/0/0: the internal key and its address.
+        let internal_key = "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115";
+        let group_key =
+            frost::VerifyingKey::deserialize(&hex::decode(format!("02{internal_key}")).unwrap())
+                .unwrap();
+        let pubkey_package = frost::keys::PublicKeyPackage::new(BTreeMap::new(), group_key);
+        assert_eq!(
+            group_bip86_address(&pubkey_package, Network::Bitcoin)
+                .unwrap()
+                .to_string(),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+    }
+}
@@ -1,24 +1,125 @@
-// src/lib.rs
 use bitcoin::address::Address;
-use bitcoin::key::UntweakedPublicKey;
+use bitcoin::hashes::Hash;
 use bitcoin::network::Network;
-use bitcoin::secp256k1::{PublicKey, Secp256k1};
+use clap::builder::TypedValueParser;
 use clap::{Parser, Subcommand};
-use dotenv::dotenv;
-use env_logger;
-use frost_secp256k1 as frost;
-use log::{debug, error, info, trace, warn};
-use rand::thread_rng;
-use serde::{Deserialize, Serialize};
+use frost_demo::error::AppError;
+use frost_demo::*;
+use log::{error, info};
+use rand::{thread_rng, SeedableRng};
 use std::collections::BTreeMap;
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 
+/// Optional cargo features of this crate and whether they were compiled in.
+const FEATURES: &[(&str, bool)] = &[("rpc", cfg!(feature = "rpc"))];
 
-const MAX_SIGNERS: u16 = 5;
-const MIN_SIGNERS: u16 = 3;
+const DEFAULT_KEY_FILE: &str = "my_map.json";
+const DEFAULT_PUBKEY_FILE: &str = "pubkey_package.json";
+/// Default cap on `generate --count`.
+const DEFAULT_MAX_KEY_SETS: u32 = 100;
 
+/// Where the key artifacts live, as chosen with `--key-file` and `--pubkey-file`.
+struct KeyFiles {
+    key_file: std::path::PathBuf,
+    pubkey_file: std::path::PathBuf,
+    /// Encrypts the key files written and decrypts encrypted ones read.
+    passphrase: Option<zeroize::Zeroizing<String>>,
+}
+
+impl KeyFiles {
+    /// Side file next to the key file describing how the keys were generated,
+    /// e.g. `my_map.meta.json`.
+    fn metadata_file(&self) -> std::path::PathBuf {
+        metadata_file_for(&self.key_file)
+    }
+
+    fn load_map(&self) -> Result<KeyPackages, AppError> {
+        frost_demo::load_map(
+            &self.key_file,
+            self.passphrase.as_deref().map(String::as_str),
+        )
+    }
+}
+
+static KEY_FILES: std::sync::OnceLock<KeyFiles> = std::sync::OnceLock::new();
+
+fn key_files() -> &'static KeyFiles {
+    KEY_FILES.get_or_init(|| KeyFiles {
+        key_file: DEFAULT_KEY_FILE.into(),
+        pubkey_file: DEFAULT_PUBKEY_FILE.into(),
+        passphrase: None,
+    })
+}
+
+/// Where `get_keys` reads the hex group secret from.
+enum PrivateKeySource {
+    /// An environment variable, possibly set by .env: PRIVATE_KEY unless
+    /// a test names one nothing sets.
+    Env(&'static str),
+    File(std::path::PathBuf),
+    Stdin,
+}
+
+static PRIVATE_KEY_SOURCE: std::sync::OnceLock<PrivateKeySource> = std::sync::OnceLock::new();
+
+fn private_key_source() -> &'static PrivateKeySource {
+    PRIVATE_KEY_SOURCE
+        .get()
+        .unwrap_or(&PrivateKeySource::Env("PRIVATE_KEY"))
+}
+/// Stdin can only be read once, so the key read from it is kept here.
+static STDIN_PRIVATE_KEY: std::sync::OnceLock<zeroize::Zeroizing<String>> =
+    std::sync::OnceLock::new();
+
+/// Reads the private key from `source`, without surrounding whitespace.
+fn read_private_key(source: &PrivateKeySource) -> Result<zeroize::Zeroizing<String>, AppError> {
+    let private_key = match source {
+        PrivateKeySource::Env(name) => {
+            zeroize::Zeroizing::new(env::var(name).map_err(|_| AppError::MissingEnv(name))?)
+        }
+        PrivateKeySource::File(path) => zeroize::Zeroizing::new(std::fs::read_to_string(path)?),
+        PrivateKeySource::Stdin => match STDIN_PRIVATE_KEY.get() {
+            Some(private_key) => private_key.clone(),
+            None => {
+                let mut private_key = zeroize::Zeroizing::new(String::new());
+                std::io::stdin().read_to_string(&mut private_key)?;
+                STDIN_PRIVATE_KEY.get_or_init(|| private_key).clone()
+            }
+        },
+    };
+    if private_key.trim().is_empty() {
+        return Err("the private key is empty".into());
+    }
+    Ok(zeroize::Zeroizing::new(private_key.trim().to_string()))
+}
+
+/// The JSONL file each signing ceremony is appended to, from `--audit-log`.
+static AUDIT_LOG: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+
+/// How reports list participants, from `--participant-order`.
+static PARTICIPANT_ORDER: std::sync::OnceLock<report::ParticipantOrder> =
+    std::sync::OnceLock::new();
+
+static RNG_SEED: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
+
+/// The RNG of key splitting and signing.
+type DemoRng = Box<dyn frost::rand_core::CryptoRngCore>;
+
+/// ChaCha20 seeded with `seed` when one is given, `thread_rng` otherwise.
+fn seeded_rng(seed: Option<u64>) -> DemoRng {
+    match seed {
+        Some(seed) => Box::new(rand_chacha::ChaCha20Rng::seed_from_u64(seed)),
+        None => Box::new(thread_rng()),
+    }
+}
+
+/// The RNG for this run, seeded by `--rng-seed` if it was given. Every
+/// share, nonce and sample the commands draw comes from here.
+fn demo_rng() -> DemoRng {
+    seeded_rng(RNG_SEED.get().copied())
+}
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -26,190 +127,1952 @@ struct Cli {
     /// Optional name to operate on
     name: Option<String>,
 
-    /// Turn debugging information on
-    #[arg(short, long, action = clap::ArgAction::Count)]
+    /// More log output: -d info, -dd debug, -ddd trace (RUST_LOG overrides)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
     debug: u8,
 
+    /// Hex-encoded 32-byte key used to decrypt .env.enc
+    #[arg(long, global = true, env = "FROST_ENV_KEY", hide_env_values = true)]
+    env_key: Option<String>,
+
+    /// Network the group's addresses are encoded for
+    #[arg(
+        long,
+        global = true,
+        default_value_t = Network::Bitcoin,
+        value_parser = clap::builder::PossibleValuesParser::new(["bitcoin", "testnet", "signet", "regtest"])
+            .map(|network| network.parse::<Network>().expect("listed networks parse")),
+    )]
+    network: Network,
+
+    /// Key-package map written by `generate` and read by the other commands
+    #[arg(long, global = true, default_value = DEFAULT_KEY_FILE)]
+    key_file: std::path::PathBuf,
+
+    /// Public key package written by `generate`
+    #[arg(long, global = true, default_value = DEFAULT_PUBKEY_FILE)]
+    pubkey_file: std::path::PathBuf,
+
+    /// Print each command's results as one JSON object on stdout; everything else goes to stderr
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Passphrase the key-package map is encrypted with
+    #[arg(long, global = true, env = "KEY_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+
+    /// Order of the participants in reports; signing itself always uses identifier order
+    #[arg(long, global = true, value_enum, default_value_t = report::ParticipantOrder::Identifier)]
+    participant_order: report::ParticipantOrder,
+
+    /// Append a JSON line recording each signing ceremony and share refresh to this file
+    #[arg(long, global = true, env = "FROST_AUDIT_LOG")]
+    audit_log: Option<std::path::PathBuf>,
+
+    /// File holding the hex private key; takes precedence over PRIVATE_KEY
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        conflicts_with = "private_key_stdin"
+    )]
+    private_key_file: Option<std::path::PathBuf>,
+
+    /// Read the hex private key from stdin; takes precedence over PRIVATE_KEY
+    #[arg(long, global = true)]
+    private_key_stdin: bool,
+
+    /// INSECURE, for reproducible tests and demos only: seed key generation,
+    /// key splitting and every signing nonce from this number, so anyone who
+    /// knows or guesses it can recompute every share. Never use it for real keys.
+    #[arg(long, global = true, hide = true)]
+    rng_seed: Option<u64>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+/// What `verify` reports about its ceremony besides the signature.
+#[derive(clap::Args, Default)]
+struct CeremonyChecks {
+    /// Print a signing report card in this format
+    #[arg(long, value_enum)]
+    report: Option<report::ReportFormat>,
+    /// Have the group sign the report card too
+    #[arg(long, requires = "report")]
+    sign_report: bool,
+    /// Print the commitments, signing package and shares as hex wire frames
+    #[arg(long)]
+    wire_format: bool,
+    /// Audit that at least the threshold of valid shares produced the signature
+    #[arg(long)]
+    check_quorum: bool,
+    /// Print the binding factor FROST derives for each signer
+    #[arg(long)]
+    binding_factors: bool,
+}
+
+/// The sighash `sign-key-path` signs: given, or computed from a transaction.
+#[derive(clap::Args)]
+#[group(required = true, multiple = true)]
+struct SighashSource {
+    /// Hex 32-byte BIP341 sighash of the spending input
+    #[arg(long, conflicts_with = "tx")]
+    sighash: Option<String>,
+    /// Hex unsigned transaction whose input to sign
+    #[arg(long, requires = "prevouts")]
+    tx: Option<String>,
+    /// Index of the input of --tx to sign
+    #[arg(long, default_value_t = 0, requires = "tx")]
+    input_index: usize,
+    /// Output spent by each input of --tx, in order, as <amount>:<hex scriptPubKey>; the amount is in satoshis unless suffixed, e.g. 0.001btc
+    #[arg(long = "prevout", value_parser = parse_prevout, requires = "tx")]
+    prevouts: Vec<bitcoin::TxOut>,
+    /// Hex taproot annex of the input, starting 0x50, for the sighash to commit to
+    #[arg(long, requires = "tx")]
+    annex: Option<String>,
+}
+
+/// The key-path spend of the group's outputs that `build-tx` and
+/// `test-mempool-accept` build: one output to a destination, or whatever a
+/// `--tx-spec` file describes.
+#[derive(clap::Args)]
+struct SpendArgs {
+    /// Funding output of the group's address, as <txid>:<vout>
+    #[arg(long, required_unless_present = "tx_spec")]
+    outpoint: Option<String>,
+    /// Value of the funding output, e.g. 100000, 100000sat or 0.001btc
+    #[arg(long, required_unless_present = "tx_spec")]
+    amount: Option<String>,
+    /// Fee, deducted from the amount, e.g. 1000 or 1000sat
+    #[arg(long, required_unless_present = "tx_spec")]
+    fee: Option<String>,
+    /// Unit of amounts without a suffix (btc, mbtc, sat, ...); satoshis by default
+    #[arg(long)]
+    unit: Option<bitcoin::Denomination>,
+    /// Address that receives the amount less the fee
+    #[arg(long, required_unless_present = "tx_spec")]
+    destination: Option<String>,
+    /// JSON file of the inputs (outpoint, amount) and outputs (address, amount) to build instead
+    #[arg(long, conflicts_with_all = ["outpoint", "amount", "fee", "destination"])]
+    tx_spec: Option<std::path::PathBuf>,
+    /// Hex merkle root of the script tree the funding address commits to
+    #[arg(long)]
+    merkle_root: Option<String>,
+    /// Signal replace-by-fee on every input (BIP125); --rbf false to disable
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    rbf: bool,
+}
+
+/// What `emergency-sweep` sweeps, where to, and the operator's confirmation.
+#[cfg(feature = "rpc")]
+#[derive(clap::Args)]
+struct SweepArgs {
+    /// Address that receives every UTXO of the group's address, less the fee
+    #[arg(long)]
+    destination: String,
+    /// The group's taproot address, typed out, to confirm reconstructing and exposing the full key
+    #[arg(long)]
+    confirm: Option<String>,
+    /// Participants to reconstruct from, by index or name (defaults to the first threshold of them)
+    #[arg(long, value_delimiter = ',')]
+    participants: Vec<String>,
+    /// Fee rate in sat/vB; defaults to the node's estimate for --target
+    #[arg(long)]
+    fee_rate: Option<f64>,
+    /// Confirmation target, in blocks, of the fee estimate to check --fee-rate against
+    #[arg(long, default_value_t = 1)]
+    target: u16,
+    /// Hex merkle root of the script tree the group's address commits to
+    #[arg(long)]
+    merkle_root: Option<String>,
+}
+
+impl SighashSource {
+    fn sighash(&self) -> Result<[u8; 32], AppError> {
+        if let Some(sighash_hex) = &self.sighash {
+            return hex::decode(sighash_hex.trim())?
+                .try_into()
+                .map_err(|_| "the sighash must be 32 bytes of hex".into());
+        }
+        let tx: bitcoin::Transaction = bitcoin::consensus::encode::deserialize_hex(
+            self.tx.as_deref().unwrap_or_default().trim(),
+        )?;
+        if self.prevouts.len() != tx.input.len() {
+            return Err(format!(
+                "{} prevouts for {} inputs",
+                self.prevouts.len(),
+                tx.input.len()
+            )
+            .into());
+        }
+        let annex = self
+            .annex
+            .as_deref()
+            .map(taproot::parse_annex)
+            .transpose()?;
+        taproot::key_spend_sighash(&tx, self.input_index, &self.prevouts, annex.as_deref())
+    }
+}
+
+/// Parses one `<amount>:<hex scriptPubKey>` of `--prevout`.
+fn parse_prevout(prevout: &str) -> Result<bitcoin::TxOut, String> {
+    let (amount, script_pubkey) = prevout
+        .split_once(':')
+        .ok_or_else(|| format!("expected <amount>:<hex scriptPubKey>, got {prevout:?}"))?;
+    Ok(bitcoin::TxOut {
+        value: psbt::parse_amount(amount, None).map_err(|err| err.to_string())?,
+        script_pubkey: bitcoin::ScriptBuf::from_hex(script_pubkey.trim())
+            .map_err(|err| format!("scriptPubKey: {err}"))?,
+    })
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// does testing things
-    Test {},
-    Verify {},
-    Generate {},
+    Test {
+        /// Fail unless the group key fingerprint equals this value (8 hex chars)
+        #[arg(long)]
+        expect_fingerprint: Option<String>,
+        /// Hex merkle root of a script tree the address commits to
+        #[arg(long)]
+        merkle_root: Option<String>,
+        /// Fail unless the key `sign` signs with has this address, or without a value the one `generate` recorded
+        #[arg(long, value_name = "ADDRESS", num_args = 0..=1)]
+        check_address: Option<Option<String>>,
+    },
+    Verify {
+        /// Command that must approve the signing request (JSON on stdin)
+        #[arg(long, env = "FROST_POLICY_HOOK")]
+        policy_hook: Option<String>,
+        /// Check this hex signature against the group key in --pubkey-file instead of signing; exits 1 if invalid
+        #[arg(long)]
+        signature: Option<String>,
+        /// Message the supplied signature is over (the demo message by default)
+        #[arg(long, requires = "signature")]
+        message: Option<String>,
+        /// File whose raw bytes are the message the supplied signature is over
+        #[arg(long, requires = "signature", conflicts_with = "message")]
+        message_file: Option<std::path::PathBuf>,
+        /// The message was signed as its SHA-256 digest, see `sign --prehash`
+        #[arg(long)]
+        prehash: bool,
+        #[command(flatten)]
+        selection: selection::SelectionArgs,
+        #[command(flatten)]
+        checks: CeremonyChecks,
+    },
+    /// Signs a message with the group key and prints the hex signature
+    #[command(group(clap::ArgGroup::new("source").required(true).args(["message", "message_file"])))]
+    Sign {
+        /// Message to sign (UTF-8)
+        #[arg(long)]
+        message: Option<String>,
+        /// File whose raw bytes are the message to sign
+        #[arg(long)]
+        message_file: Option<std::path::PathBuf>,
+        /// Sign the SHA-256 digest of the message instead of the message itself
+        #[arg(long)]
+        prehash: bool,
+        /// Command that must approve the signing request (JSON on stdin)
+        #[arg(long, env = "FROST_POLICY_HOOK")]
+        policy_hook: Option<String>,
+        #[command(flatten)]
+        selection: selection::SelectionArgs,
+        /// Encoding of the printed signature
+        #[arg(long, value_enum, default_value_t = output::OutputFormat::Hex)]
+        format: output::OutputFormat,
+        /// Write the signature to this file instead of stdout
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+    Generate {
+        /// Threshold t needed to sign
+        #[arg(long, default_value_t = MIN_SIGNERS)]
+        min_signers: u16,
+        /// Number of participants n
+        #[arg(long, default_value_t = MAX_SIGNERS)]
+        max_signers: u16,
+        /// Write the key file as plaintext instead of requiring --passphrase
+        #[arg(long)]
+        no_encryption: bool,
+        /// Comma-separated participant identifiers instead of 1..=n: numbers, or names hashed into identifiers
+        #[arg(long, value_delimiter = ',')]
+        dealer_identifiers: Vec<String>,
+        /// Generate this many independent groups into numbered key files, e.g. my_map_0.json
+        #[arg(long)]
+        count: Option<u32>,
+        /// Largest --count accepted, so a typo can't fill the disk with key files
+        #[arg(long, default_value_t = DEFAULT_MAX_KEY_SETS, requires = "count")]
+        max_count: u32,
+    },
+    /// Generates the group with distributed key generation, so no party ever holds the group secret
+    Dkg {
+        /// Threshold t needed to sign
+        #[arg(long, default_value_t = MIN_SIGNERS)]
+        min_signers: u16,
+        /// Number of participants n
+        #[arg(long, default_value_t = MAX_SIGNERS)]
+        max_signers: u16,
+        /// Write the key file as plaintext instead of requiring --passphrase
+        #[arg(long)]
+        no_encryption: bool,
+        /// Comma-separated participant identifiers instead of 1..=n: numbers, or names hashed into identifiers
+        #[arg(long, value_delimiter = ',')]
+        identifiers: Vec<String>,
+    },
     Load {},
+    /// Upgrades a bare key file from before key metadata: backs it up, checks it signs, then writes its metadata and public key package
+    Migrate {},
+    /// Writes a public key package received from a DKG coordinator to --pubkey-file, once the setup authority's signature over it checks out
+    ImportPubkeyPackage {
+        /// Public key package file as received
+        #[arg(long)]
+        package: std::path::PathBuf,
+        /// Authority's BIP340 signature over the SHA-256 of the package file, hex
+        #[arg(long)]
+        pubkey_package_sig: String,
+        /// Authority's x-only public key, hex
+        #[arg(long)]
+        authority_key: String,
+    },
+    /// Round 1 for one participant: writes its nonces (keep them) and its commitments (send them)
+    Commit {
+        /// Participant in the key file: its index, or the name given to `--dealer-identifiers`
+        #[arg(long)]
+        participant: String,
+        /// File to keep the secret nonces in until `sign-share`; must not exist yet
+        #[arg(long)]
+        nonces_out: std::path::PathBuf,
+        /// File to write the commitments to, for every other signer and the coordinator
+        #[arg(long)]
+        commitments_out: std::path::PathBuf,
+    },
+    /// Round 2 for one participant: consumes its nonces file and writes its signature share
+    #[command(group(clap::ArgGroup::new("round1").required(true).args(["commitments", "commitments_map"])))]
+    SignShare {
+        /// Participant in the key file: its index, or the name given to `--dealer-identifiers`
+        #[arg(long)]
+        participant: String,
+        /// Nonces file written by `commit`; deleted once read
+        #[arg(long)]
+        nonces: std::path::PathBuf,
+        /// Commitments file of one signer, this one included; repeat for each of them
+        #[arg(long = "commitments")]
+        commitments: Vec<std::path::PathBuf>,
+        /// JSON map of every signer's identifier to its commitments, instead of the commitments files
+        #[arg(long)]
+        commitments_map: Option<std::path::PathBuf>,
+        /// Message to sign (UTF-8)
+        #[arg(long)]
+        message: String,
+        /// File to write the signature share to
+        #[arg(long)]
+        share_out: std::path::PathBuf,
+    },
+    /// Coordinator: writes the signing package of the collected commitments and the message
+    SigningPackage {
+        /// Commitments file of one signer; repeat for each of them
+        #[arg(long = "commitments", required = true)]
+        commitments: Vec<std::path::PathBuf>,
+        /// Message to sign (UTF-8)
+        #[arg(long)]
+        message: String,
+        /// File to write the signing package to
+        #[arg(long)]
+        output: std::path::PathBuf,
+    },
+    /// Coordinator: aggregates the signature shares into the group signature
+    #[command(group(clap::ArgGroup::new("package").required(true).args(["commitments", "commitments_map", "signing_package"])))]
+    #[command(group(clap::ArgGroup::new("share_files").required(true).args(["shares", "share_dir"])))]
+    Aggregate {
+        /// Commitments file of one signer; repeat for each of them
+        #[arg(long = "commitments", requires = "message")]
+        commitments: Vec<std::path::PathBuf>,
+        /// JSON map of every signer's identifier to its commitments, instead of the commitments files
+        #[arg(long, requires = "message", conflicts_with = "commitments")]
+        commitments_map: Option<std::path::PathBuf>,
+        /// Signing package file written by `signing-package`, instead of the commitments and message
+        #[arg(long, conflicts_with = "message")]
+        signing_package: Option<std::path::PathBuf>,
+        /// Share file of one signer; repeat for each of them
+        #[arg(long = "share")]
+        shares: Vec<std::path::PathBuf>,
+        /// Directory holding one share file per signer; every .json file in it is read
+        #[arg(long)]
+        share_dir: Option<std::path::PathBuf>,
+        /// Message that was signed (UTF-8)
+        #[arg(long)]
+        message: Option<String>,
+        /// Check every share before aggregating and reject all invalid ones by participant
+        #[arg(long)]
+        verify_shares: bool,
+        /// Encoding of the printed signature
+        #[arg(long, value_enum, default_value_t = output::OutputFormat::Hex)]
+        format: output::OutputFormat,
+        /// Write the signature to this file instead of stdout
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Coordinator: writes each signer of an upcoming session a file with the steps to run
+    SigningInstructions {
+        /// Comma-separated signers, as each gives `--participant`: an index or a name
+        #[arg(long, value_delimiter = ',', required = true)]
+        participants: Vec<String>,
+        /// Message to sign (UTF-8)
+        #[arg(long)]
+        message: String,
+        /// Where signers send their commitments and shares, e.g. an address or a URL
+        #[arg(long)]
+        coordinator: String,
+        /// Directory to write one <participant>.txt file per signer to
+        #[arg(long)]
+        out_dir: std::path::PathBuf,
+    },
+    /// Coordinator: checks whether the commitments collected so far reach the threshold
+    ThresholdCheck {
+        /// Commitments file of one signer; repeat for each one collected
+        #[arg(long = "commitments", required = true)]
+        commitments: Vec<std::path::PathBuf>,
+    },
+    /// Runs round 1 and prints the group nonce commitment R without signing
+    GroupNonce {
+        /// Message the commitment is bound to (defaults to the demo message)
+        #[arg(long)]
+        message: Option<String>,
+    },
+    /// Produces a pre-signature encrypted under an adaptor point
+    AdaptorSign {
+        /// Hex-encoded compressed adaptor point T
+        #[arg(long)]
+        adaptor_point: String,
+        /// Message to sign (defaults to the demo message)
+        #[arg(long)]
+        message: Option<String>,
+        /// Command that must approve the signing request (JSON on stdin)
+        #[arg(long, env = "FROST_POLICY_HOOK")]
+        policy_hook: Option<String>,
+    },
+    /// Checks a pre-signature against the group key and an adaptor point
+    AdaptorVerify {
+        /// Hex-encoded pre-signature
+        #[arg(long)]
+        pre_signature: String,
+        /// Hex-encoded compressed adaptor point T
+        #[arg(long)]
+        adaptor_point: String,
+        /// Message that was signed (defaults to the demo message)
+        #[arg(long)]
+        message: Option<String>,
+    },
+    /// Completes a pre-signature with the adaptor secret
+    AdaptorComplete {
+        /// Hex-encoded pre-signature
+        #[arg(long)]
+        pre_signature: String,
+        /// Hex-encoded adaptor secret t
+        #[arg(long)]
+        adaptor_secret: String,
+        /// Message that was signed (defaults to the demo message)
+        #[arg(long)]
+        message: Option<String>,
+    },
+    /// Recovers the adaptor secret from a pre-signature and its completion
+    ExtractSecret {
+        /// Hex-encoded pre-signature
+        #[arg(long)]
+        pre_signature: String,
+        /// Hex-encoded completed signature
+        #[arg(long)]
+        signature: String,
+    },
+    /// Signs a message without the signers learning it (blind Schnorr)
+    BlindSign {
+        /// Message to sign (defaults to the demo message)
+        #[arg(long)]
+        message: Option<String>,
+    },
+    /// Asks the node whether it would accept a signed transaction
+    #[cfg(feature = "rpc")]
+    TestMempoolAccept {
+        #[command(flatten)]
+        spend: SpendArgs,
+        #[command(flatten)]
+        rpc: rpc::RpcArgs,
+    },
+    /// Estimates how many blocks a fee rate needs to confirm, using the node's fee estimator
+    #[cfg(feature = "rpc")]
+    EstimateConfirmation {
+        /// Fee rate in sat/vB to find a confirmation target for
+        #[arg(long)]
+        fee_rate: Option<f64>,
+        #[command(flatten)]
+        rpc: rpc::RpcArgs,
+    },
+    /// Checks whether the chain tip satisfies a script's CSV/CLTV timelocks
+    #[cfg(feature = "rpc")]
+    CheckTimelock {
+        /// Hex-encoded script of the spending path
+        #[arg(long)]
+        script: String,
+        /// Height of the block that confirmed the output, for CSV timelocks
+        #[arg(long)]
+        confirmed_height: Option<u32>,
+        #[command(flatten)]
+        rpc: rpc::RpcArgs,
+    },
+    /// Derives the first child addresses of a tr() descriptor and reports the balance of each
+    #[cfg(feature = "rpc")]
+    ScanAddresses {
+        /// Ranged tr() descriptor, e.g. tr(xpub.../0/*)
+        #[arg(long)]
+        descriptor: String,
+        /// Number of child addresses to derive and scan
+        #[arg(long, default_value_t = 20)]
+        count: u32,
+        #[command(flatten)]
+        rpc: rpc::RpcArgs,
+    },
+    /// Spends every UTXO of the group's address back to it in one signed transaction
+    #[cfg(feature = "rpc")]
+    Consolidate {
+        /// Fee rate in sat/vB; defaults to the node's estimate for --target
+        #[arg(long)]
+        fee_rate: Option<f64>,
+        /// Confirmation target, in blocks, of the fee estimate to check --fee-rate against
+        #[arg(long, default_value_t = 6)]
+        target: u16,
+        /// Hex merkle root of the script tree the group's address commits to
+        #[arg(long)]
+        merkle_root: Option<String>,
+        /// Signal replace-by-fee on every input (BIP125); --rbf false to disable
+        #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+        rbf: bool,
+        #[command(flatten)]
+        rpc: rpc::RpcArgs,
+    },
+    /// Replaces a signed spend of the group's outputs, stuck in the mempool, with one paying a higher fee rate
+    #[cfg(feature = "rpc")]
+    BumpFee {
+        /// Hex of the signed transaction to replace; it must signal replace-by-fee
+        #[arg(long)]
+        tx: String,
+        /// New fee rate in sat/vB; defaults to the node's estimate for --target
+        #[arg(long)]
+        fee_rate: Option<f64>,
+        /// Confirmation target, in blocks, of the fee estimate to check --fee-rate against
+        #[arg(long, default_value_t = 6)]
+        target: u16,
+        /// Hex merkle root of the script tree the group's address commits to
+        #[arg(long)]
+        merkle_root: Option<String>,
+        #[command(flatten)]
+        rpc: rpc::RpcArgs,
+    },
+    /// Encrypts a plaintext environment file with --env-key
+    EncryptEnv {
+        /// Plaintext environment file to encrypt
+        #[arg(long, default_value = ".env")]
+        input: String,
+        /// Where to write the encrypted file
+        #[arg(long, default_value = envfile::ENCRYPTED_ENV_FILE)]
+        output: String,
+    },
+    /// Checks that a taproot address is produced by a tr() descriptor
+    VerifyDescriptor {
+        /// Taproot address to look for
+        #[arg(long)]
+        address: String,
+        /// tr() descriptor, optionally ranged and with a checksum
+        #[arg(long)]
+        descriptor: String,
+        /// Number of indices to scan for ranged descriptors
+        #[arg(long, default_value_t = 100)]
+        scan: u32,
+    },
+    /// Prints the BIP322 to_sign sighash of a message for the group address
+    Bip322Sighash {
+        /// Message to be signed
+        #[arg(long)]
+        message: String,
+        /// Taproot address of the group
+        #[arg(long)]
+        address: String,
+    },
+    /// Prints the group x-only public key as a Nostr npub
+    Npub {},
+    /// Signs a Nostr (NIP-01) event as the group's npub and prints the event JSON
+    NostrSign {
+        /// Event content
+        #[arg(long)]
+        content: String,
+        /// Event kind; 1 is a text note
+        #[arg(long, default_value_t = 1)]
+        kind: u16,
+        /// Comma-separated tag, e.g. `t,frost`; repeat for more tags
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Unix time of the event (defaults to now)
+        #[arg(long)]
+        created_at: Option<u64>,
+    },
+    /// Prints the group key as a 33-byte Lightning node public key
+    LnNodeId {},
+    /// Summarizes the fault and liveness tolerance of a t-of-n configuration
+    Security {
+        /// Threshold t
+        #[arg(long, default_value_t = MIN_SIGNERS)]
+        min_signers: u16,
+        /// Number of participants n
+        #[arg(long, default_value_t = MAX_SIGNERS)]
+        max_signers: u16,
+    },
+    /// Signs and verifies with every threshold subset of the participants in the key file
+    CheckSubsets {
+        /// Check this many randomly chosen subsets instead of all of them
+        #[arg(long)]
+        samples: Option<usize>,
+    },
+    /// Compares JSON, CBOR and bincode for storing the key-package map
+    BenchSerialization {
+        /// Group sizes to measure, comma separated
+        #[arg(long, value_delimiter = ',', default_value = "5,20,100")]
+        signers: Vec<u16>,
+        /// Round trips to average over per format
+        #[arg(long, default_value_t = 100)]
+        iterations: u32,
+    },
+    /// Times obtaining the keys for each of several operations against once per process
+    BenchKeys {
+        /// Consecutive operations that need the keys
+        #[arg(long, default_value_t = 2)]
+        operations: u32,
+    },
+    /// Writes a deterministic set of test vectors derived from a seed
+    GenerateFixtures {
+        /// Seed for the dealer and signing nonces
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Directory to write the fixture files to
+        #[arg(long, default_value = "fixtures")]
+        out_dir: std::path::PathBuf,
+    },
+    /// Signs several messages with one loading of the keys, fresh nonces for each
+    #[command(
+        visible_alias = "batch-sign",
+        group(clap::ArgGroup::new("batch").required(true).multiple(true).args(["messages", "messages_file"])),
+    )]
+    SignBatch {
+        /// Message to sign (UTF-8); repeat for more messages
+        #[arg(long = "message")]
+        messages: Vec<String>,
+        /// File of hex messages to sign, one per line, signed after any --message
+        #[arg(long)]
+        messages_file: Option<std::path::PathBuf>,
+        /// Also time the batch against reloading the keys for every message
+        #[arg(long)]
+        bench: bool,
+    },
+    /// Reports whether each input of a transaction was a taproot key-path or script-path spend
+    ClassifySpend {
+        /// Hex-encoded spending transaction
+        #[arg(long)]
+        tx: String,
+    },
+    /// Simulates a coordinator sending signers different signing packages
+    SimulateEquivocation {
+        /// Run an honest coordinator instead, as a control
+        #[arg(long)]
+        honest: bool,
+    },
+    /// Recovers the group signing key from a threshold of key packages and prints it as hex
+    Reconstruct {
+        /// Participants to reconstruct from, by index or name (defaults to the first threshold of them)
+        #[arg(long, value_delimiter = ',')]
+        participants: Vec<String>,
+        /// Encoding of the printed key
+        #[arg(long, value_enum, default_value_t = output::OutputFormat::Hex)]
+        format: output::OutputFormat,
+        /// Write the key to this file instead of stdout
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Last resort: reconstructs the group secret, sweeps the group's UTXOs with it alone and broadcasts the sweep
+    #[cfg(feature = "rpc")]
+    EmergencySweep {
+        #[command(flatten)]
+        sweep: SweepArgs,
+        #[command(flatten)]
+        rpc: rpc::RpcArgs,
+    },
+    /// Reconstructs the group secret from the key file into an encrypted printable backup
+    ExportColdBackup {
+        /// File to write the printable backup to
+        #[arg(long, default_value = "cold-backup.txt")]
+        output: String,
+        /// Hex-encoded 32-byte key the backup is encrypted with; must differ from --env-key
+        #[arg(long, env = "FROST_BACKUP_KEY", hide_env_values = true)]
+        backup_key: Option<String>,
+    },
+    /// Decrypts a printable cold backup into an environment file holding PRIVATE_KEY, encrypted with --env-key
+    RestoreColdBackup {
+        /// Printable backup to restore
+        #[arg(long, default_value = "cold-backup.txt")]
+        input: String,
+        /// Environment file to create; .env.enc, or .env with --plaintext
+        #[arg(long)]
+        output: Option<String>,
+        /// Hex-encoded 32-byte key the backup was encrypted with
+        #[arg(long, env = "FROST_BACKUP_KEY", hide_env_values = true)]
+        backup_key: Option<String>,
+        /// Write PRIVATE_KEY unencrypted instead of encrypting it with --env-key
+        #[arg(long)]
+        plaintext: bool,
+    },
+    /// Checks that PRIVATE_KEY and the key file describe the same group
+    CheckConsistency {},
+    /// Decodes a hex wire frame received from another coordinator or participant
+    DecodeWire {
+        /// Hex-encoded wire frame
+        #[arg(long)]
+        frame: String,
+    },
+    /// Signs a batch of messages by spreading them over worker processes
+    ClusterSign {
+        /// Message to sign (UTF-8); repeat for more messages
+        #[arg(long = "message", required = true)]
+        messages: Vec<String>,
+        /// Shared queue directory; must not exist yet
+        #[arg(long, default_value = "signing-queue")]
+        queue: std::path::PathBuf,
+        /// Local worker processes to start
+        #[arg(long, default_value_t = 4)]
+        workers: usize,
+    },
+    /// Drains a shared signing queue; can run on any machine that shares it
+    ClusterWorker {
+        /// Shared queue directory
+        #[arg(long, default_value = "signing-queue")]
+        queue: std::path::PathBuf,
+        /// Name reported in the results (defaults to the process id)
+        #[arg(long)]
+        worker_id: Option<String>,
+    },
+    /// Checks a revealed RNG seed against the commitment recorded by `--rng-seed <seed> generate`
+    VerifySeedCommitment {
+        /// The seed passed to `--rng-seed`
+        #[arg(long)]
+        seed: u64,
+    },
+    /// Merges PSBTs signed by different co-signers and checks every signature
+    Combine {
+        /// Base64 PSBT from one co-signer; repeat for each of them
+        #[arg(long = "psbt", required = true)]
+        psbts: Vec<String>,
+    },
+    /// Refreshes every share in the key file into a new key set with the same group key
+    RefreshShares {
+        /// Key-package map to write the refreshed shares to
+        #[arg(long)]
+        key_output: std::path::PathBuf,
+        /// Public key package to write for the refreshed shares
+        #[arg(long)]
+        pubkey_output: std::path::PathBuf,
+    },
+    /// Verifies the group's signature on every share refresh recorded in the audit log
+    VerifyAuditLog {
+        /// Audit log to verify; defaults to --audit-log
+        #[arg(long)]
+        log: Option<std::path::PathBuf>,
+    },
+    /// Scans the audit log for round 1 commitments used in more than one ceremony
+    CheckNonceReuse {
+        /// Audit log to scan; defaults to --audit-log
+        #[arg(long)]
+        log: Option<std::path::PathBuf>,
+        /// Nonces file still on disk, checked against the logged ceremonies; repeat for each
+        #[arg(long = "nonces")]
+        nonces: Vec<std::path::PathBuf>,
+    },
+    /// Checks that a refresh changed every share but kept the group key
+    CheckRefresh {
+        /// Key-package map from before the refresh
+        #[arg(long)]
+        old: std::path::PathBuf,
+        /// Key-package map from after the refresh
+        #[arg(long)]
+        new: std::path::PathBuf,
+    },
+    /// Signs a taproot sighash as a key-path spend of the group's address
+    SignKeyPath {
+        #[command(flatten)]
+        sighash: SighashSource,
+        /// Hex merkle root of the script tree the address commits to
+        #[arg(long)]
+        merkle_root: Option<String>,
+        /// Encoding of the printed signature
+        #[arg(long, value_enum, default_value_t = output::OutputFormat::Hex)]
+        format: output::OutputFormat,
+        /// Write the signature to this file instead of stdout
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Builds an unsigned PSBT spending a group output by key path and prints it as base64
+    BuildTx {
+        #[command(flatten)]
+        spend: SpendArgs,
+    },
+    /// Commits the group's address to a tree of tapscripts and prints each script's control block
+    ScriptTree {
+        /// Hex tapscript to add as a leaf; repeat for more leaves
+        #[arg(long = "script", required = true)]
+        scripts: Vec<String>,
+    },
+    /// Signs every input of a PSBT that spends the group's output key by key path
+    SignPsbt {
+        /// Base64 PSBT, e.g. from `build-tx`
+        #[arg(long)]
+        psbt: String,
+        /// Finalize and print the raw transaction hex instead of the signed PSBT
+        #[arg(long)]
+        extract: bool,
+        /// File of addresses, one per line, that outputs may pay; change to the group is always allowed
+        #[arg(long, env = "FROST_ALLOWED_DESTINATIONS")]
+        allowed_destinations: Option<std::path::PathBuf>,
+        /// Most the outputs may send, less change to the group, e.g. 100000sat or 0.001btc
+        #[arg(long, env = "FROST_MAX_AMOUNT")]
+        max_amount: Option<String>,
+        /// Hex taproot annex, starting 0x50, for every signature to commit to and the witness to carry
+        #[arg(long, requires = "extract")]
+        annex: Option<String>,
+    },
+    /// Signs a message with the participants in the key file and attests which of them did
+    Attest {
+        /// The message to sign
+        #[arg(long)]
+        message: String,
+        /// Write the attestation to this file instead of stdout
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Checks that an attestation's signature was produced by a threshold of the group
+    VerifyAttestation {
+        /// Attestation JSON written by `attest`
+        attestation: std::path::PathBuf,
+    },
+    /// Steps through a signing ceremony one command at a time, with all state kept in memory
+    Interactive {},
+    /// Lists the ciphersuite and optional cargo features compiled into this build
+    Features {},
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct MyMap(BTreeMap<frost::Identifier, frost::keys::KeyPackage>);
-
 fn main() {
-    // 加载 .env 文件
-    dotenv().ok();
-    env_logger::init();
+    let cli = Cli::parse();
+
+    // 加载 .env 文件 (prefers the encrypted .env.enc when present)
+    let env_loaded = envfile::load(cli.env_key.as_deref());
+    env_logger::Builder::new()
+        .filter_level(log_level(cli.debug))
+        .parse_default_env()
+        .init();
+    if let Err(err) = env_loaded {
+        error!("Failed to load environment file: {}", err);
+    }
 
+    // Parse again so env-backed options see variables from the loaded file.
     let cli = Cli::parse();
+    if cli.json {
+        output::enable_json();
+    }
+    let _ = KEY_FILES.set(KeyFiles {
+        key_file: cli.key_file.clone(),
+        pubkey_file: cli.pubkey_file.clone(),
+        passphrase: cli.passphrase.clone().map(zeroize::Zeroizing::new),
+    });
+    if let Some(audit_log) = &cli.audit_log {
+        let _ = AUDIT_LOG.set(audit_log.clone());
+    }
+    let _ = PARTICIPANT_ORDER.set(cli.participant_order);
+    let private_key_source = match (&cli.private_key_file, cli.private_key_stdin) {
+        (Some(path), _) => PrivateKeySource::File(path.clone()),
+        (None, true) => PrivateKeySource::Stdin,
+        (None, false) => PrivateKeySource::Env("PRIVATE_KEY"),
+    };
+    if !matches!(private_key_source, PrivateKeySource::Env(_))
+        && env::var_os("PRIVATE_KEY").is_some()
+    {
+        log::warn!("Ignoring PRIVATE_KEY in favour of the private key given on the command line");
+    }
+    let _ = PRIVATE_KEY_SOURCE.set(private_key_source);
+    if let Some(seed) = cli.rng_seed {
+        log::warn!("--rng-seed makes every share and nonce of this run predictable; never use it for real keys");
+        let _ = RNG_SEED.set(seed);
+    }
 
     // You can check the value provided by positional arguments, or option arguments
     if let Some(name) = cli.name.as_deref() {
-        println!("Value for name: {name}");
+        say!("Value for name: {name}");
     }
 
-    // You can see how many times a particular flag or argument occurred
-    // Note, only flags can have multiple occurrences
-    // match cli.debug {
-    //     0 => println!("Debug mode is off"),
-    //     1 => println!("Debug mode is kind of on"),
-    //     2 => println!("Debug mode is on"),
-    //     _ => println!("Don't be crazy"),
-    // }
-
     // You can check for the existence of subcommands, and if found use their
     // matches just as you would the top level cmd
-    match &cli.command {
-        Some(Commands::Test{}) => {
-            let _ = generate_address();
+    let result = match &cli.command {
+        Some(Commands::Test {
+            expect_fingerprint,
+            merkle_root,
+            check_address,
+        }) => generate_address(
+            expect_fingerprint.as_deref(),
+            merkle_root.as_deref(),
+            check_address.as_ref().map(Option::as_deref),
+            cli.network,
+        )
+        .map(drop),
+        Some(Commands::Verify {
+            signature: Some(signature),
+            message,
+            message_file,
+            prehash,
+            ..
+        }) => {
+            let message = message
+                .as_deref()
+                .map(str::as_bytes)
+                .or(message_file.is_none().then_some(DEMO_MESSAGE));
+            read_message(message, message_file.as_deref(), *prehash)
+                .and_then(|message| verify_supplied_signature(&message, signature, cli.network))
         }
-        Some(Commands::Verify{}) => {
-            let _ = generate_signature();
+        Some(Commands::Verify {
+            policy_hook,
+            signature: None,
+            selection,
+            checks,
+            prehash,
+            ..
+        }) => read_message(Some(DEMO_MESSAGE), None, *prehash)
+            .and_then(|message| {
+                generate_signature(
+                    &message,
+                    policy_hook.as_deref(),
+                    selection,
+                    checks,
+                    cli.network,
+                )
+            })
+            .map(drop),
+        Some(Commands::Sign {
+            message,
+            message_file,
+            prehash,
+            policy_hook,
+            selection,
+            format,
+            output,
+        }) => read_message(
+            message.as_deref().map(str::as_bytes),
+            message_file.as_deref(),
+            *prehash,
+        )
+        .and_then(|message| {
+            sign_command(
+                &message,
+                policy_hook.as_deref(),
+                selection,
+                *format,
+                output.as_deref(),
+                cli.network,
+            )
+        }),
+        Some(Commands::Generate {
+            min_signers,
+            max_signers,
+            no_encryption,
+            dealer_identifiers,
+            count,
+            max_count,
+        }) => match count {
+            Some(count) => generate_key_sets(
+                *count,
+                *max_count,
+                *min_signers,
+                *max_signers,
+                *no_encryption,
+                dealer_identifiers,
+                cli.network,
+            )
+            .map(drop),
+            None => generate_keys(
+                *min_signers,
+                *max_signers,
+                *no_encryption,
+                dealer_identifiers,
+                cli.network,
+            )
+            .map(drop),
+        },
+        Some(Commands::Dkg {
+            min_signers,
+            max_signers,
+            no_encryption,
+            identifiers,
+        }) => dkg_keys(
+            *min_signers,
+            *max_signers,
+            *no_encryption,
+            identifiers,
+            cli.network,
+        )
+        .map(drop),
+        Some(Commands::ImportPubkeyPackage {
+            package,
+            pubkey_package_sig,
+            authority_key,
+        }) => import_pubkey_package(package, pubkey_package_sig, authority_key).map(drop),
+        Some(Commands::Migrate {}) => migrate(cli.network).map(drop),
+        Some(Commands::Load {}) => load_map().map(|my_map| {
+            info!("Loaded {} key packages", my_map.len());
+            output::record("key_packages", my_map.len());
+        }),
+        Some(Commands::Commit {
+            participant,
+            nonces_out,
+            commitments_out,
+        }) => commit_command(participant, nonces_out, commitments_out).map(drop),
+        Some(Commands::SignShare {
+            participant,
+            nonces,
+            commitments,
+            commitments_map,
+            message,
+            share_out,
+        }) => sign_share_command(
+            participant,
+            nonces,
+            CommitmentsSource::new(commitments, commitments_map.as_deref()),
+            message.as_bytes(),
+            share_out,
+        )
+        .map(drop),
+        Some(Commands::SigningPackage {
+            commitments,
+            message,
+            output,
+        }) => signing_package_command(commitments, message.as_bytes(), output).map(drop),
+        Some(Commands::Aggregate {
+            commitments,
+            commitments_map,
+            signing_package,
+            shares,
+            share_dir,
+            message,
+            verify_shares,
+            format,
+            output,
+        }) => {
+            let package = match (signing_package, message) {
+                (Some(path), _) => PackageSource::File(path),
+                (None, Some(message)) => PackageSource::Commitments(
+                    CommitmentsSource::new(commitments, commitments_map.as_deref()),
+                    message.as_bytes(),
+                ),
+                (None, None) => unreachable!("clap requires --message with --commitments"),
+            };
+            let shares = match share_dir {
+                Some(dir) => ShareSource::Dir(dir),
+                None => ShareSource::Files(shares),
+            };
+            aggregate_command(package, shares, *verify_shares, *format, output.as_deref()).map(drop)
         }
-        Some(Commands::Generate{}) => {
-            let _ = generate_keys();
+        Some(Commands::SigningInstructions {
+            participants,
+            message,
+            coordinator,
+            out_dir,
+        }) => signing_instructions(participants, message, coordinator, out_dir).map(drop),
+        Some(Commands::ThresholdCheck { commitments }) => threshold_check(commitments).map(drop),
+        Some(Commands::GroupNonce { message }) => {
+            let message = message.as_deref().map_or(DEMO_MESSAGE, str::as_bytes);
+            generate_group_nonce(message).map(drop)
         }
-        Some(Commands::Load{}) => {
-            let _my_map = load_map();
+        Some(Commands::AdaptorSign {
+            adaptor_point,
+            message,
+            policy_hook,
+        }) => {
+            let message = message.as_deref().map_or(DEMO_MESSAGE, str::as_bytes);
+            generate_adaptor_signature(adaptor_point, message, policy_hook.as_deref()).map(drop)
         }
-        None => {}
+        Some(Commands::AdaptorVerify {
+            pre_signature,
+            adaptor_point,
+            message,
+        }) => {
+            let message = message.as_deref().map_or(DEMO_MESSAGE, str::as_bytes);
+            verify_adaptor_signature(pre_signature, adaptor_point, message).map(drop)
+        }
+        Some(Commands::AdaptorComplete {
+            pre_signature,
+            adaptor_secret,
+            message,
+        }) => {
+            let message = message.as_deref().map_or(DEMO_MESSAGE, str::as_bytes);
+            complete_adaptor_signature(pre_signature, adaptor_secret, message).map(drop)
+        }
+        Some(Commands::ExtractSecret {
+            pre_signature,
+            signature,
+        }) => extract_adaptor_secret(pre_signature, signature).map(drop),
+        Some(Commands::BlindSign { message }) => {
+            let message = message.as_deref().map_or(DEMO_MESSAGE, str::as_bytes);
+            generate_blind_signature(message).map(drop)
+        }
+        #[cfg(feature = "rpc")]
+        Some(Commands::TestMempoolAccept { spend, rpc }) => {
+            test_mempool_accept(spend, cli.network, rpc).map(drop)
+        }
+        #[cfg(feature = "rpc")]
+        Some(Commands::EstimateConfirmation { fee_rate, rpc }) => {
+            estimate_confirmation(*fee_rate, rpc).map(drop)
+        }
+        #[cfg(feature = "rpc")]
+        Some(Commands::ScanAddresses {
+            descriptor,
+            count,
+            rpc,
+        }) => scan_addresses(descriptor, *count, cli.network, rpc).map(drop),
+        #[cfg(feature = "rpc")]
+        Some(Commands::Consolidate {
+            fee_rate,
+            target,
+            merkle_root,
+            rbf,
+            rpc,
+        }) => consolidate(
+            *fee_rate,
+            *target,
+            merkle_root.as_deref(),
+            *rbf,
+            cli.network,
+            rpc,
+        )
+        .map(drop),
+        #[cfg(feature = "rpc")]
+        Some(Commands::BumpFee {
+            tx,
+            fee_rate,
+            target,
+            merkle_root,
+            rpc,
+        }) => bump_fee(tx, *fee_rate, *target, merkle_root.as_deref(), rpc).map(drop),
+        #[cfg(feature = "rpc")]
+        Some(Commands::CheckTimelock {
+            script,
+            confirmed_height,
+            rpc,
+        }) => check_timelock(script, *confirmed_height, rpc).map(drop),
+        Some(Commands::EncryptEnv { input, output }) => {
+            encrypt_env_file(input, output, cli.env_key.as_deref()).map(drop)
+        }
+        Some(Commands::VerifyDescriptor {
+            address,
+            descriptor,
+            scan,
+        }) => verify_descriptor_address(address, descriptor, *scan).map(drop),
+        Some(Commands::Bip322Sighash { message, address }) => {
+            compute_bip322_sighash(message, address).map(drop)
+        }
+        Some(Commands::Npub {}) => generate_npub().map(drop),
+        Some(Commands::NostrSign {
+            content,
+            kind,
+            tags,
+            created_at,
+        }) => {
+            let event = nostr::UnsignedEvent {
+                created_at: created_at.unwrap_or_else(report::unix_now),
+                kind: *kind,
+                tags: tags
+                    .iter()
+                    .map(|tag| tag.split(',').map(str::to_string).collect())
+                    .collect(),
+                content: content.clone(),
+            };
+            sign_nostr_event(event).map(drop)
+        }
+        Some(Commands::LnNodeId {}) => print_ln_node_id().map(drop),
+        Some(Commands::Security {
+            min_signers,
+            max_signers,
+        }) => print_security_level(*min_signers, *max_signers).map(drop),
+        Some(Commands::CheckSubsets { samples }) => check_threshold_subsets(*samples).map(drop),
+        Some(Commands::BenchSerialization {
+            signers,
+            iterations,
+        }) => bench_serialization(signers, *iterations).map(drop),
+        Some(Commands::BenchKeys { operations }) => bench_keys(*operations).map(drop),
+        Some(Commands::GenerateFixtures { seed, out_dir }) => {
+            match fixtures::write(*seed, DEMO_MESSAGE, out_dir) {
+                Ok(()) => {
+                    info!("Wrote fixtures for seed {} to {}", seed, out_dir.display());
+                    output::record("seed", seed);
+                    output::record("out_dir", out_dir);
+                    Ok(())
+                }
+                Err(err) => {
+                    error!("Failed to write fixtures: {}", err);
+                    Err(err)
+                }
+            }
+        }
+        Some(Commands::SignBatch {
+            messages,
+            messages_file,
+            bench,
+        }) => sign_batch(messages, messages_file.as_deref(), *bench).map(drop),
+        Some(Commands::ClassifySpend { tx }) => classify_spend(tx).map(drop),
+        Some(Commands::SimulateEquivocation { honest }) => {
+            simulate_equivocation(!*honest).map(drop)
+        }
+        Some(Commands::Reconstruct {
+            participants,
+            format,
+            output,
+        }) => reconstruct(participants, *format, output.as_deref()).map(drop),
+        #[cfg(feature = "rpc")]
+        Some(Commands::EmergencySweep { sweep, rpc }) => {
+            emergency_sweep(sweep, cli.network, rpc).map(drop)
+        }
+        Some(Commands::ExportColdBackup { output, backup_key }) => {
+            export_cold_backup(output, backup_key.as_deref(), cli.env_key.as_deref()).map(drop)
+        }
+        Some(Commands::RestoreColdBackup {
+            input,
+            output,
+            backup_key,
+            plaintext,
+        }) => restore_cold_backup(
+            input,
+            output.as_deref(),
+            backup_key.as_deref(),
+            *plaintext,
+            cli.env_key.as_deref(),
+        )
+        .map(drop),
+        Some(Commands::CheckConsistency {}) => check_consistency().map(drop),
+        Some(Commands::DecodeWire { frame }) => decode_wire_frame(frame).map(drop),
+        Some(Commands::ClusterSign {
+            messages,
+            queue,
+            workers,
+        }) => cluster_sign(messages, queue, *workers).map(drop),
+        Some(Commands::ClusterWorker { queue, worker_id }) => {
+            let worker_id = worker_id
+                .clone()
+                .unwrap_or_else(|| format!("pid-{}", std::process::id()));
+            cluster_worker(queue, &worker_id).map(drop)
+        }
+        Some(Commands::VerifySeedCommitment { seed }) => verify_seed_commitment(*seed).map(drop),
+        Some(Commands::Combine { psbts }) => combine_psbts(psbts).map(drop),
+        Some(Commands::RefreshShares {
+            key_output,
+            pubkey_output,
+        }) => refresh_shares(key_output, pubkey_output).map(drop),
+        Some(Commands::VerifyAuditLog { log }) => verify_audit_log(log.as_deref()).map(drop),
+        Some(Commands::CheckNonceReuse { log, nonces }) => {
+            check_nonce_reuse(log.as_deref(), nonces).map(drop)
+        }
+        Some(Commands::CheckRefresh { old, new }) => check_refresh(old, new).map(drop),
+        Some(Commands::SignKeyPath {
+            sighash,
+            merkle_root,
+            format,
+            output,
+        }) => sign_key_path(
+            sighash,
+            merkle_root.as_deref(),
+            *format,
+            output.as_deref(),
+            cli.network,
+        )
+        .map(drop),
+        Some(Commands::BuildTx { spend }) => build_tx(spend, cli.network).map(drop),
+        Some(Commands::ScriptTree { scripts }) => script_tree(scripts, cli.network).map(drop),
+        Some(Commands::SignPsbt {
+            psbt,
+            extract,
+            allowed_destinations,
+            max_amount,
+            annex,
+        }) => sign_psbt(
+            psbt,
+            *extract,
+            allowed_destinations.as_deref(),
+            max_amount.as_deref(),
+            annex.as_deref(),
+            cli.network,
+        )
+        .map(drop),
+        Some(Commands::Attest { message, output }) => {
+            attest_command(message.as_bytes(), output.as_deref()).map(drop)
+        }
+        Some(Commands::VerifyAttestation { attestation }) => {
+            verify_attestation(attestation).map(drop)
+        }
+        Some(Commands::Interactive {}) => {
+            let mut session = repl::Session::new(demo_rng(), cli.network);
+            // Under --json the transcript goes to stderr with the other human output.
+            let result = if output::json_enabled() {
+                repl::run(&mut session, std::io::stdin().lock(), std::io::stderr())
+            } else {
+                repl::run(&mut session, std::io::stdin().lock(), std::io::stdout())
+            };
+            output::record("state", session.state());
+            result
+        }
+        Some(Commands::Features {}) => {
+            print_features();
+            Ok(())
+        }
+        None => Ok(()),
+    };
+    if let Err(err) = &result {
+        output::record("error", err.to_string());
+    }
+    if let Some(json) = output::take_json() {
+        println!("{}", json);
+    }
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(err.exit_code());
     }
 
     // Continued program logic goes here...
 }
 
-fn generate_keys() -> Result<(), Box<dyn std::error::Error>> {
+fn generate_keys(
+    min_signers: u16,
+    max_signers: u16,
+    no_encryption: bool,
+    dealer_identifiers: &[String],
+    network: Network,
+) -> Result<(), AppError> {
     // let secp = Secp256k1::verification_only();
+    let mut metadata =
+        dealer_metadata(min_signers, max_signers, no_encryption, dealer_identifiers)?;
+    let identifiers = identifier_list(&metadata.identifiers);
+    if let Some(seed) = RNG_SEED.get() {
+        let commitment = hex::encode(seed_commitment(*seed));
+        info!("Seed commitment: {}", commitment);
+        metadata.seed_commitment = Some(commitment);
+    }
+    let (key_packages, pubkey_package) =
+        dealer_keygen_with(min_signers, max_signers, identifiers, &mut demo_rng())?;
+    write_key_files(
+        key_files(),
+        metadata,
+        &key_packages,
+        &pubkey_package,
+        network,
+    )
+    .map(drop)
+}
 
-    let mut rng = thread_rng();
-    let (shares, pubkey_package) = frost::keys::generate_with_dealer(
-        MAX_SIGNERS,
-        MIN_SIGNERS,
-        frost::keys::IdentifierList::Default,
-        &mut rng,
-    )?;
-        
-    let pubkey_buffer = pubkey_package.verifying_key().serialize()?;
-    let pubkey = bitcoin::secp256k1::PublicKey::from_slice(&pubkey_buffer)?;
-    let internal_key = UntweakedPublicKey::from(pubkey);
-    let taproot_address = Address::p2tr(&bitcoin::secp256k1::Secp256k1::new(), internal_key, None, Network::Bitcoin);
-    info!("Pubkey : {}", internal_key);
-    info!("Taproot address: {}", taproot_address);
+/// Checks the options of a dealer generation and starts its metadata.
+fn dealer_metadata(
+    min_signers: u16,
+    max_signers: u16,
+    no_encryption: bool,
+    dealer_identifiers: &[String],
+) -> Result<KeyMetadata, AppError> {
+    if let Err(e) = validate_threshold(min_signers, max_signers) {
+        error!("{}", e);
+        return Err(e);
+    }
+    check_key_file_encryption(no_encryption)?;
+
+    let mut metadata = KeyMetadata::default();
+    if !dealer_identifiers.is_empty() {
+        match parse_dealer_identifiers(dealer_identifiers, max_signers) {
+            Ok(identifiers) => metadata.identifiers = Some(identifiers),
+            Err(err) => {
+                error!("{}", err);
+                return Err(err);
+            }
+        }
+    }
+    Ok(metadata)
+}
+
+/// Deals `count` independent groups, the i-th into the key files numbered
+/// i, and prints each one's address.
+fn generate_key_sets(
+    count: u32,
+    max_count: u32,
+    min_signers: u16,
+    max_signers: u16,
+    no_encryption: bool,
+    dealer_identifiers: &[String],
+    network: Network,
+) -> Result<(), AppError> {
+    if count == 0 {
+        return Err("--count must be at least 1".into());
+    }
+    if count > max_count {
+        error!(
+            "--count {} is over the limit of {} key sets; pass --max-count to raise it",
+            count, max_count
+        );
+        return Err(format!("--count {count} exceeds --max-count {max_count}").into());
+    }
+    let metadata = dealer_metadata(min_signers, max_signers, no_encryption, dealer_identifiers)?;
+    // One RNG for every set, so even a seeded one draws fresh keys each time.
+    let mut rng = demo_rng();
+    let mut key_sets = Vec::new();
+    for index in 0..count {
+        let files = KeyFiles {
+            key_file: numbered_path(&key_files().key_file, index),
+            pubkey_file: numbered_path(&key_files().pubkey_file, index),
+            passphrase: key_files().passphrase.clone(),
+        };
+        let (key_packages, pubkey_package) = dealer_keygen_with(
+            min_signers,
+            max_signers,
+            identifier_list(&metadata.identifiers),
+            &mut rng,
+        )?;
+        let set_metadata = KeyMetadata {
+            identifiers: metadata.identifiers.clone(),
+            ..KeyMetadata::default()
+        };
+        let address = write_key_files(
+            &files,
+            set_metadata,
+            &key_packages,
+            &pubkey_package,
+            network,
+        )?;
+        info!("Key set {} written to {}", index, files.key_file.display());
+        key_sets.push((index, files.key_file, address));
+    }
+
+    say!("{:>5}  {:<24}  {}", "index", "key file", "taproot address");
+    for (index, key_file, address) in &key_sets {
+        say!("{:>5}  {:<24}  {}", index, key_file.display(), address);
+    }
+    output::record(
+        "key_sets",
+        key_sets
+            .iter()
+            .map(|(index, key_file, address)| {
+                serde_json::json!({"index": index, "key_file": key_file, "taproot_address": address.to_string()})
+            })
+            .collect::<Vec<_>>(),
+    );
+    Ok(())
+}
+
+/// Generates the keys with distributed key generation instead of a dealer,
+/// see [`dkg`].
+fn dkg_keys(
+    min_signers: u16,
+    max_signers: u16,
+    no_encryption: bool,
+    identifiers: &[String],
+    network: Network,
+) -> Result<(), AppError> {
+    if let Err(e) = validate_threshold(min_signers, max_signers) {
+        error!("{}", e);
+        return Err(e);
+    }
+    check_key_file_encryption(no_encryption)?;
 
-    // Verifies the secret shares from the dealer and store them in a BTreeMap.
-    // In practice, the KeyPackages must be sent to its respective participants
-    // through a confidential and authenticated channel.
-    let mut key_packages: BTreeMap<_, _> = BTreeMap::new();
+    let mut metadata = KeyMetadata::default();
+    let participants = if identifiers.is_empty() {
+        (1..=max_signers)
+            .map(frost::Identifier::try_from)
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        match parse_dealer_identifiers(identifiers, max_signers) {
+            Ok(identifiers) => {
+                metadata.identifiers = Some(identifiers.clone());
+                identifiers
+            }
+            Err(err) => {
+                error!("{}", err);
+                return Err(err);
+            }
+        }
+    };
+    let (key_packages, pubkey_package) = match dkg::run(min_signers, &participants, &mut demo_rng())
+    {
+        Ok(keys) => keys,
+        Err(err) => {
+            error!("Distributed key generation failed: {}", err);
+            return Err(err);
+        }
+    };
+    info!(
+        "Ran distributed key generation for {} participants",
+        key_packages.len()
+    );
+    write_key_files(
+        key_files(),
+        metadata,
+        &key_packages,
+        &pubkey_package,
+        network,
+    )
+    .map(drop)
+}
 
-    for (identifier, secret_share) in shares {
-        let key_package = frost::keys::KeyPackage::try_from(secret_share)?;
-        key_packages.insert(identifier, key_package);
+/// Refuses to write my_map.json unencrypted unless --no-encryption says so.
+fn check_key_file_encryption(no_encryption: bool) -> Result<(), AppError> {
+    match (&key_files().passphrase, no_encryption) {
+        (None, false) => {
+            error!(
+                "Pass --passphrase (or KEY_PASSPHRASE) to encrypt {}, or --no-encryption",
+                key_files().key_file.display()
+            );
+            Err("no key file passphrase".into())
+        }
+        (Some(_), true) => {
+            error!("--no-encryption conflicts with a passphrase");
+            Err("conflicting key file encryption options".into())
+        }
+        _ => Ok(()),
     }
+}
+
+/// Writes the metadata, the key-package map and the public key package of a
+/// freshly generated group to `files`, and reports its address.
+fn write_key_files(
+    files: &KeyFiles,
+    mut metadata: KeyMetadata,
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    network: Network,
+) -> Result<bitcoin::Address, AppError> {
+    let (min_signers, max_signers) = map_threshold(key_packages)?;
+    let (internal_key, taproot_address) = group_taproot_address(pubkey_package, None, network)?;
+    metadata.taproot_address = Some(taproot_address.to_string());
+    metadata.min_signers = Some(min_signers);
+    // Always rewritten, so a stale commitment never describes fresh keys.
+    let mut file = create_key_artifact(&files.metadata_file())?;
+    file.write_all((serde_json::to_string_pretty(&metadata)? + "\n").as_bytes())?;
+
+    info!("Pubkey : {}", internal_key);
+    info!("Taproot address: {} ({})", taproot_address, network);
+    info!("Group fingerprint: {}", group_fingerprint(pubkey_package)?);
+    info!("Threshold: {}-of-{}", min_signers, max_signers);
+    output::record("taproot_address", taproot_address.to_string());
+    output::record("pubkey", internal_key.to_string());
+    output::record("network", network.to_string());
+    output::record("fingerprint", group_fingerprint(pubkey_package)?);
+    output::record("min_signers", min_signers);
+    output::record("max_signers", max_signers);
+
     // info!("Key packages: {:?}", key_packages);
 
-    // frost::keys::reconstruct()
+    write_map(&files.key_file, key_packages)?;
+
+    // The public half, for nodes that only verify and hold no share.
+    let mut file = create_key_artifact(&files.pubkey_file)?;
+    file.write_all(serde_json::to_string(pubkey_package)?.as_bytes())?;
+    Ok(taproot_address)
+}
 
-    // 序列化 BTreeMap 为 JSON
-    let my_map_json = serde_json::to_string(&key_packages)?;
+/// Checks a revealed seed against the recorded commitment and that it
+/// regenerates exactly the key packages in my_map.json.
+fn verify_seed_commitment(seed: u64) -> Result<(), AppError> {
+    let metadata_file = key_files().metadata_file();
+    let metadata = read_metadata(&key_files().key_file)?;
+    let Some(commitment) = metadata.seed_commitment else {
+        error!(
+            "{} records no seed commitment; the keys were not generated from a seed",
+            metadata_file.display()
+        );
+        return Err("no seed commitment".into());
+    };
+    if hex::encode(seed_commitment(seed)) != commitment {
+        error!("The seed does not match the commitment {}", commitment);
+        return Err("seed commitment mismatch".into());
+    }
+    info!("The seed matches the commitment {}", commitment);
 
-    // 将 JSON 保存到文件
-    let mut file = File::create("my_map.json")?;
-    file.write_all(my_map_json.as_bytes())?;
+    let key_packages = load_map()?;
+    let (min_signers, max_signers) = map_threshold(&key_packages)?;
+    let (regenerated, _) = dealer_keygen_with(
+        min_signers,
+        max_signers,
+        identifier_list(&metadata.identifiers),
+        &mut seeded_rng(Some(seed)),
+    )?;
+    if serde_json::to_value(&regenerated)? != serde_json::to_value(key_packages)? {
+        error!(
+            "{} was not generated from this seed",
+            key_files().key_file.display()
+        );
+        return Err("key packages differ".into());
+    }
+    info!(
+        "{} is exactly what the committed seed generates",
+        key_files().key_file.display()
+    );
+    output::record("seed_commitment", commitment);
+    output::record("matches", true);
     Ok(())
 }
 
-fn load_map(
-) -> Result<BTreeMap<frost::Identifier, frost::keys::KeyPackage>, Box<dyn std::error::Error>> {
-    // 从文件中读取 JSON 字符串
-    let mut file = File::open("my_map.json")?;
+/// The threshold chosen at `generate` time, or the defaults without a my_map.json.
+fn stored_threshold(files: &KeyFiles) -> Result<(u16, u16), AppError> {
+    if !files.key_file.exists() {
+        return Ok((MIN_SIGNERS, MAX_SIGNERS));
+    }
+    map_threshold(&files.load_map()?)
+}
+
+fn load_map() -> Result<KeyPackages, AppError> {
+    key_files().load_map()
+}
+
+/// The passphrase given with `--passphrase` or KEY_PASSPHRASE, if any.
+fn passphrase() -> Option<&'static str> {
+    key_files().passphrase.as_deref().map(String::as_str)
+}
+
+fn load_map_from(path: &std::path::Path) -> Result<KeyPackages, AppError> {
+    frost_demo::load_map(path, passphrase())
+}
+
+fn write_map(path: &std::path::Path, key_packages: &KeyPackages) -> Result<(), AppError> {
+    frost_demo::write_map(path, key_packages, passphrase())
+}
+
+fn load_pubkey_package() -> Result<frost::keys::PublicKeyPackage, AppError> {
+    let mut file = open_key_artifact(&key_files().pubkey_file)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
+    Ok(serde_json::from_str(&contents)?)
+}
 
-    // 反序列化 JSON 字符串为 BTreeMap
-    let my_map: BTreeMap<_, _> = serde_json::from_str(&contents)?;
-    Ok(my_map)
+/// Checks a public key package from outside against the setup authority's
+/// signature, then writes it where `generate` writes the group's own. An
+/// existing pubkey file holding another group key is never replaced.
+fn import_pubkey_package(
+    package: &std::path::Path,
+    signature: &str,
+    authority_key: &str,
+) -> Result<(), AppError> {
+    let json = std::fs::read(package)
+        .map_err(|err| format!("cannot read {}: {}", package.display(), err))?;
+    let pubkey_package = match frost_demo::import_pubkey_package(&json, signature, authority_key) {
+        Ok(pubkey_package) => pubkey_package,
+        Err(err) => {
+            error!("Refusing {}: {}", package.display(), err);
+            return Err(err);
+        }
+    };
+    let pubkey_file = &key_files().pubkey_file;
+    if pubkey_file.exists() && load_pubkey_package()? != pubkey_package {
+        error!(
+            "{} already holds another group key; move it away to import this one",
+            pubkey_file.display()
+        );
+        return Err(format!("{} holds another group key", pubkey_file.display()).into());
+    }
+    let mut file = create_key_artifact(pubkey_file)?;
+    file.write_all(serde_json::to_string(&pubkey_package)?.as_bytes())?;
+    let fingerprint = group_fingerprint(&pubkey_package)?;
+    info!(
+        "Imported group key {} with {} participants into {}",
+        fingerprint,
+        pubkey_package.verifying_shares().len(),
+        pubkey_file.display()
+    );
+    output::record("fingerprint", fingerprint);
+    output::record(
+        "participants",
+        pubkey_package
+            .verifying_shares()
+            .keys()
+            .map(identifier_label)
+            .collect::<Vec<_>>(),
+    );
+    output::record("pubkey_file", pubkey_file);
+    Ok(())
 }
 
-fn private_key_to_signing_key(
-    private_key_str: &str,
-) -> Result<frost::SigningKey, Box<dyn std::error::Error>> {
-    // 这里需要根据 frost-secp256k1 的 API 来转换私钥字符串为 SigningKey
-    // 以下代码是一个示例，具体实现可能需要根据库的文档进行调整
+/// Upgrades a key file written before `generate` recorded metadata beside
+/// it. The threshold, identifiers and address are inferred from the key
+/// packages, and a threshold of them must sign and verify before anything
+/// is written. The original is copied to `<key file>.bak` first.
+fn migrate(network: Network) -> Result<(), AppError> {
+    let files = key_files();
+    let metadata_file = files.metadata_file();
+    if metadata_file.exists() {
+        error!(
+            "{} already has its metadata in {}",
+            files.key_file.display(),
+            metadata_file.display()
+        );
+        return Err(format!("{} is already migrated", files.key_file.display()).into());
+    }
+    let key_packages = read_map(&files.key_file, passphrase())?;
+    let identifiers = inferred_identifiers(&key_packages);
+    validate_map(&key_packages, identifiers.as_deref())?;
+    let pubkey_package = map_pubkey_package(&key_packages)?;
+    if files.pubkey_file.exists()
+        && load_pubkey_package()?.verifying_key() != pubkey_package.verifying_key()
+    {
+        error!(
+            "{} holds another group's public key package",
+            files.pubkey_file.display()
+        );
+        return Err(format!(
+            "{} and {} describe different groups",
+            files.key_file.display(),
+            files.pubkey_file.display()
+        )
+        .into());
+    }
 
-    // 假设私钥是十六进制字符串，需要将其转换为字节序列
-    let private_key_bytes = hex::decode(private_key_str)?;
+    let signers = default_signers(&key_packages);
+    let signature = sign_message(
+        &key_packages,
+        &pubkey_package,
+        &signers,
+        DEMO_MESSAGE,
+        &mut demo_rng(),
+    )?;
+    if pubkey_package
+        .verifying_key()
+        .verify(DEMO_MESSAGE, &signature)
+        .is_err()
+    {
+        error!("A signature of the migrated key set does not verify against its group key");
+        return Err("the migrated key set does not sign".into());
+    }
+    info!("Signed and verified with participants {:?}", signers);
 
-    // 然后使用 frost-secp256k1 的函数来从字节序列创建 SigningKey
-    // 这里需要查阅 frost-secp256k1 的文档来找到正确的方法
-    let signing_key = frost::SigningKey::deserialize(&private_key_bytes)?;
+    let mut backup_name = files.key_file.as_os_str().to_owned();
+    backup_name.push(".bak");
+    let backup = std::path::PathBuf::from(backup_name);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&backup)
+        .and_then(|mut file| file.write_all(&std::fs::read(&files.key_file)?))
+        .map_err(|err| format!("cannot back up to {}: {}", backup.display(), err))?;
+    info!(
+        "Backed up {} to {}",
+        files.key_file.display(),
+        backup.display()
+    );
+    output::record("backup", backup.display().to_string());
 
-    Ok(signing_key)
+    let metadata = KeyMetadata {
+        identifiers,
+        ..KeyMetadata::default()
+    };
+    write_key_files(files, metadata, &key_packages, &pubkey_package, network)?;
+    say!(
+        "Migrated {} to {}",
+        files.key_file.display(),
+        metadata_file.display()
+    );
+    Ok(())
 }
 
-fn get_keys() -> Result<
-    (
-        BTreeMap<frost::Identifier, frost::keys::KeyPackage>,
-        frost::keys::PublicKeyPackage,
-        rand::rngs::ThreadRng,
-    ),
-    Box<dyn std::error::Error>,
-> {
-    // let mut key_packages = load_map().ok().unwrap();
-    // info!("Key packages: {:?}", key_packages);
-    let mut rng = thread_rng();
+/// The keys of this run, computed by the first `get_keys` and shared by
+/// every later one.
+static KEYS: std::sync::OnceLock<Keys> = std::sync::OnceLock::new();
+
+/// A group's key packages and public key package.
+type Keys = (KeyPackages, frost::keys::PublicKeyPackage);
+
+/// The group's key packages and public key package, with a fresh RNG. They
+/// are loaded once per process; see [`load_keys`].
+fn get_keys() -> Result<(KeyPackages, frost::keys::PublicKeyPackage, DemoRng), AppError> {
+    let (key_packages, pubkey_package) = match KEYS.get() {
+        Some(keys) => keys,
+        None => {
+            let keys = load_keys(key_files(), private_key_source())?;
+            KEYS.get_or_init(|| keys)
+        }
+    };
+    Ok((key_packages.clone(), pubkey_package.clone(), demo_rng()))
+}
 
+/// The persisted key file of `files` when it exists, so every command signs
+/// with the shares the participants hold, or else a split of the secret read
+/// from `source`.
+fn load_keys(files: &KeyFiles, source: &PrivateKeySource) -> Result<Keys, AppError> {
+    if files.key_file.exists() {
+        info!("Using the key packages in {}", files.key_file.display());
+        return load_map_keys(files);
+    }
+    split_private_key(files, source)
+}
+
+fn load_map_keys(files: &KeyFiles) -> Result<Keys, AppError> {
+    let key_packages = files.load_map()?;
+    let pubkey_package = map_pubkey_package(&key_packages)?;
+    Ok((key_packages, pubkey_package))
+}
+
+/// Splits the secret from `source` with the threshold and identifiers of the
+/// key file, or the defaults without one.
+fn split_private_key(files: &KeyFiles, source: &PrivateKeySource) -> Result<Keys, AppError> {
     // 获取私钥字符串
-    let private_key_str = env::var("PRIVATE_KEY")?;
-    // println!("Private key: {}", private_key_str);
+    let private_key_str = read_private_key(source)?;
 
     // 将私钥字符串转换为 SigningKey
     let signing_key = private_key_to_signing_key(&private_key_str)?;
-
-    let (shares, pubkey_package) = frost::keys::split(
+    let (min_signers, max_signers) = stored_threshold(files)?;
+    let identifiers = recorded_identifiers(&files.key_file)?;
+    split_signing_key(
         &signing_key,
-        MAX_SIGNERS,
-        MIN_SIGNERS,
-        frost::keys::IdentifierList::Default,
-        &mut rng,
-    )?;
+        min_signers,
+        max_signers,
+        identifier_list(&identifiers),
+        &mut demo_rng(),
+    )
+}
+
+/// The group's public key package: the persisted one when present, so a
+/// verifier-only node needs no PRIVATE_KEY, or else one split from PRIVATE_KEY.
+fn public_key_package() -> Result<frost::keys::PublicKeyPackage, AppError> {
+    if key_files().pubkey_file.exists() {
+        info!(
+            "Using the group key from {}",
+            key_files().pubkey_file.display()
+        );
+        return load_pubkey_package();
+    }
+    Ok(get_keys()?.1)
+}
 
-    let mut key_packages: BTreeMap<_, _> = BTreeMap::new();
+fn generate_address(
+    expect_fingerprint: Option<&str>,
+    merkle_root: Option<&str>,
+    check_address: Option<Option<&str>>,
+    network: Network,
+) -> Result<(), AppError> {
+    let merkle_root = merkle_root.map(parse_merkle_root).transpose()?;
+    let pubkey_package = public_key_package()?;
 
-    for (identifier, secret_share) in shares {
-        let key_package = frost::keys::KeyPackage::try_from(secret_share)?;
-        key_packages.insert(identifier, key_package);
+    let fingerprint = group_fingerprint(&pubkey_package)?;
+    info!("Group fingerprint: {}", fingerprint);
+    if let Some(expected) = expect_fingerprint {
+        if !expected.trim().eq_ignore_ascii_case(&fingerprint) {
+            error!(
+                "Group fingerprint mismatch: expected {}, computed {}",
+                expected.trim(),
+                fingerprint
+            );
+            return Err("group fingerprint does not match the expected value".into());
+        }
     }
 
-    Ok((key_packages, pubkey_package, rng))
-}
+    let (internal_key, taproot_address) =
+        group_taproot_address(&pubkey_package, merkle_root, network)?;
 
-fn generate_address() -> Result<(), Box<dyn std::error::Error>> {
-    let (_key_packages, pubkey_package, _rng) = get_keys()?;
+    let output_key = taproot::output_key(&pubkey_package, merkle_root)?;
+    let (_, parity) = taproot::internal_key_parity(&pubkey_package)?;
+    info!("Internal key: {}", internal_key);
+    if parity == bitcoin::secp256k1::Parity::Odd {
+        info!("The group key has an odd Y; key-path signers negate their shares");
+    }
+    info!("Output key: {}", output_key);
+    info!("Taproot address: {} ({})", taproot_address, network);
+    // What a BIP86 wallet shows for the group key, with no script tree.
+    let bip86_address = group_bip86_address(&pubkey_package, network)?;
+    if merkle_root.is_some() {
+        info!("BIP86 address: {}", bip86_address);
+    }
+    output::record("taproot_address", taproot_address.to_string());
+    output::record("bip86_address", bip86_address.to_string());
+    output::record("pubkey", internal_key.to_string());
+    output::record("output_key", output_key.to_string());
+    output::record("network", network.to_string());
+    output::record("fingerprint", fingerprint);
 
-    let pubkey_buffer = pubkey_package.verifying_key().serialize()?;
+    match check_address {
+        Some(Some(address)) => check_signing_address(address, network)?,
+        Some(None) => match recorded_address(&key_files().key_file)? {
+            Some(address) => check_signing_address(&address, network)?,
+            None => {
+                error!(
+                    "{} records no address; pass one to --check-address",
+                    key_files().metadata_file().display()
+                );
+                return Err("no persisted address to check".into());
+            }
+        },
+        None => {}
+    }
 
-    let pubkey = bitcoin::secp256k1::PublicKey::from_slice(&pubkey_buffer)?;
+    Ok(())
+}
 
-    let internal_key = UntweakedPublicKey::from(pubkey);
+/// Checks that `persisted` is the address of the key `sign` and `verify`
+/// sign with, so a stale my_map.json or PRIVATE_KEY shows up here rather
+/// than as funds sent to an address nobody can spend.
+fn check_signing_address(persisted: &str, network: Network) -> Result<(), AppError> {
+    let signing_package = match get_keys() {
+        Ok((_, pubkey_package, _)) => pubkey_package,
+        Err(AppError::MissingEnv(_)) => {
+            log::warn!("PRIVATE_KEY is not set; checking the public key package instead of the signing key");
+            public_key_package()?
+        }
+        Err(err) => return Err(err),
+    };
+    let (_, signing_address) = group_taproot_address(&signing_package, None, network)?;
+    let matches = address_matches(persisted, &signing_address)?;
+    output::record("address_matches", matches);
+    if !matches {
+        error!("The signing key does not match the persisted address");
+        error!("Persisted address: {}", persisted.trim());
+        error!("Signing key address: {}", signing_address);
+        return Err("the signing key's address differs from the persisted address".into());
+    }
+    info!(
+        "The signing key matches the persisted address {}",
+        persisted.trim()
+    );
+    Ok(())
+}
 
-    let taproot_address = Address::p2tr(&bitcoin::secp256k1::Secp256k1::new(), internal_key, None, Network::Bitcoin);
+/// Asks the configured policy hook, if any, to approve `signers` signing
+/// `message`.
+fn enforce_policy(
+    policy_hook: Option<&str>,
+    kind: &'static str,
+    message: &[u8],
+    signers: &[frost::Identifier],
+    pubkey_package: &frost::keys::PublicKeyPackage,
+) -> Result<(), AppError> {
+    let Some(policy_hook) = policy_hook else {
+        return Ok(());
+    };
 
-    info!("Taproot address: {}", taproot_address);
+    let request = policy::SigningRequest::new(kind, message, signers, pubkey_package)?;
+    policy::check(policy_hook, &request)?;
+    info!("Signing request approved by policy hook");
 
     Ok(())
 }
 
-fn generate_signature() -> Result<(), Box<dyn std::error::Error>> {
+fn generate_signature(
+    message: &[u8],
+    policy_hook: Option<&str>,
+    selection: &selection::SelectionArgs,
+    checks: &CeremonyChecks,
+    network: Network,
+) -> Result<frost::Signature, AppError> {
+    let started_at = report::unix_now();
     let (key_packages, pubkey_package, mut rng) = get_keys()?;
 
     // info!("pubkey_package: {:?}", pubkey_package);
@@ -220,70 +2083,2892 @@ fn generate_signature() -> Result<(), Box<dyn std::error::Error>> {
     // let taproot_address = Address::p2tr(&bitcoin::secp256k1::Secp256k1::new(), internal_key, None, Network::Bitcoin);
     // info!("Taproot address: {}", taproot_address);
 
-    let mut nonces_map = BTreeMap::new();
-    let mut commitments_map = BTreeMap::new();
-
-    ////////////////////////////////////////////////////////////////////////////
-    // Round 1: generating nonces and signing commitments for each participant
-    ////////////////////////////////////////////////////////////////////////////
-
-    // In practice, each iteration of this loop will be executed by its respective participant.
-    for participant_index in 1..=MIN_SIGNERS {
-        let participant_identifier = participant_index.try_into().expect("should be nonzero");
-        let key_package = &key_packages[&participant_identifier];
-        // Generate one (1) nonce and one SigningCommitments instance for each
-        // participant, up to _threshold_.
-        let (nonces, commitments) = frost::round1::commit(key_package.signing_share(), &mut rng);
-        // In practice, the nonces must be kept by the participant to use in the
-        // next round, while the commitment must be sent to the coordinator
-        // (or to every other participant if there is no coordinator) using
-        // an authenticated channel.
-        nonces_map.insert(participant_identifier, nonces);
-        commitments_map.insert(participant_identifier, commitments);
-    }
-
-    // This is what the signature aggregator / coordinator needs to do:
-    // - decide what message to sign
-    // - take one (unused) commitment per signing participant
-    let mut signature_shares = BTreeMap::new();
-    let message: &[u8] = "0x68c158664c20d9d7df31a747782bcc9d36d1f595c36184ee0fc62627e2a72fc0".as_bytes();
+    let chosen = match selection.chosen_signers(&key_packages) {
+        Ok(chosen) => chosen,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    let signers = match (chosen, selection.selection()?) {
+        (Some(signers), _) => {
+            info!("Signing with {}", participant_labels(&signers).join(","));
+            signers
+        }
+        (None, Some(selection)) => match selection.select(&key_packages) {
+            Ok(signers) => {
+                info!(
+                    "Drew signers {} with seed {}",
+                    participant_labels(&signers).join(","),
+                    hex::encode(selection.seed)
+                );
+                signers
+            }
+            Err(err) => {
+                error!("{}", err);
+                return Err(err);
+            }
+        },
+        (None, None) => default_signers(&key_packages),
+    };
+    let participants: Vec<_> = key_packages.keys().copied().collect();
+    let retried = retry::run(
+        &participants,
+        signers,
+        selection.ceremony_retries,
+        |signers| {
+            enforce_policy(policy_hook, "message", message, signers, &pubkey_package)?;
+            let ceremony =
+                run_ceremony(&key_packages, &pubkey_package, signers, message, &mut rng)?;
+            Ok((signers.to_vec(), ceremony))
+        },
+    );
+    if !retried.excluded.is_empty() {
+        log::warn!(
+            "Left out after failed attempts: {}",
+            participant_labels(&retried.excluded).join(",")
+        );
+    }
+    let (signers, ceremony) = match retried.outcome {
+        Ok(signed) => {
+            if retried.attempts > 1 {
+                info!("Signed on attempt {}", retried.attempts);
+            }
+            signed
+        }
+        Err(err) => {
+            error!(
+                "Signing failed after {} attempt(s): {}",
+                retried.attempts, err
+            );
+            return Err(err);
+        }
+    };
+    let group_signature = ceremony.signature;
+    info!(
+        "Group signature: {:?}",
+        hex::encode(group_signature.serialize()?)
+    );
+    if checks.wire_format {
+        print_wire_frames(&ceremony)?;
+    }
+    if checks.binding_factors {
+        for (identifier, binding_factor) in
+            binding_factors(&ceremony.signing_package, &pubkey_package)?
+        {
+            say!(
+                "{:<12} {}",
+                identifier_label(&identifier),
+                hex::encode(binding_factor.serialize())
+            );
+        }
+    }
+
+    // Check that the threshold signature can be verified by the group public
+    // key (the verification key).
+    let is_signature_valid = pubkey_package
+        .verifying_key()
+        .verify(message, &group_signature)
+        .is_ok();
+    info!("Signature valid: {}", is_signature_valid);
+    if !is_signature_valid {
+        output::record("valid", false);
+        return Err("the group signature doesn't verify".into());
+    }
+    if let Some(audit_log) = AUDIT_LOG.get() {
+        if let Err(err) = audit::append(
+            audit_log,
+            &audit::AuditEntry::new("message", &signers, message, &group_signature)?
+                .with_commitments(&ceremony.signing_package)?,
+        ) {
+            error!(
+                "Failed to write the audit log {}: {}",
+                audit_log.display(),
+                err
+            );
+            return Err(err);
+        }
+    }
+
+    if checks.check_quorum {
+        let (threshold, _) = map_threshold(&key_packages)?;
+        let audit = quorum::audit(
+            &pubkey_package,
+            &ceremony.signing_package,
+            &ceremony.signature_shares,
+            &group_signature,
+            threshold,
+        );
+        for identifier in &audit.invalid {
+            error!(
+                "Share from participant {} is invalid",
+                identifier_label(identifier)
+            );
+        }
+        info!(
+            "Quorum: {} valid shares from {}, threshold {}, aggregate matches: {}",
+            audit.valid.len(),
+            participant_labels(&audit.valid).join(","),
+            audit.threshold,
+            audit.signature_matches
+        );
+        if !audit.met() {
+            error!("The signature was not produced by a full quorum");
+            return Err("quorum not met".into());
+        }
+        info!("Threshold met");
+    }
+
+    if let Some(report_format) = checks.report {
+        let mut report = report::SigningReport {
+            kind: "message",
+            message_hex: hex::encode(message),
+            participants: participant_labels(&signers),
+            signature_hex: hex::encode(group_signature.serialize()?),
+            verified: is_signature_valid,
+            taproot_address: group_taproot_address(&pubkey_package, None, network)?
+                .1
+                .to_string(),
+            started_at,
+            finished_at: report::unix_now(),
+            attestation: None,
+        };
+        if checks.sign_report {
+            let attestation = sign_message(
+                &key_packages,
+                &pubkey_package,
+                &signers,
+                &report.attested_bytes()?,
+                &mut rng,
+            )?;
+            report.attestation = Some(hex::encode(attestation.serialize()?));
+        }
+        output::record("report", &report);
+        say!("{}", report.render(report_format)?);
+    }
+
+    output::record("signature", hex::encode(group_signature.serialize()?));
+    output::record("valid", is_signature_valid);
+    Ok(group_signature)
+}
+
+/// The bytes to sign or verify: `message`, or the contents of
+/// `message_file`, or with `prehash` their SHA-256 digest.
+fn read_message(
+    message: Option<&[u8]>,
+    message_file: Option<&std::path::Path>,
+    prehash: bool,
+) -> Result<Vec<u8>, AppError> {
+    let read = match (message, message_file) {
+        (Some(message), None) if prehash => frost_demo::prehash(message).map(Vec::from),
+        (Some(message), None) => Ok(message.to_vec()),
+        (None, Some(path)) if prehash => File::open(path)
+            .and_then(frost_demo::prehash)
+            .map(Vec::from),
+        (None, Some(path)) => std::fs::read(path),
+        _ => return Err("pass exactly one of --message and --message-file".into()),
+    };
+    let message = match read {
+        Ok(message) => message,
+        Err(err) => {
+            error!(
+                "Failed to read {}: {}",
+                message_file
+                    .unwrap_or(std::path::Path::new("the message"))
+                    .display(),
+                err
+            );
+            return Err(err.into());
+        }
+    };
+    if prehash {
+        info!("Message SHA-256: {}", hex::encode(&message));
+    }
+    Ok(message)
+}
+
+/// Signs `message` and prints the signature.
+fn sign_command(
+    message: &[u8],
+    policy_hook: Option<&str>,
+    selection: &selection::SelectionArgs,
+    format: output::OutputFormat,
+    output: Option<&std::path::Path>,
+    network: Network,
+) -> Result<(), AppError> {
+    let signature = generate_signature(
+        message,
+        policy_hook,
+        selection,
+        &CeremonyChecks::default(),
+        network,
+    )?;
+    output::write(format, "frost-signature", &signature.serialize()?, output)
+}
+
+/// Prints every artifact of `ceremony` as one hex-encoded wire frame per line.
+fn print_wire_frames(ceremony: &Ceremony) -> Result<(), AppError> {
+    let mut messages = Vec::new();
+    for (identifier, commitments) in ceremony.signing_package.signing_commitments() {
+        messages.push(wire::Message::Commitments(
+            *identifier,
+            Box::new(*commitments),
+        ));
+    }
+    messages.push(wire::Message::SigningPackage(
+        ceremony.signing_package.clone(),
+    ));
+    for (identifier, share) in &ceremony.signature_shares {
+        messages.push(wire::Message::SignatureShare(*identifier, *share));
+    }
+    for message in messages {
+        say!("{}", hex::encode(message.encode()?));
+    }
+    Ok(())
+}
+
+fn generate_group_nonce(message: &[u8]) -> Result<(), AppError> {
+    let (key_packages, pubkey_package, mut rng) = get_keys()?;
+
+    // The nonces are dropped here, so this R is only usable by a ceremony that
+    // keeps them around for round 2 (e.g. adaptor signing).
+    let (_nonces_map, commitments_map) =
+        commit_round1(&key_packages, &default_signers(&key_packages), &mut rng)?;
     let signing_package = frost::SigningPackage::new(commitments_map, message);
 
-    ////////////////////////////////////////////////////////////////////////////
-    // Round 2: each participant generates their signature share
-    ////////////////////////////////////////////////////////////////////////////
+    let group_nonce = group_commitment(&signing_package, &pubkey_package)?;
+    let group_nonce = <frost::Secp256K1Group as frost_core::Group>::serialize(&group_nonce)?;
+    say!("{}", hex::encode(group_nonce));
+    output::record("group_nonce", hex::encode(group_nonce));
+
+    Ok(())
+}
+
+fn generate_adaptor_signature(
+    adaptor_point: &str,
+    message: &[u8],
+    policy_hook: Option<&str>,
+) -> Result<(), AppError> {
+    let (key_packages, pubkey_package, mut rng) = get_keys()?;
+    let adaptor_point = adaptor::parse_element(adaptor_point)?;
+    if let Err(err) = enforce_policy(
+        policy_hook,
+        "adaptor",
+        message,
+        &default_signers(&key_packages),
+        &pubkey_package,
+    ) {
+        error!("{}", err);
+        return Err(err);
+    }
 
-    // In practice, each iteration of this loop will be executed by its respective participant.
-    for participant_identifier in nonces_map.keys() {
-        let key_package = &key_packages[participant_identifier];
+    let pre_signature = adaptor::adaptor_sign(
+        &key_packages,
+        &pubkey_package,
+        message,
+        &adaptor_point,
+        &mut rng,
+    )?;
+    say!("{}", hex::encode(pre_signature.serialize()?));
+    output::record("pre_signature", hex::encode(pre_signature.serialize()?));
 
-        let nonces = &nonces_map[participant_identifier];
+    Ok(())
+}
 
-        // Each participant generates their signature share.
-        let signature_share = frost::round2::sign(&signing_package, nonces, key_package)?;
+fn verify_adaptor_signature(
+    pre_signature: &str,
+    adaptor_point: &str,
+    message: &[u8],
+) -> Result<(), AppError> {
+    let pubkey_package = public_key_package()?;
+    let pre_signature = adaptor::PreSignature::deserialize(&hex::decode(pre_signature.trim())?)?;
+    let adaptor_point = adaptor::parse_element(adaptor_point)?;
 
-        // In practice, the signature share must be sent to the Coordinator
-        // using an authenticated channel.
-        signature_shares.insert(*participant_identifier, signature_share);
+    let is_valid = adaptor::adaptor_verify(
+        pubkey_package.verifying_key(),
+        message,
+        &adaptor_point,
+        &pre_signature,
+    )
+    .is_ok();
+    say!("Pre-signature valid: {}", is_valid);
+    output::record("valid", is_valid);
+    if !is_valid {
+        return Err("invalid pre-signature".into());
     }
 
-    ////////////////////////////////////////////////////////////////////////////
-    // Aggregation: collects the signing shares from all participants,
-    // generates the final signature.
-    ////////////////////////////////////////////////////////////////////////////
+    Ok(())
+}
+
+fn complete_adaptor_signature(
+    pre_signature: &str,
+    adaptor_secret: &str,
+    message: &[u8],
+) -> Result<(), AppError> {
+    let pubkey_package = public_key_package()?;
+    let pre_signature = adaptor::PreSignature::deserialize(&hex::decode(pre_signature.trim())?)?;
+    let adaptor_secret = adaptor::parse_scalar(adaptor_secret)?;
 
-    // Aggregate (also verifies the signature shares)
-    let group_signature = frost::aggregate(&signing_package, &signature_shares, &pubkey_package)?;
-    info!("Group signature: {:?}", hex::encode(group_signature.serialize()?));
+    let signature = adaptor::adaptor_complete(&pre_signature, &adaptor_secret);
+    pubkey_package.verifying_key().verify(message, &signature)?;
+    say!("{}", hex::encode(signature.serialize()?));
+    output::record("signature", hex::encode(signature.serialize()?));
 
-    // Check that the threshold signature can be verified by the group public
-    // key (the verification key).
+    Ok(())
+}
+
+fn extract_adaptor_secret(pre_signature: &str, signature: &str) -> Result<(), AppError> {
+    let pre_signature = adaptor::PreSignature::deserialize(&hex::decode(pre_signature.trim())?)?;
+    let signature = frost::Signature::deserialize(&hex::decode(signature.trim())?)?;
+
+    let adaptor_secret = adaptor::extract_secret(&pre_signature, &signature)?;
+    let adaptor_secret =
+        hex::encode(<frost::Secp256K1ScalarField as frost_core::Field>::serialize(&adaptor_secret));
+    say!("{}", adaptor_secret);
+    output::record("adaptor_secret", adaptor_secret);
+
+    Ok(())
+}
+
+fn generate_blind_signature(message: &[u8]) -> Result<(), AppError> {
+    let (key_packages, pubkey_package, mut rng) = get_keys()?;
+    let (blinded_challenge, signature) = blind::blind_sign(
+        &key_packages,
+        &pubkey_package,
+        &default_signers(&key_packages),
+        message,
+        &mut rng,
+    )?;
+    let blinded_challenge = hex::encode(
+        <frost::Secp256K1ScalarField as frost_core::Field>::serialize(&blinded_challenge),
+    );
+    info!(
+        "Blinded challenge seen by the signers: {}",
+        blinded_challenge
+    );
     let is_signature_valid = pubkey_package
         .verifying_key()
-        .verify(message, &group_signature)
+        .verify(message, &signature)
         .is_ok();
-    info!("Signature valid: {}", is_signature_valid);
-    assert!(is_signature_valid);
+    info!("Signature verified: {}", is_signature_valid);
+    output::record("blinded_challenge", blinded_challenge);
+    output::record("valid", is_signature_valid);
+    if !is_signature_valid {
+        error!("The unblinded signature does not verify against the group key");
+        return Err("invalid group signature".into());
+    }
+    say!("{}", hex::encode(signature.serialize()?));
+    output::record("signature", hex::encode(signature.serialize()?));
+    Ok(())
+}
+
+#[cfg(feature = "rpc")]
+/// Builds the spend, has the group sign it and asks the node whether its
+/// mempool would accept the result.
+fn test_mempool_accept(
+    spend: &SpendArgs,
+    network: Network,
+    rpc_args: &rpc::RpcArgs,
+) -> Result<(), AppError> {
+    let mut psbt = spend_psbt(spend, network)?;
+    sign_group_psbt(&mut psbt, None)?;
+    let tx = psbt::finalize_key_path(psbt, None)?;
+    let tx_hex = bitcoin::consensus::encode::serialize_hex(&tx);
+    info!(
+        "Testing transaction {} against the node mempool",
+        tx.compute_txid()
+    );
+    output::record("txid", tx.compute_txid().to_string());
+    output::record("tx", &tx_hex);
+
+    let client = rpc::RpcClient::new(rpc_args)?;
+    let results = match client.call("testmempoolaccept", serde_json::json!([[tx_hex]])) {
+        Ok(results) => results,
+        Err(err @ rpc::RpcError::Unavailable(_)) => {
+            log::warn!("Skipping mempool acceptance check: {}", err);
+            return Err(err.into());
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let result = &results[0];
+    if result["allowed"].as_bool() == Some(true) {
+        say!("Mempool accepted: true");
+        output::record("accepted", true);
+        return Ok(());
+    }
+    let reason = result["reject-reason"].as_str().unwrap_or("unknown");
+    say!("Mempool accepted: false (reason: {})", reason);
+    output::record("accepted", false);
+    output::record("reject_reason", reason);
+    Err(format!("the node's mempool rejects the transaction: {reason}").into())
+}
+
+#[cfg(feature = "rpc")]
+fn estimate_confirmation(fee_rate: Option<f64>, rpc_args: &rpc::RpcArgs) -> Result<(), AppError> {
+    let client = rpc::RpcClient::new(rpc_args)?;
+    let mut estimates = Vec::new();
+    for &target in chain::CONFIRMATION_TARGETS {
+        let sat_per_vb = match chain::fee_estimate(&client, target) {
+            Ok(sat_per_vb) => sat_per_vb,
+            Err(err @ rpc::RpcError::Unavailable(_)) => {
+                log::warn!("Can't estimate confirmation times: {}", err);
+                return Err(err.into());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        estimates.push((target, sat_per_vb));
+    }
+
+    say!("{:>7}  {:>9}  {:>12}", "blocks", "minutes", "sat/vB");
+    for (target, sat_per_vb) in &estimates {
+        let sat_per_vb = sat_per_vb.map_or("n/a".to_string(), |rate| format!("{rate:.1}"));
+        say!(
+            "{:>7}  {:>9}  {:>12}",
+            target,
+            u32::from(*target) * 10,
+            sat_per_vb
+        );
+    }
+    output::record(
+        "estimates",
+        estimates
+            .iter()
+            .map(|(target, sat_per_vb)| serde_json::json!({ "blocks": target, "sat_per_vb": sat_per_vb }))
+            .collect::<Vec<_>>(),
+    );
+
+    if let Some(fee_rate) = fee_rate {
+        match chain::confirmation_target(&estimates, fee_rate) {
+            Some(target) => {
+                info!(
+                    "{} sat/vB is expected to confirm within {} blocks (~{} minutes)",
+                    fee_rate,
+                    target,
+                    u32::from(target) * 10
+                );
+                output::record("confirmation_target", target);
+            }
+            None => log::warn!(
+                "{} sat/vB is below every estimate; it may not confirm within {} blocks",
+                fee_rate,
+                chain::CONFIRMATION_TARGETS[chain::CONFIRMATION_TARGETS.len() - 1]
+            ),
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(feature = "rpc")]
+fn consolidate(
+    fee_rate: Option<f64>,
+    target: u16,
+    merkle_root: Option<&str>,
+    rbf: bool,
+    network: Network,
+    rpc_args: &rpc::RpcArgs,
+) -> Result<(), AppError> {
+    let merkle_root = merkle_root.map(parse_merkle_root).transpose()?;
+    let pubkey_package = public_key_package()?;
+    let internal_key = taproot::internal_key(&pubkey_package)?;
+    let address = group_taproot_address(&pubkey_package, merkle_root, network)?.1;
+
+    let client = rpc::RpcClient::new(rpc_args)?;
+    info!("Scanning the UTXO set for {}", address);
+    let utxos = match chain::scan_utxos(&client, &address) {
+        Ok(utxos) => utxos,
+        Err(AppError::Rpc(err @ rpc::RpcError::Unavailable(_))) => {
+            log::warn!("Can't list the group's UTXOs: {}", err);
+            return Err(err.into());
+        }
+        Err(err) => return Err(err),
+    };
+    let estimate = chain::fee_estimate(&client, target)?;
+    let fee_rate = psbt::checked_fee_rate(fee_rate, estimate, target)?;
+
+    let mut psbt = match psbt::consolidation(&utxos, fee_rate, internal_key, merkle_root, rbf) {
+        Ok(psbt) => psbt,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    let total: bitcoin::Amount = utxos.iter().map(|(_, amount)| *amount).sum();
+    let value = psbt.unsigned_tx.output[0].value;
+    info!(
+        "Consolidating {} UTXOs worth {} at {} sat/vB, paying {} in fees",
+        utxos.len(),
+        total,
+        fee_rate,
+        total - value
+    );
+    sign_group_psbt(&mut psbt, None)?;
+    let tx = psbt::finalize_key_path(psbt, None)?;
+
+    output::record("inputs", utxos.len());
+    output::record("amount", value.to_sat());
+    output::record("fee", (total - value).to_sat());
+    output::record("fee_rate", fee_rate);
+    output::record("tx", bitcoin::consensus::encode::serialize_hex(&tx));
+    say!("{}", bitcoin::consensus::encode::serialize_hex(&tx));
+    Ok(())
+}
+
+#[cfg(feature = "rpc")]
+fn scan_addresses(
+    descriptor: &str,
+    count: u32,
+    network: Network,
+    rpc_args: &rpc::RpcArgs,
+) -> Result<(), AppError> {
+    let addresses = match taproot::descriptor_addresses(descriptor, count, network) {
+        Ok(addresses) => addresses,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    let scripts: Vec<_> = addresses.iter().map(Address::script_pubkey).collect();
+    let client = rpc::RpcClient::new(rpc_args)?;
+    info!(
+        "Scanning the UTXO set for {} child addresses",
+        addresses.len()
+    );
+    let balances = match chain::scan_descriptor(&client, descriptor, &scripts) {
+        Ok(balances) => balances,
+        Err(AppError::Rpc(err @ rpc::RpcError::Unavailable(_))) => {
+            log::warn!("Can't scan the descriptor's addresses: {}", err);
+            return Err(err.into());
+        }
+        Err(err) => return Err(err),
+    };
+
+    say!(
+        "{:<6} {:<64} {:>5} {:>16}",
+        "index",
+        "address",
+        "utxos",
+        "balance"
+    );
+    let mut rows = Vec::new();
+    for (index, (address, (utxos, balance))) in addresses.iter().zip(&balances).enumerate() {
+        say!(
+            "{:<6} {:<64} {:>5} {:>16}",
+            index,
+            address,
+            utxos,
+            balance.to_string_in(bitcoin::Denomination::Bitcoin)
+        );
+        rows.push(serde_json::json!({
+            "index": index,
+            "address": address.to_string(),
+            "utxos": utxos,
+            "balance": balance.to_sat(),
+        }));
+    }
+    let total: bitcoin::Amount = balances.iter().map(|(_, balance)| *balance).sum();
+    let funded = balances.iter().filter(|(utxos, _)| *utxos > 0).count();
+    say!(
+        "total  {} in {} of {} addresses",
+        total
+            .display_in(bitcoin::Denomination::Bitcoin)
+            .show_denomination(),
+        funded,
+        addresses.len()
+    );
+    output::record("addresses", rows);
+    output::record("funded", funded);
+    output::record("total", total.to_sat());
+    Ok(())
+}
+
+#[cfg(feature = "rpc")]
+fn bump_fee(
+    tx_hex: &str,
+    fee_rate: Option<f64>,
+    target: u16,
+    merkle_root: Option<&str>,
+    rpc_args: &rpc::RpcArgs,
+) -> Result<(), AppError> {
+    let original: bitcoin::Transaction =
+        bitcoin::consensus::encode::deserialize_hex(tx_hex.trim())?;
+    let merkle_root = merkle_root.map(parse_merkle_root).transpose()?;
+    let pubkey_package = public_key_package()?;
+    let internal_key = taproot::internal_key(&pubkey_package)?;
+
+    let client = rpc::RpcClient::new(rpc_args)?;
+    let prevouts = match chain::prevouts(&client, &original) {
+        Ok(prevouts) => prevouts,
+        Err(AppError::Rpc(err @ rpc::RpcError::Unavailable(_))) => {
+            log::warn!(
+                "Can't look up the outputs {} spends: {}",
+                original.compute_txid(),
+                err
+            );
+            return Err(err.into());
+        }
+        Err(err) => return Err(err),
+    };
+    let estimate = chain::fee_estimate(&client, target)?;
+    let fee_rate = psbt::checked_fee_rate(fee_rate, estimate, target)?;
+
+    let mut psbt = match psbt::fee_bump(&original, &prevouts, fee_rate, internal_key, merkle_root) {
+        Ok(psbt) => psbt,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    let total_in: bitcoin::Amount = prevouts.iter().map(|prevout| prevout.value).sum();
+    let fee = |tx: &bitcoin::Transaction| {
+        total_in
+            - tx.output
+                .iter()
+                .map(|output| output.value)
+                .sum::<bitcoin::Amount>()
+    };
+    let original_fee = fee(&original);
+    let replacement_fee = fee(&psbt.unsigned_tx);
+    info!(
+        "Replacing {} at {} sat/vB, raising the fee from {} to {}",
+        original.compute_txid(),
+        fee_rate,
+        original_fee,
+        replacement_fee
+    );
+    sign_group_psbt(&mut psbt, None)?;
+    let tx = psbt::finalize_key_path(psbt, None)?;
+
+    output::record("replaces", original.compute_txid().to_string());
+    output::record("txid", tx.compute_txid().to_string());
+    output::record("original_fee", original_fee.to_sat());
+    output::record("fee", replacement_fee.to_sat());
+    output::record("fee_rate", fee_rate);
+    output::record("tx", bitcoin::consensus::encode::serialize_hex(&tx));
+    say!("{}", bitcoin::consensus::encode::serialize_hex(&tx));
+    Ok(())
+}
+
+#[cfg(feature = "rpc")]
+fn check_timelock(
+    script_hex: &str,
+    confirmed_height: Option<u32>,
+    rpc_args: &rpc::RpcArgs,
+) -> Result<(), AppError> {
+    let script = bitcoin::ScriptBuf::from_hex(script_hex.trim())?;
+    let timelocks = timelock::script_timelocks(&script);
+    if timelocks.is_empty() {
+        info!("Script has no CSV or CLTV timelock");
+        output::record("timelocks", Vec::<serde_json::Value>::new());
+        output::record("mature", true);
+        return Ok(());
+    }
+
+    let client = rpc::RpcClient::new(rpc_args)?;
+    let chain = match client.call("getblockchaininfo", serde_json::json!([])) {
+        Ok(chain) => chain,
+        Err(err) => {
+            error!("Can't read the chain tip: {}", err);
+            return Err(err.into());
+        }
+    };
+    let tip = timelock::Tip {
+        height: chain["blocks"]
+            .as_u64()
+            .ok_or("getblockchaininfo without blocks")?
+            .try_into()?,
+        median_time: chain["mediantime"]
+            .as_u64()
+            .ok_or("getblockchaininfo without mediantime")?
+            .try_into()?,
+    };
+    let confirmation = match confirmed_height {
+        Some(height) => Some(timelock::Confirmation {
+            height,
+            median_time: Some(chain::median_time_at(&client, height.saturating_sub(1))?),
+        }),
+        None => None,
+    };
+
+    let mut all_mature = true;
+    let mut maturities = Vec::new();
+    for timelock in timelocks {
+        let maturity = match timelock::maturity(timelock, tip, confirmation) {
+            Ok(maturity) => maturity,
+            Err(err) => {
+                error!("{}: {}", timelock, err);
+                return Err(err);
+            }
+        };
+        all_mature &= maturity.mature;
+        info!(
+            "{}: requires {}, chain is at {} ({})",
+            timelock,
+            maturity.required,
+            maturity.current,
+            if maturity.mature {
+                "mature"
+            } else {
+                "not yet mature"
+            }
+        );
+        maturities.push(serde_json::json!({
+            "timelock": timelock.to_string(),
+            "required": maturity.required.to_string(),
+            "current": maturity.current.to_string(),
+            "mature": maturity.mature,
+        }));
+    }
+    output::record("timelocks", maturities);
+    output::record("mature", all_mature);
+    if !all_mature {
+        error!("Timelock not satisfied; a spend would be rejected until it matures");
+        return Err("timelock not satisfied".into());
+    }
+    Ok(())
+}
+
+fn encrypt_env_file(input: &str, output: &str, env_key: Option<&str>) -> Result<(), AppError> {
+    let env_key = env_key.ok_or("--env-key is required to encrypt an environment file")?;
+    let plaintext = std::fs::read(input)?;
+    let sealed = envfile::encrypt(&envfile::parse_key(env_key)?, &plaintext)?;
+
+    let mut file = File::create(output)?;
+    file.write_all(&sealed)?;
+    info!(
+        "Encrypted {} to {}; the plaintext file can now be removed",
+        input, output
+    );
+    output::record("output", output);
+
+    Ok(())
+}
+
+fn reconstruct(
+    participants: &[String],
+    format: output::OutputFormat,
+    output: Option<&std::path::Path>,
+) -> Result<(), AppError> {
+    let key_packages = load_map()?;
+    let participants = participants
+        .iter()
+        .map(|participant| parse_identifier(participant))
+        .collect::<Result<Vec<_>, _>>()?;
+    let signing_key = match reconstruct_group_key(&key_packages, &participants) {
+        Ok(signing_key) => signing_key,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+
+    // The persisted public package is the reference when there is one.
+    if key_files().pubkey_file.exists() {
+        let pubkey_package = load_pubkey_package()?;
+        if frost::VerifyingKey::from(&signing_key) != *pubkey_package.verifying_key() {
+            error!(
+                "Reconstructed key doesn't match {}",
+                key_files().pubkey_file.display()
+            );
+            return Err("reconstructed key doesn't match the public key package".into());
+        }
+        info!(
+            "Matches the group key in {}",
+            key_files().pubkey_file.display()
+        );
+    }
+
+    log::warn!("This is the whole group secret: whoever holds it can spend alone");
+    output::write_secret(
+        format,
+        "frost-signing-key",
+        &signing_key.serialize(),
+        output,
+    )
+}
+
+#[cfg(feature = "rpc")]
+fn emergency_sweep(
+    sweep: &SweepArgs,
+    network: Network,
+    rpc_args: &rpc::RpcArgs,
+) -> Result<(), AppError> {
+    let destination = match sweep
+        .destination
+        .parse::<Address<_>>()
+        .map(|address| address.require_network(network))
+    {
+        Ok(Ok(destination)) => destination,
+        Ok(Err(err)) => {
+            error!("{}", err);
+            return Err(err.into());
+        }
+        Err(err) => {
+            error!("Invalid destination address: {}", err);
+            return Err(err.into());
+        }
+    };
+    let merkle_root = sweep
+        .merkle_root
+        .as_deref()
+        .map(parse_merkle_root)
+        .transpose()?;
+    let pubkey_package = public_key_package()?;
+    let internal_key = taproot::internal_key(&pubkey_package)?;
+    let address = group_taproot_address(&pubkey_package, merkle_root, network)?.1;
+
+    // Reconstructing puts the whole key in one place, so the operator must
+    // name the address they mean to empty.
+    if sweep.confirm.as_deref().map(str::trim) != Some(address.to_string().as_str()) {
+        error!(
+            "Sweeping reconstructs the full group key in this process; confirm by passing the group's address as --confirm"
+        );
+        return Err("--confirm must be the group's taproot address".into());
+    }
+    log::warn!(
+        "Reconstructing the group secret: it signs alone, bypassing the threshold, and anyone who sees this process can spend"
+    );
+    let participants = sweep
+        .participants
+        .iter()
+        .map(|participant| parse_identifier(participant))
+        .collect::<Result<Vec<_>, _>>()?;
+    let signing_key = match reconstruct_group_key(&load_map()?, &participants) {
+        Ok(signing_key) => signing_key,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    if frost::VerifyingKey::from(&signing_key) != *pubkey_package.verifying_key() {
+        error!(
+            "Reconstructed key doesn't match {}",
+            key_files().pubkey_file.display()
+        );
+        return Err("reconstructed key doesn't match the public key package".into());
+    }
+
+    let client = rpc::RpcClient::new(rpc_args)?;
+    info!("Scanning the UTXO set for {}", address);
+    let utxos = match chain::scan_utxos(&client, &address) {
+        Ok(utxos) => utxos,
+        Err(AppError::Rpc(err @ rpc::RpcError::Unavailable(_))) => {
+            log::warn!("Can't list the group's UTXOs: {}", err);
+            return Err(err.into());
+        }
+        Err(err) => return Err(err),
+    };
+    let estimate = chain::fee_estimate(&client, sweep.target)?;
+    let fee_rate = psbt::checked_fee_rate(sweep.fee_rate, estimate, sweep.target)?;
+
+    let mut psbt = match psbt::sweep(
+        &utxos,
+        &destination.script_pubkey(),
+        fee_rate,
+        internal_key,
+        merkle_root,
+        true,
+    ) {
+        Ok(psbt) => psbt,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    psbt::sign_inputs_with_secret(&mut psbt, &signing_key, &mut thread_rng())?;
+    let total: bitcoin::Amount = utxos.iter().map(|(_, amount)| *amount).sum();
+    let value = psbt.unsigned_tx.output[0].value;
+    let tx = psbt::finalize_key_path(psbt, None)?;
+    let tx_hex = bitcoin::consensus::encode::serialize_hex(&tx);
+
+    let txid = client.call("sendrawtransaction", serde_json::json!([tx_hex]))?;
+    log::warn!(
+        "Broadcast the sweep of {} UTXOs worth {} to {}; move every other fund of this group too, since its key is no longer secret",
+        utxos.len(),
+        total,
+        destination
+    );
+    output::record("txid", txid.as_str().unwrap_or_default());
+    output::record("inputs", utxos.len());
+    output::record("amount", value.to_sat());
+    output::record("fee", (total - value).to_sat());
+    output::record("fee_rate", fee_rate);
+    output::record("tx", tx_hex);
+    say!("{}", txid.as_str().unwrap_or_default());
+    Ok(())
+}
+
+fn export_cold_backup(
+    output: &str,
+    backup_key: Option<&str>,
+    env_key: Option<&str>,
+) -> Result<(), AppError> {
+    let backup_key = envfile::parse_key(
+        backup_key
+            .ok_or("--backup-key (or FROST_BACKUP_KEY) is required to encrypt a cold backup")?,
+    )?;
+    // The backup is meant to be stored apart from everything else, so a key
+    // shared with the environment file would tie the two back together.
+    if env_key.map(envfile::parse_key).transpose()? == Some(backup_key) {
+        error!("The backup key is the --env-key; pick a key of its own for the backup");
+        return Err("--backup-key must differ from --env-key".into());
+    }
+    let key_packages = load_map()?;
+    let first = key_packages
+        .values()
+        .next()
+        .ok_or_else(|| format!("{} has no key packages", key_files().key_file.display()))?;
+
+    log::warn!(
+        "Reconstructing the group secret: whoever holds this backup and its key can spend alone"
+    );
+    let signing_key = match reconstruct_group_key(&key_packages, &[]) {
+        Ok(signing_key) => signing_key,
+        Err(err) => {
+            error!("{}; not writing a backup", err);
+            return Err(err);
+        }
+    };
+
+    let backup = coldbackup::export(&backup_key, &signing_key)?;
+    let restored = coldbackup::restore(&backup_key, &backup)?;
+    if frost::VerifyingKey::from(&restored) != *first.verifying_key() {
+        return Err("cold backup doesn't restore to the group key".into());
+    }
+
+    let mut file = create_secret_file(std::path::Path::new(output))?;
+    file.write_all(backup.as_bytes())?;
+    info!(
+        "Wrote cold backup to {}; it restores to group key {}",
+        output,
+        hex::encode(first.verifying_key().serialize()?)
+    );
+    output::record("output", output);
+    output::record("group_key", hex::encode(first.verifying_key().serialize()?));
+    log::warn!(
+        "Store {} and the --backup-key separately, offline, and never together",
+        output
+    );
+    Ok(())
+}
+
+/// Restores the backup into an environment file encrypted with `env_key`,
+/// or into a plaintext one if asked to.
+fn restore_cold_backup(
+    input: &str,
+    output: Option<&str>,
+    backup_key: Option<&str>,
+    plaintext: bool,
+    env_key: Option<&str>,
+) -> Result<(), AppError> {
+    let backup_key = envfile::parse_key(
+        backup_key
+            .ok_or("--backup-key (or FROST_BACKUP_KEY) is required to decrypt a cold backup")?,
+    )?;
+    let env_key = (!plaintext)
+        .then(|| {
+            envfile::parse_key(env_key.ok_or(
+                "--env-key is required to encrypt the restored key; pass --plaintext to write it unencrypted",
+            )?)
+        })
+        .transpose()?;
+    let output = output.unwrap_or(match env_key {
+        Some(_) => envfile::ENCRYPTED_ENV_FILE,
+        None => ".env",
+    });
+    if std::path::Path::new(output).exists() {
+        error!("{} already exists; refusing to overwrite it", output);
+        return Err(format!("{output} already exists").into());
+    }
+    let signing_key = match coldbackup::restore(&backup_key, &std::fs::read_to_string(input)?) {
+        Ok(signing_key) => signing_key,
+        Err(err) => {
+            error!("Failed to restore {}: {}", input, err);
+            return Err(err);
+        }
+    };
+    let verifying_key = frost::VerifyingKey::from(&signing_key);
+    info!(
+        "Restored group key {}",
+        hex::encode(verifying_key.serialize()?)
+    );
+    if let Ok(key_packages) = load_map() {
+        match key_packages.values().next() {
+            Some(key_package) if key_package.verifying_key() == &verifying_key => {
+                info!(
+                    "Matches the group key in {}",
+                    key_files().key_file.display()
+                );
+            }
+            _ => log::warn!(
+                "Doesn't match the group key in {}",
+                key_files().key_file.display()
+            ),
+        }
+    }
+
+    let env = zeroize::Zeroizing::new(format!(
+        "PRIVATE_KEY={}\n",
+        hex::encode(signing_key.serialize())
+    ));
+    let mut file = create_secret_file(std::path::Path::new(output))?;
+    match env_key {
+        Some(env_key) => {
+            file.write_all(&envfile::encrypt(&env_key, env.as_bytes())?)?;
+            info!("Wrote PRIVATE_KEY to {}, encrypted with --env-key", output);
+        }
+        None => {
+            file.write_all(env.as_bytes())?;
+            log::warn!("Wrote PRIVATE_KEY to {} unencrypted", output);
+        }
+    }
+    output::record("output", output);
+    output::record("encrypted", env_key.is_some());
+    output::record("group_key", hex::encode(verifying_key.serialize()?));
+    Ok(())
+}
+
+fn print_features() {
+    let ciphersuite = <frost::Secp256K1Sha256 as frost_core::Ciphersuite>::ID;
+    if output::json_enabled() {
+        let features: serde_json::Map<_, _> = FEATURES
+            .iter()
+            .map(|(name, enabled)| (name.to_string(), serde_json::Value::Bool(*enabled)))
+            .collect();
+        output::record("ciphersuite", ciphersuite);
+        output::record("features", features);
+    } else {
+        say!("ciphersuite: {ciphersuite}");
+        for (name, enabled) in FEATURES {
+            say!("{name}: {}", if *enabled { "enabled" } else { "disabled" });
+        }
+    }
+}
+
+fn verify_descriptor_address(address: &str, descriptor: &str, scan: u32) -> Result<(), AppError> {
+    let address: Address<bitcoin::address::NetworkUnchecked> = address.trim().parse()?;
+    match taproot::find_descriptor_index(&address, descriptor, scan)? {
+        Some(index) => {
+            info!("Descriptor match: address is derived at index {}", index);
+            output::record("index", index);
+        }
+        None => {
+            error!(
+                "Descriptor mismatch: address not found in the first {} indices",
+                scan
+            );
+            return Err("address does not belong to the descriptor".into());
+        }
+    }
+
+    Ok(())
+}
+
+fn compute_bip322_sighash(message: &str, address: &str) -> Result<(), AppError> {
+    let pubkey_package = public_key_package()?;
+    let address: Address<bitcoin::address::NetworkUnchecked> = address.trim().parse()?;
+    let address = address.assume_checked();
+
+    if address.address_type() != Some(bitcoin::AddressType::P2tr) {
+        return Err("BIP322 sighash is only supported for taproot addresses".into());
+    }
+    // Only the script pubkey is compared, which is the same on every network.
+    let (_internal_key, group_address) =
+        group_taproot_address(&pubkey_package, None, Network::Bitcoin)?;
+    if address.script_pubkey() != group_address.script_pubkey() {
+        error!(
+            "Address {} is not the group address {}",
+            address, group_address
+        );
+        return Err("address does not belong to the group key".into());
+    }
+
+    let sighash = bip322::taproot_sighash(&address.script_pubkey(), message.as_bytes())?;
+    say!("{}", hex::encode(sighash.to_byte_array()));
+    output::record("sighash", hex::encode(sighash.to_byte_array()));
+
+    Ok(())
+}
+
+fn generate_npub() -> Result<(), AppError> {
+    let pubkey_package = public_key_package()?;
+    let (internal_key, _taproot_address) =
+        group_taproot_address(&pubkey_package, None, Network::Bitcoin)?;
+
+    let npub = nostr::npub_encode(&internal_key)?;
+    if nostr::npub_decode(&npub)? != internal_key {
+        return Err("npub does not decode back to the group key".into());
+    }
+    info!("Pubkey : {}", internal_key);
+    say!("{}", npub);
+    output::record("npub", &npub);
+    output::record("pubkey", internal_key.to_string());
+
+    Ok(())
+}
+
+fn sign_nostr_event(event: nostr::UnsignedEvent) -> Result<(), AppError> {
+    let (key_packages, pubkey_package, mut rng) = get_keys()?;
+    let npub = nostr::npub_encode(&taproot::internal_key(&pubkey_package)?)?;
+    let event = match nostr::sign_event(
+        &key_packages,
+        &pubkey_package,
+        &default_signers(&key_packages),
+        event,
+        &mut rng,
+    ) {
+        Ok(event) => event,
+        Err(err) => {
+            error!("Signing the event failed: {}", err);
+            return Err(err);
+        }
+    };
+    nostr::verify_event(&event, &npub)?;
+    info!("Event {} verifies against {}", event.id, npub);
+    say!("{}", serde_json::to_string(&event)?);
+    output::record("event", &event);
+    output::record("npub", npub);
+    Ok(())
+}
+fn print_ln_node_id() -> Result<(), AppError> {
+    let node_id = ln_node_id(&public_key_package()?)?;
+    say!("{}", node_id);
+    output::record("node_id", node_id.to_string());
+    info!("Only the identity is derived; channel operations need signatures this tool doesn't produce");
+    Ok(())
+}
+
+fn print_security_level(min_signers: u16, max_signers: u16) -> Result<(), AppError> {
+    if min_signers == 0 || min_signers > max_signers {
+        error!("Invalid threshold {}-of-{}", min_signers, max_signers);
+        return Err("threshold must satisfy 1 <= min_signers <= max_signers".into());
+    }
+
+    say!("configuration         {}-of-{}", min_signers, max_signers);
+    say!(
+        "fault tolerance       {} (compromised shares that still can't forge)",
+        min_signers - 1
+    );
+    say!(
+        "liveness tolerance    {} (shares that can be offline or lost)",
+        max_signers - min_signers
+    );
+    say!(
+        "assessment            {}",
+        security_assessment(min_signers, max_signers)
+    );
+    output::record("min_signers", min_signers);
+    output::record("max_signers", max_signers);
+    output::record("fault_tolerance", min_signers - 1);
+    output::record("liveness_tolerance", max_signers - min_signers);
+    output::record("assessment", security_assessment(min_signers, max_signers));
+
+    Ok(())
+}
+
+/// Runs a full sign and verify with each threshold subset of the key packages
+/// in my_map.json, or with `samples` distinct random subsets.
+fn check_threshold_subsets(samples: Option<usize>) -> Result<(), AppError> {
+    let key_packages = load_map()?;
+    let first = key_packages
+        .values()
+        .next()
+        .ok_or_else(|| format!("{} has no key packages", key_files().key_file.display()))?;
+    let threshold = usize::from(*first.min_signers());
+    let pubkey_package = map_pubkey_package(&key_packages)?;
+
+    let identifiers: Vec<_> = key_packages.keys().copied().collect();
+    let total = subset_count(identifiers.len(), threshold);
+    let mut rng = demo_rng();
+    let subsets = sampled_subsets(&identifiers, threshold, samples, &mut rng);
+    info!(
+        "Checking {} of {} {}-of-{} subsets",
+        subsets.len(),
+        total,
+        threshold,
+        identifiers.len()
+    );
+
+    let mut failures = 0;
+    let mut failed = Vec::new();
+    let outcomes = check_subsets(&key_packages, &pubkey_package, &subsets, &mut rng);
+    for (subset, outcome) in subsets.iter().zip(outcomes) {
+        let label = subset
+            .iter()
+            .map(identifier_label)
+            .collect::<Vec<_>>()
+            .join(",");
+        match outcome {
+            Ok(()) => info!("pass  {}", label),
+            Err(err) => {
+                failures += 1;
+                error!("FAIL  {}: {}", label, err);
+                failed.push(label);
+            }
+        }
+    }
+    output::record("checked", subsets.len());
+    output::record("total", total.to_string());
+    output::record("failed", failed);
+
+    if failures > 0 {
+        error!(
+            "{} of {} subsets failed to produce a valid signature",
+            failures,
+            subsets.len()
+        );
+        return Err(format!("{failures} threshold subsets failed").into());
+    }
+    info!("All {} subsets produced a valid signature", subsets.len());
+    Ok(())
+}
+
+/// Prints serialize/deserialize timings and file sizes of each key-file format
+/// for groups of each size in `signer_counts`, keeping the demo's threshold ratio.
+fn bench_serialization(signer_counts: &[u16], iterations: u32) -> Result<(), AppError> {
+    if iterations == 0 {
+        return Err("--iterations must be at least 1".into());
+    }
+
+    say!(
+        "{:>7}  {:<8}  {:>10}  {:>12}  {:>12}",
+        "signers",
+        "format",
+        "bytes",
+        "serialize",
+        "deserialize"
+    );
+    let mut samples = Vec::new();
+    for &max_signers in signer_counts {
+        let min_signers = (max_signers * MIN_SIGNERS / MAX_SIGNERS).max(2);
+        let key_packages = match dealer_keygen(min_signers, max_signers, &mut demo_rng()) {
+            Ok((key_packages, _)) => key_packages,
+            Err(err) => {
+                error!(
+                    "Can't build a {}-of-{} group: {}",
+                    min_signers, max_signers, err
+                );
+                return Err(err);
+            }
+        };
+        for sample in bench::serialization(&key_packages, iterations)? {
+            say!(
+                "{:>7}  {:<8}  {:>10}  {:>12?}  {:>12?}",
+                sample.signers,
+                sample.format.name(),
+                sample.size,
+                sample.serialize,
+                sample.deserialize
+            );
+            samples.push(serde_json::json!({
+                "signers": sample.signers,
+                "format": sample.format.name(),
+                "bytes": sample.size,
+                "serialize_micros": sample.serialize.as_micros().to_string(),
+                "deserialize_micros": sample.deserialize.as_micros().to_string(),
+            }));
+        }
+    }
+    output::record("samples", samples);
+    Ok(())
+}
+
+/// Times `operations` consecutive operations that each split PRIVATE_KEY or
+/// load my_map.json, against `get_keys`, which does it once per process.
+fn bench_keys(operations: u32) -> Result<(), AppError> {
+    if operations == 0 {
+        return Err("--operations must be at least 1".into());
+    }
+    let mut sources = vec![(
+        "split PRIVATE_KEY".to_string(),
+        (|| split_private_key(key_files(), private_key_source())) as fn() -> _,
+    )];
+    if key_files().key_file.exists() {
+        sources.push((format!("load {}", key_files().key_file.display()), || {
+            load_map_keys(key_files())
+        }));
+    }
+
+    say!(
+        "{:<18}  {:>14}  {:>12}",
+        "source",
+        "per operation",
+        "shared"
+    );
+    let mut timings = Vec::new();
+    for (name, load) in sources {
+        let (per_operation, shared) = match bench::key_reuse(operations, load) {
+            Ok(timings) => timings,
+            Err(err) => {
+                error!("Can't time {}: {}", name, err);
+                return Err(err);
+            }
+        };
+        say!("{:<18}  {:>14?}  {:>12?}", name, per_operation, shared);
+        timings.push(serde_json::json!({
+            "source": name,
+            "per_operation_ns": per_operation.as_nanos() as u64,
+            "shared_ns": shared.as_nanos() as u64,
+        }));
+    }
+    output::record("operations", operations);
+    output::record("timings", timings);
+    Ok(())
+}
+
+/// Signs the messages in order and prints one signature per line, in the
+/// same order.
+fn sign_batch(
+    messages: &[String],
+    messages_file: Option<&std::path::Path>,
+    bench: bool,
+) -> Result<(), AppError> {
+    let mut owned: Vec<Vec<u8>> = messages
+        .iter()
+        .map(|message| message.as_bytes().to_vec())
+        .collect();
+    if let Some(path) = messages_file {
+        match parse_hex_messages(&std::fs::read_to_string(path)?) {
+            Ok(file_messages) => owned.extend(file_messages),
+            Err(err) => {
+                error!("Invalid messages file {}: {}", path.display(), err);
+                return Err(err);
+            }
+        }
+    }
+    if owned.is_empty() {
+        return Err("no messages to sign".into());
+    }
+    let messages: Vec<&[u8]> = owned.iter().map(Vec::as_slice).collect();
+    let (key_packages, pubkey_package, mut rng) = get_keys()?;
+    let signatures = sign_messages(
+        &key_packages,
+        &pubkey_package,
+        &default_signers(&key_packages),
+        &messages,
+        &mut rng,
+    )?;
+    let mut signature_hexes = Vec::with_capacity(signatures.len());
+    for (message, signature) in messages.iter().zip(&signatures) {
+        pubkey_package.verifying_key().verify(message, signature)?;
+        let signature_hex = hex::encode(signature.serialize()?);
+        info!("{}: {}", hex::encode(message), signature_hex);
+        say!("{}", signature_hex);
+        signature_hexes.push(signature_hex);
+    }
+    output::record("signatures", signature_hexes);
+
+    if bench {
+        let (naive, cached) =
+            bench::batch_signing(&messages, || load_keys(key_files(), private_key_source()))?;
+        say!("{:<24}  {:>12}", "path", "time");
+        say!("{:<24}  {:>12?}", "reload keys per message", naive);
+        say!("{:<24}  {:>12?}", "load keys once", cached);
+    }
+    Ok(())
+}
+
+fn classify_spend(tx_hex: &str) -> Result<(), AppError> {
+    let tx: bitcoin::Transaction = match bitcoin::consensus::encode::deserialize_hex(tx_hex.trim())
+    {
+        Ok(tx) => tx,
+        Err(err) => {
+            error!("Invalid transaction: {}", err);
+            return Err(err.into());
+        }
+    };
+
+    let mut inputs = Vec::new();
+    for (index, input) in tx.input.iter().enumerate() {
+        match spend::classify(&input.witness) {
+            spend::TaprootSpend::KeyPath { signature_len } => {
+                say!(
+                    "input {}: key path ({}-byte signature)",
+                    index,
+                    signature_len
+                );
+                inputs.push(
+                    serde_json::json!({ "spend": "key_path", "signature_len": signature_len }),
+                );
+            }
+            spend::TaprootSpend::ScriptPath {
+                leaf_version,
+                script,
+                depth,
+                inputs: script_inputs,
+            } => {
+                say!(
+                    "input {}: script path (leaf version {}, depth {}, {} script inputs)",
+                    index,
+                    leaf_version,
+                    depth,
+                    script_inputs
+                );
+                say!("  script: {}", script.to_asm_string());
+                inputs.push(serde_json::json!({
+                    "spend": "script_path",
+                    "leaf_version": leaf_version.to_consensus(),
+                    "depth": depth,
+                    "script_inputs": script_inputs,
+                    "script": script.to_asm_string(),
+                }));
+            }
+            spend::TaprootSpend::Unknown => {
+                say!("input {}: not a taproot spend", index);
+                inputs.push(serde_json::json!({ "spend": "unknown" }));
+            }
+        }
+    }
+    output::record("inputs", inputs);
+    Ok(())
+}
+
+fn simulate_equivocation(equivocate: bool) -> Result<(), AppError> {
+    let (key_packages, pubkey_package, mut rng) = get_keys()?;
+    let outcome = equivocation::simulate(
+        &key_packages,
+        &pubkey_package,
+        &default_signers(&key_packages),
+        DEMO_MESSAGE,
+        b"pay the coordinator",
+        equivocate,
+        &mut rng,
+    )?;
+
+    match outcome {
+        equivocation::Outcome::Signed(signature) => {
+            info!(
+                "No equivocation detected; group signature: {}",
+                hex::encode(signature.serialize()?)
+            );
+            output::record("equivocation_detected", false);
+            output::record("signature", hex::encode(signature.serialize()?));
+        }
+        equivocation::Outcome::Detected(views) => {
+            error!(
+                "Equivocation detected: signers received {} different signing packages",
+                views.len()
+            );
+            let mut packages = BTreeMap::new();
+            for (digest, identifiers) in &views {
+                let identifiers: Vec<_> = identifiers.iter().map(identifier_label).collect();
+                error!("  package {} seen by {}", digest, identifiers.join(","));
+                packages.insert(digest.to_string(), identifiers);
+            }
+            output::record("equivocation_detected", true);
+            output::record("views", packages);
+            info!("Ceremony aborted before round 2; no signature shares were produced");
+        }
+    }
+    Ok(())
+}
+
+fn check_consistency() -> Result<(), AppError> {
+    let (_, env_pubkey_package) = split_private_key(key_files(), private_key_source())?;
+    let env_group_key = env_pubkey_package.verifying_key();
+
+    let stored_key_packages = load_map()?;
+    let mut stored_group_keys: Vec<_> = stored_key_packages
+        .values()
+        .map(|key_package| *key_package.verifying_key())
+        .collect();
+    stored_group_keys.dedup();
+    let [stored_group_key] = stored_group_keys[..] else {
+        let key_file = key_files().key_file.display();
+        error!("{} holds key packages of more than one group", key_file);
+        return Err(format!("{key_file} is not a single group").into());
+    };
+
+    let consistent = stored_group_key == *env_group_key;
+    output::record("consistent", consistent);
+    output::record("env_group_key", hex::encode(env_group_key.serialize()?));
+    output::record("map_group_key", hex::encode(stored_group_key.serialize()?));
+    if !consistent {
+        log::warn!(
+            "PRIVATE_KEY and {} describe different groups; one of them is stale",
+            key_files().key_file.display()
+        );
+        log::warn!(
+            "PRIVATE_KEY group key: {}",
+            hex::encode(env_group_key.serialize()?)
+        );
+        log::warn!(
+            "{} group key: {}",
+            key_files().key_file.display(),
+            hex::encode(stored_group_key.serialize()?)
+        );
+        return Err("group keys diverge".into());
+    }
+    info!(
+        "PRIVATE_KEY and {} share group key {}",
+        key_files().key_file.display(),
+        hex::encode(stored_group_key.serialize()?)
+    );
+    Ok(())
+}
+
+fn decode_wire_frame(frame_hex: &str) -> Result<(), AppError> {
+    let message = match wire::Message::decode(&hex::decode(frame_hex.trim())?) {
+        Ok(message) => message,
+        Err(err) => {
+            error!("Invalid wire frame: {}", err);
+            return Err(err);
+        }
+    };
+    match message {
+        wire::Message::Commitments(identifier, commitments) => {
+            info!(
+                "Signing commitments from participant {}",
+                identifier_label(&identifier)
+            );
+            info!(
+                "  hiding:  {}",
+                hex::encode(commitments.hiding().serialize()?)
+            );
+            info!(
+                "  binding: {}",
+                hex::encode(commitments.binding().serialize()?)
+            );
+            output::record("kind", "commitments");
+            output::record("participant", identifier_label(&identifier));
+            output::record("hiding", hex::encode(commitments.hiding().serialize()?));
+            output::record("binding", hex::encode(commitments.binding().serialize()?));
+        }
+        wire::Message::SigningPackage(signing_package) => {
+            let signers = participant_labels(signing_package.signing_commitments().keys());
+            info!("Signing package for participants {}", signers.join(","));
+            info!("  message: {}", hex::encode(signing_package.message()));
+            output::record("kind", "signing_package");
+            output::record("participants", signers);
+            output::record("message", hex::encode(signing_package.message()));
+        }
+        wire::Message::SignatureShare(identifier, share) => {
+            info!(
+                "Signature share from participant {}: {}",
+                identifier_label(&identifier),
+                hex::encode(share.serialize())
+            );
+            output::record("kind", "signature_share");
+            output::record("participant", identifier_label(&identifier));
+            output::record("share", hex::encode(share.serialize()));
+        }
+    }
+    Ok(())
+}
+
+fn cluster_worker(queue: &std::path::Path, worker_id: &str) -> Result<(), AppError> {
+    let (key_packages, pubkey_package, mut rng) = get_keys()?;
+    let signers = default_signers(&key_packages);
+    let signed = cluster::work(
+        queue,
+        worker_id,
+        &key_packages,
+        &pubkey_package,
+        &signers,
+        &mut rng,
+    )?;
+    info!("Worker {} signed {} jobs", worker_id, signed);
+    output::record("worker", worker_id);
+    output::record("signed", signed);
+    Ok(())
+}
+
+fn cluster_sign(
+    messages: &[String],
+    queue: &std::path::Path,
+    workers: usize,
+) -> Result<(), AppError> {
+    if queue.exists() {
+        error!(
+            "{} already exists; pick a fresh queue directory",
+            queue.display()
+        );
+        return Err("queue directory already exists".into());
+    }
+    let pubkey_package = public_key_package()?;
+    let messages: Vec<&[u8]> = messages.iter().map(|message| message.as_bytes()).collect();
+    cluster::submit(queue, &messages)?;
+
+    let start = std::time::Instant::now();
+    let mut children = Vec::new();
+    for index in 0..workers {
+        let mut worker = std::process::Command::new(env::current_exe()?);
+        worker
+            .arg("--key-file")
+            .arg(&key_files().key_file)
+            .arg("--pubkey-file")
+            .arg(&key_files().pubkey_file)
+            .arg("cluster-worker")
+            .arg("--queue")
+            .arg(queue)
+            .arg("--worker-id")
+            .arg(format!("worker-{index}"));
+        // Through the environment rather than argv, where other users can see it.
+        if let Some(passphrase) = passphrase() {
+            worker.env("KEY_PASSPHRASE", passphrase);
+        }
+        // Workers read the key from where this process did.
+        match PRIVATE_KEY_SOURCE.get() {
+            Some(PrivateKeySource::File(path)) => {
+                worker.arg("--private-key-file").arg(path);
+            }
+            Some(PrivateKeySource::Stdin) => {
+                worker
+                    .arg("--private-key-stdin")
+                    .stdin(std::process::Stdio::piped());
+            }
+            _ => {}
+        }
+        let mut child = worker.spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(read_private_key(&PrivateKeySource::Stdin)?.as_bytes())?;
+        }
+        children.push(child);
+    }
+    for mut child in children {
+        if !child.wait()?.success() {
+            log::warn!("A worker exited with an error; its claimed jobs stay in the queue");
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let results = cluster::collect(queue)?;
+    let cluster::Tally {
+        per_worker,
+        invalid,
+    } = cluster::tally(&results, &pubkey_package)?;
+    for job in &invalid {
+        error!("Job {} has an invalid signature", job);
+    }
+
+    say!("{:<12}  {:>6}  {:>10}", "worker", "jobs", "jobs/s");
+    for (worker, (jobs, micros)) in &per_worker {
+        say!(
+            "{:<12}  {:>6}  {:>10.1}",
+            worker,
+            jobs,
+            *jobs as f64 / (*micros as f64 / 1e6)
+        );
+    }
+    say!(
+        "{:<12}  {:>6}  {:>10.1}  ({}/{} jobs in {:?}, {} invalid)",
+        "total",
+        results.len(),
+        results.len() as f64 / elapsed.as_secs_f64(),
+        results.len(),
+        messages.len(),
+        elapsed,
+        invalid.len()
+    );
+
+    output::record(
+        "workers",
+        per_worker
+            .iter()
+            .map(|(worker, (jobs, _))| (worker.to_string(), *jobs))
+            .collect::<BTreeMap<_, _>>(),
+    );
+    output::record("jobs", messages.len());
+    output::record("signed", results.len());
+    output::record("invalid", invalid.len());
+    if results.len() != messages.len() || !invalid.is_empty() {
+        return Err("not every job produced a valid signature".into());
+    }
+    Ok(())
+}
+
+fn combine_psbts(psbts: &[String]) -> Result<(), AppError> {
+    let psbts = match psbts
+        .iter()
+        .map(|psbt| psbt.trim().parse())
+        .collect::<Result<Vec<bitcoin::Psbt>, _>>()
+    {
+        Ok(psbts) => psbts,
+        Err(err) => {
+            error!("Invalid PSBT: {}", err);
+            return Err(err.into());
+        }
+    };
+    let combined = match psbt::combine(psbts) {
+        Ok(combined) => combined,
+        Err(err) => {
+            error!("Cannot combine the PSBTs: {}", err);
+            return Err(err);
+        }
+    };
+
+    // Lets the group's own key-path signature be told apart from the others.
+    let group_output_key = public_key_package().ok().and_then(|pubkey_package| {
+        let (_, address) = group_taproot_address(&pubkey_package, None, Network::Bitcoin).ok()?;
+        bitcoin::XOnlyPublicKey::from_slice(&address.script_pubkey().as_bytes()[2..]).ok()
+    });
+
+    let contributions = psbt::contributions(&combined)?;
+    for contribution in &contributions {
+        let is_group = matches!(contribution.signer, psbt::Signer::TaprootKey(key) if Some(key) == group_output_key);
+        info!(
+            "input {}: {}{} {}",
+            contribution.input,
+            contribution.signer,
+            if is_group { " (FROST group)" } else { "" },
+            if contribution.valid {
+                "valid"
+            } else {
+                "INVALID"
+            }
+        );
+    }
+    let invalid = contributions
+        .iter()
+        .filter(|contribution| !contribution.valid)
+        .count();
+    info!(
+        "{} signatures from co-signers, {} invalid",
+        contributions.len(),
+        invalid
+    );
+    if invalid > 0 {
+        return Err("the combined PSBT carries invalid signatures".into());
+    }
+
+    output::record("psbt", combined.to_string());
+    say!("{}", combined);
+    Ok(())
+}
+
+fn refresh_shares(
+    key_output: &std::path::Path,
+    pubkey_output: &std::path::Path,
+) -> Result<(), AppError> {
+    for output in [key_output, pubkey_output] {
+        if output.exists() {
+            error!(
+                "{} already exists; refusing to overwrite it",
+                output.display()
+            );
+            return Err("output file already exists".into());
+        }
+    }
+    let key_packages = load_map()?;
+    let mut rng = demo_rng();
+    let (refreshed, pubkey_package) = refresh::refresh(&key_packages, &mut rng)?;
+
+    write_map(key_output, &refreshed)?;
+    let key_file = &key_files().key_file;
+    if metadata_file_for(key_file).exists() {
+        // The seed commitment no longer describes the refreshed shares.
+        let KeyMetadata {
+            identifiers,
+            taproot_address,
+            min_signers,
+            ..
+        } = read_metadata(key_file)?;
+        let metadata = KeyMetadata {
+            identifiers,
+            taproot_address,
+            min_signers,
+            ..KeyMetadata::default()
+        };
+        create_key_artifact(&metadata_file_for(key_output))?
+            .write_all((serde_json::to_string_pretty(&metadata)? + "\n").as_bytes())?;
+    }
+    create_key_artifact(pubkey_output)?
+        .write_all(serde_json::to_string(&pubkey_package)?.as_bytes())?;
+    info!(
+        "Refreshed {} shares into {}",
+        refreshed.len(),
+        key_output.display()
+    );
+    if let Some(audit_log) = AUDIT_LOG.get() {
+        let change = audit::QuorumChange::attest(
+            "refresh",
+            audit::QuorumParameters::of(&key_packages)?,
+            &refreshed,
+            &pubkey_package,
+            &mut rng,
+        )?;
+        if let Err(err) = audit::append(audit_log, &change) {
+            error!(
+                "Failed to write the audit log {}: {}",
+                audit_log.display(),
+                err
+            );
+            return Err(err);
+        }
+        info!("Recorded the refresh in {}", audit_log.display());
+    }
+    info!("Group fingerprint: {}", group_fingerprint(&pubkey_package)?);
+    output::record("key_file", key_output);
+    output::record("pubkey_file", pubkey_output);
+    output::record("shares", refreshed.len());
+    output::record("fingerprint", group_fingerprint(&pubkey_package)?);
+    Ok(())
+}
+
+fn verify_audit_log(log: Option<&std::path::Path>) -> Result<(), AppError> {
+    let log = log
+        .or(AUDIT_LOG.get().map(|path| path.as_path()))
+        .ok_or("--log (or --audit-log) names the audit log to verify")?;
+    let pubkey_package = public_key_package()?;
+    let check = match audit::read(log)
+        .and_then(|entries| audit::verify_log(&entries, pubkey_package.verifying_key()))
+    {
+        Ok(check) => check,
+        Err(err) => {
+            error!("{}: {}", log.display(), err);
+            return Err(err);
+        }
+    };
+    say!(
+        "{} quorum changes verified against the group key in {}; {} signing ceremonies",
+        check.quorum_changes,
+        key_files().pubkey_file.display(),
+        check.ceremonies
+    );
+    output::record("quorum_changes", check.quorum_changes);
+    output::record("ceremonies", check.ceremonies);
+    output::record("valid", true);
+    Ok(())
+}
+
+/// `identifiers` in the `--participant-order` order, for a report.
+/// Enrollment order is the one recorded next to the key file; without one
+/// the identifiers are the default 1..=n and already in that order.
+fn report_order<'a>(
+    identifiers: impl IntoIterator<Item = &'a frost::Identifier>,
+) -> Vec<frost::Identifier> {
+    let identifiers: Vec<_> = identifiers.into_iter().copied().collect();
+    match PARTICIPANT_ORDER.get().copied().unwrap_or_default() {
+        report::ParticipantOrder::Identifier => {
+            let mut ordered = identifiers;
+            ordered.sort();
+            ordered
+        }
+        report::ParticipantOrder::Enrollment => {
+            let enrolled = recorded_identifiers(&key_files().key_file).unwrap_or_else(|err| {
+                log::warn!("Listing participants by identifier: {}", err);
+                None
+            });
+            report::enrollment_order(&identifiers, enrolled.as_deref().unwrap_or_default())
+        }
+    }
+}
+
+/// Labels of `identifiers` for a report, see [`report_order`].
+fn participant_labels<'a>(
+    identifiers: impl IntoIterator<Item = &'a frost::Identifier>,
+) -> Vec<String> {
+    report_order(identifiers)
+        .iter()
+        .map(identifier_label)
+        .collect()
+}
+
+/// Reports every round 1 commitment the audit log records in more than one
+/// ceremony, and every nonces file whose commitments a logged ceremony
+/// already used, and fails if there is any.
+fn check_nonce_reuse(
+    log: Option<&std::path::Path>,
+    nonces: &[std::path::PathBuf],
+) -> Result<(), AppError> {
+    let log = log
+        .or(AUDIT_LOG.get().map(|path| path.as_path()))
+        .ok_or("--log (or --audit-log) names the audit log to scan")?;
+    let mut sessions = match audit::read(log) {
+        Ok(entries) => audit::logged_sessions(&entries),
+        Err(err) => {
+            error!("{}: {}", log.display(), err);
+            return Err(err);
+        }
+    };
+    let logged = sessions.len();
+    for path in nonces {
+        // Read, not taken: the file is evidence. The commitments are
+        // recomputed from the scalars, like `rounds::sign_share` does.
+        let file: rounds::NoncesFile = rounds::read_round(path)?;
+        let mut derived = frost::round1::SigningNonces::from_nonces(
+            *file.nonces.hiding(),
+            *file.nonces.binding(),
+        );
+        let commitments = [(file.identifier, *derived.commitments())].into();
+        zeroize::Zeroize::zeroize(&mut derived);
+        sessions.push((
+            format!("nonces file {}", path.display()),
+            audit::commitments_hex(&commitments)?,
+        ));
+    }
+    let reused = audit::nonce_reuse(&sessions);
+    report_nonce_reuse(&reused);
+    say!(
+        "{} logged ceremonies and {} nonces files scanned, {} reused commitments",
+        logged,
+        nonces.len(),
+        reused.len()
+    );
+    output::record("ceremonies", logged);
+    if !reused.is_empty() {
+        return Err(format!("{} reused commitments", reused.len()).into());
+    }
+    Ok(())
+}
+
+/// Warns about each reused commitment, naming the participant and every
+/// session it appears in, and records them as `reused_nonces`.
+fn report_nonce_reuse(reused: &[audit::NonceReuse]) {
+    for reuse in reused {
+        let participant = hex::decode(&reuse.participant)
+            .ok()
+            .and_then(|bytes| frost::Identifier::deserialize(&bytes).ok())
+            .map_or(reuse.participant.clone(), |identifier| {
+                identifier_label(&identifier)
+            });
+        error!(
+            "NONCE REUSE: participant {} used commitments {} in {}",
+            participant,
+            reuse.commitments,
+            reuse.sessions.join(", ")
+        );
+    }
+    output::record("reused_nonces", reused);
+}
+
+/// Refuses to sign over commitments that a ceremony in the audit log already
+/// used: signing with the same nonces twice gives the signer's share away.
+/// Without an audit log there is nothing to check against.
+fn refuse_reused_nonces(signing_package: &frost::SigningPackage) -> Result<(), AppError> {
+    let Some(audit_log) = AUDIT_LOG.get().filter(|path| path.exists()) else {
+        return Ok(());
+    };
+    let this_session = "this session".to_string();
+    let mut sessions = audit::logged_sessions(&audit::read(audit_log)?);
+    sessions.push((
+        this_session.clone(),
+        audit::commitments_hex(signing_package.signing_commitments())?,
+    ));
+    let reused: Vec<_> = audit::nonce_reuse(&sessions)
+        .into_iter()
+        .filter(|reuse| reuse.sessions.contains(&this_session))
+        .collect();
+    if reused.is_empty() {
+        return Ok(());
+    }
+    report_nonce_reuse(&reused);
+    Err(format!(
+        "refusing to sign: {} of the commitments were already used in {}",
+        reused.len(),
+        audit_log.display()
+    )
+    .into())
+}
+
+fn check_refresh(old: &std::path::Path, new: &std::path::Path) -> Result<(), AppError> {
+    let check = match refresh::check(&load_map_from(old)?, &load_map_from(new)?) {
+        Ok(check) => check,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+
+    let order = report_order(check.shares.iter().map(|share| &share.identifier));
+    let mut shares: Vec<_> = check.shares.iter().collect();
+    shares.sort_by_key(|share| order.iter().position(|id| *id == share.identifier));
+    say!("{:<12}  {:<8}  {:<8}", "participant", "old", "new");
+    for share in &shares {
+        say!(
+            "{:<12}  {:<8}  {:<8}{}",
+            identifier_label(&share.identifier),
+            share.old_fingerprint,
+            share.new_fingerprint,
+            if share.changed() { "" } else { "  UNCHANGED" }
+        );
+    }
+    output::record(
+        "shares",
+        shares
+            .iter()
+            .map(|share| {
+                serde_json::json!({
+                    "participant": identifier_label(&share.identifier),
+                    "old": share.old_fingerprint,
+                    "new": share.new_fingerprint,
+                    "changed": share.changed(),
+                })
+            })
+            .collect::<Vec<_>>(),
+    );
+    output::record("group_key_unchanged", check.group_key_unchanged());
+    output::record("passed", check.passed());
+    if check.group_key_unchanged() {
+        info!(
+            "Group key unchanged: {}",
+            hex::encode(check.new_group_key.serialize()?)
+        );
+    } else {
+        error!(
+            "Group key changed from {} to {}",
+            hex::encode(check.old_group_key.serialize()?),
+            hex::encode(check.new_group_key.serialize()?)
+        );
+    }
+    if !check.passed() {
+        error!("The refresh did not replace every share of the same group");
+        return Err("refresh check failed".into());
+    }
+    info!("Every share was refreshed");
+    Ok(())
+}
+
+/// Signs a BIP341 sighash with the group's tweaked key and prints the 64-byte
+/// BIP340 signature for the input's witness.
+fn sign_key_path(
+    sighash: &SighashSource,
+    merkle_root: Option<&str>,
+    format: output::OutputFormat,
+    output: Option<&std::path::Path>,
+    network: Network,
+) -> Result<(), AppError> {
+    let sighash = match sighash.sighash() {
+        Ok(sighash) => sighash,
+        Err(err) => {
+            error!("Cannot compute the sighash: {}", err);
+            return Err(err);
+        }
+    };
+    info!("Signing sighash {}", hex::encode(sighash));
+    let merkle_root = merkle_root.map(parse_merkle_root).transpose()?;
+    let (key_packages, pubkey_package, mut rng) = get_keys()?;
+
+    let signers = default_signers(&key_packages);
+    let signature = match taproot::sign_key_path(
+        &key_packages,
+        &pubkey_package,
+        &signers,
+        &sighash,
+        merkle_root,
+        &mut rng,
+    ) {
+        Ok(signature) => signature,
+        Err(err) => {
+            error!("Key-path signing failed: {}", err);
+            return Err(err);
+        }
+    };
+    let (_, address) = group_taproot_address(&pubkey_package, merkle_root, network)?;
+    info!("Valid key-path signature for {}", address);
+    output::write(format, "bip340-signature", signature.as_ref(), output)
+}
+
+/// Prints the address of the group's key committed to `scripts`, and for
+/// each script the leaf hash and control block a script-path spend needs.
+/// The key path stays with the group; pass the printed merkle root to
+/// `--merkle-root` to sign it.
+fn script_tree(scripts: &[String], network: Network) -> Result<(), AppError> {
+    let scripts = scripts
+        .iter()
+        .enumerate()
+        .map(|(index, script)| {
+            hex::decode(script.trim())
+                .map(bitcoin::ScriptBuf::from_bytes)
+                .map_err(|err| AppError::from(format!("script {index} is not hex: {err}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let pubkey_package = public_key_package()?;
+    let tree = match taproot::script_tree(&pubkey_package, &scripts) {
+        Ok(tree) => tree,
+        Err(err) => {
+            error!("Cannot build the script tree: {}", err);
+            return Err(err);
+        }
+    };
+    let (internal_key, address) =
+        group_taproot_address(&pubkey_package, Some(tree.merkle_root), network)?;
+    info!("Internal key: {}", internal_key);
+    info!("Merkle root: {}", tree.merkle_root);
+    info!("Output key: {}", tree.output_key);
+    info!("Taproot address: {} ({})", address, network);
+    let mut leaves = Vec::new();
+    for (index, leaf) in tree.leaves.iter().enumerate() {
+        let control_block = hex::encode(leaf.control_block.serialize());
+        say!("{:<3} {} {}", index, leaf.leaf_hash, control_block);
+        leaves.push(serde_json::json!({
+            "script": hex::encode(leaf.script.as_bytes()),
+            "leaf_hash": leaf.leaf_hash.to_string(),
+            "control_block": control_block,
+        }));
+    }
+    output::record("taproot_address", address.to_string());
+    output::record("pubkey", internal_key.to_string());
+    output::record("output_key", tree.output_key.to_string());
+    output::record("merkle_root", tree.merkle_root.to_string());
+    output::record("network", network.to_string());
+    output::record("leaves", leaves);
+    Ok(())
+}
+
+/// The unsigned PSBT of `spend`.
+fn spend_psbt(spend: &SpendArgs, network: Network) -> Result<bitcoin::Psbt, AppError> {
+    if let Some(tx_spec) = &spend.tx_spec {
+        return tx_spec_psbt(tx_spec, spend, network);
+    }
+    let outpoint: bitcoin::OutPoint =
+        match spend.outpoint.as_deref().unwrap_or_default().trim().parse() {
+            Ok(outpoint) => outpoint,
+            Err(err) => {
+                error!("The outpoint must be <txid>:<vout>: {}", err);
+                return Err(format!("invalid outpoint: {err}").into());
+            }
+        };
+    let destination = match spend
+        .destination
+        .as_deref()
+        .unwrap_or_default()
+        .parse::<Address<_>>()
+        .map(|address| address.require_network(network))
+    {
+        Ok(Ok(destination)) => destination,
+        Ok(Err(err)) => {
+            error!("{}", err);
+            return Err(err.into());
+        }
+        Err(err) => {
+            error!("Invalid destination address: {}", err);
+            return Err(err.into());
+        }
+    };
+    let (amount, fee) = match (
+        psbt::parse_amount(spend.amount.as_deref().unwrap_or_default(), spend.unit),
+        psbt::parse_amount(spend.fee.as_deref().unwrap_or_default(), spend.unit),
+    ) {
+        (Ok(amount), Ok(fee)) => (amount, fee),
+        (Err(err), _) | (_, Err(err)) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    let merkle_root = spend
+        .merkle_root
+        .as_deref()
+        .map(parse_merkle_root)
+        .transpose()?;
+    let pubkey_package = public_key_package()?;
+    let internal_key = taproot::internal_key(&pubkey_package)?;
+
+    let psbt = match psbt::key_path_spend(
+        outpoint,
+        amount,
+        fee,
+        &destination,
+        internal_key,
+        merkle_root,
+        spend.rbf,
+    ) {
+        Ok(psbt) => psbt,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    info!(
+        "Spends {} from {}",
+        outpoint,
+        group_taproot_address(&pubkey_package, merkle_root, network)?.1
+    );
+    Ok(psbt)
+}
+
+/// The PSBT the JSON file `tx_spec` describes, see [`psbt::TxSpec`].
+fn tx_spec_psbt(
+    tx_spec: &std::path::Path,
+    spend: &SpendArgs,
+    network: Network,
+) -> Result<bitcoin::Psbt, AppError> {
+    let spec = psbt::TxSpec::parse(&std::fs::read_to_string(tx_spec)?)
+        .and_then(|spec| spec.resolve(spend.unit, network));
+    let (inputs, outputs) = match spec {
+        Ok(spec) => spec,
+        Err(err) => {
+            error!("{}: {}", tx_spec.display(), err);
+            return Err(err);
+        }
+    };
+    let merkle_root = spend
+        .merkle_root
+        .as_deref()
+        .map(parse_merkle_root)
+        .transpose()?;
+    let pubkey_package = public_key_package()?;
+    let internal_key = taproot::internal_key(&pubkey_package)?;
+
+    let psbt =
+        match psbt::key_path_transaction(&inputs, &outputs, internal_key, merkle_root, spend.rbf) {
+            Ok(psbt) => psbt,
+            Err(err) => {
+                error!("{}", err);
+                return Err(err);
+            }
+        };
+    info!(
+        "Spends {} outputs of {} to {} outputs",
+        inputs.len(),
+        group_taproot_address(&pubkey_package, merkle_root, network)?.1,
+        outputs.len()
+    );
+    Ok(psbt)
+}
+
+fn build_tx(spend: &SpendArgs, network: Network) -> Result<(), AppError> {
+    let psbt = spend_psbt(spend, network)?;
+    let sighashes = (0..psbt.inputs.len())
+        .map(|index| psbt::key_path_sighash(&psbt, index, None).map(hex::encode))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (index, sighash) in sighashes.iter().enumerate() {
+        info!("Key-path sighash of input {}: {}", index, sighash);
+    }
+    output::record("sighash", sighashes[0].clone());
+    output::record("sighashes", sighashes);
+    output::record("psbt", psbt.to_string());
+    say!("{}", psbt);
+    Ok(())
+}
+
+/// Signs each key-path input of `psbt_base64` whose output key is the group's,
+/// using the merkle root recorded in the input.
+fn sign_psbt(
+    psbt_base64: &str,
+    extract: bool,
+    allowed_destinations: Option<&std::path::Path>,
+    max_amount: Option<&str>,
+    annex: Option<&str>,
+    network: Network,
+) -> Result<(), AppError> {
+    let mut psbt: bitcoin::Psbt = match psbt_base64.trim().parse() {
+        Ok(psbt) => psbt,
+        Err(err) => {
+            error!("Invalid PSBT: {}", err);
+            return Err(err.into());
+        }
+    };
+    let change = if allowed_destinations.is_some() || max_amount.is_some() {
+        let internal_key = taproot::internal_key(&public_key_package()?)?;
+        policy::change_scripts(&psbt, internal_key)
+    } else {
+        Vec::new()
+    };
+    if let Some(allowed_destinations) = allowed_destinations {
+        let allowlist =
+            policy::parse_allowlist(&std::fs::read_to_string(allowed_destinations)?, network)?;
+        if let Err(err) = policy::check_destinations(&psbt, &allowlist, &change, network) {
+            error!("Refusing to sign: {}", err);
+            return Err(err);
+        }
+        info!(
+            "Every output pays one of the {} allowed destinations or the group",
+            allowlist.len()
+        );
+    }
+    if let Some(max_amount) = max_amount {
+        let limit = psbt::parse_amount(max_amount, None)?;
+        if let Err(err) = policy::check_max_amount(&psbt, &change, limit) {
+            error!("Refusing to sign: {}", err);
+            return Err(err);
+        }
+    }
+    let annex = annex.map(taproot::parse_annex).transpose()?;
+    sign_group_psbt(&mut psbt, annex.as_deref())?;
+    if !extract {
+        output::record("psbt", psbt.to_string());
+        say!("{}", psbt);
+        return Ok(());
+    }
+    let tx = match psbt::finalize_key_path(psbt, annex.as_deref()) {
+        Ok(tx) => tx,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    output::record("tx", bitcoin::consensus::encode::serialize_hex(&tx));
+    say!("{}", bitcoin::consensus::encode::serialize_hex(&tx));
+    Ok(())
+}
+
+/// Has the group sign every input of `psbt` that spends its output key by
+/// key path, see [`psbt::sign_group_inputs`].
+fn sign_group_psbt(psbt: &mut bitcoin::Psbt, annex: Option<&[u8]>) -> Result<(), AppError> {
+    let (key_packages, pubkey_package, mut rng) = get_keys()?;
+    let signers = default_signers(&key_packages);
+    let signed = psbt::sign_group_inputs(
+        psbt,
+        &key_packages,
+        &pubkey_package,
+        &signers,
+        annex,
+        &mut rng,
+    )?;
+    info!("Signed {} of {} inputs", signed, psbt.inputs.len());
+    Ok(())
+}
+
+/// The key package of participant `index` in the key file.
+fn participant_key_package(participant: &str) -> Result<frost::keys::KeyPackage, AppError> {
+    let identifier = parse_identifier(participant)?;
+    load_map()?.remove(&identifier).ok_or_else(|| {
+        format!(
+            "no participant {} in {}",
+            participant,
+            key_files().key_file.display()
+        )
+        .into()
+    })
+}
+
+fn read_commitments(
+    paths: &[std::path::PathBuf],
+) -> Result<Vec<rounds::CommitmentsFile>, AppError> {
+    paths.iter().map(|path| rounds::read_round(path)).collect()
+}
+
+fn commit_command(
+    participant: &str,
+    nonces_out: &std::path::Path,
+    commitments_out: &std::path::Path,
+) -> Result<(), AppError> {
+    let key_package = match participant_key_package(participant) {
+        Ok(key_package) => key_package,
+        Err(err) => {
+            error!("{}", err);
+            return Err(err);
+        }
+    };
+    let (nonces, commitments) = rounds::commit(&key_package, &mut demo_rng());
+    if let Err(err) = rounds::write_new(nonces_out, &nonces) {
+        error!("Cannot write {}: {}", nonces_out.display(), err);
+        return Err(err);
+    }
+    rounds::write_new(commitments_out, &commitments)?;
+    info!(
+        "Participant {} committed; keep {} secret and send {}",
+        participant,
+        nonces_out.display(),
+        commitments_out.display()
+    );
+    output::record("participant", identifier_label(key_package.identifier()));
+    output::record("nonces", nonces_out);
+    output::record("commitments", commitments_out);
+    Ok(())
+}
+
+/// Where the round 1 commitments come from.
+enum CommitmentsSource<'a> {
+    /// One commitments file per signer, written by `commit`.
+    Files(&'a [std::path::PathBuf]),
+    /// A serialized map of every signer's commitments, checked against the
+    /// group before use.
+    Map(&'a std::path::Path),
+}
+
+impl<'a> CommitmentsSource<'a> {
+    fn new(files: &'a [std::path::PathBuf], map: Option<&'a std::path::Path>) -> Self {
+        map.map_or(Self::Files(files), Self::Map)
+    }
+
+    fn read(&self) -> Result<Vec<rounds::CommitmentsFile>, AppError> {
+        match self {
+            Self::Files(paths) => read_commitments(paths),
+            Self::Map(path) => rounds::read_commitments_map(path),
+        }
+    }
+}
+
+fn sign_share_command(
+    participant: &str,
+    nonces: &std::path::Path,
+    source: CommitmentsSource,
+    message: &[u8],
+    share_out: &std::path::Path,
+) -> Result<(), AppError> {
+    let key_package = participant_key_package(participant)?;
+    let commitments = source.read()?;
+    if let CommitmentsSource::Map(_) = source {
+        let group: Vec<_> = load_map()?.keys().copied().collect();
+        if let Err(err) = rounds::check_commitments(
+            &commitments,
+            &group,
+            &[*key_package.identifier()],
+            Some(*key_package.min_signers()),
+        ) {
+            error!("Refusing the commitments map: {}", err);
+            return Err(err);
+        }
+    }
+    refuse_reused_nonces(&rounds::signing_package_file(&commitments, message)?.signing_package)?;
+    let nonces = match rounds::take_nonces(nonces) {
+        Ok(nonces) => nonces,
+        Err(err) => {
+            error!("Cannot read the nonces from {}: {}", nonces.display(), err);
+            return Err(err);
+        }
+    };
+    let share = match rounds::sign_share(&key_package, nonces, &commitments, message) {
+        Ok(share) => share,
+        Err(err) => {
+            error!("Signing failed, commit again before retrying: {}", err);
+            return Err(err);
+        }
+    };
+    rounds::write_new(share_out, &share)?;
+    info!(
+        "Participant {} signed; send {} to the coordinator",
+        participant,
+        share_out.display()
+    );
+    output::record("participant", identifier_label(key_package.identifier()));
+    output::record("share", share_out);
+    Ok(())
+}
+
+/// Where `aggregate` gets the signing package from.
+enum PackageSource<'a> {
+    /// Rebuilt from the commitments and the message, like `sign-share` does.
+    Commitments(CommitmentsSource<'a>, &'a [u8]),
+    /// A file written by `signing-package`.
+    File(&'a std::path::Path),
+}
+
+/// Where `aggregate` gets the signature shares from.
+enum ShareSource<'a> {
+    Files(&'a [std::path::PathBuf]),
+    Dir(&'a std::path::Path),
+}
+
+fn signing_package_command(
+    commitments: &[std::path::PathBuf],
+    message: &[u8],
+    output: &std::path::Path,
+) -> Result<(), AppError> {
+    let package = rounds::signing_package_file(&read_commitments(commitments)?, message)?;
+    rounds::write_new(output, &package)?;
+    let signers = participant_labels(package.signing_package.signing_commitments().keys());
+    info!(
+        "Wrote the signing package for participants {} to {}",
+        signers.join(","),
+        output.display()
+    );
+    output::record("participants", signers);
+    output::record("signing_package", output);
+    Ok(())
+}
+
+fn aggregate_command(
+    package: PackageSource,
+    shares: ShareSource,
+    verify_shares: bool,
+    format: output::OutputFormat,
+    output: Option<&std::path::Path>,
+) -> Result<(), AppError> {
+    let pubkey_package = load_pubkey_package()?;
+    let shares = match shares {
+        ShareSource::Files(paths) => paths
+            .iter()
+            .map(|path| rounds::read_round(path))
+            .collect::<Result<Vec<rounds::ShareFile>, _>>()?,
+        ShareSource::Dir(dir) => match rounds::read_share_dir(dir) {
+            Ok(shares) => shares,
+            Err(err) => {
+                error!("Cannot read the shares in {}: {}", dir.display(), err);
+                return Err(err);
+            }
+        },
+    };
+    let signing_package = match package {
+        PackageSource::Commitments(source, message) => {
+            let commitments = source.read()?;
+            if let CommitmentsSource::Map(_) = source {
+                let group: Vec<_> = pubkey_package.verifying_shares().keys().copied().collect();
+                let signers: Vec<_> = shares.iter().map(|file| file.identifier).collect();
+                if let Err(err) = rounds::check_commitments(&commitments, &group, &signers, None) {
+                    error!("Refusing the commitments map: {}", err);
+                    return Err(err);
+                }
+            }
+            rounds::signing_package_file(&commitments, message)?.signing_package
+        }
+        PackageSource::File(path) => {
+            rounds::read_round::<rounds::SigningPackageFile>(path)?.signing_package
+        }
+    };
+    refuse_reused_nonces(&signing_package)?;
+    let signature = match rounds::aggregate_package(
+        &pubkey_package,
+        &signing_package,
+        &shares,
+        verify_shares,
+    ) {
+        Ok(signature) => signature,
+        Err(err) => {
+            if let AppError::InvalidShares(culprits) = &err {
+                output::record("invalid_shares", participant_labels(culprits));
+            }
+            error!("Aggregation failed: {}", err);
+            return Err(err);
+        }
+    };
+    let valid = pubkey_package
+        .verifying_key()
+        .verify(signing_package.message(), &signature)
+        .is_ok();
+    info!("Signature valid: {}", valid);
+    output::record("valid", valid);
+    if !valid {
+        error!("The aggregated signature does not verify against the group key");
+        return Err("invalid group signature".into());
+    }
+    if let Some(audit_log) = AUDIT_LOG.get() {
+        let signers: Vec<_> = signing_package
+            .signing_commitments()
+            .keys()
+            .copied()
+            .collect();
+        let entry =
+            audit::AuditEntry::new("message", &signers, signing_package.message(), &signature)?
+                .with_commitments(&signing_package)?;
+        if let Err(err) = audit::append(audit_log, &entry) {
+            error!(
+                "Failed to write the audit log {}: {}",
+                audit_log.display(),
+                err
+            );
+            return Err(err);
+        }
+    }
+    output::record("signature", hex::encode(signature.serialize()?));
+    output::write(format, "frost-signature", &signature.serialize()?, output)
+}
+
+/// The threshold recorded at `generate` time, or else the one in the key
+/// file, for a coordinator.
+fn group_threshold() -> Result<u16, AppError> {
+    let key_file = &key_files().key_file;
+    match recorded_threshold(key_file)? {
+        Some(threshold) => Ok(threshold),
+        None if key_file.exists() => Ok(map_threshold(&load_map()?)?.0),
+        None => {
+            error!(
+                "Neither {} nor {} records the threshold",
+                key_files().metadata_file().display(),
+                key_file.display()
+            );
+            Err("no recorded threshold".into())
+        }
+    }
+}
+
+/// Writes each signer its instruction file for a session over `message`,
+/// once the signers are known participants of the group and reach the
+/// threshold.
+fn signing_instructions(
+    participants: &[String],
+    message: &str,
+    coordinator: &str,
+    out_dir: &std::path::Path,
+) -> Result<(), AppError> {
+    let group = public_key_package()?;
+    let participants: Vec<_> = participants.iter().map(|p| p.trim().to_string()).collect();
+    let mut identifiers = Vec::new();
+    for participant in &participants {
+        if participant.contains(['/', '\\']) {
+            error!("Participant {} can't name an instruction file", participant);
+            return Err(format!("participant {participant} contains a path separator").into());
+        }
+        let identifier = parse_identifier(participant)?;
+        if !group.verifying_shares().contains_key(&identifier) {
+            error!("No participant {} in the group", participant);
+            return Err(format!("no participant {participant} in the group").into());
+        }
+        if identifiers.contains(&identifier) {
+            error!("Participant {} is given twice", participant);
+            return Err(format!("participant {participant} is given twice").into());
+        }
+        identifiers.push(identifier);
+    }
+    let threshold = group_threshold()?;
+    if identifiers.len() < usize::from(threshold) {
+        error!(
+            "{} signers can't reach the threshold of {}",
+            identifiers.len(),
+            threshold
+        );
+        return Err("not enough signers".into());
+    }
+
+    std::fs::create_dir_all(out_dir)?;
+    let mut files = Vec::new();
+    for participant in &participants {
+        let path = out_dir.join(format!("{participant}.txt"));
+        let text = rounds::instructions(participant, &participants, message, coordinator);
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|err| format!("cannot write {}: {}", path.display(), err))?;
+        info!(
+            "Wrote the steps of participant {} to {}",
+            participant,
+            path.display()
+        );
+        files.push(path);
+    }
+    output::record("instructions", files);
+    Ok(())
+}
+
+/// Reports how many of the group have committed, against the threshold
+/// recorded at `generate` time, and fails while it isn't reached.
+fn threshold_check(commitments: &[std::path::PathBuf]) -> Result<(), AppError> {
+    let threshold = group_threshold()?;
+    let participants: Vec<_> = public_key_package()?
+        .verifying_shares()
+        .keys()
+        .copied()
+        .collect();
+    let readiness =
+        match rounds::readiness(&participants, &read_commitments(commitments)?, threshold) {
+            Ok(readiness) => readiness,
+            Err(err) => {
+                error!("{}", err);
+                return Err(err);
+            }
+        };
+
+    let labels = |identifiers: &[frost::Identifier]| participant_labels(identifiers);
+    say!(
+        "{} of {} committed, threshold {}",
+        readiness.committed.len(),
+        participants.len(),
+        threshold
+    );
+    say!("missing: {}", labels(&readiness.missing).join(","));
+    output::record("committed", labels(&readiness.committed));
+    output::record("threshold", threshold);
+    output::record("missing", labels(&readiness.missing));
+    output::record("needed", readiness.needed());
+    if readiness.needed() > 0 {
+        error!(
+            "{} more commitments needed before signing",
+            readiness.needed()
+        );
+        return Err("threshold not met".into());
+    }
+    info!("Enough commitments to sign");
+    Ok(())
+}
+
+/// Signs `message` with the persisted key packages and writes an attestation
+/// of the ceremony. The persisted ones are used, not a split of PRIVATE_KEY,
+/// since a verifier checks the shares against the verifying shares it holds.
+fn attest_command(message: &[u8], output: Option<&std::path::Path>) -> Result<(), AppError> {
+    let key_packages = load_map()?;
+    let pubkey_package = load_pubkey_package()?;
+    let signers = default_signers(&key_packages);
+    let attestation = match attestation::attest(
+        &key_packages,
+        &pubkey_package,
+        &signers,
+        message,
+        &mut demo_rng(),
+    ) {
+        Ok(attestation) => attestation,
+        Err(err) => {
+            error!("Attesting failed: {}", err);
+            return Err(err);
+        }
+    };
+    info!("Attested signature: {}", attestation.body.signature_hex);
+    let json = serde_json::to_string_pretty(&attestation)?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, json)?;
+            output::record("output", path);
+            output::record("signature", &attestation.body.signature_hex);
+        }
+        None if output::json_enabled() => output::record("attestation", &attestation),
+        None => say!("{}", json),
+    }
+    Ok(())
+}
+
+fn verify_attestation(path: &std::path::Path) -> Result<(), AppError> {
+    let attestation: attestation::Attestation = match rounds::read(path) {
+        Ok(attestation) => attestation,
+        Err(err) => {
+            error!("Cannot read {}: {}", path.display(), err);
+            return Err(err);
+        }
+    };
+    let check = match attestation::verify(&attestation, &public_key_package()?) {
+        Ok(check) => check,
+        Err(err) => {
+            error!("Malformed attestation: {}", err);
+            return Err(err);
+        }
+    };
+    for identifier in &check.audit.invalid {
+        error!(
+            "Share from participant {} is invalid",
+            identifier_label(identifier)
+        );
+    }
+    info!(
+        "{} valid shares from {}, threshold {}, aggregate matches: {}",
+        check.audit.valid.len(),
+        participant_labels(&check.audit.valid).join(","),
+        check.audit.threshold,
+        check.audit.signature_matches
+    );
+    if !check.message_matches {
+        error!("The signing package is over another message");
+    }
+    if !check.body_signed {
+        error!("The group's signature over the attestation doesn't verify");
+    }
+    if !check.passed() {
+        output::record("valid", false);
+        return Err("attestation rejected".into());
+    }
+    output::record("valid", true);
+    let signers = participant_labels(&check.audit.valid);
+    say!("Attestation valid: signed by {}", signers.join(","));
+    output::record("signers", signers);
+    Ok(())
+}
+
+/// Verifies a supplied signature with the persisted public key package only,
+/// so a watch-only node needs neither PRIVATE_KEY nor my_map.json.
+fn verify_supplied_signature(
+    message: &[u8],
+    signature_hex: &str,
+    network: Network,
+) -> Result<(), AppError> {
+    let pubkey_package = match load_pubkey_package() {
+        Ok(pubkey_package) => pubkey_package,
+        Err(err) => {
+            error!(
+                "Verifying a supplied signature needs the public key package: {}",
+                err
+            );
+            return Err(err);
+        }
+    };
+    info!(
+        "Taproot address: {}",
+        group_taproot_address(&pubkey_package, None, network)?.1
+    );
+    let valid = match check_signature(&pubkey_package, message, signature_hex) {
+        Ok(valid) => valid,
+        Err(err) => {
+            error!("Malformed signature: {}", err);
+            return Err(err);
+        }
+    };
+    output::record("valid", valid);
+    say!("Signature valid: {}", valid);
+    if !valid {
+        return Err("invalid signature".into());
+    }
+    Ok(())
+}
+
+/// The log filter for `count` occurrences of `--debug`, used unless RUST_LOG
+/// is set.
+fn log_level(count: u8) -> log::LevelFilter {
+    match count {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        2 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_private_key_is_reported_as_missing_env() {
+        let dir = std::env::temp_dir().join(format!("frost-missing-key-{}", std::process::id()));
+        let files = KeyFiles {
+            key_file: dir.join("my_map.json"),
+            pubkey_file: dir.join("pubkey_package.json"),
+            passphrase: None,
+        };
+        // A variable of its own, so the test neither depends on nor changes
+        // the environment the other tests run in.
+        let source = PrivateKeySource::Env("FROST_TEST_UNSET_PRIVATE_KEY");
+        assert!(matches!(
+            load_keys(&files, &source),
+            Err(AppError::MissingEnv("FROST_TEST_UNSET_PRIVATE_KEY"))
+        ));
+    }
+
+    #[test]
+    fn private_key_file_is_trimmed_and_excludes_stdin() {
+        let path = std::env::temp_dir().join(format!("frost-private-key-{}", std::process::id()));
+        std::fs::write(&path, "  0707\n\n").unwrap();
+        let private_key = read_private_key(&PrivateKeySource::File(path.clone()));
+        std::fs::write(&path, " \n").unwrap();
+        let empty = read_private_key(&PrivateKeySource::File(path.clone()));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(private_key.unwrap().as_str(), "0707");
+        assert!(empty.is_err());
+        assert!(Cli::try_parse_from([
+            "frost",
+            "test",
+            "--private-key-file",
+            "key.hex",
+            "--private-key-stdin"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn debug_count_raises_the_log_level() {
+        assert_eq!(log_level(0), log::LevelFilter::Warn);
+        assert_eq!(log_level(1), log::LevelFilter::Info);
+        assert_eq!(log_level(2), log::LevelFilter::Debug);
+        assert_eq!(log_level(7), log::LevelFilter::Trace);
+        let cli = Cli::try_parse_from(["frost", "features", "-dd"]).unwrap();
+        assert_eq!(cli.debug, 2);
+    }
+
+    #[test]
+    fn a_seeded_rng_repeats_the_split_and_the_signature() {
+        let signing_key = frost::SigningKey::deserialize(&[7u8; 32]).unwrap();
+        let run = |seed| {
+            let mut rng = seeded_rng(Some(seed));
+            let (key_packages, pubkey_package) = split_signing_key(
+                &signing_key,
+                2,
+                3,
+                frost::keys::IdentifierList::Default,
+                &mut rng,
+            )
+            .unwrap();
+            let signers = default_signers(&key_packages);
+            let signature = sign_message(
+                &key_packages,
+                &pubkey_package,
+                &signers,
+                DEMO_MESSAGE,
+                &mut rng,
+            )
+            .unwrap();
+            (serde_json::to_value(&key_packages).unwrap(), signature)
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1).0, run(2).0);
+    }
+}
@@ -0,0 +1,186 @@
+//! Nostr (NIP-19) encodings of the group key, and NIP-01 events signed by it.
+//!
+//! A Nostr identity is a BIP340 key, so the group's x-only key is one and the
+//! group signs events with [`taproot::sign_internal_key`].
+
+use crate::error::AppError;
+use crate::{frost, taproot, KeyPackages};
+use bech32::{Bech32, Hrp};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::key::XOnlyPublicKey;
+use bitcoin::secp256k1::{schnorr, Message, Secp256k1};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+const NPUB_HRP: Hrp = Hrp::parse_unchecked("npub");
+
+/// Encodes an x-only public key as a bech32 `npub`.
+pub fn npub_encode(public_key: &XOnlyPublicKey) -> Result<String, bech32::EncodeError> {
+    bech32::encode::<Bech32>(NPUB_HRP, &public_key.serialize())
+}
+
+/// Decodes an `npub` back into the x-only public key it encodes.
+pub fn npub_decode(npub: &str) -> Result<XOnlyPublicKey, AppError> {
+    let (hrp, data) = bech32::decode(npub)?;
+    if hrp != NPUB_HRP {
+        return Err(format!("expected an npub, got human-readable part {hrp}").into());
+    }
+    Ok(XOnlyPublicKey::from_slice(&data)?)
+}
+
+/// The parts of a NIP-01 event the author chooses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsignedEvent {
+    /// Unix time in seconds.
+    pub created_at: u64,
+    pub kind: u16,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+}
+
+/// A signed NIP-01 event, as relays accept it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub id: String,
+    pub pubkey: String,
+    pub created_at: u64,
+    pub kind: u16,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+    pub sig: String,
+}
+
+/// The NIP-01 event id: SHA-256 of `[0,pubkey,created_at,kind,tags,content]`
+/// as JSON without whitespace.
+pub fn event_id(pubkey: &XOnlyPublicKey, event: &UnsignedEvent) -> Result<[u8; 32], AppError> {
+    let serialized = serde_json::to_string(&(
+        0,
+        pubkey.to_string(),
+        event.created_at,
+        event.kind,
+        &event.tags,
+        &event.content,
+    ))?;
+    Ok(sha256::Hash::hash(serialized.as_bytes()).to_byte_array())
+}
+
+/// Has `signers` sign `event` as the group's Nostr identity.
+pub fn sign_event<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    event: UnsignedEvent,
+    rng: &mut R,
+) -> Result<Event, AppError> {
+    let pubkey = taproot::internal_key(pubkey_package)?;
+    let id = event_id(&pubkey, &event)?;
+    let signature = taproot::sign_internal_key(key_packages, pubkey_package, signers, &id, rng)?;
+    Ok(Event {
+        id: hex::encode(id),
+        pubkey: pubkey.to_string(),
+        created_at: event.created_at,
+        kind: event.kind,
+        tags: event.tags,
+        content: event.content,
+        sig: hex::encode(signature.serialize()),
+    })
+}
+
+/// Checks that `event` is by `npub`, that its id matches its contents and
+/// that its signature is valid for that id.
+pub fn verify_event(event: &Event, npub: &str) -> Result<(), AppError> {
+    let pubkey = npub_decode(npub)?;
+    if event.pubkey != pubkey.to_string() {
+        return Err(format!("the event is by {}, not {npub}", event.pubkey).into());
+    }
+    let id = event_id(
+        &pubkey,
+        &UnsignedEvent {
+            created_at: event.created_at,
+            kind: event.kind,
+            tags: event.tags.clone(),
+            content: event.content.clone(),
+        },
+    )?;
+    if hex::encode(id) != event.id {
+        return Err("the event id doesn't match its contents".into());
+    }
+    let signature = schnorr::Signature::from_slice(&hex::decode(&event.sig)?)?;
+    Secp256k1::verification_only().verify_schnorr(
+        &signature,
+        &Message::from_digest(id),
+        &pubkey,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, default_signers};
+    use rand::thread_rng;
+    use std::str::FromStr;
+
+    // Test vector from NIP-19.
+    const HEX: &str = "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e";
+    const NPUB: &str = "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+
+    #[test]
+    fn npub_round_trip_matches_nip19() {
+        let public_key = XOnlyPublicKey::from_str(HEX).unwrap();
+        assert_eq!(npub_encode(&public_key).unwrap(), NPUB);
+        assert_eq!(npub_decode(NPUB).unwrap(), public_key);
+    }
+
+    #[test]
+    fn npub_decode_rejects_other_prefixes() {
+        let public_key = XOnlyPublicKey::from_str(HEX).unwrap();
+        let nsec =
+            bech32::encode::<Bech32>(Hrp::parse("nsec").unwrap(), &public_key.serialize()).unwrap();
+        assert!(npub_decode(&nsec).is_err());
+    }
+
+    #[test]
+    fn event_id_hashes_the_compact_serialization() {
+        let public_key = XOnlyPublicKey::from_str(HEX).unwrap();
+        let event = UnsignedEvent {
+            created_at: 1_700_000_000,
+            kind: 1,
+            tags: vec![vec!["t".to_string(), "frost".to_string()]],
+            content: "line one\n\"quoted\" \\ é".to_string(),
+        };
+        let serialized =
+            format!(r#"[0,"{HEX}",1700000000,1,[["t","frost"]],"line one\n\"quoted\" \\ é"]"#);
+        assert_eq!(
+            event_id(&public_key, &event).unwrap(),
+            sha256::Hash::hash(serialized.as_bytes()).to_byte_array()
+        );
+    }
+
+    #[test]
+    fn signed_events_verify_against_the_group_npub() {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let npub = npub_encode(&taproot::internal_key(&pubkey_package).unwrap()).unwrap();
+        let event = sign_event(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            UnsignedEvent {
+                created_at: 1_700_000_000,
+                kind: 1,
+                tags: Vec::new(),
+                content: "hello from a threshold".to_string(),
+            },
+            &mut thread_rng(),
+        )
+        .unwrap();
+        assert!(verify_event(&event, &npub).is_ok());
+
+        let mut edited = event.clone();
+        edited.content.push('!');
+        assert!(verify_event(&edited, &npub).is_err());
+        let (_, other_group) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let other_npub = npub_encode(&taproot::internal_key(&other_group).unwrap()).unwrap();
+        assert!(verify_event(&event, &other_npub).is_err());
+    }
+}
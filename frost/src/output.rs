@@ -0,0 +1,184 @@
+//! Encodings for the signatures and keys commands print, so they can be piped
+//! into other tools as-is.
+//!
+//! Under the global `--json` flag a command prints nothing on stdout while it
+//! runs. It [`record`]s its results instead, and they are printed as one JSON
+//! object when it finishes. Human-readable lines printed with [`say!`](crate::say!) go to
+//! stderr, next to the log.
+
+use crate::error::AppError;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The `--json` result, while `--json` is on.
+static JSON_RESULT: Mutex<Option<serde_json::Map<String, serde_json::Value>>> = Mutex::new(None);
+
+/// Prints a line for people: to stdout, or to stderr under `--json`.
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::output::say_line(format_args!($($arg)*))
+    };
+}
+
+pub fn say_line(line: std::fmt::Arguments) {
+    if json_enabled() {
+        eprintln!("{line}");
+    } else {
+        println!("{line}");
+    }
+}
+
+fn json_result(
+) -> std::sync::MutexGuard<'static, Option<serde_json::Map<String, serde_json::Value>>> {
+    JSON_RESULT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub fn enable_json() {
+    *json_result() = Some(serde_json::Map::new());
+}
+
+pub fn json_enabled() -> bool {
+    json_result().is_some()
+}
+
+/// Adds `value` under `key` to the `--json` result. Does nothing without `--json`.
+pub fn record(key: &str, value: impl Serialize) {
+    if let Some(result) = json_result().as_mut() {
+        let value = serde_json::to_value(value).unwrap_or_else(|err| err.to_string().into());
+        result.insert(key.to_string(), value);
+    }
+}
+
+/// Takes the `--json` result, if `--json` is on.
+pub fn take_json() -> Option<serde_json::Value> {
+    json_result().take().map(serde_json::Value::Object)
+}
+
+/// Version tag of the JSON wrapper.
+const JSON_VERSION: u8 = 1;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Hex on one line
+    Hex,
+    /// A versioned JSON object holding the hex
+    Json,
+    /// The raw serialized bytes
+    Binary,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Encoded {
+    pub version: u8,
+    /// What the bytes are, e.g. `frost-signature`.
+    pub kind: String,
+    pub hex: String,
+}
+
+pub fn encode(format: OutputFormat, kind: &str, bytes: &[u8]) -> Result<Vec<u8>, AppError> {
+    Ok(match format {
+        OutputFormat::Hex => format!("{}\n", hex::encode(bytes)).into_bytes(),
+        OutputFormat::Json => {
+            let encoded = Encoded {
+                version: JSON_VERSION,
+                kind: kind.to_string(),
+                hex: hex::encode(bytes),
+            };
+            format!("{}\n", serde_json::to_string(&encoded)?).into_bytes()
+        }
+        OutputFormat::Binary => bytes.to_vec(),
+    })
+}
+
+/// Writes `bytes` in `format` to `output`, or to stdout without one. Under
+/// `--json` stdout gets the hex under `kind` in the result instead, or the
+/// path under `output` when there is one.
+pub fn write(
+    format: OutputFormat,
+    kind: &str,
+    bytes: &[u8],
+    output: Option<&Path>,
+) -> Result<(), AppError> {
+    write_with(format, kind, bytes, output, |path| {
+        std::fs::File::create(path)
+    })
+}
+
+/// [`write`] for a secret: a file at `output` is readable only by its owner.
+pub fn write_secret(
+    format: OutputFormat,
+    kind: &str,
+    bytes: &[u8],
+    output: Option<&Path>,
+) -> Result<(), AppError> {
+    write_with(format, kind, bytes, output, crate::create_secret_file)
+}
+
+fn write_with(
+    format: OutputFormat,
+    kind: &str,
+    bytes: &[u8],
+    output: Option<&Path>,
+    create: fn(&Path) -> std::io::Result<std::fs::File>,
+) -> Result<(), AppError> {
+    let encoded = encode(format, kind, bytes)?;
+    match output {
+        Some(path) => {
+            create(path)?.write_all(&encoded)?;
+            record("output", path);
+        }
+        None if json_enabled() => record(kind, hex::encode(bytes)),
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&encoded)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_format_carries_the_same_bytes() {
+        let bytes = [0x02, 0xab, 0xcd];
+        assert_eq!(encode(OutputFormat::Binary, "test", &bytes).unwrap(), bytes);
+        assert_eq!(
+            encode(OutputFormat::Hex, "test", &bytes).unwrap(),
+            b"02abcd\n"
+        );
+
+        let json = encode(OutputFormat::Json, "test", &bytes).unwrap();
+        let encoded: Encoded = serde_json::from_slice(&json).unwrap();
+        assert_eq!(
+            encoded,
+            Encoded {
+                version: JSON_VERSION,
+                kind: "test".to_string(),
+                hex: "02abcd".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn json_mode_collects_one_object() {
+        record("ignored", 1);
+        enable_json();
+        record("signature", "02abcd");
+        record("valid", true);
+        assert_eq!(
+            take_json().unwrap(),
+            serde_json::json!({ "signature": "02abcd", "valid": true })
+        );
+        assert!(!json_enabled());
+        assert!(take_json().is_none());
+    }
+}
@@ -0,0 +1,271 @@
+//! External pre-signing policy hook.
+//!
+//! Before a ceremony starts, the configured command is run through `sh -c`
+//! with a JSON description of the signing request on stdin. Signing only
+//! proceeds if the command exits successfully; anything it prints is reported
+//! as the rejection reason.
+//!
+//! Transaction policies run in-process on a PSBT before the group signs it.
+//! [`check_destinations`] refuses outputs to addresses off an allowlist, and
+//! [`check_max_amount`] a PSBT that sends more than a limit. Change, paid
+//! back to the group, is always allowed and never counts.
+
+use crate::error::AppError;
+use crate::frost;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::psbt::Psbt;
+use bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+use bitcoin::{Address, Amount, Network, ScriptBuf};
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// What the group is about to sign, as handed to the policy hook.
+#[derive(Serialize, Debug)]
+pub struct SigningRequest {
+    /// Kind of signing operation, e.g. `"message"` or `"adaptor"`.
+    pub kind: &'static str,
+    pub message_hex: String,
+    pub group_key: String,
+    pub signers: Vec<String>,
+}
+
+impl SigningRequest {
+    /// The request for `signers` signing `message` with the group key of
+    /// `pubkey_package`.
+    pub fn new(
+        kind: &'static str,
+        message: &[u8],
+        signers: &[frost::Identifier],
+        pubkey_package: &frost::keys::PublicKeyPackage,
+    ) -> Result<Self, AppError> {
+        Ok(SigningRequest {
+            kind,
+            message_hex: hex::encode(message),
+            group_key: hex::encode(pubkey_package.verifying_key().serialize()?),
+            signers: signers
+                .iter()
+                .map(|identifier| hex::encode(identifier.serialize()))
+                .collect(),
+        })
+    }
+}
+
+/// Runs `hook` with `request` on stdin and fails unless it exits with status 0.
+pub fn check(hook: &str, request: &SigningRequest) -> Result<(), AppError> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run policy hook `{hook}`: {e}"))?;
+
+    let payload = serde_json::to_vec(request)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The hook may exit without reading its input; that's its decision.
+        let _ = stdin.write_all(&payload);
+    }
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        let mut reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if reason.is_empty() {
+            reason = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        }
+        if reason.is_empty() {
+            reason = output.status.to_string();
+        }
+        return Err(format!("signing rejected by policy hook: {reason}").into());
+    }
+    Ok(())
+}
+
+/// The scripts of an allowlist: one address for `network` per line, with
+/// blank lines and `#` comments skipped.
+pub fn parse_allowlist(text: &str, network: Network) -> Result<Vec<ScriptBuf>, AppError> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.split('#').next().unwrap_or_default().trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(number, line)| {
+            line.parse::<Address<NetworkUnchecked>>()
+                .map_err(|err| err.to_string())
+                .and_then(|address| {
+                    address
+                        .require_network(network)
+                        .map_err(|err| err.to_string())
+                })
+                .map(|address| address.script_pubkey())
+                .map_err(|err| format!("allowlist line {number}, {line:?}: {err}").into())
+        })
+        .collect()
+}
+
+/// The scripts that pay the group back: its BIP86 address, and the output of
+/// every input of `psbt` that the group spends by key path.
+pub fn change_scripts(psbt: &Psbt, internal_key: XOnlyPublicKey) -> Vec<ScriptBuf> {
+    let mut scripts = vec![ScriptBuf::new_p2tr(
+        &Secp256k1::verification_only(),
+        internal_key,
+        None,
+    )];
+    for input in &psbt.inputs {
+        if input.tap_internal_key != Some(internal_key) {
+            continue;
+        }
+        if let Some(utxo) = &input.witness_utxo {
+            if !scripts.contains(&utxo.script_pubkey) {
+                scripts.push(utxo.script_pubkey.clone());
+            }
+        }
+    }
+    scripts
+}
+
+/// Fails on the first output of `psbt`, other than to a `change` script, that
+/// pays a script not `allowed`.
+pub fn check_destinations(
+    psbt: &Psbt,
+    allowed: &[ScriptBuf],
+    change: &[ScriptBuf],
+    network: Network,
+) -> Result<(), AppError> {
+    for (index, output) in psbt.unsigned_tx.output.iter().enumerate() {
+        let script = &output.script_pubkey;
+        if change.contains(script) || allowed.contains(script) {
+            continue;
+        }
+        let destination = Address::from_script(script, network)
+            .map(|address| address.to_string())
+            .unwrap_or_else(|_| format!("script {script}"));
+        return Err(format!(
+            "output {index} pays {} to {destination}, which is not an allowed destination",
+            output.value
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Fails if the outputs of `psbt` that don't pay a `change` script send more
+/// than `limit` in total.
+pub fn check_max_amount(psbt: &Psbt, change: &[ScriptBuf], limit: Amount) -> Result<(), AppError> {
+    let amount = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .filter(|output| !change.contains(&output.script_pubkey))
+        .try_fold(Amount::ZERO, |total, output| {
+            total.checked_add(output.value)
+        })
+        .ok_or("the outputs overflow their total")?;
+    if amount > limit {
+        return Err(format!("the PSBT sends {amount}, over the {limit} limit").into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> SigningRequest {
+        SigningRequest {
+            kind: "message",
+            message_hex: "deadbeef".to_string(),
+            group_key: "02".to_string(),
+            signers: vec!["1".to_string()],
+        }
+    }
+
+    #[test]
+    fn accepting_hook_allows_signing() {
+        check("true", &request()).unwrap();
+    }
+
+    #[test]
+    fn hook_receives_the_request_on_stdin() {
+        check("grep -q '\"message_hex\":\"deadbeef\"'", &request()).unwrap();
+        assert!(check("grep -q cafebabe", &request()).is_err());
+    }
+
+    fn key(secret: u8) -> XOnlyPublicKey {
+        bitcoin::secp256k1::Keypair::from_seckey_slice(&Secp256k1::new(), &[secret; 32])
+            .unwrap()
+            .x_only_public_key()
+            .0
+    }
+
+    /// The group's spend, by key path, of an output of its BIP86 address.
+    fn spend(internal_key: XOnlyPublicKey, destination: &Address) -> Psbt {
+        let outpoint = bitcoin::OutPoint {
+            txid: bitcoin::hashes::Hash::all_zeros(),
+            vout: 0,
+        };
+        crate::psbt::key_path_spend(
+            outpoint,
+            bitcoin::Amount::from_sat(10_000),
+            bitcoin::Amount::from_sat(500),
+            destination,
+            internal_key,
+            None,
+            true,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn only_allowed_destinations_and_change_are_signed() {
+        let network = Network::Regtest;
+        let secp = Secp256k1::new();
+        let group_key = key(1);
+        let allowed = Address::p2tr(&secp, key(2), None, network);
+        let other = Address::p2tr(&secp, key(3), None, network);
+        let allowlist =
+            parse_allowlist(&format!("# cold storage\n\n{allowed}  # vault\n"), network).unwrap();
+        assert_eq!(allowlist, vec![allowed.script_pubkey()]);
+        assert!(parse_allowlist("not an address", network).is_err());
+        let mainnet = Address::p2tr(&secp, key(2), None, Network::Bitcoin);
+        assert!(parse_allowlist(&mainnet.to_string(), network).is_err());
+
+        let psbt = spend(group_key, &allowed);
+        let change = change_scripts(&psbt, group_key);
+        check_destinations(&psbt, &allowlist, &change, network).unwrap();
+
+        let psbt = spend(group_key, &other);
+        let error = check_destinations(&psbt, &allowlist, &change, network).unwrap_err();
+        assert!(error.to_string().starts_with("output 0 pays"));
+        assert!(error.to_string().contains(&other.to_string()));
+
+        let group = Address::p2tr(&secp, group_key, None, network);
+        check_destinations(&spend(group_key, &group), &[], &change, network).unwrap();
+    }
+
+    #[test]
+    fn spends_over_the_limit_are_refused() {
+        let network = Network::Regtest;
+        let secp = Secp256k1::new();
+        let group_key = key(1);
+        let other = Address::p2tr(&secp, key(3), None, network);
+        let psbt = spend(group_key, &other);
+        let change = change_scripts(&psbt, group_key);
+
+        check_max_amount(&psbt, &change, Amount::from_sat(9_500)).unwrap();
+        let error = check_max_amount(&psbt, &change, Amount::from_sat(9_499)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the PSBT sends 0.00009500 BTC, over the 0.00009499 BTC limit"
+        );
+
+        let group = Address::p2tr(&secp, group_key, None, network);
+        check_max_amount(&spend(group_key, &group), &change, Amount::ZERO).unwrap();
+    }
+
+    #[test]
+    fn rejection_reports_the_hook_output() {
+        let error = check("echo 'amount over limit' >&2; exit 3", &request()).unwrap_err();
+        assert!(error.to_string().contains("amount over limit"));
+    }
+}
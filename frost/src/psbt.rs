@@ -0,0 +1,1237 @@
+//! Building, merging and checking PSBTs.
+//!
+//! [`key_path_spend`] builds the unsigned PSBT that spends one of the group's
+//! taproot outputs, and [`key_path_sighash`] is the message the group signs
+//! for it. Each co-signer (the FROST group, or an external single-sig wallet)
+//! signs its own copy of the same unsigned transaction. [`combine`] merges
+//! the copies, and [`contributions`] lists every signature in the result with
+//! whether it verifies against the input's sighash. [`consolidation`] spends
+//! many of the group's outputs back to its address. [`parse_amount`] reads
+//! the amounts they take, in any unit. [`sign_group_inputs`] has the group
+//! sign its own inputs of a PSBT, and [`finalize_key_path`] turns the signed
+//! PSBT into the transaction to broadcast. [`fee_bump`] rebuilds a signed
+//! spend at a higher fee rate, to replace it. [`key_path_transaction`]
+//! spends several of the group's outputs to several outputs, such as a
+//! [`TxSpec`] read from JSON describes. [`sweep`] spends all of them to one
+//! destination, and [`sign_inputs_with_secret`] signs with the reconstructed
+//! group secret alone.
+
+use crate::error::AppError;
+use crate::{frost, KeyPackages};
+use bitcoin::hashes::Hash;
+use bitcoin::key::TapTweak;
+use bitcoin::psbt::Psbt;
+use bitcoin::secp256k1::{self, Keypair, Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::{
+    absolute, transaction, Address, Amount, Denomination, OutPoint, ScriptBuf, Sequence,
+    TapNodeHash, Transaction, TxIn, TxOut, Witness,
+};
+use rand::{CryptoRng, RngCore};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// One signature found in a PSBT input.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Contribution {
+    pub input: usize,
+    pub signer: Signer,
+    pub valid: bool,
+}
+
+/// The key a signature claims to be from.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Signer {
+    /// An ECDSA signature in `partial_sigs`.
+    Ecdsa(bitcoin::PublicKey),
+    /// A taproot key-path signature for the output key.
+    TaprootKey(XOnlyPublicKey),
+    /// A taproot script-path signature for one leaf.
+    TaprootScript(XOnlyPublicKey, bitcoin::TapLeafHash),
+}
+
+impl fmt::Display for Signer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signer::Ecdsa(key) => write!(f, "ecdsa {key}"),
+            Signer::TaprootKey(key) => write!(f, "taproot key path {key}"),
+            Signer::TaprootScript(key, leaf) => write!(f, "taproot leaf {leaf} {key}"),
+        }
+    }
+}
+
+/// Parses an amount such as `100000`, `100000sat`, `0.001btc` or `1 mBTC`.
+/// A suffix names the unit, as `bitcoin` spells denominations; without one
+/// the amount is in `unit`, or satoshis. Giving both a suffix and `unit` is
+/// ambiguous, and so are denominations like `MBTC`. Negative amounts, and
+/// amounts finer than a satoshi, are rejected.
+pub fn parse_amount(amount: &str, unit: Option<Denomination>) -> Result<Amount, AppError> {
+    let amount = amount.trim();
+    let (number, suffix) = amount.split_at(
+        amount
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(amount.len()),
+    );
+    let denomination = match (suffix, unit) {
+        ("", unit) => unit.unwrap_or(Denomination::Satoshi),
+        (_, Some(_)) => {
+            return Err(
+                format!("{amount:?} has a unit suffix, so it can't also take --unit").into(),
+            )
+        }
+        (suffix, None) => suffix
+            .parse()
+            .map_err(|err| format!("amount {amount:?}: {err}"))?,
+    };
+    let number = number.trim();
+    if number.starts_with('-') {
+        return Err(format!("amount {amount:?} is negative").into());
+    }
+    Amount::from_str_in(number, denomination)
+        .map_err(|err| format!("amount {amount:?}: {err}").into())
+}
+
+/// The sequence of every input a built transaction spends: 0xfffffffd, which
+/// signals replace-by-fee (BIP125), with `rbf`, and 0xfffffffe without.
+pub fn input_sequence(rbf: bool) -> Sequence {
+    if rbf {
+        Sequence::ENABLE_RBF_NO_LOCKTIME
+    } else {
+        Sequence::ENABLE_LOCKTIME_NO_RBF
+    }
+}
+
+/// An unsigned PSBT spending the group's taproot output `outpoint`, worth
+/// `amount`, to `destination` less `fee`. With `rbf` it opts in to
+/// replacement, so a stuck spend can be bumped.
+pub fn key_path_spend(
+    outpoint: OutPoint,
+    amount: Amount,
+    fee: Amount,
+    destination: &Address,
+    internal_key: XOnlyPublicKey,
+    merkle_root: Option<TapNodeHash>,
+    rbf: bool,
+) -> Result<Psbt, AppError> {
+    let value = amount
+        .checked_sub(fee)
+        .ok_or("the fee exceeds the amount")?;
+    let script_pubkey = destination.script_pubkey();
+    if value < script_pubkey.minimal_non_dust() {
+        return Err(format!("sending {value} after the fee would be dust").into());
+    }
+    key_path_transaction(
+        &[(outpoint, amount)],
+        &[(script_pubkey, value)],
+        internal_key,
+        merkle_root,
+        rbf,
+    )
+}
+
+/// An unsigned PSBT spending the group's taproot outputs `inputs`, with their
+/// amounts, to `outputs`. The fee is what the inputs leave over. With `rbf`
+/// every input opts in to replacement.
+pub fn key_path_transaction(
+    inputs: &[(OutPoint, Amount)],
+    outputs: &[(ScriptBuf, Amount)],
+    internal_key: XOnlyPublicKey,
+    merkle_root: Option<TapNodeHash>,
+    rbf: bool,
+) -> Result<Psbt, AppError> {
+    if inputs.is_empty() || outputs.is_empty() {
+        return Err("a transaction needs at least one input and one output".into());
+    }
+    let mut spent = BTreeSet::new();
+    if let Some((outpoint, _)) = inputs.iter().find(|(outpoint, _)| !spent.insert(*outpoint)) {
+        return Err(format!("{outpoint} is spent twice").into());
+    }
+    for (index, (script_pubkey, value)) in outputs.iter().enumerate() {
+        if *value < script_pubkey.minimal_non_dust() {
+            return Err(format!("output {index} of {value} would be dust").into());
+        }
+    }
+    let total_in = inputs
+        .iter()
+        .try_fold(Amount::ZERO, |total, (_, amount)| {
+            total.checked_add(*amount)
+        })
+        .ok_or("the inputs overflow their total")?;
+    let total_out = outputs
+        .iter()
+        .try_fold(Amount::ZERO, |total, (_, value)| total.checked_add(*value))
+        .ok_or("the outputs overflow their total")?;
+    if total_out > total_in {
+        return Err(format!("the outputs' {total_out} exceed the inputs' {total_in}").into());
+    }
+
+    let tx = Transaction {
+        version: transaction::Version::TWO,
+        lock_time: absolute::LockTime::ZERO,
+        input: inputs
+            .iter()
+            .map(|(outpoint, _)| TxIn {
+                previous_output: *outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: input_sequence(rbf),
+                witness: Witness::new(),
+            })
+            .collect(),
+        output: outputs
+            .iter()
+            .map(|(script_pubkey, value)| TxOut {
+                value: *value,
+                script_pubkey: script_pubkey.clone(),
+            })
+            .collect(),
+    };
+    let script_pubkey =
+        ScriptBuf::new_p2tr(&Secp256k1::verification_only(), internal_key, merkle_root);
+    let mut psbt = Psbt::from_unsigned_tx(tx)?;
+    for (input, (_, amount)) in psbt.inputs.iter_mut().zip(inputs) {
+        input.witness_utxo = Some(TxOut {
+            value: *amount,
+            script_pubkey: script_pubkey.clone(),
+        });
+        input.tap_internal_key = Some(internal_key);
+        input.tap_merkle_root = merkle_root;
+        input.sighash_type = Some(TapSighashType::Default.into());
+    }
+    Ok(psbt)
+}
+
+/// The inputs and outputs of a transaction to build, as JSON:
+///
+/// ```json
+/// {
+///   "inputs": [{ "outpoint": "<txid>:<vout>", "amount": "0.001btc" }],
+///   "outputs": [{ "address": "bc1p...", "amount": "99000sat" }]
+/// }
+/// ```
+///
+/// Amounts are strings, as [`parse_amount`] reads them.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TxSpec {
+    pub inputs: Vec<SpecInput>,
+    pub outputs: Vec<SpecOutput>,
+}
+
+/// One of the group's outputs a [`TxSpec`] spends.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpecInput {
+    pub outpoint: String,
+    pub amount: String,
+}
+
+/// One output of a [`TxSpec`].
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpecOutput {
+    pub address: String,
+    pub amount: String,
+}
+
+/// The outpoints and amounts a [`TxSpec`] spends.
+pub type SpecInputs = Vec<(OutPoint, Amount)>;
+
+/// The scripts and amounts a [`TxSpec`] pays.
+pub type SpecOutputs = Vec<(ScriptBuf, Amount)>;
+
+impl TxSpec {
+    /// Reads a spec, reporting where the JSON departs from the schema.
+    pub fn parse(json: &str) -> Result<Self, AppError> {
+        serde_json::from_str(json).map_err(|err| format!("invalid tx spec: {err}").into())
+    }
+
+    /// The inputs and output scripts of the spec, with their amounts in
+    /// `unit` unless they carry a suffix. Errors name the field at fault.
+    pub fn resolve(
+        &self,
+        unit: Option<Denomination>,
+        network: bitcoin::Network,
+    ) -> Result<(SpecInputs, SpecOutputs), AppError> {
+        let inputs = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let outpoint = input.outpoint.trim().parse().map_err(|err| {
+                    format!("inputs[{index}].outpoint must be <txid>:<vout>: {err}")
+                })?;
+                let amount = parse_amount(&input.amount, unit)
+                    .map_err(|err| format!("inputs[{index}].amount: {err}"))?;
+                Ok((outpoint, amount))
+            })
+            .collect::<Result<_, AppError>>()?;
+        let outputs = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(index, output)| {
+                let address = output
+                    .address
+                    .trim()
+                    .parse::<Address<_>>()
+                    .map_err(|err| err.to_string())
+                    .and_then(|address| {
+                        address
+                            .require_network(network)
+                            .map_err(|err| err.to_string())
+                    })
+                    .map_err(|err| format!("outputs[{index}].address: {err}"))?;
+                let amount = parse_amount(&output.amount, unit)
+                    .map_err(|err| format!("outputs[{index}].amount: {err}"))?;
+                Ok((address.script_pubkey(), amount))
+            })
+            .collect::<Result<_, AppError>>()?;
+        Ok((inputs, outputs))
+    }
+}
+
+/// The lowest fee rate, in sat/vB, that nodes relay by default.
+pub const MIN_RELAY_FEE_RATE: f64 = 1.0;
+
+/// The fee rate, in sat/vB, by which a replacement must outpay the
+/// transaction it replaces (BIP125 rule 4), by default.
+pub const INCREMENTAL_RELAY_FEE_RATE: f64 = 1.0;
+
+/// A fee rate above this many times the node's estimate is taken for a typo.
+pub const MAX_FEE_RATE_MULTIPLE: f64 = 10.0;
+
+/// The fee rate (sat/vB) to pay: `fee_rate`, checked against the node's
+/// `estimate` for `target` blocks, or the estimate itself.
+pub fn checked_fee_rate(
+    fee_rate: Option<f64>,
+    estimate: Option<f64>,
+    target: u16,
+) -> Result<f64, AppError> {
+    match (fee_rate, estimate) {
+        (None, Some(estimate)) => Ok(estimate.max(MIN_RELAY_FEE_RATE)),
+        (None, None) => Err("the node has no fee estimate yet; pass --fee-rate".into()),
+        (Some(fee_rate), Some(estimate)) if fee_rate > estimate * MAX_FEE_RATE_MULTIPLE => {
+            Err(format!(
+                "{fee_rate} sat/vB is over {MAX_FEE_RATE_MULTIPLE} times the node's estimate of {estimate:.1} sat/vB"
+            )
+            .into())
+        }
+        (Some(fee_rate), Some(estimate)) => {
+            if fee_rate < estimate {
+                log::warn!(
+                    "{} sat/vB is below the node's estimate of {:.1} sat/vB; it may take longer than {} blocks to confirm",
+                    fee_rate,
+                    estimate,
+                    target
+                );
+            }
+            Ok(fee_rate)
+        }
+        (Some(fee_rate), None) => Ok(fee_rate),
+    }
+}
+
+/// The virtual size of `tx` once every input carries its 64-byte key-path
+/// signature.
+fn signed_vsize(tx: &Transaction) -> usize {
+    let mut signed = tx.clone();
+    for input in &mut signed.input {
+        input.witness = Witness::from_slice(&[[0; 64]]);
+    }
+    signed.vsize()
+}
+
+/// An unsigned PSBT replacing `original`, a signed spend of the group's
+/// taproot outputs `prevouts`, at `fee_rate` (sat/vB). It keeps the inputs
+/// and outputs, taking the higher fee from the output paying the group back
+/// or, failing that, the only output. The replacement must satisfy BIP125:
+/// `original` signals replacement, and the new fee is higher and outpays it
+/// by the incremental relay fee for the replacement's size.
+pub fn fee_bump(
+    original: &Transaction,
+    prevouts: &[TxOut],
+    fee_rate: f64,
+    internal_key: XOnlyPublicKey,
+    merkle_root: Option<TapNodeHash>,
+) -> Result<Psbt, AppError> {
+    if !original.is_explicitly_rbf() {
+        return Err(format!(
+            "{} doesn't signal replace-by-fee, so it can't be replaced",
+            original.compute_txid()
+        )
+        .into());
+    }
+    if prevouts.len() != original.input.len() {
+        return Err(format!(
+            "{} prevouts for {} inputs",
+            prevouts.len(),
+            original.input.len()
+        )
+        .into());
+    }
+    let script_pubkey =
+        ScriptBuf::new_p2tr(&Secp256k1::verification_only(), internal_key, merkle_root);
+    if let Some(index) = prevouts
+        .iter()
+        .position(|prevout| prevout.script_pubkey != script_pubkey)
+    {
+        return Err(format!("input {index} doesn't spend the group's address").into());
+    }
+    let total_in = prevouts
+        .iter()
+        .try_fold(Amount::ZERO, |total, prevout| {
+            total.checked_add(prevout.value)
+        })
+        .ok_or("the inputs overflow their total")?;
+    let total_out = original
+        .output
+        .iter()
+        .try_fold(Amount::ZERO, |total, output| {
+            total.checked_add(output.value)
+        })
+        .ok_or("the outputs overflow their total")?;
+    let original_fee = total_in
+        .checked_sub(total_out)
+        .ok_or("the outputs exceed the inputs")?;
+
+    let mut tx = original.clone();
+    for input in &mut tx.input {
+        input.script_sig = ScriptBuf::new();
+        input.witness = Witness::new();
+    }
+    let vsize = signed_vsize(&tx);
+    let fee = Amount::from_sat((fee_rate * vsize as f64).ceil() as u64);
+    if fee <= original_fee {
+        return Err(format!(
+            "a {fee} fee at {fee_rate} sat/vB doesn't exceed the {original_fee} already paid"
+        )
+        .into());
+    }
+    let increment = Amount::from_sat((INCREMENTAL_RELAY_FEE_RATE * vsize as f64).ceil() as u64);
+    if fee - original_fee < increment {
+        return Err(format!(
+            "the fee must rise by at least {increment}, the incremental relay fee for {vsize} vB, not {}",
+            fee - original_fee
+        )
+        .into());
+    }
+    let change = match tx
+        .output
+        .iter()
+        .position(|output| output.script_pubkey == script_pubkey)
+    {
+        Some(change) => change,
+        None if tx.output.len() == 1 => 0,
+        None => return Err("no output pays the group back to take the higher fee from".into()),
+    };
+    let output = &mut tx.output[change];
+    output.value = output
+        .value
+        .checked_sub(fee - original_fee)
+        .filter(|value| *value >= output.script_pubkey.minimal_non_dust())
+        .ok_or_else(|| format!("output {change} can't pay the higher {fee} fee"))?;
+
+    let mut psbt = Psbt::from_unsigned_tx(tx)?;
+    for (input, prevout) in psbt.inputs.iter_mut().zip(prevouts) {
+        input.witness_utxo = Some(prevout.clone());
+        input.tap_internal_key = Some(internal_key);
+        input.tap_merkle_root = merkle_root;
+        input.sighash_type = Some(TapSighashType::Default.into());
+    }
+    Ok(psbt)
+}
+
+/// An unsigned PSBT spending all of `utxos`, the group's taproot outputs
+/// committing to `merkle_root`, to one output at the same address, as
+/// [`sweep`] builds it.
+pub fn consolidation(
+    utxos: &[(OutPoint, Amount)],
+    fee_rate: f64,
+    internal_key: XOnlyPublicKey,
+    merkle_root: Option<TapNodeHash>,
+    rbf: bool,
+) -> Result<Psbt, AppError> {
+    if utxos.len() < 2 {
+        return Err(format!("{} UTXOs is nothing to consolidate", utxos.len()).into());
+    }
+    let script_pubkey =
+        ScriptBuf::new_p2tr(&Secp256k1::verification_only(), internal_key, merkle_root);
+    sweep(
+        utxos,
+        &script_pubkey,
+        fee_rate,
+        internal_key,
+        merkle_root,
+        rbf,
+    )
+}
+
+/// An unsigned PSBT spending all of `utxos`, the group's taproot outputs
+/// committing to `merkle_root`, to one output paying `destination`. The fee
+/// is `fee_rate` (sat/vB) times the transaction's size once every input
+/// carries its 64-byte key-path signature. With `rbf` it opts in to
+/// replacement.
+pub fn sweep(
+    utxos: &[(OutPoint, Amount)],
+    destination: &ScriptBuf,
+    fee_rate: f64,
+    internal_key: XOnlyPublicKey,
+    merkle_root: Option<TapNodeHash>,
+    rbf: bool,
+) -> Result<Psbt, AppError> {
+    if utxos.is_empty() {
+        return Err("there are no UTXOs to sweep".into());
+    }
+    if fee_rate.is_nan() || fee_rate < MIN_RELAY_FEE_RATE {
+        return Err(format!(
+            "{fee_rate} sat/vB is below the {MIN_RELAY_FEE_RATE} sat/vB nodes relay"
+        )
+        .into());
+    }
+    let total = utxos
+        .iter()
+        .try_fold(Amount::ZERO, |total, (_, amount)| {
+            total.checked_add(*amount)
+        })
+        .ok_or("the UTXOs overflow their total")?;
+    let mut psbt = key_path_transaction(
+        utxos,
+        &[(destination.clone(), total)],
+        internal_key,
+        merkle_root,
+        rbf,
+    )?;
+    let fee = Amount::from_sat((fee_rate * signed_vsize(&psbt.unsigned_tx) as f64).ceil() as u64);
+    let value = total
+        .checked_sub(fee)
+        .ok_or_else(|| format!("the {fee} fee exceeds the {total} being swept"))?;
+    if value < destination.minimal_non_dust() {
+        return Err(format!("sweeping {value} after the fee would be dust").into());
+    }
+    psbt.unsigned_tx.output[0].value = value;
+    Ok(psbt)
+}
+
+/// The BIP341 key-path sighash of input `index`, with `SIGHASH_DEFAULT` and
+/// committing to `annex`, if any.
+pub fn key_path_sighash(
+    psbt: &Psbt,
+    index: usize,
+    annex: Option<&[u8]>,
+) -> Result<[u8; 32], AppError> {
+    let prevouts: Vec<TxOut> = psbt
+        .iter_funding_utxos()
+        .map(|utxo| utxo.cloned())
+        .collect::<Result<_, _>>()?;
+    crate::taproot::key_spend_sighash(&psbt.unsigned_tx, index, &prevouts, annex)
+}
+
+/// Has `signers` sign every input of `psbt` that spends the group's output
+/// key by key path, using the merkle root recorded in the input, and returns
+/// how many inputs that was. Finding none is an error. The signatures commit
+/// to `annex`, if any, which [`finalize_key_path`] must then be given too: a
+/// PSBT has no field to carry it.
+pub fn sign_group_inputs<R: RngCore + CryptoRng>(
+    psbt: &mut Psbt,
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    annex: Option<&[u8]>,
+    rng: &mut R,
+) -> Result<usize, AppError> {
+    let internal_key = crate::taproot::internal_key(pubkey_package)?;
+    let secp = Secp256k1::verification_only();
+
+    let mut signed = 0;
+    for index in 0..psbt.inputs.len() {
+        let input = &psbt.inputs[index];
+        let merkle_root = input.tap_merkle_root;
+        let ours = input.tap_internal_key == Some(internal_key)
+            && psbt.spend_utxo(index)?.script_pubkey
+                == ScriptBuf::new_p2tr(&secp, internal_key, merkle_root);
+        if !ours {
+            continue;
+        }
+        let sighash = key_path_sighash(psbt, index, annex)?;
+        let signature = crate::taproot::sign_key_path(
+            key_packages,
+            pubkey_package,
+            signers,
+            &sighash,
+            merkle_root,
+            rng,
+        )
+        .inspect_err(|err| log::error!("Signing input {} failed: {}", index, err))?;
+        psbt.inputs[index].tap_key_sig = Some(bitcoin::taproot::Signature {
+            signature,
+            sighash_type: TapSighashType::Default,
+        });
+        signed += 1;
+    }
+    if signed == 0 {
+        return Err("no input spends the group's output key by key path".into());
+    }
+    Ok(signed)
+}
+
+/// Signs every input of `psbt` that spends the taproot output of `secret`'s
+/// key by key path with that key alone, as a single-sig wallet would, and
+/// returns how many inputs that was. Finding none is an error. This is the
+/// spend of a reconstructed group secret, bypassing FROST.
+pub fn sign_inputs_with_secret<R: RngCore + CryptoRng>(
+    psbt: &mut Psbt,
+    secret: &frost::SigningKey,
+    rng: &mut R,
+) -> Result<usize, AppError> {
+    let secp = Secp256k1::new();
+    let keypair = Keypair::from_seckey_slice(&secp, &secret.serialize())?;
+    let (internal_key, _) = keypair.x_only_public_key();
+
+    let mut signed = 0;
+    for index in 0..psbt.inputs.len() {
+        let merkle_root = psbt.inputs[index].tap_merkle_root;
+        let ours = psbt.inputs[index].tap_internal_key == Some(internal_key)
+            && psbt.spend_utxo(index)?.script_pubkey
+                == ScriptBuf::new_p2tr(&secp, internal_key, merkle_root);
+        if !ours {
+            continue;
+        }
+        let sighash = key_path_sighash(psbt, index, None)?;
+        let mut aux_rand = [0; 32];
+        rng.fill_bytes(&mut aux_rand);
+        let signature = secp.sign_schnorr_with_aux_rand(
+            &Message::from_digest(sighash),
+            &keypair.tap_tweak(&secp, merkle_root).to_keypair(),
+            &aux_rand,
+        );
+        psbt.inputs[index].tap_key_sig = Some(bitcoin::taproot::Signature {
+            signature,
+            sighash_type: TapSighashType::Default,
+        });
+        signed += 1;
+    }
+    if signed == 0 {
+        return Err("no input spends the key's taproot output by key path".into());
+    }
+    Ok(signed)
+}
+
+/// The transaction of `psbt`, whose inputs must all have key-path signatures.
+/// The `annex` the signatures commit to, if any, follows each of them in the
+/// witness.
+pub fn finalize_key_path(mut psbt: Psbt, annex: Option<&[u8]>) -> Result<Transaction, AppError> {
+    for (index, input) in psbt.inputs.iter_mut().enumerate() {
+        let signature = input.tap_key_sig.take().ok_or_else(|| {
+            format!("input {index} is not signed by key path; it can't be finalized here")
+        })?;
+        let mut witness = Witness::p2tr_key_spend(&signature);
+        if let Some(annex) = annex {
+            witness.push(annex);
+        }
+        input.final_script_witness = Some(witness);
+    }
+    Ok(psbt.extract_tx()?)
+}
+
+/// Merges PSBTs of the same unsigned transaction.
+pub fn combine(psbts: Vec<Psbt>) -> Result<Psbt, AppError> {
+    let mut psbts = psbts.into_iter();
+    let mut combined = psbts.next().ok_or("no PSBTs to combine")?;
+    for psbt in psbts {
+        combined.combine(psbt)?;
+    }
+    Ok(combined)
+}
+
+/// Every signature in `psbt`, input by input, checked against its sighash.
+pub fn contributions(psbt: &Psbt) -> Result<Vec<Contribution>, AppError> {
+    let secp = Secp256k1::verification_only();
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+    let mut contributions = Vec::new();
+
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        for (key, signature) in &input.partial_sigs {
+            let valid = match psbt.sighash_ecdsa(index, &mut cache) {
+                Ok((message, sighash_type)) => {
+                    signature.sighash_type == sighash_type
+                        && secp
+                            .verify_ecdsa(&message, &signature.signature, &key.inner)
+                            .is_ok()
+                }
+                Err(_) => false,
+            };
+            contributions.push(Contribution {
+                input: index,
+                signer: Signer::Ecdsa(*key),
+                valid,
+            });
+        }
+
+        if input.tap_key_sig.is_none() && input.tap_script_sigs.is_empty() {
+            continue;
+        }
+        // Taproot sighashes commit to every input's prevout.
+        let prevouts: Vec<TxOut> = psbt
+            .iter_funding_utxos()
+            .map(|utxo| utxo.cloned())
+            .collect::<Result<_, _>>()?;
+        let prevouts = Prevouts::All(&prevouts);
+
+        if let Some(signature) = input.tap_key_sig {
+            let output_key = taproot_output_key(&psbt.spend_utxo(index)?.script_pubkey)?;
+            let valid = cache
+                .taproot_key_spend_signature_hash(index, &prevouts, signature.sighash_type)
+                .is_ok_and(|sighash| {
+                    schnorr_valid(&secp, sighash.as_byte_array(), &signature, &output_key)
+                });
+            contributions.push(Contribution {
+                input: index,
+                signer: Signer::TaprootKey(output_key),
+                valid,
+            });
+        }
+        for ((key, leaf_hash), signature) in &input.tap_script_sigs {
+            let valid = cache
+                .taproot_script_spend_signature_hash(
+                    index,
+                    &prevouts,
+                    *leaf_hash,
+                    signature.sighash_type,
+                )
+                .is_ok_and(|sighash| schnorr_valid(&secp, sighash.as_byte_array(), signature, key));
+            contributions.push(Contribution {
+                input: index,
+                signer: Signer::TaprootScript(*key, *leaf_hash),
+                valid,
+            });
+        }
+    }
+    Ok(contributions)
+}
+
+fn taproot_output_key(script_pubkey: &bitcoin::Script) -> Result<XOnlyPublicKey, AppError> {
+    if !script_pubkey.is_p2tr() {
+        return Err("taproot signature on an input that does not spend a P2TR output".into());
+    }
+    Ok(XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..])?)
+}
+
+fn schnorr_valid(
+    secp: &Secp256k1<secp256k1::VerifyOnly>,
+    sighash: &[u8; 32],
+    signature: &bitcoin::taproot::Signature,
+    key: &XOnlyPublicKey,
+) -> bool {
+    secp.verify_schnorr(&signature.signature, &Message::from_digest(*sighash), key)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::{Keypair, TapTweak};
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::sighash::EcdsaSighashType;
+    use bitcoin::CompressedPublicKey;
+
+    /// A transaction spending one P2WPKH and one P2TR output, as a PSBT.
+    fn unsigned_psbt(wpkh_key: &CompressedPublicKey, tr_key: &Keypair) -> Psbt {
+        let secp = Secp256k1::new();
+        let input = |vout| TxIn {
+            previous_output: OutPoint {
+                txid: bitcoin::Txid::all_zeros(),
+                vout,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        };
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![input(0), input(1)],
+            output: vec![TxOut {
+                value: Amount::from_sat(15_000),
+                script_pubkey: ScriptBuf::new_op_return([]),
+            }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: ScriptBuf::new_p2wpkh(&wpkh_key.wpubkey_hash()),
+        });
+        psbt.inputs[1].witness_utxo = Some(TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: ScriptBuf::new_p2tr(&secp, tr_key.x_only_public_key().0, None),
+        });
+        psbt
+    }
+
+    fn signed_copies() -> (Psbt, Psbt) {
+        let secp = Secp256k1::new();
+        let ecdsa_secret = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let wpkh_key = CompressedPublicKey(ecdsa_secret.public_key(&secp));
+        let tr_key = Keypair::from_seckey_slice(&secp, &[2u8; 32]).unwrap();
+        let psbt = unsigned_psbt(&wpkh_key, &tr_key);
+
+        // The single-sig wallet signs the P2WPKH input.
+        let mut single_sig = psbt.clone();
+        let mut cache = SighashCache::new(&psbt.unsigned_tx);
+        let (message, _) = psbt.sighash_ecdsa(0, &mut cache).unwrap();
+        single_sig.inputs[0].partial_sigs.insert(
+            wpkh_key.into(),
+            bitcoin::ecdsa::Signature {
+                signature: secp.sign_ecdsa(&message, &ecdsa_secret),
+                sighash_type: EcdsaSighashType::All,
+            },
+        );
+
+        // The other co-signer signs the taproot input's key path.
+        let mut taproot = psbt.clone();
+        let prevouts: Vec<_> = psbt
+            .inputs
+            .iter()
+            .map(|input| input.witness_utxo.clone().unwrap())
+            .collect();
+        let sighash = cache
+            .taproot_key_spend_signature_hash(1, &Prevouts::All(&prevouts), TapSighashType::Default)
+            .unwrap();
+        let tweaked = tr_key.tap_tweak(&secp, None).to_keypair();
+        taproot.inputs[1].tap_key_sig = Some(bitcoin::taproot::Signature {
+            signature: secp
+                .sign_schnorr_no_aux_rand(&Message::from_digest(sighash.to_byte_array()), &tweaked),
+            sighash_type: TapSighashType::Default,
+        });
+        (single_sig, taproot)
+    }
+
+    #[test]
+    fn amounts_parse_with_or_without_a_unit() {
+        let sats = |sats| Ok(Amount::from_sat(sats));
+        let parse = |amount| parse_amount(amount, None).map_err(|err| err.to_string());
+        assert_eq!(parse("100000"), sats(100_000));
+        assert_eq!(parse("100000sat"), sats(100_000));
+        assert_eq!(parse("0.001btc"), sats(100_000));
+        assert_eq!(parse("1 mBTC"), sats(100_000));
+        assert_eq!(
+            parse_amount("0.001", Some(Denomination::Bitcoin)).unwrap(),
+            Amount::from_sat(100_000)
+        );
+        for rejected in ["-1", "-0.1btc", "0.5", "1e5", "1MBTC", "1parsec", "btc", ""] {
+            assert!(parse(rejected).is_err(), "{rejected:?}");
+        }
+        assert!(parse_amount("1btc", Some(Denomination::Bitcoin)).is_err());
+    }
+
+    #[test]
+    fn combined_psbt_reports_every_signer() {
+        let (single_sig, taproot) = signed_copies();
+        let combined = combine(vec![single_sig, taproot]).unwrap();
+        let contributions = contributions(&combined).unwrap();
+
+        assert_eq!(contributions.len(), 2);
+        assert!(contributions.iter().all(|contribution| contribution.valid));
+        assert!(matches!(contributions[0].signer, Signer::Ecdsa(_)));
+        assert!(matches!(contributions[1].signer, Signer::TaprootKey(_)));
+    }
+
+    #[test]
+    fn the_group_signs_a_built_key_path_spend() {
+        let (key_packages, pubkey_package) =
+            crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let internal_key = crate::taproot::internal_key(&pubkey_package).unwrap();
+        let destination = Address::p2tr(
+            &Secp256k1::new(),
+            internal_key,
+            None,
+            bitcoin::Network::Regtest,
+        );
+        let outpoint = OutPoint {
+            txid: bitcoin::Txid::all_zeros(),
+            vout: 1,
+        };
+        let build = |fee| {
+            key_path_spend(
+                outpoint,
+                Amount::from_sat(10_000),
+                Amount::from_sat(fee),
+                &destination,
+                internal_key,
+                None,
+                true,
+            )
+        };
+        assert!(build(10_001).is_err());
+        assert!(build(9_900).is_err());
+
+        let mut psbt = build(500).unwrap();
+        assert_eq!(psbt.unsigned_tx.output[0].value, Amount::from_sat(9_500));
+        assert!(psbt.unsigned_tx.is_explicitly_rbf());
+        let sighash = key_path_sighash(&psbt, 0, None).unwrap();
+        let signature = crate::taproot::sign_key_path(
+            &key_packages,
+            &pubkey_package,
+            &crate::default_signers(&key_packages),
+            &sighash,
+            None,
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
+        psbt.inputs[0].tap_key_sig = Some(bitcoin::taproot::Signature {
+            signature,
+            sighash_type: TapSighashType::Default,
+        });
+
+        let contributions = contributions(&psbt).unwrap();
+        assert_eq!(contributions.len(), 1);
+        assert!(contributions[0].valid);
+    }
+
+    #[test]
+    fn an_annex_is_signed_and_follows_the_signature() {
+        let (key_packages, pubkey_package) =
+            crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let internal_key = crate::taproot::internal_key(&pubkey_package).unwrap();
+        let utxos: Vec<_> = (0..2)
+            .map(|vout| {
+                let outpoint = OutPoint {
+                    txid: bitcoin::Txid::all_zeros(),
+                    vout,
+                };
+                (outpoint, Amount::from_sat(10_000))
+            })
+            .collect();
+        let annex = crate::taproot::parse_annex("50cafe").unwrap();
+        let mut psbt = consolidation(&utxos, 2.0, internal_key, None, true).unwrap();
+        let signed = sign_group_inputs(
+            &mut psbt,
+            &key_packages,
+            &pubkey_package,
+            &crate::default_signers(&key_packages),
+            Some(&annex),
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
+        assert_eq!(signed, 2);
+        let sighashes: Vec<_> = (0..2)
+            .map(|index| key_path_sighash(&psbt, index, Some(&annex)).unwrap())
+            .collect();
+        let output_key = psbt.inputs[0]
+            .witness_utxo
+            .as_ref()
+            .unwrap()
+            .script_pubkey
+            .as_bytes()[2..]
+            .to_vec();
+        let output_key = XOnlyPublicKey::from_slice(&output_key).unwrap();
+
+        let tx = finalize_key_path(psbt, Some(&annex)).unwrap();
+        let secp = Secp256k1::verification_only();
+        for (input, sighash) in tx.input.iter().zip(sighashes) {
+            assert_eq!(input.witness.len(), 2);
+            assert_eq!(input.witness.taproot_annex(), Some(&annex[..]));
+            let signature = secp256k1::schnorr::Signature::from_slice(&input.witness[0]).unwrap();
+            let message = Message::from_digest(sighash);
+            secp.verify_schnorr(&signature, &message, &output_key)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn consolidation_pays_its_fee_rate_once_signed() {
+        let (key_packages, pubkey_package) =
+            crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let internal_key = crate::taproot::internal_key(&pubkey_package).unwrap();
+        let utxos: Vec<_> = (0..3)
+            .map(|vout| {
+                let outpoint = OutPoint {
+                    txid: bitcoin::Txid::all_zeros(),
+                    vout,
+                };
+                (outpoint, Amount::from_sat(10_000))
+            })
+            .collect();
+        assert!(consolidation(&utxos[..1], 2.0, internal_key, None, true).is_err());
+        assert!(consolidation(&utxos, 0.5, internal_key, None, true).is_err());
+        assert!(consolidation(&utxos, 1000.0, internal_key, None, true).is_err());
+
+        let mut psbt = consolidation(&utxos, 2.0, internal_key, None, true).unwrap();
+        let output = &psbt.unsigned_tx.output[0];
+        assert_eq!(
+            output.script_pubkey,
+            psbt.inputs[0].witness_utxo.as_ref().unwrap().script_pubkey
+        );
+        let fee = Amount::from_sat(30_000) - output.value;
+        let signers = crate::default_signers(&key_packages);
+        let signed = sign_group_inputs(
+            &mut psbt,
+            &key_packages,
+            &pubkey_package,
+            &signers,
+            None,
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
+        assert_eq!(signed, utxos.len());
+        assert!(contributions(&psbt)
+            .unwrap()
+            .iter()
+            .all(|contribution| contribution.valid));
+
+        let vsize = finalize_key_path(psbt, None).unwrap().vsize() as u64;
+        assert_eq!(fee, Amount::from_sat(2 * vsize));
+    }
+
+    #[test]
+    fn a_tx_spec_builds_a_transaction() {
+        let (_, pubkey_package) = crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let internal_key = crate::taproot::internal_key(&pubkey_package).unwrap();
+        let network = bitcoin::Network::Regtest;
+        let address = Address::p2tr(&Secp256k1::new(), internal_key, None, network);
+        let txid = "11".repeat(32);
+        let spec = |inputs: &str, outputs: &str| {
+            format!(r#"{{ "inputs": [{inputs}], "outputs": [{outputs}] }}"#)
+        };
+        let input =
+            |vout, amount| format!(r#"{{ "outpoint": "{txid}:{vout}", "amount": "{amount}" }}"#);
+        let output = |amount| format!(r#"{{ "address": "{address}", "amount": "{amount}" }}"#);
+        let build = |json: &str| {
+            let (inputs, outputs) = TxSpec::parse(json)?.resolve(None, network)?;
+            key_path_transaction(&inputs, &outputs, internal_key, None, true)
+        };
+
+        let psbt = build(&spec(
+            &[input(0, "0.001btc"), input(1, "50000")].join(","),
+            &[output("100000sat"), output("49000")].join(","),
+        ))
+        .unwrap();
+        assert_eq!(psbt.unsigned_tx.input.len(), 2);
+        assert_eq!(psbt.unsigned_tx.output[1].value, Amount::from_sat(49_000));
+        assert_eq!(
+            psbt.inputs[1].witness_utxo.as_ref().unwrap().value,
+            Amount::from_sat(50_000)
+        );
+        assert!(psbt.unsigned_tx.is_explicitly_rbf());
+
+        let error = |json: &str| build(json).unwrap_err().to_string();
+        assert!(error(r#"{ "inputs": [] }"#).contains("missing field `outputs`"));
+        assert!(
+            error(r#"{ "inputs": [], "outputs": [], "fee": 1 }"#).contains("unknown field `fee`")
+        );
+        assert!(error(&spec(&input(0, "1000"), "")).contains("at least one input and one output"));
+        let bad_outpoint = spec(r#"{ "outpoint": "11", "amount": "1000" }"#, &output("500"));
+        assert!(error(&bad_outpoint).starts_with("inputs[0].outpoint"));
+        assert!(error(&spec(&input(0, "-1"), &output("500"))).starts_with("inputs[0].amount"));
+        let foreign = spec(
+            &input(0, "1000"),
+            r#"{ "address": "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr", "amount": "500" }"#,
+        );
+        assert!(error(&foreign).starts_with("outputs[0].address"));
+        assert!(error(&spec(&input(0, "1000"), &output("2000"))).contains("exceed the inputs"));
+        assert!(error(&spec(&input(0, "1000"), &output("100"))).contains("dust"));
+        let twice = [input(0, "1000"), input(0, "1000")].join(",");
+        assert!(error(&spec(&twice, &output("500"))).contains("spent twice"));
+    }
+
+    #[test]
+    fn every_input_signals_rbf_unless_disabled() {
+        let (_, pubkey_package) = crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let internal_key = crate::taproot::internal_key(&pubkey_package).unwrap();
+        let utxos: Vec<_> = (0..3)
+            .map(|vout| {
+                let outpoint = OutPoint {
+                    txid: bitcoin::Txid::all_zeros(),
+                    vout,
+                };
+                (outpoint, Amount::from_sat(10_000))
+            })
+            .collect();
+        let destination = Address::p2tr(
+            &Secp256k1::new(),
+            internal_key,
+            None,
+            bitcoin::Network::Regtest,
+        );
+        for rbf in [true, false] {
+            let spend = key_path_spend(
+                utxos[0].0,
+                utxos[0].1,
+                Amount::from_sat(500),
+                &destination,
+                internal_key,
+                None,
+                rbf,
+            )
+            .unwrap();
+            let consolidated = consolidation(&utxos, 2.0, internal_key, None, rbf).unwrap();
+            for tx in [&spend.unsigned_tx, &consolidated.unsigned_tx] {
+                assert_eq!(tx.is_explicitly_rbf(), rbf);
+                for input in &tx.input {
+                    let expected = if rbf { 0xfffffffd } else { 0xfffffffe };
+                    assert_eq!(input.sequence.to_consensus_u32(), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_fee_bump_outpays_the_original_by_bip125() {
+        let (key_packages, pubkey_package) =
+            crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let internal_key = crate::taproot::internal_key(&pubkey_package).unwrap();
+        let signers = crate::default_signers(&key_packages);
+        let utxos: Vec<_> = (0..2)
+            .map(|vout| {
+                let outpoint = OutPoint {
+                    txid: bitcoin::Txid::all_zeros(),
+                    vout,
+                };
+                (outpoint, Amount::from_sat(10_000))
+            })
+            .collect();
+        let sign = |mut psbt: Psbt| {
+            sign_group_inputs(
+                &mut psbt,
+                &key_packages,
+                &pubkey_package,
+                &signers,
+                None,
+                &mut rand::thread_rng(),
+            )
+            .unwrap();
+            finalize_key_path(psbt, None).unwrap()
+        };
+        let original = consolidation(&utxos, 2.0, internal_key, None, true).unwrap();
+        let prevouts: Vec<_> = original
+            .inputs
+            .iter()
+            .map(|input| input.witness_utxo.clone().unwrap())
+            .collect();
+        let original = sign(original);
+        let original_fee = Amount::from_sat(20_000) - original.output[0].value;
+
+        assert!(fee_bump(&original, &prevouts, 2.0, internal_key, None).is_err());
+        // Higher, but by less than the incremental relay fee.
+        assert!(fee_bump(&original, &prevouts, 2.5, internal_key, None).is_err());
+        assert!(fee_bump(&original, &prevouts[..1], 5.0, internal_key, None).is_err());
+        let (_, other_group) = crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let foreign_key = crate::taproot::internal_key(&other_group).unwrap();
+        assert!(fee_bump(&original, &prevouts, 5.0, foreign_key, None).is_err());
+
+        let bumped = fee_bump(&original, &prevouts, 5.0, internal_key, None).unwrap();
+        assert_eq!(bumped.unsigned_tx.input.len(), original.input.len());
+        assert!(bumped.unsigned_tx.is_explicitly_rbf());
+        let replacement = sign(bumped);
+        let fee = Amount::from_sat(20_000) - replacement.output[0].value;
+        assert_eq!(fee, Amount::from_sat(5 * replacement.vsize() as u64));
+        assert!(fee - original_fee >= Amount::from_sat(replacement.vsize() as u64));
+
+        let final_spend = consolidation(&utxos, 2.0, internal_key, None, false).unwrap();
+        assert!(fee_bump(&sign(final_spend), &prevouts, 5.0, internal_key, None).is_err());
+    }
+
+    #[test]
+    fn the_reconstructed_secret_sweeps_alone() {
+        let (key_packages, pubkey_package) =
+            crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let internal_key = crate::taproot::internal_key(&pubkey_package).unwrap();
+        let secret = crate::reconstruct_group_key(&key_packages, &[]).unwrap();
+        let destination = ScriptBuf::from_hex(&format!("0014{}", "22".repeat(20))).unwrap();
+        let utxo = OutPoint {
+            txid: bitcoin::Txid::all_zeros(),
+            vout: 0,
+        };
+        let utxos = [(utxo, Amount::from_sat(10_000))];
+        assert!(sweep(&[], &destination, 2.0, internal_key, None, true).is_err());
+        assert!(sweep(&utxos, &destination, 200.0, internal_key, None, true).is_err());
+
+        let mut psbt = sweep(&utxos, &destination, 2.0, internal_key, None, true).unwrap();
+        assert_eq!(psbt.unsigned_tx.output[0].script_pubkey, destination);
+        let (other_group, _) = crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let other_secret = crate::reconstruct_group_key(&other_group, &[]).unwrap();
+        assert!(
+            sign_inputs_with_secret(&mut psbt, &other_secret, &mut rand::thread_rng()).is_err()
+        );
+        assert_eq!(
+            sign_inputs_with_secret(&mut psbt, &secret, &mut rand::thread_rng()).unwrap(),
+            1
+        );
+        assert!(contributions(&psbt)
+            .unwrap()
+            .iter()
+            .all(|contribution| contribution.valid));
+
+        let tx = finalize_key_path(psbt, None).unwrap();
+        let fee = Amount::from_sat(10_000) - tx.output[0].value;
+        assert_eq!(fee, Amount::from_sat(2 * tx.vsize() as u64));
+    }
+
+    #[test]
+    fn fee_rates_are_checked_against_the_estimate() {
+        assert_eq!(checked_fee_rate(None, Some(12.5), 6).unwrap(), 12.5);
+        assert_eq!(
+            checked_fee_rate(None, Some(0.5), 6).unwrap(),
+            MIN_RELAY_FEE_RATE
+        );
+        assert!(checked_fee_rate(None, None, 6).is_err());
+        assert_eq!(checked_fee_rate(Some(3.0), Some(12.5), 6).unwrap(), 3.0);
+        assert!(checked_fee_rate(Some(200.0), Some(12.5), 6).is_err());
+        assert_eq!(checked_fee_rate(Some(200.0), None, 6).unwrap(), 200.0);
+    }
+
+    #[test]
+    fn only_the_groups_inputs_are_signed() {
+        let (key_packages, pubkey_package) =
+            crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let (_, other_group) = crate::dealer_keygen(2, 3, &mut rand::thread_rng()).unwrap();
+        let signers = crate::default_signers(&key_packages);
+        let internal_key = crate::taproot::internal_key(&pubkey_package).unwrap();
+        let foreign_key = crate::taproot::internal_key(&other_group).unwrap();
+        let utxo = |vout| {
+            let outpoint = OutPoint {
+                txid: bitcoin::Txid::all_zeros(),
+                vout,
+            };
+            (outpoint, Amount::from_sat(10_000))
+        };
+
+        let mut foreign = consolidation(&[utxo(0), utxo(1)], 2.0, foreign_key, None, true).unwrap();
+        assert!(sign_group_inputs(
+            &mut foreign,
+            &key_packages,
+            &pubkey_package,
+            &signers,
+            None,
+            &mut rand::thread_rng()
+        )
+        .is_err());
+        assert!(finalize_key_path(foreign, None).is_err());
+
+        let mut psbt = consolidation(&[utxo(0), utxo(1)], 2.0, internal_key, None, true).unwrap();
+        psbt.inputs[1].tap_internal_key = Some(foreign_key);
+        let signed = sign_group_inputs(
+            &mut psbt,
+            &key_packages,
+            &pubkey_package,
+            &signers,
+            None,
+            &mut rand::thread_rng(),
+        )
+        .unwrap();
+        assert_eq!(signed, 1);
+        assert!(psbt.inputs[0].tap_key_sig.is_some());
+        assert!(psbt.inputs[1].tap_key_sig.is_none());
+    }
+
+    #[test]
+    fn a_signature_over_a_different_amount_is_invalid() {
+        let (mut single_sig, _) = signed_copies();
+        // Segwit sighashes commit to the amount being spent.
+        single_sig.inputs[0].witness_utxo.as_mut().unwrap().value = Amount::from_sat(9_999);
+
+        let contributions = contributions(&single_sig).unwrap();
+        assert_eq!(contributions.len(), 1);
+        assert!(!contributions[0].valid);
+    }
+}
@@ -0,0 +1,155 @@
+//! Audits that a group signature came from a full quorum of valid shares.
+//!
+//! A valid group signature only shows that the group key signed. This checks
+//! each contributing `SignatureShare` against its participant's verifying
+//! share, and that the valid shares alone aggregate to the signature under
+//! review.
+
+use crate::frost;
+use std::collections::BTreeMap;
+
+/// Outcome of auditing one group signature.
+#[derive(Debug)]
+pub struct QuorumAudit {
+    /// Participants whose share verified against the signing package.
+    pub valid: Vec<frost::Identifier>,
+    /// Participants whose share did not verify, or who were not in the
+    /// signing package or the group.
+    pub invalid: Vec<frost::Identifier>,
+    pub threshold: u16,
+    /// Whether the valid shares aggregate to the audited signature.
+    pub signature_matches: bool,
+}
+
+impl QuorumAudit {
+    /// At least `threshold` distinct valid shares produced the signature.
+    pub fn met(&self) -> bool {
+        self.signature_matches && self.valid.len() >= usize::from(self.threshold)
+    }
+}
+
+/// Whether `share` verifies as `identifier`'s share of `signing_package`.
+fn share_is_valid(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signing_package: &frost::SigningPackage,
+    identifier: &frost::Identifier,
+    share: &frost::round2::SignatureShare,
+) -> bool {
+    signing_package
+        .signing_commitment(identifier)
+        .and(pubkey_package.verifying_shares().get(identifier))
+        .is_some_and(|verifying_share| {
+            frost_core::verify_signature_share(
+                *identifier,
+                verifying_share,
+                share,
+                signing_package,
+                pubkey_package.verifying_key(),
+            )
+            .is_ok()
+        })
+}
+
+/// The participants whose share in `signature_shares` doesn't verify.
+pub fn invalid_shares(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signing_package: &frost::SigningPackage,
+    signature_shares: &BTreeMap<frost::Identifier, frost::round2::SignatureShare>,
+) -> Vec<frost::Identifier> {
+    signature_shares
+        .iter()
+        .filter(|(identifier, share)| {
+            !share_is_valid(pubkey_package, signing_package, identifier, share)
+        })
+        .map(|(identifier, _)| *identifier)
+        .collect()
+}
+
+pub fn audit(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signing_package: &frost::SigningPackage,
+    signature_shares: &BTreeMap<frost::Identifier, frost::round2::SignatureShare>,
+    signature: &frost::Signature,
+    threshold: u16,
+) -> QuorumAudit {
+    let mut valid_shares = BTreeMap::new();
+    let mut invalid = Vec::new();
+    for (identifier, share) in signature_shares {
+        if share_is_valid(pubkey_package, signing_package, identifier, share) {
+            valid_shares.insert(*identifier, *share);
+        } else {
+            invalid.push(*identifier);
+        }
+    }
+
+    // Aggregation fails unless every participant in the signing package has
+    // a valid share, and the result has to be the signature under review.
+    let signature_matches = frost::aggregate(signing_package, &valid_shares, pubkey_package)
+        .is_ok_and(|aggregated| aggregated == *signature);
+
+    QuorumAudit {
+        valid: valid_shares.into_keys().collect(),
+        invalid,
+        threshold,
+        signature_matches,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, default_signers, run_ceremony, DEMO_MESSAGE};
+    use rand::thread_rng;
+
+    #[test]
+    fn an_honest_ceremony_meets_the_threshold() {
+        let (key_packages, pubkey_package) = dealer_keygen(3, 5, &mut thread_rng()).unwrap();
+        let ceremony = run_ceremony(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            DEMO_MESSAGE,
+            &mut thread_rng(),
+        )
+        .unwrap();
+
+        let audit = audit(
+            &pubkey_package,
+            &ceremony.signing_package,
+            &ceremony.signature_shares,
+            &ceremony.signature,
+            3,
+        );
+        assert_eq!(audit.valid.len(), 3);
+        assert!(audit.invalid.is_empty());
+        assert!(audit.met());
+    }
+
+    #[test]
+    fn a_swapped_share_fails_the_audit() {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let signers = default_signers(&key_packages);
+        let ceremony = run_ceremony(
+            &key_packages,
+            &pubkey_package,
+            &signers,
+            DEMO_MESSAGE,
+            &mut thread_rng(),
+        )
+        .unwrap();
+
+        // Replaying the second signer's share as the first one's.
+        let mut shares = ceremony.signature_shares.clone();
+        shares.insert(signers[0], ceremony.signature_shares[&signers[1]]);
+        let audit = audit(
+            &pubkey_package,
+            &ceremony.signing_package,
+            &shares,
+            &ceremony.signature,
+            2,
+        );
+        assert_eq!(audit.invalid, vec![signers[0]]);
+        assert!(!audit.signature_matches);
+        assert!(!audit.met());
+    }
+}
@@ -0,0 +1,163 @@
+//! Proactive share refresh and a check that a refresh actually happened.
+//!
+//! A refresh adds a fresh sharing of zero to every participant's share. The
+//! group key stays the same, while every share, and so every verifying
+//! share, changes. A refresh that leaves some share untouched gives that
+//! share's old copies their full value, so [`check`] insists on both.
+//!
+//! frost-secp256k1 2.0 does not re-export frost-core's refresh module, so it
+//! is used directly.
+
+use crate::error::AppError;
+use crate::{frost, map_pubkey_package, KeyPackages};
+use bitcoin::hashes::{hash160, Hash};
+use rand::{CryptoRng, RngCore};
+
+/// One participant's share before and after the refresh.
+#[derive(Debug)]
+pub struct ShareChange {
+    pub identifier: frost::Identifier,
+    /// Fingerprints of the public verifying shares, never of the secrets.
+    pub old_fingerprint: String,
+    pub new_fingerprint: String,
+}
+
+impl ShareChange {
+    pub fn changed(&self) -> bool {
+        self.old_fingerprint != self.new_fingerprint
+    }
+}
+
+#[derive(Debug)]
+pub struct RefreshCheck {
+    pub old_group_key: frost::VerifyingKey,
+    pub new_group_key: frost::VerifyingKey,
+    pub shares: Vec<ShareChange>,
+}
+
+impl RefreshCheck {
+    pub fn group_key_unchanged(&self) -> bool {
+        self.old_group_key == self.new_group_key
+    }
+
+    pub fn passed(&self) -> bool {
+        self.group_key_unchanged() && self.shares.iter().all(ShareChange::changed)
+    }
+}
+
+/// Refreshes every share with a trusted dealer's sharing of zero.
+pub fn refresh<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    rng: &mut R,
+) -> Result<(KeyPackages, frost::keys::PublicKeyPackage), AppError> {
+    let (min_signers, max_signers) = crate::map_threshold(key_packages)?;
+    let identifiers: Vec<_> = key_packages.keys().copied().collect();
+    let (zero_shares, new_pubkey_package) = frost_core::keys::refresh::compute_refreshing_shares(
+        map_pubkey_package(key_packages)?,
+        max_signers,
+        min_signers,
+        &identifiers,
+        rng,
+    )?;
+
+    let mut refreshed = KeyPackages::new();
+    for zero_share in zero_shares {
+        let identifier = *zero_share.identifier();
+        let current = key_packages
+            .get(&identifier)
+            .ok_or(frost::Error::UnknownIdentifier)?;
+        let refreshed_package = frost_core::keys::refresh::refresh_share(zero_share, current)?;
+        // frost-core 2.0 keeps the old verifying share in the refreshed
+        // package, so it is derived again from the new signing share.
+        let signing_share = *refreshed_package.signing_share();
+        refreshed.insert(
+            identifier,
+            frost::keys::KeyPackage::new(
+                identifier,
+                signing_share,
+                signing_share.into(),
+                *refreshed_package.verifying_key(),
+                *refreshed_package.min_signers(),
+            ),
+        );
+    }
+    Ok((refreshed, new_pubkey_package))
+}
+
+/// Compares the key packages from before and after a refresh.
+pub fn check(old: &KeyPackages, new: &KeyPackages) -> Result<RefreshCheck, AppError> {
+    if old.keys().ne(new.keys()) {
+        return Err("the refreshed key packages belong to different participants".into());
+    }
+    if crate::map_threshold(old)? != crate::map_threshold(new)? {
+        return Err("the refresh changed the threshold".into());
+    }
+
+    let shares = old
+        .iter()
+        .map(|(identifier, old_package)| {
+            Ok(ShareChange {
+                identifier: *identifier,
+                old_fingerprint: fingerprint(old_package)?,
+                new_fingerprint: fingerprint(&new[identifier])?,
+            })
+        })
+        .collect::<Result<_, AppError>>()?;
+    Ok(RefreshCheck {
+        old_group_key: *map_pubkey_package(old)?.verifying_key(),
+        new_group_key: *map_pubkey_package(new)?.verifying_key(),
+        shares,
+    })
+}
+
+/// First 4 bytes of HASH160 of the verifying share, like the group
+/// fingerprint. It is derived from the signing share rather than read from the
+/// package, so a stale stored copy can't hide an unchanged share.
+fn fingerprint(key_package: &frost::keys::KeyPackage) -> Result<String, AppError> {
+    let verifying_share = frost::keys::VerifyingShare::from(*key_package.signing_share());
+    let digest = hash160::Hash::hash(&verifying_share.serialize()?);
+    Ok(hex::encode(&digest[..4]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, default_signers, sign_message, DEMO_MESSAGE};
+    use rand::thread_rng;
+
+    #[test]
+    fn refreshed_shares_change_and_still_sign() {
+        let (key_packages, pubkey_package) = dealer_keygen(3, 5, &mut thread_rng()).unwrap();
+        let (refreshed, new_pubkey_package) = refresh(&key_packages, &mut thread_rng()).unwrap();
+
+        assert!(check(&key_packages, &refreshed).unwrap().passed());
+        assert_eq!(
+            new_pubkey_package.verifying_key(),
+            pubkey_package.verifying_key()
+        );
+
+        let signature = sign_message(
+            &refreshed,
+            &new_pubkey_package,
+            &default_signers(&refreshed),
+            DEMO_MESSAGE,
+            &mut thread_rng(),
+        )
+        .unwrap();
+        assert!(pubkey_package
+            .verifying_key()
+            .verify(DEMO_MESSAGE, &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn a_no_op_refresh_fails_the_check() {
+        let (key_packages, _) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let no_op = check(&key_packages, &key_packages).unwrap();
+        assert!(no_op.group_key_unchanged());
+        assert!(!no_op.passed());
+
+        let (other_group, _) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        assert!(!check(&key_packages, &other_group).unwrap().passed());
+    }
+}
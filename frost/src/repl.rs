@@ -0,0 +1,331 @@
+//! An interactive session that steps through one FROST ceremony a command
+//! at a time, keeping the keys, nonces, commitments and shares in memory.
+//! See [`HELP`] for the commands.
+//!
+//! The first `sign` fixes the signing package to the commitments made so
+//! far, so later commits are refused until `reset`. A participant's nonces
+//! are dropped as soon as it has signed.
+
+use crate::error::AppError;
+use crate::{
+    aggregate_shares, dealer_keygen_with, frost, group_taproot_address, identifier_label,
+    parse_identifier, KeyPackages, DEMO_MESSAGE, MAX_SIGNERS, MIN_SIGNERS,
+};
+use bitcoin::Network;
+use rand::{CryptoRng, RngCore};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+/// What `help` prints.
+pub const HELP: &str = "\
+gen [t n]        deal fresh t-of-n keys (3-of-5 by default)
+addr             print the group's taproot address
+message [text]   show or set the message to sign; setting it starts a new ceremony
+commit <id>      round 1 for one participant
+sign <id>        round 2 for one participant
+aggregate        combine the shares into the group signature
+verify           check the group signature
+reset            start a new ceremony with the same keys
+state            print who has committed and signed
+quit";
+
+/// The keys and the ceremony in progress.
+pub struct Session<R> {
+    rng: R,
+    network: Network,
+    keys: Option<(KeyPackages, frost::keys::PublicKeyPackage)>,
+    message: Vec<u8>,
+    nonces: BTreeMap<frost::Identifier, frost::round1::SigningNonces>,
+    commitments: BTreeMap<frost::Identifier, frost::round1::SigningCommitments>,
+    signing_package: Option<frost::SigningPackage>,
+    shares: BTreeMap<frost::Identifier, frost::round2::SignatureShare>,
+    signature: Option<frost::Signature>,
+}
+
+impl<R: RngCore + CryptoRng> Session<R> {
+    pub fn new(rng: R, network: Network) -> Self {
+        Session {
+            rng,
+            network,
+            keys: None,
+            message: DEMO_MESSAGE.to_vec(),
+            nonces: BTreeMap::new(),
+            commitments: BTreeMap::new(),
+            signing_package: None,
+            shares: BTreeMap::new(),
+            signature: None,
+        }
+    }
+
+    /// Runs one command line and returns what it prints.
+    pub fn execute(&mut self, line: &str) -> Result<String, AppError> {
+        let (command, argument) = line
+            .trim()
+            .split_once(char::is_whitespace)
+            .map_or((line.trim(), ""), |(command, rest)| (command, rest.trim()));
+        match command {
+            "help" => Ok(HELP.to_string()),
+            "gen" => self.gen(argument),
+            "addr" => {
+                let (_, pubkey_package) = self.keys()?;
+                let (internal_key, address) =
+                    group_taproot_address(pubkey_package, None, self.network)?;
+                Ok(format!(
+                    "internal key {internal_key}\ntaproot address {address}"
+                ))
+            }
+            "message" if !argument.is_empty() => {
+                self.message = argument.as_bytes().to_vec();
+                self.reset();
+                Ok(format!("message set to {argument:?}"))
+            }
+            "message" => Ok(format!(
+                "message {:?}",
+                String::from_utf8_lossy(&self.message)
+            )),
+            "commit" => self.commit(argument),
+            "sign" => self.sign(argument),
+            "aggregate" => self.aggregate(),
+            "verify" => {
+                let (_, pubkey_package) = self.keys()?;
+                let signature = self.signature.ok_or("no signature yet; run aggregate")?;
+                let valid = pubkey_package
+                    .verifying_key()
+                    .verify(&self.message, &signature)
+                    .is_ok();
+                Ok(format!("signature valid: {valid}"))
+            }
+            "reset" => {
+                self.reset();
+                Ok("new ceremony".to_string())
+            }
+            "state" => Ok(String::new()),
+            _ => Err(format!("unknown command {line:?}; try help").into()),
+        }
+    }
+
+    /// One line per step: the participants, who committed, who signed.
+    pub fn state(&self) -> String {
+        let labels = |identifiers: Vec<&frost::Identifier>| {
+            let labels: Vec<_> = identifiers.into_iter().map(identifier_label).collect();
+            if labels.is_empty() {
+                "-".to_string()
+            } else {
+                labels.join(",")
+            }
+        };
+        let Some((key_packages, _)) = &self.keys else {
+            return "[no keys; run gen]".to_string();
+        };
+        let threshold = key_packages
+            .values()
+            .next()
+            .map_or(0, |key_package| *key_package.min_signers());
+        format!(
+            "[{}-of-{} | participants {} | committed {} | signed {} | signature {}]",
+            threshold,
+            key_packages.len(),
+            labels(key_packages.keys().collect()),
+            labels(self.commitments.keys().collect()),
+            labels(self.shares.keys().collect()),
+            if self.signature.is_some() {
+                "yes"
+            } else {
+                "no"
+            }
+        )
+    }
+
+    fn keys(&self) -> Result<&(KeyPackages, frost::keys::PublicKeyPackage), AppError> {
+        self.keys
+            .as_ref()
+            .ok_or_else(|| "no keys; run gen first".into())
+    }
+
+    fn reset(&mut self) {
+        self.nonces.clear();
+        self.commitments.clear();
+        self.signing_package = None;
+        self.shares.clear();
+        self.signature = None;
+    }
+
+    fn gen(&mut self, argument: &str) -> Result<String, AppError> {
+        let numbers = argument
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<u16>, _>>()
+            .map_err(|err| format!("gen takes a threshold and a size: {err}"))?;
+        let (min_signers, max_signers) = match numbers[..] {
+            [] => (MIN_SIGNERS, MAX_SIGNERS),
+            [min_signers, max_signers] => (min_signers, max_signers),
+            _ => return Err("usage: gen [t n]".into()),
+        };
+        self.keys = Some(dealer_keygen_with(
+            min_signers,
+            max_signers,
+            frost::keys::IdentifierList::Default,
+            &mut self.rng,
+        )?);
+        self.reset();
+        Ok(format!("dealt {min_signers}-of-{max_signers} keys"))
+    }
+
+    /// The participant named by `argument`, which must hold a key package.
+    fn participant(&self, argument: &str) -> Result<frost::Identifier, AppError> {
+        let (key_packages, _) = self.keys()?;
+        if argument.is_empty() {
+            return Err("name a participant".into());
+        }
+        let identifier = parse_identifier(argument)?;
+        if !key_packages.contains_key(&identifier) {
+            return Err(format!("no participant {argument}").into());
+        }
+        Ok(identifier)
+    }
+
+    fn commit(&mut self, argument: &str) -> Result<String, AppError> {
+        let identifier = self.participant(argument)?;
+        if self.signing_package.is_some() {
+            return Err("signing has started; reset to commit again".into());
+        }
+        if self.commitments.contains_key(&identifier) {
+            return Err(format!("participant {argument} has already committed").into());
+        }
+        let key_package = self.keys()?.0[&identifier].clone();
+        let (nonces, commitments) =
+            frost::round1::commit(key_package.signing_share(), &mut self.rng);
+        self.nonces.insert(identifier, nonces);
+        self.commitments.insert(identifier, commitments);
+        Ok(format!(
+            "participant {} committed: hiding {} binding {}",
+            identifier_label(&identifier),
+            hex::encode(commitments.hiding().serialize()?),
+            hex::encode(commitments.binding().serialize()?)
+        ))
+    }
+
+    fn sign(&mut self, argument: &str) -> Result<String, AppError> {
+        let identifier = self.participant(argument)?;
+        let key_package = self.keys()?.0[&identifier].clone();
+        if self.signing_package.is_none() {
+            if self.commitments.len() < usize::from(*key_package.min_signers()) {
+                return Err(format!(
+                    "{} commitments, {} needed before signing",
+                    self.commitments.len(),
+                    key_package.min_signers()
+                )
+                .into());
+            }
+            self.signing_package = Some(frost::SigningPackage::new(
+                self.commitments.clone(),
+                &self.message,
+            ));
+        }
+        let nonces = self.nonces.get(&identifier).ok_or_else(|| {
+            if self.shares.contains_key(&identifier) {
+                format!("participant {argument} has already signed")
+            } else {
+                format!("participant {argument} has not committed")
+            }
+        })?;
+        let signing_package = self.signing_package.as_ref().expect("created above");
+        let share = frost::round2::sign(signing_package, nonces, &key_package)?;
+        self.nonces.remove(&identifier);
+        self.shares.insert(identifier, share);
+        Ok(format!(
+            "participant {} signed: share {}",
+            identifier_label(&identifier),
+            hex::encode(share.serialize())
+        ))
+    }
+
+    fn aggregate(&mut self) -> Result<String, AppError> {
+        let signing_package = self
+            .signing_package
+            .as_ref()
+            .ok_or("nobody has signed yet")?;
+        let missing: Vec<_> = self
+            .commitments
+            .keys()
+            .filter(|identifier| !self.shares.contains_key(identifier))
+            .map(identifier_label)
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("waiting for shares from {}", missing.join(",")).into());
+        }
+        let signature = aggregate_shares(signing_package, &self.shares, &self.keys()?.1, false)?;
+        self.signature = Some(signature);
+        Ok(format!(
+            "group signature {}",
+            hex::encode(signature.serialize()?)
+        ))
+    }
+}
+
+/// Reads commands from `input` until it ends or says `quit`, printing each
+/// command's output and then the session state to `output`.
+pub fn run<R: RngCore + CryptoRng>(
+    session: &mut Session<R>,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<(), AppError> {
+    writeln!(output, "{}", session.state())?;
+    write!(output, "frost> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        match line.trim() {
+            "" => {}
+            "quit" | "exit" => break,
+            line => {
+                match session.execute(line) {
+                    Ok(printed) if printed.is_empty() => {}
+                    Ok(printed) => writeln!(output, "{printed}")?,
+                    Err(err) => writeln!(output, "error: {err}")?,
+                }
+                writeln!(output, "{}", session.state())?;
+            }
+        }
+        write!(output, "frost> ")?;
+        output.flush()?;
+    }
+    writeln!(output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    #[test]
+    fn a_scripted_ceremony_produces_a_valid_signature() {
+        let script = "gen 2 3\naddr\ncommit 1\ncommit 3\nsign 1\ncommit 2\nsign 1\naggregate\nsign 3\naggregate\nverify\nquit\nverify\n";
+        let mut session = Session::new(thread_rng(), Network::Regtest);
+        let mut output = Vec::new();
+        run(&mut session, script.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("taproot address bcrt1p"));
+        assert!(output.contains("error: signing has started; reset to commit again"));
+        assert!(output.contains("error: participant 1 has already signed"));
+        assert!(output.contains("error: waiting for shares from 3"));
+        assert!(output.contains(
+            "[2-of-3 | participants 1,2,3 | committed 1,3 | signed 1,3 | signature yes]"
+        ));
+        assert_eq!(output.matches("signature valid: true").count(), 1);
+    }
+
+    #[test]
+    fn commands_need_keys_and_a_threshold_of_commitments() {
+        let mut session = Session::new(thread_rng(), Network::Bitcoin);
+        assert!(session.execute("commit 1").is_err());
+        session.execute("gen").unwrap();
+        assert!(session.execute("commit 9").is_err());
+        session.execute("commit 1").unwrap();
+        assert!(session.execute("commit 1").is_err());
+        assert!(session.execute("sign 1").is_err());
+        assert!(session.execute("frobnicate").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! One-page summaries of a signing ceremony for human review.
+
+use crate::frost;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+/// Everything a reviewer needs to audit one ceremony.
+#[derive(Serialize, Debug)]
+pub struct SigningReport {
+    /// Kind of signing operation, as passed to the policy hook.
+    pub kind: &'static str,
+    pub message_hex: String,
+    pub participants: Vec<String>,
+    pub signature_hex: String,
+    pub verified: bool,
+    pub taproot_address: String,
+    /// Unix timestamps, in seconds, of the start and end of the ceremony.
+    pub started_at: u64,
+    pub finished_at: u64,
+    /// Group signature over the JSON report without this field, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<String>,
+}
+
+impl SigningReport {
+    /// The bytes the group signs to attest to the report.
+    pub fn attested_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(object) = value.as_object_mut() {
+            object.remove("attestation");
+        }
+        serde_json::to_vec(&value)
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String, serde_json::Error> {
+        match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self),
+            ReportFormat::Text => Ok(self.render_text()),
+        }
+    }
+
+    fn render_text(&self) -> String {
+        let mut lines = vec![
+            "FROST signing report".to_string(),
+            format!("kind             {}", self.kind),
+            format!("message          {}", self.message_hex),
+            format!("participants     {}", self.participants.join(", ")),
+            format!("signature        {}", self.signature_hex),
+            format!(
+                "verified         {}",
+                if self.verified { "yes" } else { "NO" }
+            ),
+            format!("taproot address  {}", self.taproot_address),
+            format!("started at       {}", self.started_at),
+            format!(
+                "finished at      {} ({}s)",
+                self.finished_at,
+                self.finished_at.saturating_sub(self.started_at)
+            ),
+        ];
+        if let Some(attestation) = &self.attestation {
+            lines.push(format!("attestation      {attestation}"));
+        }
+        lines.join("\n")
+    }
+}
+
+/// How reports list participants. The ceremony itself always keeps them in
+/// identifier order, which is the order FROST binds them in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParticipantOrder {
+    /// By identifier bytes.
+    #[default]
+    Identifier,
+    /// In the order the participants were given to `--dealer-identifiers` or
+    /// `dkg --identifiers`.
+    Enrollment,
+}
+
+/// `identifiers` in the order of `enrolled`, followed by any not enrolled, in
+/// identifier order.
+pub fn enrollment_order<'a>(
+    identifiers: impl IntoIterator<Item = &'a frost::Identifier>,
+    enrolled: &[frost::Identifier],
+) -> Vec<frost::Identifier> {
+    let mut ordered: Vec<_> = identifiers.into_iter().copied().collect();
+    ordered.sort_by_key(|identifier| {
+        let position = enrolled.iter().position(|enrolled| enrolled == identifier);
+        (position.unwrap_or(usize::MAX), *identifier)
+    });
+    ordered
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> SigningReport {
+        SigningReport {
+            kind: "message",
+            message_hex: "deadbeef".to_string(),
+            participants: vec!["1".to_string(), "2".to_string()],
+            signature_hex: "02ab".to_string(),
+            verified: true,
+            taproot_address: "bc1p".to_string(),
+            started_at: 100,
+            finished_at: 103,
+            attestation: None,
+        }
+    }
+
+    #[test]
+    fn text_report_lists_every_field() {
+        let text = report().render(ReportFormat::Text).unwrap();
+        assert!(text.contains("participants     1, 2"));
+        assert!(text.contains("finished at      103 (3s)"));
+        assert!(!text.contains("attestation"));
+    }
+
+    #[test]
+    fn enrollment_order_follows_the_enrolled_names() {
+        let [carol, alice, bob] = ["carol", "alice", "bob"]
+            .map(|name| frost::Identifier::derive(name.as_bytes()).unwrap());
+        let dave = frost::Identifier::try_from(4).unwrap();
+        let mut by_bytes = vec![alice, bob, carol, dave];
+        by_bytes.sort();
+        assert_eq!(
+            enrollment_order(&by_bytes, &[carol, alice, bob]),
+            [carol, alice, bob, dave]
+        );
+        assert_eq!(enrollment_order(&by_bytes, &[]), by_bytes);
+    }
+
+    #[test]
+    fn attestation_covers_the_report_without_itself() {
+        let mut report = report();
+        let unsigned = report.attested_bytes().unwrap();
+        report.attestation = Some("00".to_string());
+        assert_eq!(report.attested_bytes().unwrap(), unsigned);
+        assert!(report
+            .render(ReportFormat::Json)
+            .unwrap()
+            .contains("\"attestation\": \"00\""));
+    }
+}
@@ -0,0 +1,181 @@
+//! Retrying a failed signing ceremony with another signer subset.
+//!
+//! A participant that sent an invalid share is named by frost's cheater
+//! detection and left out of every later attempt. A failure nobody can be
+//! blamed for, such as a participant dropping out, only moves that attempt's
+//! signers to the back of the queue, so the next attempt uses fresh ones when
+//! there are enough.
+
+use crate::error::AppError;
+use crate::frost;
+use std::collections::BTreeSet;
+
+/// The final outcome of a ceremony and what it took to get there.
+#[derive(Debug)]
+pub struct RetryReport<T> {
+    pub outcome: Result<T, AppError>,
+    pub attempts: u32,
+    /// Participants blamed for a failed attempt.
+    pub excluded: BTreeSet<frost::Identifier>,
+}
+
+/// The participants an error is attributable to, if any.
+fn culprits(err: &AppError) -> Vec<frost::Identifier> {
+    match err {
+        AppError::Frost(err) => err.culprit().into_iter().collect(),
+        AppError::InvalidShares(culprits) => culprits.clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn next_subset(
+    participants: &[frost::Identifier],
+    current: &[frost::Identifier],
+    excluded: &BTreeSet<frost::Identifier>,
+    suspects: &BTreeSet<frost::Identifier>,
+) -> Option<Vec<frost::Identifier>> {
+    let usable = |identifier: &&frost::Identifier| !excluded.contains(*identifier);
+    let mut next: Vec<_> = current
+        .iter()
+        .filter(usable)
+        .filter(|identifier| !suspects.contains(*identifier))
+        .copied()
+        .collect();
+    let fresh = participants
+        .iter()
+        .filter(usable)
+        .filter(|identifier| !suspects.contains(*identifier));
+    let retried = participants
+        .iter()
+        .filter(usable)
+        .filter(|identifier| suspects.contains(*identifier));
+    for identifier in fresh.chain(retried) {
+        if next.len() == current.len() {
+            break;
+        }
+        if !next.contains(identifier) {
+            next.push(*identifier);
+        }
+    }
+    next.sort();
+    (next.len() == current.len()).then_some(next)
+}
+
+/// Runs `ceremony` with `signers`, and after a failure up to `retries` more
+/// times with subsets of the same size drawn from `participants`.
+pub fn run<T>(
+    participants: &[frost::Identifier],
+    signers: Vec<frost::Identifier>,
+    retries: u32,
+    mut ceremony: impl FnMut(&[frost::Identifier]) -> Result<T, AppError>,
+) -> RetryReport<T> {
+    let mut excluded = BTreeSet::new();
+    let mut suspects = BTreeSet::new();
+    let mut signers = signers;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let err = match ceremony(&signers) {
+            Ok(value) => {
+                return RetryReport {
+                    outcome: Ok(value),
+                    attempts,
+                    excluded,
+                }
+            }
+            Err(err) => err,
+        };
+        let culprits = culprits(&err);
+        if culprits.is_empty() {
+            suspects.extend(signers.iter().copied());
+        }
+        excluded.extend(culprits);
+        if attempts > retries {
+            return RetryReport {
+                outcome: Err(err),
+                attempts,
+                excluded,
+            };
+        }
+        log::warn!("Signing attempt {} failed: {}", attempts, err);
+        signers = match next_subset(participants, &signers, &excluded, &suspects) {
+            Some(next) => next,
+            None => {
+                return RetryReport {
+                    outcome: Err(format!("too few participants left to retry after: {err}").into()),
+                    attempts,
+                    excluded,
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(indices: &[u16]) -> Vec<frost::Identifier> {
+        indices
+            .iter()
+            .map(|index| frost::Identifier::try_from(*index).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn culprits_are_excluded_and_drops_rotate_the_subset() {
+        let participants = ids(&[1, 2, 3, 4, 5]);
+        let mut tried = Vec::new();
+        let report = run(&participants, ids(&[1, 2, 3]), 3, |signers| {
+            tried.push(signers.to_vec());
+            match tried.len() {
+                1 => Err(AppError::Frost(frost::Error::InvalidSignatureShare {
+                    culprit: signers[1],
+                })),
+                2 => Err("participant dropped".into()),
+                _ => Ok(()),
+            }
+        });
+
+        assert!(report.outcome.is_ok());
+        assert_eq!(report.attempts, 3);
+        assert_eq!(report.excluded, BTreeSet::from_iter(ids(&[2])));
+        assert_eq!(tried[1], ids(&[1, 3, 4]));
+        // Only 5 is untried; the rest come from the suspects of the drop.
+        assert_eq!(tried[2], ids(&[1, 3, 5]));
+    }
+
+    #[test]
+    fn retries_stop_at_the_limit_or_without_enough_participants() {
+        let participants = ids(&[1, 2, 3]);
+        let always_fails = |_: &[frost::Identifier]| -> Result<(), AppError> { Err("down".into()) };
+        let report = run(&participants, ids(&[1, 2]), 2, always_fails);
+        assert!(report.outcome.is_err());
+        assert_eq!(report.attempts, 3);
+
+        let report = run(&participants, ids(&[1, 2]), 5, |signers| {
+            Err::<(), _>(AppError::Frost(frost::Error::InvalidSignatureShare {
+                culprit: signers[0],
+            }))
+        });
+        assert!(report.outcome.is_err());
+        assert_eq!(report.attempts, 2);
+    }
+
+    #[test]
+    fn every_attributed_culprit_is_excluded() {
+        let participants = ids(&[1, 2, 3, 4, 5]);
+        let mut tried = Vec::new();
+        let report = run(&participants, ids(&[1, 2, 3]), 1, |signers| {
+            tried.push(signers.to_vec());
+            match tried.len() {
+                1 => Err(AppError::InvalidShares(ids(&[1, 3]))),
+                _ => Ok(()),
+            }
+        });
+
+        assert!(report.outcome.is_ok());
+        assert_eq!(report.excluded, BTreeSet::from_iter(ids(&[1, 3])));
+        assert_eq!(tried[1], ids(&[2, 4, 5]));
+    }
+}
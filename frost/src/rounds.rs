@@ -0,0 +1,725 @@
+//! The signing ceremony split into the steps each party runs on its own
+//! machine, exchanging files:
+//!
+//! 1. every signer runs `commit`, keeps its nonces file and sends its
+//!    commitments file to the others;
+//! 2. every signer runs `sign-share` over all the commitments, which consumes
+//!    its nonces file, and sends the share file to the coordinator;
+//! 3. the coordinator runs `aggregate` over the commitments and the shares.
+//!
+//! The coordinator can also fix the signing package in a file once every
+//! commitment is in, and aggregate a directory of share files against it.
+//! Before a session it can hand each signer its steps, see [`instructions`].
+//!
+//! A nonces file is deleted as soon as it is read, before the share is made,
+//! so a crash or a second run can't sign twice with the same nonces.
+//!
+//! Every file carries a format `version`. Files from before versioning, or
+//! from a newer format, are refused with an error naming the version instead
+//! of being misread.
+
+use crate::error::AppError;
+use crate::frost;
+use bitcoin::hashes::Hash;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use zeroize::Zeroize;
+
+const VERSION: u8 = 1;
+
+/// A file exchanged between the rounds.
+pub trait RoundFile: Serialize + DeserializeOwned {
+    /// What the file holds, for error messages.
+    const KIND: &'static str;
+}
+
+/// A signer's secret round 1 nonces. Never leaves the signer's machine.
+#[derive(Serialize, Deserialize)]
+pub struct NoncesFile {
+    pub version: u8,
+    pub identifier: frost::Identifier,
+    pub nonces: frost::round1::SigningNonces,
+}
+
+impl Drop for NoncesFile {
+    fn drop(&mut self) {
+        self.nonces.zeroize();
+    }
+}
+
+/// What a signer sends everyone after round 1.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommitmentsFile {
+    pub version: u8,
+    pub identifier: frost::Identifier,
+    pub commitments: frost::round1::SigningCommitments,
+}
+
+/// What a signer sends the coordinator after round 2.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShareFile {
+    pub version: u8,
+    pub identifier: frost::Identifier,
+    pub share: frost::round2::SignatureShare,
+}
+
+/// The commitments and message everyone signs, as fixed by the coordinator.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SigningPackageFile {
+    pub version: u8,
+    pub signing_package: frost::SigningPackage,
+}
+
+impl RoundFile for NoncesFile {
+    const KIND: &'static str = "nonces";
+}
+
+impl RoundFile for CommitmentsFile {
+    const KIND: &'static str = "commitments";
+}
+
+impl RoundFile for ShareFile {
+    const KIND: &'static str = "signature share";
+}
+
+impl RoundFile for SigningPackageFile {
+    const KIND: &'static str = "signing package";
+}
+
+/// Serializes a round file as JSON.
+pub fn to_json<T: RoundFile>(file: &T) -> Result<Vec<u8>, AppError> {
+    Ok(serde_json::to_vec_pretty(file)?)
+}
+
+/// Deserializes a round file, refusing any format version but this one.
+pub fn from_json<T: RoundFile>(json: &[u8]) -> Result<T, AppError> {
+    #[derive(Deserialize)]
+    struct Header {
+        version: Option<u8>,
+    }
+    match serde_json::from_slice::<Header>(json)?.version {
+        Some(VERSION) => Ok(serde_json::from_slice(json)?),
+        None => Err(format!(
+            "{} file has no format version; it was written by an older release, run that round again",
+            T::KIND
+        )
+        .into()),
+        Some(version) => Err(format!(
+            "{} file has format version {version}, this build reads version {VERSION}",
+            T::KIND
+        )
+        .into()),
+    }
+}
+
+/// Round 1 for one signer.
+pub fn commit<R: rand::RngCore + rand::CryptoRng>(
+    key_package: &frost::keys::KeyPackage,
+    rng: &mut R,
+) -> (NoncesFile, CommitmentsFile) {
+    let identifier = *key_package.identifier();
+    let (nonces, commitments) = frost::round1::commit(key_package.signing_share(), rng);
+    (
+        NoncesFile {
+            version: VERSION,
+            identifier,
+            nonces,
+        },
+        CommitmentsFile {
+            version: VERSION,
+            identifier,
+            commitments,
+        },
+    )
+}
+
+fn signing_package(
+    commitments: &[CommitmentsFile],
+    message: &[u8],
+) -> Result<frost::SigningPackage, AppError> {
+    let mut commitments_map = BTreeMap::new();
+    for file in commitments {
+        if commitments_map
+            .insert(file.identifier, file.commitments)
+            .is_some()
+        {
+            return Err(format!(
+                "two commitments files for participant {}",
+                crate::identifier_label(&file.identifier)
+            )
+            .into());
+        }
+    }
+    Ok(frost::SigningPackage::new(commitments_map, message))
+}
+
+/// The signing package of `commitments` and `message`, for the coordinator to
+/// write out. It is the one `sign_share` signs over the same inputs.
+pub fn signing_package_file(
+    commitments: &[CommitmentsFile],
+    message: &[u8],
+) -> Result<SigningPackageFile, AppError> {
+    Ok(SigningPackageFile {
+        version: VERSION,
+        signing_package: signing_package(commitments, message)?,
+    })
+}
+
+/// Whether the commitments collected after round 1 are enough to sign.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Readiness {
+    pub committed: Vec<frost::Identifier>,
+    pub threshold: u16,
+    /// Participants that haven't committed yet.
+    pub missing: Vec<frost::Identifier>,
+}
+
+impl Readiness {
+    /// How many more commitments are needed to reach the threshold.
+    pub fn needed(&self) -> usize {
+        usize::from(self.threshold).saturating_sub(self.committed.len())
+    }
+}
+
+/// Counts the commitments of `participants` against `threshold`, refusing
+/// duplicates and commitments from anyone outside the group.
+pub fn readiness(
+    participants: &[frost::Identifier],
+    commitments: &[CommitmentsFile],
+    threshold: u16,
+) -> Result<Readiness, AppError> {
+    let mut committed = Vec::new();
+    for file in commitments {
+        if !participants.contains(&file.identifier) {
+            return Err(format!(
+                "commitments file from participant {}, who is not in the group",
+                crate::identifier_label(&file.identifier)
+            )
+            .into());
+        }
+        if committed.contains(&file.identifier) {
+            return Err(format!(
+                "two commitments files for participant {}",
+                crate::identifier_label(&file.identifier)
+            )
+            .into());
+        }
+        committed.push(file.identifier);
+    }
+    committed.sort();
+    let missing = participants
+        .iter()
+        .filter(|identifier| !committed.contains(identifier))
+        .copied()
+        .collect();
+    Ok(Readiness {
+        committed,
+        threshold,
+        missing,
+    })
+}
+
+/// Checks commitments supplied from outside, such as a coordinator's
+/// commitments map, before signing or aggregating over them: each must come
+/// from a participant of `group`, every one of `signers` must have committed,
+/// and with a `threshold` there must be at least that many.
+pub fn check_commitments(
+    commitments: &[CommitmentsFile],
+    group: &[frost::Identifier],
+    signers: &[frost::Identifier],
+    threshold: Option<u16>,
+) -> Result<(), AppError> {
+    let labels = |identifiers: Vec<&frost::Identifier>| {
+        identifiers
+            .into_iter()
+            .map(crate::identifier_label)
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let outsiders: Vec<_> = commitments
+        .iter()
+        .map(|file| &file.identifier)
+        .filter(|identifier| !group.contains(identifier))
+        .collect();
+    if !outsiders.is_empty() {
+        return Err(format!(
+            "commitments from participants {}, who are not in the group's key packages",
+            labels(outsiders)
+        )
+        .into());
+    }
+    let uncommitted: Vec<_> = signers
+        .iter()
+        .filter(|signer| !commitments.iter().any(|file| file.identifier == **signer))
+        .collect();
+    if !uncommitted.is_empty() {
+        return Err(format!(
+            "the commitments don't cover signers {}",
+            labels(uncommitted)
+        )
+        .into());
+    }
+    match threshold {
+        Some(threshold) if commitments.len() < usize::from(threshold) => Err(format!(
+            "{} commitments, the group needs {threshold} signers",
+            commitments.len()
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Round 2 for one signer. Takes the nonces by value: they are zeroized when
+/// this returns.
+pub fn sign_share(
+    key_package: &frost::keys::KeyPackage,
+    nonces: NoncesFile,
+    commitments: &[CommitmentsFile],
+    message: &[u8],
+) -> Result<ShareFile, AppError> {
+    if nonces.identifier != *key_package.identifier() {
+        return Err("the nonces file belongs to another participant".into());
+    }
+    let signing_package = signing_package(commitments, message)?;
+    let own_commitments = signing_package
+        .signing_commitments()
+        .get(&nonces.identifier)
+        .ok_or("the commitments don't include this participant's own")?;
+    // Recomputed from the scalars, like `check_nonces_match_commitments`.
+    let mut derived = frost::round1::SigningNonces::from_nonces(
+        *nonces.nonces.hiding(),
+        *nonces.nonces.binding(),
+    );
+    let matches = derived.commitments() == own_commitments;
+    derived.zeroize();
+    if !matches {
+        return Err("the nonces don't match this participant's commitments".into());
+    }
+
+    let share = frost::round2::sign(&signing_package, &nonces.nonces, key_package)?;
+    Ok(ShareFile {
+        version: VERSION,
+        identifier: nonces.identifier,
+        share,
+    })
+}
+
+/// Aggregation by the coordinator. With `verify_shares` every share is
+/// checked before aggregating, see [`crate::aggregate_shares`].
+pub fn aggregate(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    commitments: &[CommitmentsFile],
+    shares: &[ShareFile],
+    message: &[u8],
+    verify_shares: bool,
+) -> Result<frost::Signature, AppError> {
+    aggregate_package(
+        pubkey_package,
+        &signing_package(commitments, message)?,
+        shares,
+        verify_shares,
+    )
+}
+
+/// Aggregation against a signing package read from disk. There must be
+/// exactly one share from each participant of the signing package.
+pub fn aggregate_package(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signing_package: &frost::SigningPackage,
+    shares: &[ShareFile],
+    verify_shares: bool,
+) -> Result<frost::Signature, AppError> {
+    let committed = signing_package.signing_commitments();
+    let mut share_map = BTreeMap::new();
+    for file in shares {
+        let label = crate::identifier_label(&file.identifier);
+        if !committed.contains_key(&file.identifier) {
+            return Err(format!(
+                "share from participant {label}, who is not in the signing package"
+            )
+            .into());
+        }
+        if share_map.insert(file.identifier, file.share).is_some() {
+            return Err(format!("two share files for participant {label}").into());
+        }
+    }
+    let missing: Vec<_> = committed
+        .keys()
+        .filter(|identifier| !share_map.contains_key(identifier))
+        .map(crate::identifier_label)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("no share from participants {}", missing.join(",")).into());
+    }
+    crate::aggregate_shares(signing_package, &share_map, pubkey_package, verify_shares)
+}
+
+/// The steps one signer of a session runs, as the text of the instruction
+/// file the coordinator hands out: its `commit` and `sign-share` commands,
+/// over the commitments files of every one of `signers`, and where to send
+/// what. Participants are named as `--participant` takes them.
+pub fn instructions(
+    participant: &str,
+    signers: &[String],
+    message: &str,
+    coordinator: &str,
+) -> String {
+    let nonces = format!("nonces-{participant}.json");
+    let commitments = format!("commitments-{participant}.json");
+    let share = format!("share-{participant}.json");
+    let mut lines = vec![
+        format!("FROST signing session for participant {participant}"),
+        String::new(),
+        format!("Signers:        {}", signers.join(", ")),
+        format!(
+            "Message SHA256: {}",
+            bitcoin::hashes::sha256::Hash::hash(message.as_bytes())
+        ),
+        format!("Coordinator:    {coordinator}"),
+        String::new(),
+        "1. Round 1. Commit:".to_string(),
+        String::new(),
+        format!("   frost commit --participant {participant} \\"),
+        format!("       --nonces-out {nonces} --commitments-out {commitments}"),
+        String::new(),
+        format!("   Keep {nonces} on this machine and never send it."),
+        format!("   Send {commitments} to the coordinator."),
+        String::new(),
+        "2. Round 2. Once the coordinator sends every signer's commitments file,".to_string(),
+        "   check the message and sign:".to_string(),
+        String::new(),
+        format!("   frost sign-share --participant {participant} --nonces {nonces} \\"),
+    ];
+    for signer in signers {
+        lines.push(format!("       --commitments commitments-{signer}.json \\"));
+    }
+    lines.extend([
+        format!(
+            "       --message {} --share-out {share}",
+            shell_quote(message)
+        ),
+        String::new(),
+        format!("   Send {share} to the coordinator. sign-share deletes {nonces};"),
+        "   if it fails, commit again.".to_string(),
+    ]);
+    lines.join("\n") + "\n"
+}
+
+/// `value` as one POSIX shell word.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Writes a new file, refusing to replace one: replacing a nonces file would
+/// lose nonces whose commitments are already out.
+pub fn write_new<T: Serialize>(path: &Path, value: &T) -> Result<(), AppError> {
+    let mut json = serde_json::to_vec_pretty(value)?;
+    let written = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(&json));
+    json.zeroize();
+    Ok(written?)
+}
+
+pub fn read<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, AppError> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Reads a commitments or share file, see [`from_json`].
+pub fn read_round<T: RoundFile>(path: &Path) -> Result<T, AppError> {
+    from_json(&fs::read(path)?)
+}
+
+/// Reads a serialized commitments map, identifier to round 1 commitments, as
+/// a coordinator or another FROST implementation writes it.
+pub fn read_commitments_map(path: &Path) -> Result<Vec<CommitmentsFile>, AppError> {
+    let map: BTreeMap<frost::Identifier, frost::round1::SigningCommitments> =
+        serde_json::from_slice(&fs::read(path)?)
+            .map_err(|err| format!("invalid commitments map {}: {err}", path.display()))?;
+    Ok(map
+        .into_iter()
+        .map(|(identifier, commitments)| CommitmentsFile {
+            version: VERSION,
+            identifier,
+            commitments,
+        })
+        .collect())
+}
+
+/// Reads every `.json` file in `dir` as a share file, in file name order.
+pub fn read_share_dir(dir: &Path) -> Result<Vec<ShareFile>, AppError> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+        {
+            paths.push(path);
+        }
+    }
+    if paths.is_empty() {
+        return Err(format!("no share files in {}", dir.display()).into());
+    }
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| read_round(path).map_err(|err| format!("{}: {err}", path.display()).into()))
+        .collect()
+}
+
+/// Reads a nonces file and deletes it, so the nonces can be used only once.
+pub fn take_nonces(path: &Path) -> Result<NoncesFile, AppError> {
+    let mut json = fs::read(path)?;
+    // Overwrite before unlinking, so the nonces don't linger on disk.
+    fs::write(path, vec![0u8; json.len()])?;
+    fs::remove_file(path)?;
+    let nonces = from_json(&json);
+    json.zeroize();
+    nonces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, default_signers, DEMO_MESSAGE};
+    use rand::thread_rng;
+
+    #[test]
+    fn separately_run_rounds_aggregate_to_a_valid_signature() {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let dir = std::env::temp_dir().join(format!("frost-rounds-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut commitments = Vec::new();
+        for identifier in default_signers(&key_packages) {
+            let (nonces, own) = commit(&key_packages[&identifier], &mut thread_rng());
+            let path = dir.join(format!(
+                "nonces-{}.json",
+                crate::identifier_label(&identifier)
+            ));
+            write_new(&path, &nonces).unwrap();
+            assert!(write_new(&path, &nonces).is_err());
+            commitments.push(own);
+        }
+
+        let mut shares = Vec::new();
+        for own in &commitments {
+            let path = dir.join(format!(
+                "nonces-{}.json",
+                crate::identifier_label(&own.identifier)
+            ));
+            let nonces = take_nonces(&path).unwrap();
+            assert!(!path.exists());
+            shares.push(
+                sign_share(
+                    &key_packages[&own.identifier],
+                    nonces,
+                    &commitments,
+                    DEMO_MESSAGE,
+                )
+                .unwrap(),
+            );
+        }
+
+        let signature =
+            aggregate(&pubkey_package, &commitments, &shares, DEMO_MESSAGE, true).unwrap();
+        assert!(pubkey_package
+            .verifying_key()
+            .verify(DEMO_MESSAGE, &signature)
+            .is_ok());
+        assert!(aggregate(&pubkey_package, &commitments, &shares, b"other", false).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_directory_of_shares_aggregates_against_a_signing_package_file() {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let dir = std::env::temp_dir().join(format!("frost-share-dir-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let (nonces, commitments): (Vec<_>, Vec<_>) = default_signers(&key_packages)
+            .iter()
+            .map(|identifier| commit(&key_packages[identifier], &mut thread_rng()))
+            .unzip();
+        let package_json =
+            to_json(&signing_package_file(&commitments, DEMO_MESSAGE).unwrap()).unwrap();
+        let package: SigningPackageFile = from_json(&package_json).unwrap();
+        let mut shares = Vec::new();
+        for nonces in nonces {
+            let identifier = nonces.identifier;
+            let share = sign_share(
+                &key_packages[&identifier],
+                nonces,
+                &commitments,
+                DEMO_MESSAGE,
+            )
+            .unwrap();
+            let path = dir.join(format!(
+                "share-{}.json",
+                crate::identifier_label(&identifier)
+            ));
+            write_new(&path, &share).unwrap();
+            shares.push(share);
+        }
+        fs::write(dir.join("notes.txt"), "not a share").unwrap();
+
+        let loaded = read_share_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded, shares);
+        let signature =
+            aggregate_package(&pubkey_package, &package.signing_package, &loaded, false).unwrap();
+        assert!(pubkey_package
+            .verifying_key()
+            .verify(DEMO_MESSAGE, &signature)
+            .is_ok());
+
+        let missing = aggregate_package(
+            &pubkey_package,
+            &package.signing_package,
+            &loaded[..1],
+            false,
+        );
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .contains("no share from participants"));
+        let duplicated = [loaded[0], loaded[0], loaded[1]];
+        let duplicated = aggregate_package(
+            &pubkey_package,
+            &package.signing_package,
+            &duplicated,
+            false,
+        );
+        assert!(duplicated
+            .unwrap_err()
+            .to_string()
+            .contains("two share files"));
+        assert!(read_share_dir(&dir).is_err());
+    }
+
+    #[test]
+    fn round_tripped_nonces_still_sign() {
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let mut commitments = Vec::new();
+        let mut nonces_json = Vec::new();
+        for identifier in default_signers(&key_packages) {
+            let (nonces, own) = commit(&key_packages[&identifier], &mut thread_rng());
+            nonces_json.push(to_json(&nonces).unwrap());
+            commitments.push(from_json(&to_json(&own).unwrap()).unwrap());
+        }
+
+        let shares: Vec<ShareFile> = nonces_json
+            .iter()
+            .map(|json| {
+                let nonces: NoncesFile = from_json(json).unwrap();
+                let share = sign_share(
+                    &key_packages[&nonces.identifier],
+                    nonces,
+                    &commitments,
+                    DEMO_MESSAGE,
+                )
+                .unwrap();
+                from_json(&to_json(&share).unwrap()).unwrap()
+            })
+            .collect();
+        let signature =
+            aggregate(&pubkey_package, &commitments, &shares, DEMO_MESSAGE, true).unwrap();
+        assert!(pubkey_package
+            .verifying_key()
+            .verify(DEMO_MESSAGE, &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn readiness_counts_commitments_against_the_threshold() {
+        let (key_packages, _) = dealer_keygen(3, 5, &mut thread_rng()).unwrap();
+        let participants: Vec<_> = key_packages.keys().copied().collect();
+        let commitments: Vec<_> = [4, 1]
+            .map(|index| commit(&key_packages[&participants[index]], &mut thread_rng()).1)
+            .to_vec();
+
+        let short = readiness(&participants, &commitments, 3).unwrap();
+        assert_eq!(short.committed, [participants[1], participants[4]]);
+        assert_eq!(
+            short.missing,
+            [participants[0], participants[2], participants[3]]
+        );
+        assert_eq!(short.needed(), 1);
+        assert_eq!(
+            readiness(&participants, &commitments, 2).unwrap().needed(),
+            0
+        );
+
+        let duplicated = [commitments[0], commitments[0]];
+        assert!(readiness(&participants, &duplicated, 3).is_err());
+        assert!(readiness(&participants[..4], &commitments, 3).is_err());
+    }
+
+    #[test]
+    fn a_commitments_map_is_checked_against_the_group_and_signers() {
+        let (key_packages, _) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let group: Vec<_> = key_packages.keys().copied().collect();
+        let (nonces, own) = commit(&key_packages[&group[0]], &mut thread_rng());
+        let (_, other) = commit(&key_packages[&group[2]], &mut thread_rng());
+        let map: BTreeMap<_, _> = [own, other]
+            .iter()
+            .map(|file| (file.identifier, file.commitments))
+            .collect();
+        let path = std::env::temp_dir().join(format!("frost-map-{}.json", std::process::id()));
+        fs::write(&path, serde_json::to_vec(&map).unwrap()).unwrap();
+        let commitments = read_commitments_map(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(commitments, [own, other]);
+
+        check_commitments(&commitments, &group, &[group[0], group[2]], Some(2)).unwrap();
+        let uncovered = check_commitments(&commitments, &group, &[group[1]], None)
+            .unwrap_err()
+            .to_string();
+        assert!(uncovered.contains("don't cover signers"), "{uncovered}");
+        assert!(check_commitments(&commitments, &group[..2], &[], None).is_err());
+        assert!(check_commitments(&commitments, &group, &[], Some(3)).is_err());
+
+        let share = sign_share(&key_packages[&group[0]], nonces, &commitments, DEMO_MESSAGE);
+        assert_eq!(share.unwrap().identifier, group[0]);
+    }
+
+    #[test]
+    fn instructions_name_every_signers_commitments() {
+        let signers = ["alice".to_string(), "carol".to_string()];
+        let text = instructions("carol", &signers, "pay bob's invoice", "ops@example.com");
+        assert!(text.contains("frost commit --participant carol"));
+        assert!(text.contains("--commitments commitments-alice.json"));
+        assert!(text.contains("--commitments commitments-carol.json"));
+        assert!(text.contains("--message 'pay bob'\\''s invoice'"));
+        assert!(text.contains("Coordinator:    ops@example.com"));
+        assert!(!text.contains("nonces-alice"));
+    }
+
+    #[test]
+    fn other_format_versions_are_refused() {
+        let (key_packages, _) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let (_, own) = commit(
+            &key_packages[&default_signers(&key_packages)[0]],
+            &mut thread_rng(),
+        );
+        let mut json: serde_json::Value = serde_json::from_slice(&to_json(&own).unwrap()).unwrap();
+
+        json["version"] = 2.into();
+        let newer = from_json::<CommitmentsFile>(json.to_string().as_bytes()).unwrap_err();
+        assert!(newer.to_string().contains("format version 2"));
+
+        json.as_object_mut().unwrap().remove("version");
+        let older = from_json::<CommitmentsFile>(json.to_string().as_bytes()).unwrap_err();
+        assert!(older.to_string().contains("older release"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Minimal Bitcoin Core JSON-RPC client.
+//!
+//! Only plain HTTP with basic auth is supported, which is what a local or
+//! tunnelled `bitcoind` exposes. Connection failures are reported as
+//! [`RpcError::Unavailable`] so callers can degrade gracefully.
+
+use bitcoin::base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Args;
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connection options shared by every subcommand that talks to a node.
+#[derive(Args, Debug, Clone)]
+pub struct RpcArgs {
+    /// Node RPC endpoint, e.g. http://127.0.0.1:18443
+    #[arg(long, env = "BITCOIN_RPC_URL", default_value = "http://127.0.0.1:8332")]
+    pub rpc_url: String,
+    /// RPC username
+    #[arg(long, env = "BITCOIN_RPC_USER")]
+    pub rpc_user: Option<String>,
+    /// RPC password
+    #[arg(long, env = "BITCOIN_RPC_PASSWORD", hide_env_values = true)]
+    pub rpc_password: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum RpcError {
+    /// The node could not be reached at all.
+    Unavailable(String),
+    /// The node answered with something that isn't a JSON-RPC response.
+    Protocol(String),
+    /// The node rejected the call.
+    Node { code: i64, message: String },
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::Unavailable(reason) => write!(f, "node RPC unavailable: {reason}"),
+            RpcError::Protocol(reason) => write!(f, "unexpected RPC response: {reason}"),
+            RpcError::Node { code, message } => write!(f, "RPC error {code}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+pub struct RpcClient {
+    host: String,
+    path: String,
+    authorization: Option<String>,
+}
+
+impl RpcClient {
+    pub fn new(args: &RpcArgs) -> Result<Self, RpcError> {
+        let without_scheme = args
+            .rpc_url
+            .strip_prefix("http://")
+            .ok_or_else(|| RpcError::Unavailable(format!("unsupported URL {}", args.rpc_url)))?;
+        let (host, path) = match without_scheme.find('/') {
+            Some(index) => without_scheme.split_at(index),
+            None => (without_scheme, "/"),
+        };
+        let authorization = args.rpc_user.as_ref().map(|user| {
+            let password = args.rpc_password.as_deref().unwrap_or_default();
+            STANDARD.encode(format!("{user}:{password}"))
+        });
+
+        Ok(RpcClient {
+            host: host.to_string(),
+            path: path.to_string(),
+            authorization,
+        })
+    }
+
+    /// Performs a single JSON-RPC call and returns its `result`.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let body = json!({
+            "jsonrpc": "1.0",
+            "id": "frost",
+            "method": method,
+            "params": params,
+        })
+        .to_string();
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.path,
+            self.host,
+            body.len()
+        );
+        if let Some(authorization) = &self.authorization {
+            request.push_str(&format!("Authorization: Basic {authorization}\r\n"));
+        }
+        request.push_str("\r\n");
+        request.push_str(&body);
+
+        let mut stream = TcpStream::connect(&self.host)
+            .map_err(|e| RpcError::Unavailable(format!("{}: {e}", self.host)))?;
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .and_then(|_| stream.write_all(request.as_bytes()))
+            .map_err(|e| RpcError::Unavailable(e.to_string()))?;
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .map_err(|e| RpcError::Unavailable(e.to_string()))?;
+
+        parse_response(&response)
+    }
+}
+
+/// Extracts the JSON-RPC `result` from a raw HTTP response.
+fn parse_response(response: &str) -> Result<Value, RpcError> {
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| RpcError::Protocol("missing HTTP header terminator".to_string()))?;
+    let status_line = head.lines().next().unwrap_or_default();
+    if status_line.contains(" 401 ") {
+        return Err(RpcError::Unavailable(
+            "authentication failed, check the RPC credentials".to_string(),
+        ));
+    }
+
+    // bitcoind reports RPC errors with a non-200 status but a JSON body.
+    let reply: Value = serde_json::from_str(body)
+        .map_err(|_| RpcError::Protocol(format!("{status_line}: {}", body.trim())))?;
+    match reply.get("error") {
+        Some(error) if !error.is_null() => Err(RpcError::Node {
+            code: error["code"].as_i64().unwrap_or_default(),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+        }),
+        _ => Ok(reply["result"].clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn serve_once(reply: &'static str) -> RpcArgs {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request).unwrap();
+            stream.write_all(reply.as_bytes()).unwrap();
+        });
+        RpcArgs {
+            rpc_url: format!("http://{address}"),
+            rpc_user: Some("user".to_string()),
+            rpc_password: Some("pass".to_string()),
+        }
+    }
+
+    #[test]
+    fn call_returns_result() {
+        let args = serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"result\":42,\"error\":null,\"id\":\"frost\"}",
+        );
+        let result = RpcClient::new(&args)
+            .unwrap()
+            .call("getblockcount", json!([]))
+            .unwrap();
+        assert_eq!(result, json!(42));
+    }
+
+    #[test]
+    fn call_surfaces_node_errors() {
+        let args = serve_once(
+            "HTTP/1.1 500 Internal Server Error\r\n\r\n{\"result\":null,\"error\":{\"code\":-22,\"message\":\"TX decode failed\"},\"id\":\"frost\"}",
+        );
+        let error = RpcClient::new(&args)
+            .unwrap()
+            .call("testmempoolaccept", json!([["00"]]))
+            .unwrap_err();
+        assert!(matches!(error, RpcError::Node { code: -22, .. }));
+    }
+
+    #[test]
+    fn unreachable_node_is_unavailable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        let args = RpcArgs {
+            rpc_url: format!("http://{address}"),
+            rpc_user: None,
+            rpc_password: None,
+        };
+        let error = RpcClient::new(&args)
+            .unwrap()
+            .call("getblockcount", json!([]))
+            .unwrap_err();
+        assert!(matches!(error, RpcError::Unavailable(_)));
+    }
+}
@@ -0,0 +1,98 @@
+//! Zeroizing the secrets this tool holds in memory.
+//!
+//! frost's secret types implement `Zeroize` but don't clear themselves on
+//! drop, and a `BTreeMap` of them can't be zeroized at all. [`KeyPackages`]
+//! wraps the map of key packages so every signing share in it is overwritten
+//! when it is dropped. Short-lived buffers use `zeroize::Zeroizing` directly.
+//!
+//! `frost::SigningKey` is `Copy` and has no `Zeroize`, so its copies can't be
+//! tracked. It is kept for as short a time as possible instead.
+
+use crate::frost;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+
+/// Every participant's key package, by identifier. Zeroized on drop.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct KeyPackages(BTreeMap<frost::Identifier, frost::keys::KeyPackage>);
+
+impl KeyPackages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Deref for KeyPackages {
+    type Target = BTreeMap<frost::Identifier, frost::keys::KeyPackage>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for KeyPackages {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl FromIterator<(frost::Identifier, frost::keys::KeyPackage)> for KeyPackages {
+    fn from_iter<I: IntoIterator<Item = (frost::Identifier, frost::keys::KeyPackage)>>(
+        iter: I,
+    ) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a KeyPackages {
+    type Item = (&'a frost::Identifier, &'a frost::keys::KeyPackage);
+    type IntoIter =
+        std::collections::btree_map::Iter<'a, frost::Identifier, frost::keys::KeyPackage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Zeroize for KeyPackages {
+    fn zeroize(&mut self) {
+        for key_package in self.0.values_mut() {
+            key_package.zeroize();
+        }
+    }
+}
+
+impl Drop for KeyPackages {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, default_signers, sign_message, DEMO_MESSAGE};
+    use rand::thread_rng;
+
+    #[test]
+    fn cloned_secrets_are_cleared_without_touching_the_original() {
+        // What dropping a `KeyPackages` runs.
+        let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let mut clone = key_packages.clone();
+        clone.zeroize();
+        assert!(clone
+            .values()
+            .all(|key_package| *key_package.signing_share() == Default::default()));
+        assert!(sign_message(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            DEMO_MESSAGE,
+            &mut thread_rng(),
+        )
+        .is_ok());
+    }
+}
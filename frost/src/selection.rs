@@ -0,0 +1,203 @@
+//! Stake-weighted random choice of the participants that sign.
+//!
+//! Always signing with the same first `t` participants puts the whole load on
+//! them. Drawing the subset with probability proportional to each
+//! participant's weight spreads it out over time, and drawing from a seeded
+//! RNG lets anyone holding the seed reproduce and check the draw.
+//! `--signers` skips the draw and names the subset, to simulate which
+//! shareholders are online.
+
+use crate::error::AppError;
+use crate::{frost, KeyPackages};
+use clap::Args;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::collections::BTreeMap;
+
+/// Options of a command that chooses its signers.
+#[derive(Args, Debug, Clone, Default)]
+pub struct SelectionArgs {
+    /// Sign with exactly these participants: comma-separated indices or dealer identifier names
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["weights", "selection_seed"])]
+    pub signers: Vec<String>,
+    /// Draw the signers at random, weighted by stake: comma-separated <index>=<weight>, others weigh 1
+    #[arg(long, value_delimiter = ',', value_parser = parse_weight)]
+    pub weights: Vec<(u16, u32)>,
+    /// 32-byte hex seed of the weighted draw (random by default); implies a draw
+    #[arg(long)]
+    pub selection_seed: Option<String>,
+    /// Retry a failed ceremony up to this many times, leaving out participants that caused it
+    #[arg(long, default_value_t = 0)]
+    pub ceremony_retries: u32,
+}
+
+impl SelectionArgs {
+    /// The sorted signers given with `--signers`, if any, checked against
+    /// `key_packages`.
+    pub fn chosen_signers(
+        &self,
+        key_packages: &KeyPackages,
+    ) -> Result<Option<Vec<frost::Identifier>>, AppError> {
+        if self.signers.is_empty() {
+            return Ok(None);
+        }
+        let (threshold, _) = crate::map_threshold(key_packages)?;
+        let mut signers = Vec::new();
+        for value in &self.signers {
+            let identifier = crate::parse_identifier(value)?;
+            if !key_packages.contains_key(&identifier) {
+                return Err(format!("participant {value:?} is not in the key file").into());
+            }
+            if signers.contains(&identifier) {
+                return Err(format!("participant {value:?} is given twice").into());
+            }
+            signers.push(identifier);
+        }
+        if signers.len() < usize::from(threshold) {
+            return Err(AppError::InvalidThreshold(format!(
+                "{} signers given, but {threshold} must sign",
+                signers.len()
+            )));
+        }
+        signers.sort();
+        Ok(Some(signers))
+    }
+
+    /// The draw asked for, if any, with a fresh seed unless one was given.
+    pub fn selection(&self) -> Result<Option<WeightedSelection>, AppError> {
+        if self.weights.is_empty() && self.selection_seed.is_none() {
+            return Ok(None);
+        }
+        let seed = match &self.selection_seed {
+            Some(seed) => crate::parse_rng_seed(seed)?,
+            None => {
+                let mut seed = [0u8; 32];
+                thread_rng().fill_bytes(&mut seed);
+                seed
+            }
+        };
+        Ok(Some(WeightedSelection {
+            weights: self.weights.iter().copied().collect(),
+            seed,
+        }))
+    }
+}
+
+/// Weights by participant index plus the seed of the draw.
+#[derive(Debug, Clone)]
+pub struct WeightedSelection {
+    /// Participants without an entry weigh 1. A weight of 0 never signs.
+    pub weights: BTreeMap<u16, u32>,
+    pub seed: [u8; 32],
+}
+
+impl WeightedSelection {
+    /// Draws a threshold of distinct participants of `key_packages`, sorted.
+    pub fn select(&self, key_packages: &KeyPackages) -> Result<Vec<frost::Identifier>, AppError> {
+        let (threshold, _) = crate::map_threshold(key_packages)?;
+        let mut weights = BTreeMap::new();
+        for (index, weight) in &self.weights {
+            match frost::Identifier::try_from(*index) {
+                Ok(identifier) if key_packages.contains_key(&identifier) => {
+                    weights.insert(identifier, *weight);
+                }
+                _ => return Err(format!("participant {index} is not in the key file").into()),
+            }
+        }
+
+        let candidates: Vec<(frost::Identifier, u32)> = key_packages
+            .keys()
+            .map(|identifier| (*identifier, weights.get(identifier).copied().unwrap_or(1)))
+            .collect();
+        let eligible = candidates.iter().filter(|(_, weight)| *weight > 0).count();
+        if eligible < usize::from(threshold) {
+            return Err(AppError::InvalidThreshold(format!(
+                "only {eligible} participants have a non-zero weight, but {threshold} must sign"
+            )));
+        }
+
+        let mut rng = ChaCha20Rng::from_seed(self.seed);
+        let mut selected: Vec<_> = candidates
+            .choose_multiple_weighted(&mut rng, threshold.into(), |(_, weight)| f64::from(*weight))
+            .map_err(|err| format!("cannot draw signers: {err}"))?
+            .map(|(identifier, _)| *identifier)
+            .collect();
+        selected.sort();
+        Ok(selected)
+    }
+}
+
+/// Parses one `<index>=<weight>` pair of `--weights`.
+pub fn parse_weight(pair: &str) -> Result<(u16, u32), String> {
+    let (index, weight) = pair
+        .split_once('=')
+        .ok_or_else(|| format!("expected <index>=<weight>, got {pair:?}"))?;
+    Ok((
+        index
+            .trim()
+            .parse()
+            .map_err(|err| format!("participant index {index:?}: {err}"))?,
+        weight
+            .trim()
+            .parse()
+            .map_err(|err| format!("weight {weight:?}: {err}"))?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dealer_keygen;
+    use rand::thread_rng;
+
+    #[test]
+    fn the_draw_is_reproducible_and_skips_zero_weights() {
+        let (key_packages, _) = dealer_keygen(3, 5, &mut thread_rng()).unwrap();
+        let selection = WeightedSelection {
+            weights: BTreeMap::from([(2, 0), (4, 100)]),
+            seed: [5u8; 32],
+        };
+
+        let selected = selection.select(&key_packages).unwrap();
+        assert_eq!(selected, selection.select(&key_packages).unwrap());
+        assert_eq!(selected.len(), 3);
+        assert!(!selected.contains(&frost::Identifier::try_from(2).unwrap()));
+
+        let too_few = WeightedSelection {
+            weights: BTreeMap::from([(1, 0), (2, 0), (3, 0)]),
+            seed: [5u8; 32],
+        };
+        assert!(matches!(
+            too_few.select(&key_packages),
+            Err(AppError::InvalidThreshold(_))
+        ));
+        assert_eq!(parse_weight("3=7"), Ok((3, 7)));
+    }
+
+    #[test]
+    fn chosen_signers_must_be_known_distinct_and_enough() {
+        let (key_packages, _) = dealer_keygen(3, 5, &mut thread_rng()).unwrap();
+        let chosen = |signers: &[&str]| {
+            SelectionArgs {
+                signers: signers.iter().map(|signer| signer.to_string()).collect(),
+                ..Default::default()
+            }
+            .chosen_signers(&key_packages)
+        };
+
+        let ids: Vec<_> = [2u16, 4, 5]
+            .into_iter()
+            .map(|index| frost::Identifier::try_from(index).unwrap())
+            .collect();
+        assert_eq!(chosen(&["5", "2", "4"]).unwrap(), Some(ids));
+        assert_eq!(chosen(&[]).unwrap(), None);
+        assert!(matches!(
+            chosen(&["1", "2"]),
+            Err(AppError::InvalidThreshold(_))
+        ));
+        assert!(chosen(&["1", "2", "6"]).is_err());
+        assert!(chosen(&["1", "2", "2"]).is_err());
+        assert!(parse_weight("3").is_err());
+    }
+}
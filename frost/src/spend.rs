@@ -0,0 +1,131 @@
+//! Classification of taproot input witnesses (BIP341).
+
+use bitcoin::script::ScriptBuf;
+use bitcoin::taproot::{ControlBlock, LeafVersion, TAPROOT_ANNEX_PREFIX};
+use bitcoin::Witness;
+
+/// How a taproot input was spent, as far as its witness reveals.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TaprootSpend {
+    /// A single 64-byte (or 65-byte, with explicit sighash type) signature.
+    KeyPath { signature_len: usize },
+    /// A revealed leaf script together with the control block proving it.
+    ScriptPath {
+        leaf_version: LeafVersion,
+        script: ScriptBuf,
+        /// Number of hashes in the merkle proof, i.e. the depth of the leaf.
+        depth: usize,
+        /// Witness items passed to the script.
+        inputs: usize,
+    },
+    /// The witness doesn't have the shape of either taproot spend path.
+    Unknown,
+}
+
+/// Classifies `witness`, ignoring a trailing annex.
+///
+/// The witness alone doesn't say which kind of output it spends (a P2WPKH
+/// public key can parse as a control block), so this assumes the input spends
+/// a taproot output.
+pub fn classify(witness: &Witness) -> TaprootSpend {
+    let mut items: Vec<&[u8]> = witness.iter().collect();
+    // With two or more items, a last item starting with 0x50 is the annex.
+    if items.len() >= 2 && items.last().and_then(|item| item.first()) == Some(&TAPROOT_ANNEX_PREFIX)
+    {
+        items.pop();
+    }
+
+    match items.as_slice() {
+        [signature] if matches!(signature.len(), 64 | 65) => TaprootSpend::KeyPath {
+            signature_len: signature.len(),
+        },
+        [inputs @ .., script, control_block] => match ControlBlock::decode(control_block) {
+            Ok(control_block) => TaprootSpend::ScriptPath {
+                leaf_version: control_block.leaf_version,
+                script: ScriptBuf::from_bytes(script.to_vec()),
+                depth: control_block.merkle_branch.len(),
+                inputs: inputs.len(),
+            },
+            Err(_) => TaprootSpend::Unknown,
+        },
+        _ => TaprootSpend::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::{Secp256k1, XOnlyPublicKey};
+    use bitcoin::opcodes::all::OP_CHECKSIG;
+    use bitcoin::script::Builder;
+    use bitcoin::taproot::TaprootBuilder;
+
+    const INTERNAL_KEY: &str = "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115";
+
+    fn script_path_witness(annex: bool) -> (Witness, ScriptBuf) {
+        let secp = Secp256k1::verification_only();
+        let internal_key: XOnlyPublicKey = INTERNAL_KEY.parse().unwrap();
+        let script = Builder::new()
+            .push_x_only_key(&internal_key)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, script.clone())
+            .unwrap()
+            .add_leaf(1, ScriptBuf::new())
+            .unwrap()
+            .finalize(&secp, internal_key)
+            .unwrap();
+        let control_block = spend_info
+            .control_block(&(script.clone(), LeafVersion::TapScript))
+            .unwrap();
+
+        let mut witness = Witness::new();
+        witness.push([1u8; 64]);
+        witness.push(script.as_bytes());
+        witness.push(control_block.serialize());
+        if annex {
+            witness.push([TAPROOT_ANNEX_PREFIX, 0]);
+        }
+        (witness, script)
+    }
+
+    #[test]
+    fn single_signature_is_a_key_path_spend() {
+        let witness = Witness::from_slice(&[[1u8; 64]]);
+        assert_eq!(
+            classify(&witness),
+            TaprootSpend::KeyPath { signature_len: 64 }
+        );
+
+        let witness = Witness::from_slice(&[vec![1u8; 65], vec![TAPROOT_ANNEX_PREFIX]]);
+        assert_eq!(
+            classify(&witness),
+            TaprootSpend::KeyPath { signature_len: 65 }
+        );
+    }
+
+    #[test]
+    fn script_path_spend_reveals_the_leaf() {
+        for annex in [false, true] {
+            let (witness, script) = script_path_witness(annex);
+            assert_eq!(
+                classify(&witness),
+                TaprootSpend::ScriptPath {
+                    leaf_version: LeafVersion::TapScript,
+                    script,
+                    depth: 1,
+                    inputs: 1,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn other_witnesses_are_unknown() {
+        assert_eq!(classify(&Witness::new()), TaprootSpend::Unknown);
+        // A control block is 33 bytes plus 32 per merkle proof hash.
+        let witness = Witness::from_slice(&[vec![0x51], vec![0xc0; 40]]);
+        assert_eq!(classify(&witness), TaprootSpend::Unknown);
+    }
+}
@@ -0,0 +1,652 @@
+//! BIP341 key-path signing for the group's taproot address.
+//!
+//! The rest of the demo signs with FROST(secp256k1, SHA-256), whose
+//! signatures are not BIP340 signatures. A key-path spend instead needs a
+//! BIP340 signature by the output key `Q = P + t*G`, where `P` is the group key
+//! with an even Y and `t` is its TapTweak over the optional script tree's
+//! merkle root. This runs both FROST rounds with the frost-secp256k1-tr
+//! ciphersuite, which applies that tweak to every share, so the aggregate
+//! verifies against the output key of [`crate::group_taproot_address`].
+//!
+//! Both ciphersuites share the curve and the scalar encoding, so the key
+//! packages on disk are converted rather than generated again. About half of
+//! all group keys have an odd Y. BIP340 keys are x-only and stand for the
+//! point with the even Y, so for those groups the converted key packages are
+//! negated (the shares, the verifying shares and the group key) before
+//! signing. See [`internal_key_parity`].
+//!
+//! [`script_tree`] commits the output key to a tree of alternative spending
+//! scripts, such as a timelocked recovery path. The group still controls the
+//! key path of that output, and each script can be spent with its control
+//! block.
+//!
+//! [`sign_internal_key`] runs the same rounds without the tweak, for protocols
+//! such as Nostr whose BIP340 key is the x-only group key itself.
+//!
+//! [`find_descriptor_index`] checks an address against a watch-only `tr()`
+//! descriptor.
+
+use crate::error::AppError;
+use crate::{frost, KeyPackages};
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::hashes::Hash;
+use bitcoin::key::{TapTweak, TweakedPublicKey, UntweakedPublicKey};
+use bitcoin::secp256k1::{schnorr, Message, Parity, Secp256k1};
+use bitcoin::sighash::{Annex, Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{ControlBlock, LeafVersion, TapLeafHash, TaprootBuilder};
+use bitcoin::Address;
+use bitcoin::{ScriptBuf, TapNodeHash, Transaction, TxOut};
+use frost_secp256k1_tr as tr;
+use miniscript::descriptor::{Descriptor, DescriptorPublicKey};
+use rand::{CryptoRng, RngCore};
+use std::collections::BTreeMap;
+use tr::keys::EvenY;
+
+fn to_tr_identifier(identifier: &frost::Identifier) -> Result<tr::Identifier, AppError> {
+    Ok(tr::Identifier::deserialize(&identifier.serialize())?)
+}
+
+fn to_tr_key_package(
+    key_package: &frost::keys::KeyPackage,
+) -> Result<tr::keys::KeyPackage, AppError> {
+    Ok(tr::keys::KeyPackage::new(
+        to_tr_identifier(key_package.identifier())?,
+        tr::keys::SigningShare::deserialize(&key_package.signing_share().serialize())?,
+        tr::keys::VerifyingShare::deserialize(&key_package.verifying_share().serialize()?)?,
+        tr::VerifyingKey::deserialize(&key_package.verifying_key().serialize()?)?,
+        *key_package.min_signers(),
+    ))
+}
+
+fn to_tr_pubkey_package(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+) -> Result<tr::keys::PublicKeyPackage, AppError> {
+    let verifying_shares = pubkey_package
+        .verifying_shares()
+        .iter()
+        .map(|(identifier, share)| {
+            Ok((
+                to_tr_identifier(identifier)?,
+                tr::keys::VerifyingShare::deserialize(&share.serialize()?)?,
+            ))
+        })
+        .collect::<Result<_, AppError>>()?;
+    Ok(tr::keys::PublicKeyPackage::new(
+        verifying_shares,
+        tr::VerifyingKey::deserialize(&pubkey_package.verifying_key().serialize()?)?,
+    ))
+}
+
+/// The x-only internal key of the group's taproot outputs, and the Y parity
+/// of the group key it was taken from. With [`Parity::Odd`] the internal key
+/// is the negated group key, and so are the keys that sign for it.
+pub fn internal_key_parity(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+) -> Result<(UntweakedPublicKey, Parity), AppError> {
+    let pubkey =
+        bitcoin::secp256k1::PublicKey::from_slice(&pubkey_package.verifying_key().serialize()?)?;
+    Ok(pubkey.x_only_public_key())
+}
+
+/// The x-only internal key of the group's taproot outputs.
+pub fn internal_key(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+) -> Result<UntweakedPublicKey, AppError> {
+    Ok(internal_key_parity(pubkey_package)?.0)
+}
+
+/// The tweaked key a key-path spend must be signed by.
+pub fn output_key(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    merkle_root: Option<TapNodeHash>,
+) -> Result<TweakedPublicKey, AppError> {
+    let (output_key, _parity) =
+        internal_key(pubkey_package)?.tap_tweak(&Secp256k1::verification_only(), merkle_root);
+    Ok(output_key)
+}
+
+/// One script of a [`ScriptTree`] and what spending through it needs.
+pub struct ScriptLeaf {
+    pub script: ScriptBuf,
+    pub leaf_hash: TapLeafHash,
+    /// Goes last in the witness of a script-path spend, after the script.
+    pub control_block: ControlBlock,
+}
+
+/// A tree of tapscripts the group's output key commits to.
+pub struct ScriptTree {
+    /// What `--merkle-root` takes to derive the same address or sign its
+    /// key path.
+    pub merkle_root: TapNodeHash,
+    pub output_key: TweakedPublicKey,
+    /// In the order the scripts were given.
+    pub leaves: Vec<ScriptLeaf>,
+}
+
+/// Builds a tree of `scripts` under the group's internal key. Every script
+/// gets the same weight, so the tree is as balanced as it can be and each
+/// control block is at most one hash longer than the others.
+pub fn script_tree(
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    scripts: &[ScriptBuf],
+) -> Result<ScriptTree, AppError> {
+    if scripts.is_empty() {
+        return Err("a script tree needs at least one script".into());
+    }
+    for (index, script) in scripts.iter().enumerate() {
+        if scripts[..index].contains(script) {
+            return Err(format!("script {index} is given twice").into());
+        }
+    }
+    let secp = Secp256k1::verification_only();
+    let spend_info =
+        TaprootBuilder::with_huffman_tree(scripts.iter().map(|script| (1, script.clone())))?
+            .finalize(&secp, internal_key(pubkey_package)?)
+            .map_err(|_| "the script tree is incomplete")?;
+    let leaves = scripts
+        .iter()
+        .map(|script| {
+            let leaf = (script.clone(), LeafVersion::TapScript);
+            Ok(ScriptLeaf {
+                script: script.clone(),
+                leaf_hash: TapLeafHash::from_script(script, LeafVersion::TapScript),
+                control_block: spend_info
+                    .control_block(&leaf)
+                    .ok_or("a script is missing from its own tree")?,
+            })
+        })
+        .collect::<Result<_, AppError>>()?;
+    Ok(ScriptTree {
+        merkle_root: spend_info
+            .merkle_root()
+            .ok_or("the script tree has no merkle root")?,
+        output_key: spend_info.output_key(),
+        leaves,
+    })
+}
+
+/// Decodes a hex taproot annex, which BIP341 requires to start with 0x50.
+pub fn parse_annex(annex_hex: &str) -> Result<Vec<u8>, AppError> {
+    let annex = hex::decode(annex_hex.trim())?;
+    Annex::new(&annex).map_err(|err| format!("invalid annex: {err}"))?;
+    Ok(annex)
+}
+
+/// The BIP341 key-path sighash of input `index` of `tx` with
+/// `SIGHASH_DEFAULT`, where `prevouts` are the outputs every input spends.
+/// It commits to all of their amounts and scripts, so a signature over it
+/// authorizes exactly this spend. With an `annex`, it commits to that too,
+/// and the input's witness must carry it after the signature.
+pub fn key_spend_sighash(
+    tx: &Transaction,
+    index: usize,
+    prevouts: &[TxOut],
+    annex: Option<&[u8]>,
+) -> Result<[u8; 32], AppError> {
+    if index >= tx.input.len() {
+        return Err(format!("no input {index} in a transaction of {}", tx.input.len()).into());
+    }
+    let annex = annex
+        .map(Annex::new)
+        .transpose()
+        .map_err(|err| format!("invalid annex: {err}"))?;
+    let sighash = SighashCache::new(tx).taproot_signature_hash(
+        index,
+        &Prevouts::All(prevouts),
+        annex,
+        None,
+        TapSighashType::Default,
+    )?;
+    Ok(sighash.to_byte_array())
+}
+
+/// Has `signers` sign `sighash` for a key-path spend of the group's output
+/// key committing to `merkle_root`, and checks the result as BIP340 does.
+pub fn sign_key_path<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    sighash: &[u8; 32],
+    merkle_root: Option<TapNodeHash>,
+    rng: &mut R,
+) -> Result<schnorr::Signature, AppError> {
+    let merkle_root_bytes = merkle_root.map(|root| root.to_byte_array());
+    let signature = sign_even_y(
+        key_packages,
+        pubkey_package,
+        signers,
+        sighash,
+        SignFor::OutputKey(merkle_root_bytes.as_ref().map(|root| &root[..])),
+        rng,
+    )?;
+    Secp256k1::verification_only().verify_schnorr(
+        &signature,
+        &Message::from_digest(*sighash),
+        &output_key(pubkey_package, merkle_root)?.to_x_only_public_key(),
+    )?;
+    Ok(signature)
+}
+
+/// Has `signers` sign `digest` with the untweaked internal key, and checks the
+/// result as BIP340 does.
+pub fn sign_internal_key<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    digest: &[u8; 32],
+    rng: &mut R,
+) -> Result<schnorr::Signature, AppError> {
+    let signature = sign_even_y(
+        key_packages,
+        pubkey_package,
+        signers,
+        digest,
+        SignFor::InternalKey,
+        rng,
+    )?;
+    Secp256k1::verification_only().verify_schnorr(
+        &signature,
+        &Message::from_digest(*digest),
+        &internal_key(pubkey_package)?,
+    )?;
+    Ok(signature)
+}
+
+/// The key a [`sign_even_y`] signature verifies against.
+enum SignFor<'a> {
+    /// The x-only group key.
+    InternalKey,
+    /// The internal key tweaked with this merkle root, if any.
+    OutputKey(Option<&'a [u8]>),
+}
+
+/// Runs both rounds with the frost-secp256k1-tr ciphersuite.
+fn sign_even_y<R: RngCore + CryptoRng>(
+    key_packages: &KeyPackages,
+    pubkey_package: &frost::keys::PublicKeyPackage,
+    signers: &[frost::Identifier],
+    message: &[u8; 32],
+    sign_for: SignFor,
+    rng: &mut R,
+) -> Result<schnorr::Signature, AppError> {
+    // Every share is negated along with an odd group key, so the shares
+    // still add up to the secret of the even-Y internal key.
+    let (_, parity) = internal_key_parity(pubkey_package)?;
+    let is_even = Some(parity == Parity::Even);
+
+    let mut tr_key_packages = BTreeMap::new();
+    let mut nonces_map = BTreeMap::new();
+    let mut commitments_map = BTreeMap::new();
+    for identifier in signers {
+        let key_package = key_packages
+            .get(identifier)
+            .ok_or(frost::Error::UnknownIdentifier)?;
+        let key_package = to_tr_key_package(key_package)?.into_even_y(is_even);
+        let (nonces, commitments) = tr::round1::commit(key_package.signing_share(), rng);
+        nonces_map.insert(*key_package.identifier(), nonces);
+        commitments_map.insert(*key_package.identifier(), commitments);
+        tr_key_packages.insert(*key_package.identifier(), key_package);
+    }
+
+    let signing_package = tr::SigningPackage::new(commitments_map, message);
+    let mut signature_shares = BTreeMap::new();
+    for (identifier, nonces) in &nonces_map {
+        let key_package = &tr_key_packages[identifier];
+        let share = match sign_for {
+            SignFor::InternalKey => tr::round2::sign(&signing_package, nonces, key_package)?,
+            SignFor::OutputKey(merkle_root) => {
+                tr::round2::sign_with_tweak(&signing_package, nonces, key_package, merkle_root)?
+            }
+        };
+        signature_shares.insert(*identifier, share);
+    }
+    let tr_pubkey_package = to_tr_pubkey_package(pubkey_package)?.into_even_y(is_even);
+    let signature = match sign_for {
+        SignFor::InternalKey => {
+            tr::aggregate(&signing_package, &signature_shares, &tr_pubkey_package)?
+        }
+        SignFor::OutputKey(merkle_root) => tr::aggregate_with_tweak(
+            &signing_package,
+            &signature_shares,
+            &tr_pubkey_package,
+            merkle_root,
+        )?,
+    };
+    Ok(schnorr::Signature::from_slice(&signature.serialize()?)?)
+}
+
+/// Returns the derivation index at which `descriptor` produces `address`
+/// (`Some(0)` for non-ranged descriptors), scanning the first `scan` indices.
+pub fn find_descriptor_index(
+    address: &Address<NetworkUnchecked>,
+    descriptor: &str,
+    scan: u32,
+) -> Result<Option<u32>, AppError> {
+    let descriptor: Descriptor<DescriptorPublicKey> = descriptor.trim().parse()?;
+    if !matches!(descriptor, Descriptor::Tr(_)) {
+        return Err("only tr() descriptors are supported".into());
+    }
+
+    // Script pubkeys are network independent, so compare those.
+    let target = address.assume_checked_ref().script_pubkey();
+    let indices = if descriptor.has_wildcard() {
+        0..scan
+    } else {
+        0..1
+    };
+    for index in indices {
+        let derived = descriptor.at_derivation_index(index)?;
+        if derived.script_pubkey() == target {
+            return Ok(Some(index));
+        }
+    }
+    Ok(None)
+}
+
+/// The addresses on `network` of the first `count` indices of the ranged
+/// `descriptor`.
+pub fn descriptor_addresses(
+    descriptor: &str,
+    count: u32,
+    network: bitcoin::Network,
+) -> Result<Vec<Address>, AppError> {
+    let descriptor: Descriptor<DescriptorPublicKey> = descriptor.trim().parse()?;
+    if !matches!(descriptor, Descriptor::Tr(_)) {
+        return Err("only tr() descriptors are supported".into());
+    }
+    if !descriptor.has_wildcard() {
+        return Err("the descriptor has no wildcard to derive children at, e.g. /0/*".into());
+    }
+    (0..count)
+        .map(|index| Ok(descriptor.at_derivation_index(index)?.address(network)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dealer_keygen, default_signers, group_taproot_address};
+    use bitcoin::Network;
+    use rand::thread_rng;
+
+    #[test]
+    fn key_path_signatures_verify_against_the_address_output_key() {
+        let (key_packages, pubkey_package) = dealer_keygen(3, 5, &mut thread_rng()).unwrap();
+        let sighash = [7u8; 32];
+        let script_tree = TapNodeHash::from_byte_array([9u8; 32]);
+
+        for merkle_root in [None, Some(script_tree)] {
+            let signature = sign_key_path(
+                &key_packages,
+                &pubkey_package,
+                &default_signers(&key_packages),
+                &sighash,
+                merkle_root,
+                &mut thread_rng(),
+            )
+            .unwrap();
+
+            let (_, address) =
+                group_taproot_address(&pubkey_package, merkle_root, Network::Bitcoin).unwrap();
+            let address_key =
+                bitcoin::XOnlyPublicKey::from_slice(&address.script_pubkey().as_bytes()[2..])
+                    .unwrap();
+            assert!(Secp256k1::verification_only()
+                .verify_schnorr(&signature, &Message::from_digest(sighash), &address_key)
+                .is_ok());
+            // Not a signature by the untweaked group key.
+            assert!(Secp256k1::verification_only()
+                .verify_schnorr(
+                    &signature,
+                    &Message::from_digest(sighash),
+                    &internal_key(&pubkey_package).unwrap()
+                )
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn groups_of_either_parity_sign_valid_key_path_spends() {
+        let mut seen = Vec::new();
+        while seen.len() < 2 {
+            let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+            let (internal_key, parity) = internal_key_parity(&pubkey_package).unwrap();
+            if seen.contains(&parity) {
+                continue;
+            }
+            seen.push(parity);
+            let group_key = pubkey_package.verifying_key().serialize().unwrap();
+            assert_eq!(group_key[0], if parity == Parity::Even { 2 } else { 3 });
+            assert_eq!(group_key[1..], internal_key.serialize());
+
+            for merkle_root in [None, Some(TapNodeHash::from_byte_array([9u8; 32]))] {
+                let sighash = [parity.to_u8(); 32];
+                let signature = sign_key_path(
+                    &key_packages,
+                    &pubkey_package,
+                    &default_signers(&key_packages),
+                    &sighash,
+                    merkle_root,
+                    &mut thread_rng(),
+                )
+                .unwrap();
+                assert!(Secp256k1::verification_only()
+                    .verify_schnorr(
+                        &signature,
+                        &Message::from_digest(sighash),
+                        &output_key(&pubkey_package, merkle_root)
+                            .unwrap()
+                            .to_x_only_public_key()
+                    )
+                    .is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn groups_of_either_parity_sign_with_their_internal_key() {
+        let mut seen = Vec::new();
+        while seen.len() < 2 {
+            let (key_packages, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+            let (internal_key, parity) = internal_key_parity(&pubkey_package).unwrap();
+            if seen.contains(&parity) {
+                continue;
+            }
+            seen.push(parity);
+
+            let digest = [parity.to_u8(); 32];
+            let signature = sign_internal_key(
+                &key_packages,
+                &pubkey_package,
+                &default_signers(&key_packages),
+                &digest,
+                &mut thread_rng(),
+            )
+            .unwrap();
+            assert!(Secp256k1::verification_only()
+                .verify_schnorr(&signature, &Message::from_digest(digest), &internal_key)
+                .is_ok());
+            // Not a signature by the output key of a key-path-only address.
+            assert!(Secp256k1::verification_only()
+                .verify_schnorr(
+                    &signature,
+                    &Message::from_digest(digest),
+                    &output_key(&pubkey_package, None)
+                        .unwrap()
+                        .to_x_only_public_key()
+                )
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn every_script_of_the_tree_is_committed_to_by_the_address() {
+        let (_, pubkey_package) = dealer_keygen(2, 3, &mut thread_rng()).unwrap();
+        let scripts: Vec<ScriptBuf> = (1..=3u8)
+            .map(|n| ScriptBuf::from_bytes(vec![0x50 + n, 0xb2, 0x75, 0x51]))
+            .collect();
+        let tree = script_tree(&pubkey_package, &scripts).unwrap();
+
+        let (_, address) =
+            group_taproot_address(&pubkey_package, Some(tree.merkle_root), Network::Bitcoin)
+                .unwrap();
+        assert_eq!(
+            address.script_pubkey(),
+            ScriptBuf::new_p2tr_tweaked(tree.output_key)
+        );
+        let secp = Secp256k1::verification_only();
+        for leaf in &tree.leaves {
+            assert!(leaf.control_block.verify_taproot_commitment(
+                &secp,
+                tree.output_key.to_x_only_public_key(),
+                &leaf.script
+            ));
+        }
+        assert!(!tree.leaves[0].control_block.verify_taproot_commitment(
+            &secp,
+            tree.output_key.to_x_only_public_key(),
+            &ScriptBuf::new()
+        ));
+        assert!(script_tree(&pubkey_package, &[]).is_err());
+        assert!(script_tree(&pubkey_package, &[scripts[0].clone(), scripts[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn key_spend_sighash_matches_bitcoin_core() {
+        // From Bitcoin Core's functional test framework, via rust-bitcoin.
+        let tx: Transaction = bitcoin::consensus::encode::deserialize_hex(
+            "020000000164eb050a5e3da0c2a65e4786f26d753b7bc69691fabccafb11f7acef36641f1846010000003101b2b404392a22000000000017a9147f2bde86fe78bf68a0544a4f290e12f0b7e0a08c87580200000000000017a91425d11723074ecfb96a0a83c3956bfaf362ae0c908758020000000000001600147e20f938993641de67bb0cdd71682aa34c4d29ad5802000000000000160014c64984dc8761acfa99418bd6bedc79b9287d652d72000000",
+        )
+        .unwrap();
+        let prevouts: Vec<TxOut> = bitcoin::consensus::encode::deserialize_hex(
+            "01365724000000000023542156b39dab4f8f3508e0432cfb41fab110170acaa2d4c42539cb90a4dc7c093bc500",
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(key_spend_sighash(&tx, 0, &prevouts, None).unwrap()),
+            "33ca0ebfb4a945eeee9569fc0f5040221275f88690b7f8592ada88ce3bdf6703"
+        );
+        assert!(key_spend_sighash(&tx, 1, &prevouts, None).is_err());
+
+        let mut cheaper = prevouts.clone();
+        cheaper[0].value = bitcoin::Amount::from_sat(1);
+        assert_ne!(
+            key_spend_sighash(&tx, 0, &cheaper, None).unwrap(),
+            key_spend_sighash(&tx, 0, &prevouts, None).unwrap()
+        );
+    }
+
+    /// BIP341's signature message for a `SIGHASH_DEFAULT` key-path spend,
+    /// assembled field by field from the spec, and its TapSighash.
+    fn bip341_sighash(
+        tx: &Transaction,
+        index: u32,
+        prevouts: &[TxOut],
+        annex: Option<&[u8]>,
+    ) -> [u8; 32] {
+        use bitcoin::consensus::Encodable;
+        use bitcoin::hashes::sha256;
+
+        fn sha256_of(write: impl FnOnce(&mut Vec<u8>)) -> [u8; 32] {
+            let mut data = Vec::new();
+            write(&mut data);
+            sha256::Hash::hash(&data).to_byte_array()
+        }
+        // Epoch 0 and hash type SIGHASH_DEFAULT.
+        let mut message = vec![0x00, 0x00];
+        tx.version.consensus_encode(&mut message).unwrap();
+        tx.lock_time.consensus_encode(&mut message).unwrap();
+        message.extend(sha256_of(|data| {
+            for input in &tx.input {
+                input.previous_output.consensus_encode(data).unwrap();
+            }
+        }));
+        message.extend(sha256_of(|data| {
+            for prevout in prevouts {
+                prevout.value.consensus_encode(data).unwrap();
+            }
+        }));
+        message.extend(sha256_of(|data| {
+            for prevout in prevouts {
+                prevout.script_pubkey.consensus_encode(data).unwrap();
+            }
+        }));
+        message.extend(sha256_of(|data| {
+            for input in &tx.input {
+                input.sequence.consensus_encode(data).unwrap();
+            }
+        }));
+        message.extend(sha256_of(|data| {
+            for output in &tx.output {
+                output.consensus_encode(data).unwrap();
+            }
+        }));
+        // spend_type: key path, with the annex bit.
+        message.push(u8::from(annex.is_some()));
+        index.consensus_encode(&mut message).unwrap();
+        if let Some(annex) = annex {
+            message.extend(sha256_of(|data| {
+                annex.to_vec().consensus_encode(data).unwrap();
+            }));
+        }
+        let tag = sha256::Hash::hash(b"TapSighash").to_byte_array();
+        sha256::Hash::hash(&[&tag[..], &tag, &message].concat()).to_byte_array()
+    }
+
+    #[test]
+    fn key_spend_sighash_commits_to_the_annex() {
+        // The vector of `key_spend_sighash_matches_bitcoin_core`.
+        let tx: Transaction = bitcoin::consensus::encode::deserialize_hex(
+            "020000000164eb050a5e3da0c2a65e4786f26d753b7bc69691fabccafb11f7acef36641f1846010000003101b2b404392a22000000000017a9147f2bde86fe78bf68a0544a4f290e12f0b7e0a08c87580200000000000017a91425d11723074ecfb96a0a83c3956bfaf362ae0c908758020000000000001600147e20f938993641de67bb0cdd71682aa34c4d29ad5802000000000000160014c64984dc8761acfa99418bd6bedc79b9287d652d72000000",
+        )
+        .unwrap();
+        let prevouts: Vec<TxOut> = bitcoin::consensus::encode::deserialize_hex(
+            "01365724000000000023542156b39dab4f8f3508e0432cfb41fab110170acaa2d4c42539cb90a4dc7c093bc500",
+        )
+        .unwrap();
+        let without = key_spend_sighash(&tx, 0, &prevouts, None).unwrap();
+        assert_eq!(
+            hex::encode(bip341_sighash(&tx, 0, &prevouts, None)),
+            "33ca0ebfb4a945eeee9569fc0f5040221275f88690b7f8592ada88ce3bdf6703"
+        );
+        assert_eq!(without, bip341_sighash(&tx, 0, &prevouts, None));
+
+        let annex = parse_annex("50deadbeef").unwrap();
+        let with = key_spend_sighash(&tx, 0, &prevouts, Some(&annex)).unwrap();
+        assert_ne!(with, without);
+        assert_eq!(with, bip341_sighash(&tx, 0, &prevouts, Some(&annex)));
+
+        assert!(parse_annex("").is_err());
+        assert!(parse_annex("51deadbeef").is_err());
+        assert!(parse_annex("50zz").is_err());
+        assert!(key_spend_sighash(&tx, 0, &prevouts, Some(&[0x51])).is_err());
+    }
+
+    #[test]
+    fn descriptor_scan_finds_bip86_addresses() {
+        // BIP86 test vector, account 0 external chain.
+        let descriptor = "tr(xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ/0/*)";
+        let second: Address<NetworkUnchecked> =
+            "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh"
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            find_descriptor_index(&second, descriptor, 10).unwrap(),
+            Some(1)
+        );
+        assert_eq!(find_descriptor_index(&second, descriptor, 1).unwrap(), None);
+
+        let addresses = descriptor_addresses(descriptor, 2, Network::Bitcoin).unwrap();
+        assert_eq!(
+            addresses[0].to_string(),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+        assert_eq!(
+            addresses[1].to_string(),
+            second.assume_checked().to_string()
+        );
+        let single = descriptor.replace("/0/*", "/0/0");
+        assert!(descriptor_addresses(&single, 2, Network::Bitcoin).is_err());
+    }
+}
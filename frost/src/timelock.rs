@@ -0,0 +1,176 @@
+//! CSV/CLTV conditions of a script and whether the chain has reached them.
+
+use crate::error::AppError;
+use bitcoin::locktime::{absolute, relative};
+use bitcoin::opcodes::all::{OP_CLTV, OP_CSV};
+use bitcoin::script::{Instruction, Script};
+use std::fmt;
+
+/// A timelock a spending path commits to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timelock {
+    /// `<n> OP_CHECKLOCKTIMEVERIFY`
+    Absolute(absolute::LockTime),
+    /// `<n> OP_CHECKSEQUENCEVERIFY`
+    Relative(relative::LockTime),
+}
+
+impl fmt::Display for Timelock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timelock::Absolute(lock_time) => write!(f, "CLTV {lock_time}"),
+            Timelock::Relative(relative::LockTime::Blocks(blocks)) => {
+                write!(f, "CSV {} blocks", blocks.value())
+            }
+            Timelock::Relative(relative::LockTime::Time(interval)) => {
+                write!(f, "CSV {} x 512s", interval.value())
+            }
+        }
+    }
+}
+
+/// Chain tip state as reported by the node.
+#[derive(Clone, Copy, Debug)]
+pub struct Tip {
+    pub height: u32,
+    pub median_time: u32,
+}
+
+/// Where a relative timelock starts counting from.
+#[derive(Clone, Copy, Debug)]
+pub struct Confirmation {
+    pub height: u32,
+    /// Median time past of the block before the one that confirmed the output.
+    pub median_time: Option<u32>,
+}
+
+/// Required and current chain state for one timelock.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Maturity {
+    pub required: String,
+    pub current: String,
+    pub mature: bool,
+}
+
+/// The CLTV and CSV timelocks in `script`, in order of appearance.
+pub fn script_timelocks(script: &Script) -> Vec<Timelock> {
+    let instructions: Vec<_> = script.instructions().filter_map(Result::ok).collect();
+    instructions
+        .windows(2)
+        .filter_map(|pair| {
+            let value = u32::try_from(pair[0].script_num()?).ok()?;
+            match pair[1] {
+                Instruction::Op(OP_CLTV) => Some(Timelock::Absolute(
+                    absolute::LockTime::from_consensus(value),
+                )),
+                Instruction::Op(OP_CSV) => relative::LockTime::from_consensus(value)
+                    .ok()
+                    .map(Timelock::Relative),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Checks whether a transaction satisfying `timelock` could be mined in the
+/// block after `tip`. Relative timelocks need the output's `confirmation`.
+pub fn maturity(
+    timelock: Timelock,
+    tip: Tip,
+    confirmation: Option<Confirmation>,
+) -> Result<Maturity, AppError> {
+    let next_height = tip.height + 1;
+    Ok(match timelock {
+        // A transaction is final once its lock time is below the next
+        // block's height, or below the tip's median time past.
+        Timelock::Absolute(absolute::LockTime::Blocks(height)) => Maturity {
+            required: format!("height {}", height.to_consensus_u32()),
+            current: format!("height {}", tip.height),
+            mature: height.to_consensus_u32() < next_height,
+        },
+        Timelock::Absolute(absolute::LockTime::Seconds(time)) => Maturity {
+            required: format!("median time past > {}", time.to_consensus_u32()),
+            current: format!("median time past {}", tip.median_time),
+            mature: time.to_consensus_u32() < tip.median_time,
+        },
+        Timelock::Relative(relative::LockTime::Blocks(blocks)) => {
+            let confirmation = confirmation.ok_or("relative timelocks need --confirmed-height")?;
+            let required = confirmation.height + u32::from(blocks.value());
+            Maturity {
+                required: format!(
+                    "height {required} ({} blocks after confirmation)",
+                    blocks.value()
+                ),
+                current: format!("height {}", tip.height),
+                mature: next_height >= required,
+            }
+        }
+        Timelock::Relative(relative::LockTime::Time(interval)) => {
+            let start = confirmation
+                .and_then(|confirmation| confirmation.median_time)
+                .ok_or("relative timelocks need --confirmed-height")?;
+            let required = start + u32::from(interval.value()) * 512;
+            Maturity {
+                required: format!(
+                    "median time past {required} ({} x 512s after confirmation)",
+                    interval.value()
+                ),
+                current: format!("median time past {}", tip.median_time),
+                mature: tip.median_time >= required,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::opcodes::all::{OP_CHECKSIG, OP_DROP};
+    use bitcoin::script::Builder;
+
+    const TIP: Tip = Tip {
+        height: 800_000,
+        median_time: 1_700_000_000,
+    };
+
+    #[test]
+    fn finds_csv_and_cltv_in_a_staking_script() {
+        let script = Builder::new()
+            .push_slice([2u8; 32])
+            .push_opcode(OP_CHECKSIG)
+            .push_int(64_000)
+            .push_opcode(OP_CSV)
+            .push_opcode(OP_DROP)
+            .push_int(850_000)
+            .push_opcode(OP_CLTV)
+            .into_script();
+        assert_eq!(
+            script_timelocks(&script),
+            vec![
+                Timelock::Relative(relative::LockTime::from_height(64_000)),
+                Timelock::Absolute(absolute::LockTime::from_consensus(850_000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn absolute_heights_mature_at_the_next_block() {
+        let lock = |height| Timelock::Absolute(absolute::LockTime::from_consensus(height));
+        assert!(maturity(lock(800_000), TIP, None).unwrap().mature);
+        assert!(!maturity(lock(800_001), TIP, None).unwrap().mature);
+    }
+
+    #[test]
+    fn relative_heights_count_from_confirmation() {
+        let lock = Timelock::Relative(relative::LockTime::from_height(10));
+        let confirmed_at = |height| {
+            Some(Confirmation {
+                height,
+                median_time: None,
+            })
+        };
+        assert!(maturity(lock, TIP, confirmed_at(799_991)).unwrap().mature);
+        assert!(!maturity(lock, TIP, confirmed_at(799_992)).unwrap().mature);
+        assert!(maturity(lock, TIP, None).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Wire format for exchanging round 1 and round 2 artifacts with other FROST
+//! coordinators.
+//!
+//! Every message is a single frame:
+//!
+//! ```text
+//! version 0x01 (1 byte) || kind (1 byte) || payload length (u32, big-endian) || payload
+//! ```
+//!
+//! Payloads use the native `frost-core` serialization of each type, which is
+//! what the other implementations built on `frost-core` read and write:
+//!
+//! | kind | message              | payload                                          |
+//! |------|----------------------|--------------------------------------------------|
+//! | 0x01 | signing commitments  | identifier (32 bytes) `\|\|` `SigningCommitments` |
+//! | 0x02 | signing package      | `SigningPackage`                                 |
+//! | 0x03 | signature share      | identifier (32 bytes) `\|\|` `SignatureShare`     |
+//!
+//! Identifiers are serialized scalars. Commitments and shares carry their
+//! sender's identifier because they are sent one participant at a time.
+
+use crate::error::AppError;
+use crate::frost;
+
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 6;
+const IDENTIFIER_LEN: usize = 32;
+
+/// A round 1 or round 2 artifact as sent over the wire.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Message {
+    Commitments(frost::Identifier, Box<frost::round1::SigningCommitments>),
+    SigningPackage(frost::SigningPackage),
+    SignatureShare(frost::Identifier, frost::round2::SignatureShare),
+}
+
+impl Message {
+    fn kind(&self) -> u8 {
+        match self {
+            Message::Commitments(..) => 0x01,
+            Message::SigningPackage(_) => 0x02,
+            Message::SignatureShare(..) => 0x03,
+        }
+    }
+
+    /// Encodes the message as one frame.
+    pub fn encode(&self) -> Result<Vec<u8>, AppError> {
+        let payload = match self {
+            Message::Commitments(identifier, commitments) => {
+                [identifier.serialize(), commitments.serialize()?].concat()
+            }
+            Message::SigningPackage(signing_package) => signing_package.serialize()?,
+            Message::SignatureShare(identifier, share) => {
+                [identifier.serialize(), share.serialize()].concat()
+            }
+        };
+
+        let mut frame = vec![VERSION, self.kind()];
+        frame.extend_from_slice(&u32::try_from(payload.len())?.to_be_bytes());
+        frame.extend_from_slice(&payload);
+        Ok(frame)
+    }
+
+    /// Decodes exactly one frame.
+    pub fn decode(frame: &[u8]) -> Result<Self, AppError> {
+        if frame.len() < HEADER_LEN {
+            return Err("wire frame is shorter than its header".into());
+        }
+        if frame[0] != VERSION {
+            return Err(format!("unsupported wire format version {}", frame[0]).into());
+        }
+        let length = u32::from_be_bytes(frame[2..HEADER_LEN].try_into()?) as usize;
+        let payload = &frame[HEADER_LEN..];
+        if payload.len() != length {
+            return Err(format!(
+                "wire frame declares {length} payload bytes but carries {}",
+                payload.len()
+            )
+            .into());
+        }
+
+        match frame[1] {
+            0x01 => {
+                let (identifier, rest) = split_identifier(payload)?;
+                Ok(Message::Commitments(
+                    identifier,
+                    Box::new(frost::round1::SigningCommitments::deserialize(rest)?),
+                ))
+            }
+            0x02 => Ok(Message::SigningPackage(frost::SigningPackage::deserialize(
+                payload,
+            )?)),
+            0x03 => {
+                let (identifier, rest) = split_identifier(payload)?;
+                Ok(Message::SignatureShare(
+                    identifier,
+                    frost::round2::SignatureShare::deserialize(rest)?,
+                ))
+            }
+            kind => Err(format!("unknown wire message kind {kind:#04x}").into()),
+        }
+    }
+}
+
+fn split_identifier(payload: &[u8]) -> Result<(frost::Identifier, &[u8]), AppError> {
+    if payload.len() < IDENTIFIER_LEN {
+        return Err("wire payload is missing its identifier".into());
+    }
+    let (identifier, rest) = payload.split_at(IDENTIFIER_LEN);
+    Ok((frost::Identifier::deserialize(identifier)?, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dealer_keygen, default_signers, run_ceremony, DEMO_MESSAGE, MAX_SIGNERS, MIN_SIGNERS,
+    };
+    use rand::thread_rng;
+
+    fn ceremony() -> crate::Ceremony {
+        let mut rng = thread_rng();
+        let (key_packages, pubkey_package) =
+            dealer_keygen(MIN_SIGNERS, MAX_SIGNERS, &mut rng).unwrap();
+        run_ceremony(
+            &key_packages,
+            &pubkey_package,
+            &default_signers(&key_packages),
+            DEMO_MESSAGE,
+            &mut rng,
+        )
+        .unwrap()
+    }
+
+    fn round_trip(message: Message) {
+        let frame = message.encode().unwrap();
+        assert_eq!(Message::decode(&frame).unwrap(), message);
+    }
+
+    #[test]
+    fn every_artifact_round_trips() {
+        let ceremony = ceremony();
+        for (identifier, commitments) in ceremony.signing_package.signing_commitments() {
+            round_trip(Message::Commitments(*identifier, Box::new(*commitments)));
+        }
+        for (identifier, share) in &ceremony.signature_shares {
+            round_trip(Message::SignatureShare(*identifier, *share));
+        }
+        round_trip(Message::SigningPackage(ceremony.signing_package));
+    }
+
+    #[test]
+    fn malformed_frames_are_rejected() {
+        let ceremony = ceremony();
+        let (identifier, share) = ceremony.signature_shares.iter().next().unwrap();
+        let frame = Message::SignatureShare(*identifier, *share)
+            .encode()
+            .unwrap();
+
+        assert!(Message::decode(&frame[..frame.len() - 1]).is_err());
+        let mut unknown_kind = frame.clone();
+        unknown_kind[1] = 0x7f;
+        assert!(Message::decode(&unknown_kind).is_err());
+        let mut other_version = frame;
+        other_version[0] = 2;
+        assert!(Message::decode(&other_version).is_err());
+    }
+}
@@ -0,0 +1,850 @@
+//! Running every subcommand of the `frost` binary under `--json`.
+
+use bitcoin::hashes::Hash;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+const ENV_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+const BACKUP_KEY: &str = "1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100";
+const SEED: &str = "1234567890";
+/// The secp256k1 generator, so the adaptor secret is 1.
+const ADAPTOR_POINT: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+const ADAPTOR_SECRET: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+/// BIP86 test vector, account 0 external chain.
+const BIP86_DESCRIPTOR: &str = "tr(xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ/0/*)";
+
+struct Cli {
+    dir: PathBuf,
+    private_key: Option<String>,
+}
+
+impl Cli {
+    /// A fresh working directory named `name`, so tests don't share key files.
+    fn new_in(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("frost-cli-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Cli {
+            dir,
+            private_key: None,
+        }
+    }
+
+    fn output(&self, args: &[&str]) -> Output {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_frost"));
+        command
+            .args(args)
+            .current_dir(&self.dir)
+            .env("FROST_ENV_KEY", ENV_KEY)
+            .env("FROST_BACKUP_KEY", BACKUP_KEY)
+            // Nothing listens there, so the RPC commands fail fast.
+            .env("BITCOIN_RPC_URL", "http://127.0.0.1:1")
+            .env_remove("KEY_PASSPHRASE")
+            .env_remove("FROST_AUDIT_LOG")
+            .env_remove("FROST_ALLOWED_DESTINATIONS")
+            .env_remove("FROST_MAX_AMOUNT")
+            .env_remove("RUST_LOG")
+            .stdin(Stdio::null());
+        match &self.private_key {
+            Some(private_key) => command.env("PRIVATE_KEY", private_key),
+            None => command.env_remove("PRIVATE_KEY"),
+        };
+        command.output().unwrap()
+    }
+
+    /// Runs `frost --json <args>` and returns the JSON object it printed.
+    fn try_json(&self, args: &[&str]) -> (bool, serde_json::Map<String, serde_json::Value>) {
+        let output = self.output(&[&["--json"], args].concat());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&stdout)
+            .unwrap_or_else(|err| panic!("{args:?} printed {stdout:?}: {err}"));
+        let serde_json::Value::Object(object) = value else {
+            panic!("{args:?} printed {stdout}, not an object");
+        };
+        assert!(!object.is_empty(), "{args:?} recorded nothing");
+        (output.status.success(), object)
+    }
+
+    /// Like [`Cli::try_json`], for a command that must succeed.
+    fn json(&self, args: &[&str]) -> serde_json::Map<String, serde_json::Value> {
+        let (success, object) = self.try_json(args);
+        assert!(success, "{args:?} failed: {object:?}");
+        object
+    }
+}
+
+impl Drop for Cli {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn text<'a>(object: &'a serde_json::Map<String, serde_json::Value>, key: &str) -> &'a str {
+    object[key]
+        .as_str()
+        .unwrap_or_else(|| panic!("no {key} in {object:?}"))
+}
+
+#[test]
+fn every_command_records_its_results_under_json() {
+    let mut cli = Cli::new_in("json");
+
+    let generated = cli.json(&["generate", "--no-encryption"]);
+    let address = text(&generated, "taproot_address").to_string();
+    let pubkey = text(&generated, "pubkey").to_string();
+    let reconstructed = cli.json(&["reconstruct"]);
+    cli.private_key = Some(text(&reconstructed, "frost-signing-key").to_string());
+
+    cli.json(&["load"]);
+    assert_eq!(text(&cli.json(&["test"]), "bip86_address"), address);
+    let tree = cli.json(&["test", "--merkle-root", &"11".repeat(32)]);
+    assert_ne!(text(&tree, "taproot_address"), address);
+    assert_eq!(text(&tree, "bip86_address"), address);
+    cli.json(&["verify"]);
+    cli.json(&["sign", "--message", "hello"]);
+    cli.json(&["sign", "--message", "hello", "--output", "signature.hex"]);
+    cli.json(&[
+        "--key-file",
+        "dkg.json",
+        "--pubkey-file",
+        "dkg_pub.json",
+        "dkg",
+        "--no-encryption",
+    ]);
+    // The DKG's public key package, received from its coordinator.
+    let secp = bitcoin::secp256k1::Secp256k1::new();
+    let authority = bitcoin::secp256k1::Keypair::from_seckey_slice(&secp, &[7; 32]).unwrap();
+    let authority_key = authority.x_only_public_key().0.to_string();
+    let received = std::fs::read(cli.dir.join("dkg_pub.json")).unwrap();
+    let digest = bitcoin::hashes::sha256::Hash::hash(&received);
+    let message = bitcoin::secp256k1::Message::from_digest(digest.to_byte_array());
+    let signature = secp
+        .sign_schnorr_no_aux_rand(&message, &authority)
+        .to_string();
+    let import = |signature: &str| {
+        cli.try_json(&[
+            "--pubkey-file",
+            "imported_pub.json",
+            "import-pubkey-package",
+            "--package",
+            "dkg_pub.json",
+            "--pubkey-package-sig",
+            signature,
+            "--authority-key",
+            &authority_key,
+        ])
+    };
+    let (success, refused) = import(&"00".repeat(64));
+    assert!(!success);
+    assert!(text(&refused, "error").contains("not signed by the authority key"));
+    assert!(!cli.dir.join("imported_pub.json").exists());
+    assert!(import(&signature).0);
+    // A bare key file from before metadata was written beside it.
+    std::fs::copy(cli.dir.join("dkg.json"), cli.dir.join("legacy.json")).unwrap();
+    let legacy = [
+        "--key-file",
+        "legacy.json",
+        "--pubkey-file",
+        "legacy_pub.json",
+    ];
+    let migrated = cli.json(&[&legacy[..], &["migrate"]].concat());
+    assert_eq!(text(&migrated, "backup"), "legacy.json.bak");
+    assert_eq!(
+        std::fs::read(cli.dir.join("legacy.json.bak")).unwrap(),
+        std::fs::read(cli.dir.join("dkg.json")).unwrap()
+    );
+    assert!(cli.dir.join("legacy.meta.json").exists());
+    assert_eq!(
+        std::fs::read(cli.dir.join("legacy_pub.json")).unwrap(),
+        std::fs::read(cli.dir.join("dkg_pub.json")).unwrap()
+    );
+    cli.json(&[&legacy[..], &["sign", "--message", "hello"]].concat());
+    assert!(!cli.try_json(&[&legacy[..], &["migrate"]].concat()).0);
+    let sets = ["--key-file", "sets.json", "--pubkey-file", "sets_pub.json"];
+    let generated =
+        cli.json(&[&sets[..], &["generate", "--no-encryption", "--count", "2"]].concat());
+    assert_eq!(generated["key_sets"].as_array().unwrap().len(), 2);
+    let (success, _) = cli.try_json(
+        &[
+            &sets[..],
+            &[
+                "generate",
+                "--no-encryption",
+                "--count",
+                "3",
+                "--max-count",
+                "2",
+            ],
+        ]
+        .concat(),
+    );
+    assert!(!success);
+    assert!(!cli.dir.join("sets_2.json").exists());
+    cli.json(&["security"]);
+    cli.json(&["features"]);
+    cli.json(&["check-consistency"]);
+    cli.json(&["check-subsets", "--samples", "2"]);
+    cli.json(&["bench-serialization", "--signers", "5", "--iterations", "1"]);
+    cli.json(&["bench-keys"]);
+    cli.json(&["generate-fixtures", "--out-dir", "fixtures"]);
+    cli.json(&["sign-batch", "--message", "a", "--message", "b"]);
+    cli.json(&["simulate-equivocation"]);
+    cli.json(&["interactive"]);
+
+    // The round-by-round ceremony, one participant at a time.
+    for participant in ["1", "2", "3"] {
+        cli.json(&[
+            "commit",
+            "--participant",
+            participant,
+            "--nonces-out",
+            &format!("nonces{participant}.json"),
+            "--commitments-out",
+            &format!("commitments{participant}.json"),
+        ]);
+    }
+    let commitments = [
+        "--commitments",
+        "commitments1.json",
+        "--commitments",
+        "commitments2.json",
+        "--commitments",
+        "commitments3.json",
+    ];
+    cli.json(&[&["threshold-check"], &commitments[..]].concat());
+    cli.json(
+        &[
+            &[
+                "signing-package",
+                "--message",
+                "hello",
+                "--output",
+                "package.json",
+            ],
+            &commitments[..],
+        ]
+        .concat(),
+    );
+    for participant in ["1", "2", "3"] {
+        let nonces = format!("nonces{participant}.json");
+        let share = format!("share{participant}.json");
+        let args = [
+            "sign-share",
+            "--participant",
+            participant,
+            "--nonces",
+            &nonces,
+            "--message",
+            "hello",
+            "--share-out",
+            &share,
+        ];
+        cli.json(&[&args[..], &commitments[..]].concat());
+    }
+    cli.json(&[
+        "aggregate",
+        "--signing-package",
+        "package.json",
+        "--share",
+        "share1.json",
+        "--share",
+        "share2.json",
+        "--share",
+        "share3.json",
+    ]);
+
+    // Again over a commitments map instead of files.
+    let mut map = serde_json::Map::new();
+    for participant in ["1", "2", "3"] {
+        cli.json(&[
+            "commit",
+            "--participant",
+            participant,
+            "--nonces-out",
+            &format!("map-nonces{participant}.json"),
+            "--commitments-out",
+            &format!("map-commitments{participant}.json"),
+        ]);
+        let file = std::fs::read(cli.dir.join(format!("map-commitments{participant}.json")));
+        let file: serde_json::Value = serde_json::from_slice(&file.unwrap()).unwrap();
+        let identifier = file["identifier"].as_str().unwrap().to_string();
+        map.insert(identifier, file["commitments"].clone());
+    }
+    std::fs::write(cli.dir.join("map.json"), serde_json::to_vec(&map).unwrap()).unwrap();
+    for participant in ["1", "2", "3"] {
+        cli.json(&[
+            "sign-share",
+            "--participant",
+            participant,
+            "--nonces",
+            &format!("map-nonces{participant}.json"),
+            "--commitments-map",
+            "map.json",
+            "--message",
+            "hello",
+            "--share-out",
+            &format!("map-share{participant}.json"),
+        ]);
+    }
+    let aggregate = |map: &str| {
+        cli.try_json(&[
+            "--audit-log",
+            "ceremonies.jsonl",
+            "aggregate",
+            "--commitments-map",
+            map,
+            "--message",
+            "hello",
+            "--share",
+            "map-share1.json",
+            "--share",
+            "map-share2.json",
+            "--share",
+            "map-share3.json",
+        ])
+    };
+    assert!(aggregate("map.json").0);
+    map.remove(&map.keys().next_back().unwrap().clone());
+    std::fs::write(
+        cli.dir.join("short.json"),
+        serde_json::to_vec(&map).unwrap(),
+    )
+    .unwrap();
+    let (success, refused) = aggregate("short.json");
+    assert!(!success);
+    assert!(text(&refused, "error").contains("don't cover signers 3"));
+    // The log now holds those commitments, so the same session can't run twice.
+    let (success, replayed) = aggregate("map.json");
+    assert!(!success);
+    assert!(text(&replayed, "error").contains("already used"));
+    assert_eq!(replayed["reused_nonces"].as_array().unwrap().len(), 3);
+    let scan = ["check-nonce-reuse", "--log", "ceremonies.jsonl"];
+    assert_eq!(cli.json(&scan)["ceremonies"], 1);
+    cli.json(&[
+        "commit",
+        "--participant",
+        "1",
+        "--nonces-out",
+        "fresh-nonces.json",
+        "--commitments-out",
+        "fresh-commitments.json",
+    ]);
+    cli.json(&[&scan[..], &["--nonces", "fresh-nonces.json"]].concat());
+    let log = std::fs::read_to_string(cli.dir.join("ceremonies.jsonl")).unwrap();
+    std::fs::write(cli.dir.join("ceremonies.jsonl"), log.repeat(2)).unwrap();
+    let (success, reused) = cli.try_json(&scan);
+    assert!(!success);
+    assert_eq!(reused["reused_nonces"].as_array().unwrap().len(), 3);
+
+    cli.json(&["group-nonce"]);
+    let pre_signature = cli.json(&["adaptor-sign", "--adaptor-point", ADAPTOR_POINT]);
+    let pre_signature = text(&pre_signature, "pre_signature").to_string();
+    cli.json(&[
+        "adaptor-verify",
+        "--pre-signature",
+        &pre_signature,
+        "--adaptor-point",
+        ADAPTOR_POINT,
+    ]);
+    let completed = cli.json(&[
+        "adaptor-complete",
+        "--pre-signature",
+        &pre_signature,
+        "--adaptor-secret",
+        ADAPTOR_SECRET,
+    ]);
+    let extracted = cli.json(&[
+        "extract-secret",
+        "--pre-signature",
+        &pre_signature,
+        "--signature",
+        text(&completed, "signature"),
+    ]);
+    assert_eq!(text(&extracted, "adaptor_secret"), ADAPTOR_SECRET);
+    cli.json(&["blind-sign"]);
+
+    cli.json(&[
+        "bip322-sighash",
+        "--message",
+        "hello",
+        "--address",
+        &address,
+    ]);
+    cli.json(&["npub"]);
+    cli.json(&["nostr-sign", "--content", "hello", "--tag", "t,frost"]);
+    cli.json(&["ln-node-id"]);
+    cli.json(&[
+        "verify-descriptor",
+        "--address",
+        &address,
+        "--descriptor",
+        &format!("tr({pubkey})"),
+    ]);
+    cli.json(&["sign-key-path", "--sighash", &"22".repeat(32)]);
+    cli.json(&["script-tree", "--script", "51"]);
+
+    let outpoint = format!("{}:0", "11".repeat(32));
+    let spend = [
+        "--outpoint",
+        &outpoint,
+        "--amount",
+        "0.001btc",
+        "--fee",
+        "1000sat",
+        "--destination",
+        &address,
+    ];
+    let psbt = cli.json(&[&["build-tx"], &spend[..]].concat());
+    let signed = cli.json(&["sign-psbt", "--psbt", text(&psbt, "psbt")]);
+    cli.json(&["combine", "--psbt", text(&signed, "psbt")]);
+    let tx = cli.json(&["sign-psbt", "--psbt", text(&psbt, "psbt"), "--extract"]);
+    let tx = text(&tx, "tx").to_string();
+    cli.json(&["classify-spend", "--tx", &tx]);
+    let annexed = cli.json(&[
+        "sign-psbt",
+        "--psbt",
+        text(&psbt, "psbt"),
+        "--extract",
+        "--annex",
+        "50cafe",
+    ]);
+    assert!(text(&annexed, "tx").contains("0350cafe"));
+    let (success, _) = cli.try_json(&[
+        "sign-psbt",
+        "--psbt",
+        text(&psbt, "psbt"),
+        "--extract",
+        "--annex",
+        "51cafe",
+    ]);
+    assert!(!success);
+
+    // A JSON spec builds the same spend, or several; schema errors are reported.
+    let tx_spec = cli.dir.join("spec.json");
+    std::fs::write(
+        &tx_spec,
+        format!(
+            r#"{{ "inputs": [{{ "outpoint": "{outpoint}", "amount": "0.001btc" }}],
+                 "outputs": [{{ "address": "{address}", "amount": "99000sat" }}] }}"#
+        ),
+    )
+    .unwrap();
+    let tx_spec = tx_spec.to_str().unwrap();
+    let built = cli.json(&["build-tx", "--tx-spec", tx_spec]);
+    assert_eq!(built["psbt"], psbt["psbt"]);
+    std::fs::write(tx_spec, r#"{ "inputs": [], "outputs": [], "fee": 1 }"#).unwrap();
+    let (success, invalid) = cli.try_json(&["build-tx", "--tx-spec", tx_spec]);
+    assert!(!success);
+    assert!(text(&invalid, "error").contains("unknown field `fee`"));
+
+    // Outputs off the allowlist are refused before signing; change is allowed.
+    let allowlist = cli.dir.join("allowed.txt");
+    std::fs::write(&allowlist, "# nothing but change\n").unwrap();
+    let allowlist = allowlist.to_str().unwrap();
+    let allowed = ["--allowed-destinations", allowlist];
+    cli.json(&[&["sign-psbt", "--psbt", text(&psbt, "psbt")], &allowed[..]].concat());
+    let foreign = "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr";
+    let payment = cli.json(&[&["build-tx"], &spend[..7], &[foreign]].concat());
+    let (success, refused) = cli.try_json(
+        &[
+            &["sign-psbt", "--psbt", text(&payment, "psbt")],
+            &allowed[..],
+        ]
+        .concat(),
+    );
+    assert!(!success);
+    assert!(text(&refused, "error").contains(&format!("output 0 pays 0.00099000 BTC to {foreign}")));
+    std::fs::write(allowlist, format!("{foreign}\n")).unwrap();
+    cli.json(
+        &[
+            &["sign-psbt", "--psbt", text(&payment, "psbt")],
+            &allowed[..],
+        ]
+        .concat(),
+    );
+    // So is a payment over --max-amount; change to the group never counts.
+    let payment = text(&payment, "psbt");
+    let (success, refused) =
+        cli.try_json(&["sign-psbt", "--psbt", payment, "--max-amount", "0.0005btc"]);
+    assert!(!success);
+    assert_eq!(
+        text(&refused, "error"),
+        "the PSBT sends 0.00099000 BTC, over the 0.00050000 BTC limit"
+    );
+    cli.json(&["sign-psbt", "--psbt", payment, "--max-amount", "99000sat"]);
+    cli.json(&[
+        "sign-psbt",
+        "--psbt",
+        text(&psbt, "psbt"),
+        "--max-amount",
+        "0",
+    ]);
+
+    let frames = String::from_utf8(cli.output(&["verify", "--wire-format"]).stdout).unwrap();
+    let frame = frames.lines().next().unwrap();
+    cli.json(&["decode-wire", "--frame", frame]);
+
+    std::fs::write(cli.dir.join(".env"), "PRIVATE_KEY=00\n").unwrap();
+    cli.json(&["encrypt-env", "--input", ".env", "--output", ".env.enc"]);
+    cli.json(&["export-cold-backup", "--output", "cold-backup.txt"]);
+    let restored = cli.json(&[
+        "restore-cold-backup",
+        "--input",
+        "cold-backup.txt",
+        "--output",
+        "restored.env.enc",
+    ]);
+    assert_eq!(restored["encrypted"], true);
+    let restored = cli.json(&[
+        "restore-cold-backup",
+        "--input",
+        "cold-backup.txt",
+        "--output",
+        "restored.env",
+        "--plaintext",
+    ]);
+    assert_eq!(restored["encrypted"], false);
+    assert!(std::fs::read_to_string(cli.dir.join("restored.env"))
+        .unwrap()
+        .starts_with("PRIVATE_KEY="));
+    let (reused, _) = cli.try_json(&[
+        "export-cold-backup",
+        "--output",
+        "reused-key.txt",
+        "--backup-key",
+        ENV_KEY,
+    ]);
+    assert!(!reused);
+
+    cli.json(&[
+        "cluster-sign",
+        "--message",
+        "a",
+        "--message",
+        "b",
+        "--workers",
+        "2",
+    ]);
+    cli.json(&["cluster-worker"]);
+
+    cli.json(&[
+        "--audit-log",
+        "audit.jsonl",
+        "refresh-shares",
+        "--key-output",
+        "refreshed.json",
+        "--pubkey-output",
+        "refreshed_pub.json",
+    ]);
+    let audit = cli.json(&["verify-audit-log", "--log", "audit.jsonl"]);
+    assert_eq!(audit["quorum_changes"], 1);
+    cli.json(&[
+        "check-refresh",
+        "--old",
+        "my_map.json",
+        "--new",
+        "refreshed.json",
+    ]);
+
+    cli.json(&[
+        "attest",
+        "--message",
+        "hello",
+        "--output",
+        "attestation.json",
+    ]);
+    cli.json(&["verify-attestation", "attestation.json"]);
+
+    let seeded = [
+        "--key-file",
+        "seeded.json",
+        "--pubkey-file",
+        "seeded_pub.json",
+    ];
+    cli.json(
+        &[
+            &seeded[..],
+            &["--rng-seed", SEED, "generate", "--no-encryption"],
+        ]
+        .concat(),
+    );
+    cli.json(&[&seeded[..], &["verify-seed-commitment", "--seed", SEED]].concat());
+    // The workers must sign with these key files, not the default ones.
+    cli.json(
+        &[
+            &seeded[..],
+            &["cluster-sign", "--message", "a", "--queue", "seeded-queue"],
+        ]
+        .concat(),
+    );
+
+    let encrypted = [
+        "--key-file",
+        "encrypted.json",
+        "--pubkey-file",
+        "encrypted_pub.json",
+        "--passphrase",
+        "correct horse",
+    ];
+    cli.json(&[&encrypted[..], &["generate"]].concat());
+    cli.json(
+        &[
+            &encrypted[..],
+            &[
+                "cluster-sign",
+                "--message",
+                "a",
+                "--queue",
+                "encrypted-queue",
+            ],
+        ]
+        .concat(),
+    );
+
+    // Without a node these fail, and record why.
+    if cfg!(feature = "rpc") {
+        for args in [
+            &[&["test-mempool-accept"], &spend[..]].concat()[..],
+            &["estimate-confirmation"],
+            &["check-timelock", "--script", "5ab2"],
+            &["consolidate", "--fee-rate", "2"],
+            &["scan-addresses", "--descriptor", BIP86_DESCRIPTOR],
+            &["bump-fee", "--tx", &tx, "--fee-rate", "5"],
+            &[
+                "emergency-sweep",
+                "--destination",
+                &address,
+                "--confirm",
+                &address,
+            ],
+        ] {
+            let (success, object) = cli.try_json(args);
+            assert!(
+                !success && object.contains_key("error"),
+                "{args:?}: {object:?}"
+            );
+        }
+        // Unconfirmed, the sweep stops before reconstructing anything.
+        let (success, refused) = cli.try_json(&["emergency-sweep", "--destination", &address]);
+        assert!(!success);
+        assert_eq!(
+            text(&refused, "error"),
+            "--confirm must be the group's taproot address"
+        );
+    }
+}
+
+/// A node that answers one RPC call with `result`, at the returned URL.
+#[cfg(feature = "rpc")]
+fn stub_node(result: serde_json::Value) -> String {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 8192];
+        let _ = stream.read(&mut request).unwrap();
+        let body = serde_json::json!({ "result": result, "error": null, "id": "frost" });
+        let reply = format!("HTTP/1.1 200 OK\r\n\r\n{body}");
+        stream.write_all(reply.as_bytes()).unwrap();
+    });
+    url
+}
+
+#[cfg(feature = "rpc")]
+#[test]
+fn a_mempool_rejection_fails_test_mempool_accept() {
+    let cli = Cli::new_in("mempool");
+    let generated = cli.json(&["generate", "--no-encryption"]);
+    let address = text(&generated, "taproot_address");
+    let outpoint = format!("{}:0", "11".repeat(32));
+    let spend = [
+        "test-mempool-accept",
+        "--outpoint",
+        &outpoint,
+        "--amount",
+        "100000",
+        "--fee",
+        "1000",
+        "--destination",
+        address,
+    ];
+
+    let rejecting = stub_node(serde_json::json!([
+        { "allowed": false, "reject-reason": "missing-inputs" }
+    ]));
+    let output = cli.output(&[&spend[..], &["--rpc-url", &rejecting]].concat());
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Mempool accepted: false (reason: missing-inputs)"));
+
+    let accepting = stub_node(serde_json::json!([{ "allowed": true }]));
+    let accepted = cli.json(&[&spend[..], &["--rpc-url", &accepting]].concat());
+    assert_eq!(accepted["accepted"], true);
+    assert!(accepted["tx"].as_str().unwrap().len() > 100);
+}
+
+#[cfg(feature = "rpc")]
+#[test]
+fn scanned_child_addresses_report_their_balances() {
+    let cli = Cli::new_in("scan");
+    // The BIP86 vector's second address, m/86'/0'/0'/0/1.
+    let script = "5120a82f29944d65b86ae6b5e5cc75e294ead6c59391a1edc5e016e3498c67fc7bbb";
+    let node = stub_node(serde_json::json!({
+        "success": true,
+        "unspents": [
+            { "txid": "11".repeat(32), "vout": 0, "scriptPubKey": script, "amount": 0.001 },
+            { "txid": "22".repeat(32), "vout": 3, "scriptPubKey": script, "amount": 0.0025 },
+        ],
+    }));
+    let scan = cli.json(&[
+        "--network",
+        "bitcoin",
+        "scan-addresses",
+        "--descriptor",
+        BIP86_DESCRIPTOR,
+        "--count",
+        "3",
+        "--rpc-url",
+        &node,
+    ]);
+    let addresses = scan["addresses"].as_array().unwrap();
+    assert_eq!(addresses.len(), 3);
+    assert_eq!(
+        addresses[1]["address"],
+        "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh"
+    );
+    assert_eq!(addresses[1]["utxos"], 2);
+    assert_eq!(addresses[0]["balance"], 0);
+    assert_eq!(scan["funded"], 1);
+    assert_eq!(scan["total"], 350_000);
+}
+
+#[test]
+fn named_participants_commit_by_name() {
+    let cli = Cli::new_in("named");
+    cli.json(&[
+        "generate",
+        "--no-encryption",
+        "--min-signers",
+        "2",
+        "--max-signers",
+        "3",
+        "--dealer-identifiers",
+        "alice,bob,carol",
+    ]);
+    cli.json(&[
+        "commit",
+        "--participant",
+        "bob",
+        "--nonces-out",
+        "nonces-bob.json",
+        "--commitments-out",
+        "commitments-bob.json",
+    ]);
+    assert!(cli.dir.join("commitments-bob.json").exists());
+
+    let (success, object) = cli.try_json(&[
+        "commit",
+        "--participant",
+        "mallory",
+        "--nonces-out",
+        "nonces-mallory.json",
+        "--commitments-out",
+        "commitments-mallory.json",
+    ]);
+    assert!(!success, "{object:?}");
+    assert!(text(&object, "error").contains("mallory"), "{object:?}");
+
+    let instructions = |participants: &str| {
+        cli.try_json(&[
+            "signing-instructions",
+            "--participants",
+            participants,
+            "--message",
+            "hello",
+            "--coordinator",
+            "ops@example.com",
+            "--out-dir",
+            "steps",
+        ])
+    };
+    let (success, written) = instructions("alice,carol");
+    assert!(success, "{written:?}");
+    assert_eq!(written["instructions"].as_array().unwrap().len(), 2);
+    let steps = std::fs::read_to_string(cli.dir.join("steps/carol.txt")).unwrap();
+    assert!(steps.contains("frost commit --participant carol"));
+    assert!(steps.contains("--commitments commitments-alice.json"));
+    assert!(!instructions("alice,mallory").0);
+    assert!(!instructions("alice").0);
+
+    // Reports can list participants in the order they were enrolled, here
+    // the reverse of their identifier order.
+    let keys = [
+        "--key-file",
+        "ordered.json",
+        "--pubkey-file",
+        "ordered_pub.json",
+    ];
+    cli.json(
+        &[
+            &keys[..],
+            &[
+                "generate",
+                "--no-encryption",
+                "--min-signers",
+                "2",
+                "--max-signers",
+                "3",
+                "--dealer-identifiers",
+                "carol,bob,alice",
+            ],
+        ]
+        .concat(),
+    );
+    let mut commitments = Vec::new();
+    for name in ["alice", "carol", "bob"] {
+        let commitments_file = format!("ordered-commitments-{name}.json");
+        cli.json(
+            &[
+                &keys[..],
+                &[
+                    "commit",
+                    "--participant",
+                    name,
+                    "--nonces-out",
+                    &format!("ordered-nonces-{name}.json"),
+                    "--commitments-out",
+                    &commitments_file,
+                ],
+            ]
+            .concat(),
+        );
+        commitments.extend(["--commitments".to_string(), commitments_file]);
+    }
+    let commitments: Vec<_> = commitments.iter().map(String::as_str).collect();
+    let committed = |order: &str| {
+        let check = cli.json(
+            &[
+                &keys[..],
+                &["--participant-order", order, "threshold-check"],
+                &commitments[..],
+            ]
+            .concat(),
+        );
+        check["committed"].clone()
+    };
+    let mut identifiers = ["carol", "bob", "alice"]
+        .map(|name| frost_demo::frost::Identifier::derive(name.as_bytes()).unwrap());
+    let labels = |identifiers: &[frost_demo::frost::Identifier]| {
+        serde_json::json!(identifiers
+            .iter()
+            .map(frost_demo::identifier_label)
+            .collect::<Vec<_>>())
+    };
+    assert_eq!(committed("enrollment"), labels(&identifiers));
+    identifiers.sort();
+    assert_eq!(committed("identifier"), labels(&identifiers));
+    assert_ne!(committed("identifier"), committed("enrollment"));
+}
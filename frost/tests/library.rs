@@ -0,0 +1,61 @@
+//! Using the crate as a dependency, without the CLI.
+
+use frost_demo::frost::keys::IdentifierList;
+use frost_demo::{
+    dealer_keygen_with, default_signers, group_taproot_address, load_map, sign_message,
+    sign_messages, write_map, DEMO_MESSAGE,
+};
+use rand::thread_rng;
+
+#[test]
+fn keys_round_trip_through_an_encrypted_file_and_sign() {
+    let (key_packages, pubkey_package) =
+        dealer_keygen_with(2, 3, IdentifierList::Default, &mut thread_rng()).unwrap();
+    let path = std::env::temp_dir().join(format!("frost-library-{}.json", std::process::id()));
+    write_map(&path, &key_packages, Some("correct horse")).unwrap();
+    assert!(load_map(&path, None).is_err());
+    let loaded = load_map(&path, Some("correct horse")).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, key_packages);
+
+    let signature = sign_message(
+        &loaded,
+        &pubkey_package,
+        &default_signers(&loaded),
+        DEMO_MESSAGE,
+        &mut thread_rng(),
+    )
+    .unwrap();
+    assert!(pubkey_package
+        .verifying_key()
+        .verify(DEMO_MESSAGE, &signature)
+        .is_ok());
+
+    let (_, address) =
+        group_taproot_address(&pubkey_package, None, bitcoin::Network::Regtest).unwrap();
+    assert!(address.to_string().starts_with("bcrt1p"));
+}
+
+#[test]
+fn batch_signatures_verify_independently() {
+    let (key_packages, pubkey_package) =
+        dealer_keygen_with(3, 5, IdentifierList::Default, &mut thread_rng()).unwrap();
+    let messages: [&[u8]; 4] = [b"input 0", b"input 1", b"", b"input 0"];
+    let signatures = sign_messages(
+        &key_packages,
+        &pubkey_package,
+        &default_signers(&key_packages),
+        &messages,
+        &mut thread_rng(),
+    )
+    .unwrap();
+
+    assert_eq!(signatures.len(), messages.len());
+    let verifying_key = pubkey_package.verifying_key();
+    for (index, signature) in signatures.iter().enumerate() {
+        assert!(verifying_key.verify(messages[index], signature).is_ok());
+        assert!(verifying_key
+            .verify(messages[(index + 1) % 3], signature)
+            .is_err());
+    }
+}